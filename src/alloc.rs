@@ -0,0 +1,125 @@
+use crate::call_graph::{CallGraph, TraversalResult};
+use crate::{FunctionID, ParsedData};
+use eyre::Result;
+use std::collections::HashSet;
+
+/// Mangled names of the functions that allocate on the heap: libc, the C++ `operator new`
+/// overloads for 64 and 32 bit targets and the Rust allocator shims.
+const ALLOCATOR_FUNCTIONS: &[&str] = &[
+    "malloc",
+    "calloc",
+    "realloc",
+    "reallocarray",
+    "aligned_alloc",
+    "posix_memalign",
+    "memalign",
+    "valloc",
+    "strdup",
+    "strndup",
+    "_Znwm",
+    "_Znam",
+    "_Znwj",
+    "_Znaj",
+    "_ZnwmRKSt9nothrow_t",
+    "_ZnamRKSt9nothrow_t",
+    "_ZnwmSt11align_val_t",
+    "_ZnamSt11align_val_t",
+    "_ZnwmSt11align_val_tRKSt9nothrow_t",
+    "_ZnamSt11align_val_tRKSt9nothrow_t",
+    "__rust_alloc",
+    "__rust_alloc_zeroed",
+    "__rust_realloc",
+];
+
+/// A function that must not allocate, but calls an allocator directly or indirectly.
+pub struct AllocationViolation {
+    /// Starts with the checked function and ends with the allocator.
+    pub call_path: Vec<FunctionID>,
+}
+
+pub fn allocator_functions(parsed: &ParsedData) -> HashSet<FunctionID> {
+    ALLOCATOR_FUNCTIONS
+        .iter()
+        .filter_map(|name| parsed.function_id_by_global_name(name))
+        .collect()
+}
+
+/// Functions that call an allocator directly or through other functions, with at most
+/// `max_depth` calls in between. Calls through function pointers and virtual calls are not
+/// known and therefore not included.
+pub fn transitively_allocating(
+    call_graph: &CallGraph,
+    allocators: &HashSet<FunctionID>,
+    max_depth: Option<usize>,
+) -> TraversalResult<HashSet<FunctionID>> {
+    let mut is_truncated = false;
+    let allocating = allocators
+        .iter()
+        .flat_map(|allocator| {
+            let callers = call_graph.reachable_callers(*allocator, max_depth);
+            is_truncated |= callers.is_truncated();
+            callers.into_inner()
+        })
+        .collect();
+    if is_truncated {
+        TraversalResult::Truncated(allocating)
+    } else {
+        TraversalResult::Complete(allocating)
+    }
+}
+
+/// Checks every function matching the pattern. A pattern without matches is an error, so
+/// that renamed functions do not silently pass. Allocations more than `max_depth` calls away
+/// are not found, which is reported as a warning.
+pub fn check_no_alloc(
+    parsed: &ParsedData,
+    call_graph: &CallGraph,
+    pattern: &str,
+    max_depth: Option<usize>,
+) -> Result<Vec<AllocationViolation>> {
+    let function_ids = crate::find_functions_by_pattern(parsed, pattern);
+    if function_ids.is_empty() {
+        return Err(eyre::eyre!("Can't find function {} to check.", pattern));
+    }
+    let allocators = allocator_functions(parsed);
+    let allocating = transitively_allocating(call_graph, &allocators, max_depth);
+    if allocating.is_truncated() {
+        eprintln!(
+            "Warning: Allocations of {} may be missed because the maximum depth of {} was reached.",
+            pattern,
+            max_depth.unwrap_or_default()
+        );
+    }
+    let allocating = allocating.into_inner();
+    Ok(function_ids
+        .into_iter()
+        .filter(|function_id| allocating.contains(function_id))
+        .filter_map(|function_id| {
+            let call_path = call_graph
+                .shortest_call_path_to_any(
+                    function_id,
+                    |function| allocators.contains(&function),
+                    max_depth,
+                )
+                .into_inner()?;
+            Some(AllocationViolation { call_path })
+        })
+        .collect())
+}
+
+/// Prints the call path of every violation and returns an error if there are any.
+pub fn report(parsed: &ParsedData, violations: &[AllocationViolation]) -> Result<()> {
+    if violations.is_empty() {
+        eprintln!("No checked function allocates.");
+        return Ok(());
+    }
+    for violation in violations {
+        let names: Vec<String> = violation
+            .call_path
+            .iter()
+            .map(|function_id| parsed.display(*function_id).to_string())
+            .collect();
+        println!("Allocates: {}", names.join(" → "));
+    }
+    Err(eyre::eyre!("{} functions allocate.", violations.len()))
+}
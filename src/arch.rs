@@ -0,0 +1,139 @@
+/// Architecture that an object is compiled for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Architecture {
+    X86,
+    X86_64,
+    Arm,
+    AArch64,
+    RiscV32,
+    RiscV64,
+    Other(String),
+}
+
+impl Architecture {
+    pub fn name(&self) -> &str {
+        match self {
+            Architecture::X86 => "x86",
+            Architecture::X86_64 => "x86_64",
+            Architecture::Arm => "arm",
+            Architecture::AArch64 => "aarch64",
+            Architecture::RiscV32 => "riscv32",
+            Architecture::RiscV64 => "riscv64",
+            Architecture::Other(name) => name,
+        }
+    }
+
+    /// Parses the first component of a target triple like `riscv64-unknown-elf`.
+    fn from_triple(triple: &str) -> Self {
+        let arch = triple.split('-').next().unwrap_or_default();
+        match arch {
+            "i386" | "i486" | "i586" | "i686" | "x86" => Architecture::X86,
+            "x86_64" | "amd64" => Architecture::X86_64,
+            "aarch64" | "arm64" => Architecture::AArch64,
+            "riscv32" => Architecture::RiscV32,
+            "riscv64" => Architecture::RiscV64,
+            _ if arch.starts_with("arm") || arch.starts_with("thumb") => Architecture::Arm,
+            _ => Architecture::Other(arch.to_owned()),
+        }
+    }
+
+    /// Only architectures that can be told apart from `-march` alone are detected. Values
+    /// like `-march=native` or `-march=skylake` don't change the architecture. `armv8` and
+    /// later can also be 32-bit, which only the target triple tells.
+    fn from_march(march: &str) -> Option<Self> {
+        if march.starts_with("aarch64") || march.starts_with("armv8") || march.starts_with("armv9")
+        {
+            Some(Architecture::AArch64)
+        } else if march.starts_with("arm") {
+            Some(Architecture::Arm)
+        } else if march.starts_with("rv64") {
+            Some(Architecture::RiscV64)
+        } else if march.starts_with("rv32") {
+            Some(Architecture::RiscV32)
+        } else {
+            None
+        }
+    }
+
+    fn host() -> Self {
+        Self::from_triple(std::env::consts::ARCH)
+    }
+}
+
+/// Finds the architecture that the compiler arguments target. Later flags win, as they do
+/// for the compiler, but `-march` is only used when there is no target triple. Without any
+/// relevant flags the host architecture is assumed.
+pub fn detect_target_arch(args: &[String]) -> Architecture {
+    let has_target = args.iter().any(|arg| {
+        ["-target", "--target"].contains(&arg.as_str())
+            || arg.starts_with("-target=")
+            || arg.starts_with("--target=")
+    });
+    let mut arch = None;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        if let Some(triple) = arg
+            .strip_prefix("--target=")
+            .or_else(|| arg.strip_prefix("-target="))
+        {
+            arch = Some(Architecture::from_triple(triple));
+        } else if arg == "-target" || arg == "--target" {
+            if let Some(triple) = args_iter.next() {
+                arch = Some(Architecture::from_triple(triple));
+            }
+        } else if let Some(march) = arg.strip_prefix("-march=") {
+            if has_target {
+                continue;
+            }
+            if let Some(march_arch) = Architecture::from_march(march) {
+                arch = Some(march_arch);
+            }
+        } else if arg == "-m32" {
+            arch = Some(Architecture::X86);
+        } else if arg == "-m64" {
+            arch = Some(Architecture::X86_64);
+        }
+    }
+    arch.unwrap_or_else(Architecture::host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(args: &str) -> Architecture {
+        let args: Vec<String> = args.split_whitespace().map(str::to_owned).collect();
+        detect_target_arch(&args)
+    }
+
+    #[test]
+    fn architecture_is_detected_from_flags() {
+        assert_eq!(detect("cc -c -march=aarch64 x.c"), Architecture::AArch64);
+        assert_eq!(detect("cc -march=armv7-a"), Architecture::Arm);
+        assert_eq!(detect("cc -march=rv32imac"), Architecture::RiscV32);
+        assert_eq!(
+            detect("clang --target=riscv64-unknown-elf"),
+            Architecture::RiscV64
+        );
+        assert_eq!(
+            detect("clang -target thumbv7em-none-eabi"),
+            Architecture::Arm
+        );
+        assert_eq!(detect("cc -m32"), Architecture::X86);
+        assert_eq!(detect("cc -m32 -m64"), Architecture::X86_64);
+        assert_eq!(detect("cc -march=native"), Architecture::host());
+    }
+
+    #[test]
+    fn target_triple_wins_over_march() {
+        assert_eq!(
+            detect("clang --target=arm-linux-gnueabihf -march=armv8-a"),
+            Architecture::Arm
+        );
+        assert_eq!(
+            detect("clang -march=armv8-a --target=arm-linux-gnueabihf"),
+            Architecture::Arm
+        );
+        assert_eq!(detect("gcc -march=armv8-a"), Architecture::AArch64);
+    }
+}
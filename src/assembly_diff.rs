@@ -0,0 +1,68 @@
+use eyre::Result;
+use similar::TextDiff;
+use std::io::Write;
+
+/// Number of unchanged lines shown around every change.
+const CONTEXT_LINES_NUM: usize = 3;
+
+/// Normalized instructions of two versions of a function, one per line.
+pub struct AssemblyDiff {
+    before: String,
+    after: String,
+}
+
+/// Keeps only instructions and replaces local label names, which are numbered per object
+/// and change whenever code is added anywhere before the function.
+fn normalize_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('.') || line.ends_with(':') {
+        return None;
+    }
+    let mut normalized = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(label_start) = rest.find(".L") {
+        normalized.push_str(&rest[..label_start]);
+        normalized.push_str(".L");
+        rest = rest[label_start + 2..]
+            .trim_start_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    }
+    normalized.push_str(rest);
+    Some(normalized.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Line based diff of the instructions of two versions of a function.
+pub fn diff_function_assembly(before: &[String], after: &[String]) -> AssemblyDiff {
+    let normalized = |lines: &[String]| -> String {
+        lines
+            .iter()
+            .filter_map(|line| normalize_line(line))
+            .map(|line| line + "\n")
+            .collect()
+    };
+    AssemblyDiff {
+        before: normalized(before),
+        after: normalized(after),
+    }
+}
+
+impl AssemblyDiff {
+    pub fn is_empty(&self) -> bool {
+        self.before == self.after
+    }
+
+    pub fn write_unified(
+        &self,
+        writer: &mut dyn Write,
+        before_label: &str,
+        after_label: &str,
+    ) -> Result<()> {
+        TextDiff::configure()
+            .algorithm(similar::Algorithm::Myers)
+            .diff_lines(&self.before, &self.after)
+            .unified_diff()
+            .context_radius(CONTEXT_LINES_NUM)
+            .header(before_label, after_label)
+            .to_writer(writer)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{FunctionID, ParsedData};
+use eyre::Result;
+
+/// Outcome of checking the instruction count of a single function against a limit.
+pub struct AssertionResult {
+    pub function: String,
+    pub limit: usize,
+    pub actual: usize,
+    pub message: String,
+}
+
+impl AssertionResult {
+    pub fn passed(&self) -> bool {
+        self.actual <= self.limit
+    }
+}
+
+fn check_limit(function: String, limit: usize, actual: usize, reason: &str) -> AssertionResult {
+    let message = if actual <= limit {
+        format!("{} instructions ({})", actual, reason)
+    } else {
+        format!(
+            "{} instructions, {} more than allowed ({})",
+            actual,
+            actual - limit,
+            reason
+        )
+    };
+    AssertionResult {
+        function,
+        limit,
+        actual,
+        message,
+    }
+}
+
+/// Checks every function matching the pattern. A pattern without matches is an error, so
+/// that renamed functions do not silently pass.
+pub fn check_max_instructions(
+    parsed: &ParsedData,
+    pattern: &str,
+    limit: usize,
+) -> Result<Vec<AssertionResult>> {
+    let function_ids: Vec<FunctionID> = crate::find_functions_by_pattern(parsed, pattern);
+    if function_ids.is_empty() {
+        return Err(eyre::eyre!("Can't find function {} to check.", pattern));
+    }
+    Ok(function_ids
+        .into_iter()
+        .map(|function_id| {
+            check_limit(
+                parsed.display(function_id).to_string(),
+                limit,
+                crate::function_instructions_num(parsed, function_id),
+                &format!("limit {}", limit),
+            )
+        })
+        .collect())
+}
+
+/// Every function that exists in both versions may not have more instructions than before.
+pub fn check_no_increase(before: &ParsedData, after: &ParsedData) -> Vec<AssertionResult> {
+    crate::diff_parsed_data(before, after, &Default::default())
+        .changed
+        .into_iter()
+        .map(|(name, before_num, after_num)| {
+            check_limit(name.to_string(), before_num, after_num, "baseline")
+        })
+        .collect()
+}
+
+/// Prints all failed assertions and returns an error if there are any.
+pub fn report(results: &[AssertionResult]) -> Result<()> {
+    let failed: Vec<&AssertionResult> = results.iter().filter(|r| !r.passed()).collect();
+    if failed.is_empty() {
+        eprintln!("All {} instruction count assertions passed.", results.len());
+        return Ok(());
+    }
+    for result in failed.iter() {
+        println!(
+            "Assertion failed for {}: {}",
+            result.function, result.message
+        );
+    }
+    Err(eyre::eyre!(
+        "{} of {} instruction count assertions failed.",
+        failed.len(),
+        results.len()
+    ))
+}
@@ -0,0 +1,360 @@
+use crate::compression::Compression;
+use crate::{cli, ParsedData};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Stored as `<name>.meta.json` next to the baseline data, so that baselines can be listed
+/// without loading the data and so that comparisons with different settings can be noticed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaselineMetadata {
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub objects_num: usize,
+    pub functions_num: usize,
+    pub instructions_num: usize,
+    /// Selected objects and compiler flag modifications that were used to get the data. Empty
+    /// when the data was loaded from JSON.
+    pub flags: Vec<String>,
+}
+
+impl BaselineMetadata {
+    fn new(parsed: &ParsedData, flags: Vec<String>) -> Self {
+        BaselineMetadata {
+            created: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            objects_num: parsed.name_by_object_id.len(),
+            functions_num: parsed.instructions_by_object_by_function.len(),
+            instructions_num: crate::all_instructions_num(parsed),
+            flags,
+        }
+    }
+}
+
+/// Command line options that influence the parsed data, in the form they were passed.
+pub fn analysis_flags(source: &cli::SourceArgs) -> Vec<String> {
+    let mut flags = vec![];
+    flags.extend(source.objects.iter().map(|o| format!("--object {}", o)));
+    flags.extend(
+        source
+            .extra_flags
+            .iter()
+            .map(|f| format!("--extra-flag {}", f)),
+    );
+    flags.extend(
+        source
+            .removed_flags
+            .iter()
+            .map(|f| format!("--remove-flag {}", f)),
+    );
+    flags.extend(
+        source
+            .replaced_flags
+            .iter()
+            .map(|(old, new)| format!("--replace-flag {} {}", old, new)),
+    );
+    if source.parse_options.exclude_conditional_blocks {
+        flags.push("--exclude-ifdefs".to_owned());
+    }
+    flags
+}
+
+/// Compression formats in the order in which baselines are looked up.
+const COMPRESSIONS: [Compression; 3] = [Compression::Zstd, Compression::Lz4, Compression::None];
+
+/// Baselines are files directly in the baseline directory, so their names must not lead
+/// out of it.
+fn check_name(name: &str) -> Result<()> {
+    if name.chars().any(std::path::is_separator) || name.contains("..") {
+        return Err(eyre::eyre!(
+            "The baseline name {} must not contain path separators or \"..\".",
+            name
+        ));
+    }
+    Ok(())
+}
+
+fn data_path(dir: &Path, name: &str, compression: Compression) -> PathBuf {
+    dir.join(format!("{}.{}", name, compression.json_extension()))
+}
+
+/// Path of the stored baseline data with any compression.
+fn existing_data_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    COMPRESSIONS
+        .iter()
+        .map(|compression| data_path(dir, name, *compression))
+        .find(|path| path.exists())
+}
+
+fn metadata_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.meta.json", name))
+}
+
+/// Writes a file next to its destination first and then replaces the destination, so that
+/// the previous version is kept if writing fails.
+fn write_atomically(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    if let Err(err) = write(&temp_path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(err);
+    }
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Baselines are compressed with zstd unless another format is given.
+pub fn save(
+    dir: &Path,
+    name: &str,
+    parsed: &ParsedData,
+    flags: Vec<String>,
+    compression: cli::CompressionArgs,
+) -> Result<()> {
+    check_name(name)?;
+    std::fs::create_dir_all(dir)?;
+    let format = compression.format.unwrap_or(Compression::Zstd);
+    write_atomically(&data_path(dir, name, format), |path| {
+        crate::write_parsed_data(
+            parsed,
+            path,
+            false,
+            cli::CompressionArgs {
+                format: Some(format),
+                ..compression
+            },
+        )
+    })?;
+    // A previous baseline with the same name might use another compression.
+    for other_format in COMPRESSIONS.into_iter().filter(|other| *other != format) {
+        let path = data_path(dir, name, other_format);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    let metadata = BaselineMetadata::new(parsed, flags);
+    write_atomically(&metadata_path(dir, name), |path| {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(&mut file, &metadata)?;
+        file.flush()?;
+        Ok(())
+    })
+}
+
+pub fn load(dir: &Path, name: &str) -> Result<(ParsedData, Option<BaselineMetadata>)> {
+    check_name(name)?;
+    let Some(path) = existing_data_path(dir, name) else {
+        return Err(eyre::eyre!(
+            "There is no baseline {} in {}.",
+            name,
+            dir.display()
+        ));
+    };
+    let parsed = crate::load_parsed_data(&path)?;
+    Ok((parsed, load_metadata(dir, name)))
+}
+
+/// Returns `None` for baselines without readable metadata, e.g. copied JSON files.
+fn load_metadata(dir: &Path, name: &str) -> Option<BaselineMetadata> {
+    let file = std::fs::File::open(metadata_path(dir, name)).ok()?;
+    serde_json::from_reader(std::io::BufReader::new(file)).ok()
+}
+
+/// Names of all baselines in the directory, sorted.
+fn baseline_names(dir: &Path) -> Result<Vec<String>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut names = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let file_name = entry?.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        if file_name.ends_with(".meta.json") {
+            continue;
+        }
+        let name = COMPRESSIONS.iter().find_map(|compression| {
+            file_name.strip_suffix(&format!(".{}", compression.json_extension()))
+        });
+        if let Some(name) = name {
+            names.push(name.to_owned());
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+pub fn print_baselines(dir: &Path) -> Result<()> {
+    let names = baseline_names(dir)?;
+    if names.is_empty() {
+        println!("No baselines in {}.", dir.display());
+        return Ok(());
+    }
+    for name in names {
+        match load_metadata(dir, &name) {
+            Some(metadata) => {
+                println!(
+                    "{}: created {}, {} objects, {} functions, {} instructions",
+                    name,
+                    format_timestamp(metadata.created),
+                    metadata.objects_num,
+                    metadata.functions_num,
+                    metadata.instructions_num
+                );
+                if !metadata.flags.is_empty() {
+                    println!("  {}", metadata.flags.join(" "));
+                }
+            }
+            None => println!("{}: no metadata", name),
+        }
+    }
+    Ok(())
+}
+
+/// Formats seconds since the Unix epoch as UTC date and time, e.g. `2024-03-01 14:05:09 UTC`.
+fn format_timestamp(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let seconds_of_day = seconds % 86400;
+    // Converts the days to the proleptic Gregorian calendar, with years starting in March so
+    // that the leap day is the last day of the year.
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Warns when the baseline was created with different flags than the current data.
+pub fn check_flags(name: &str, metadata: &Option<BaselineMetadata>, flags: &[String]) {
+    let Some(metadata) = metadata else {
+        eprintln!(
+            "Warning: baseline {} has no metadata, flags can't be compared.",
+            name
+        );
+        return;
+    };
+    if metadata.flags != flags {
+        eprintln!(
+            "Warning: baseline {} was created with different flags.\n  baseline: {}\n  current:  {}",
+            name,
+            metadata.flags.join(" "),
+            flags.join(" ")
+        );
+    }
+}
+
+pub fn delete(dir: &Path, name: &str) -> Result<()> {
+    check_name(name)?;
+    let Some(path) = existing_data_path(dir, name) else {
+        return Err(eyre::eyre!(
+            "There is no baseline {} in {}.",
+            name,
+            dir.display()
+        ));
+    };
+    std::fs::remove_file(path)?;
+    let metadata_path = metadata_path(dir, name);
+    if metadata_path.exists() {
+        std::fs::remove_file(metadata_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn saving_twice_replaces_the_baseline_without_leftovers() {
+        let dir = std::env::temp_dir().join(format!("baseline-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut parsed = ParsedData::default();
+        parsed.get_or_add_function(crate::FunctionName::global("main"));
+
+        save(&dir, "main", &parsed, vec![], Default::default()).unwrap();
+        let compression = cli::CompressionArgs {
+            format: Some(Compression::None),
+            level: None,
+        };
+        save(
+            &dir,
+            "main",
+            &parsed,
+            vec!["--exclude-ifdefs".to_owned()],
+            compression,
+        )
+        .unwrap();
+
+        assert_eq!(files_in(&dir), vec!["main.json", "main.meta.json"]);
+        let (loaded, metadata) = load(&dir, "main").unwrap();
+        assert!(loaded.function_id_by_global_name("main").is_some());
+        assert_eq!(metadata.unwrap().flags, vec!["--exclude-ifdefs"]);
+
+        // A failed write keeps the previous version and removes the temporary file.
+        let path = metadata_path(&dir, "main");
+        let result = write_atomically(&path, |temp_path| {
+            std::fs::write(temp_path, "partial")?;
+            Err(eyre::eyre!("Disk full."))
+        });
+        assert!(result.is_err());
+        assert_eq!(files_in(&dir), vec!["main.json", "main.meta.json"]);
+        assert!(load_metadata(&dir, "main").is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn names_leading_out_of_the_directory_are_rejected() {
+        let dir = std::env::temp_dir().join(format!("baseline-names-{}", std::process::id()));
+        let parsed = ParsedData::default();
+        for name in ["../main", "a/b", "..", "main..old"] {
+            assert!(save(&dir, name, &parsed, vec![], Default::default()).is_err());
+            assert!(load(&dir, name).is_err());
+            assert!(delete(&dir, name).is_err());
+        }
+        assert!(!dir.exists());
+        assert!(check_name("release-1.2").is_ok());
+    }
+
+    #[test]
+    fn timestamps_are_formatted_as_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_timestamp(1709301909), "2024-03-01 14:05:09 UTC");
+        assert_eq!(format_timestamp(1735689599), "2024-12-31 23:59:59 UTC");
+    }
+}
@@ -0,0 +1,635 @@
+use crate::{FunctionID, ParsedData};
+use std::collections::{HashMap, VecDeque};
+
+/// Adjacency list representation of the call graph. Function ids are used as indices, so
+/// traversals don't need any hash map lookups.
+pub struct CallGraph {
+    /// Callees of every function.
+    pub adjacency: Vec<Vec<FunctionID>>,
+    /// Callers of every function.
+    pub reverse_adjacency: Vec<Vec<FunctionID>>,
+}
+
+pub fn build_call_graph(parsed: &ParsedData) -> CallGraph {
+    let nodes_num = parsed
+        .name_by_function_id
+        .keys()
+        .map(|function_id| function_id.0 + 1)
+        .max()
+        .unwrap_or_default();
+    let mut adjacency = vec![vec![]; nodes_num];
+    let mut reverse_adjacency = vec![vec![]; nodes_num];
+    for (caller, callees) in parsed.callees_by_caller.iter() {
+        for callee in callees {
+            adjacency[caller.0].push(*callee);
+            reverse_adjacency[callee.0].push(*caller);
+        }
+    }
+    // Sort for deterministic traversal orders.
+    for neighbors in adjacency.iter_mut().chain(reverse_adjacency.iter_mut()) {
+        neighbors.sort_by_key(|function_id| function_id.0);
+    }
+    CallGraph {
+        adjacency,
+        reverse_adjacency,
+    }
+}
+
+/// Result of a traversal that may stop at a maximum depth.
+pub enum TraversalResult<T> {
+    Complete(T),
+    /// The depth limit was hit, so some functions might be missing.
+    Truncated(T),
+}
+
+impl<T> TraversalResult<T> {
+    pub fn is_truncated(&self) -> bool {
+        matches!(self, TraversalResult::Truncated(_))
+    }
+
+    pub fn into_inner(self) -> T {
+        match self {
+            TraversalResult::Complete(value) | TraversalResult::Truncated(value) => value,
+        }
+    }
+
+    fn new(value: T, is_truncated: bool) -> Self {
+        if is_truncated {
+            TraversalResult::Truncated(value)
+        } else {
+            TraversalResult::Complete(value)
+        }
+    }
+}
+
+/// Entry `[i][j]` is the smallest number of calls from function `i` to function `j`, or
+/// `None` if `j` is not reachable from `i`.
+pub type DistanceMatrix = Vec<Vec<Option<usize>>>;
+
+#[derive(Clone, Copy)]
+pub enum Direction {
+    Callees,
+    Callers,
+}
+
+impl CallGraph {
+    pub fn nodes_num(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn neighbors(&self, function: FunctionID, direction: Direction) -> &[FunctionID] {
+        match direction {
+            Direction::Callees => &self.adjacency[function.0],
+            Direction::Callers => &self.reverse_adjacency[function.0],
+        }
+    }
+
+    /// Visits every function reachable from the roots in depth-first order. The callback is
+    /// called once a function and everything reachable from it has been visited, i.e. in
+    /// post-order.
+    pub fn dfs(
+        &self,
+        roots: impl IntoIterator<Item = FunctionID>,
+        direction: Direction,
+        visited: &mut [bool],
+        mut post_visit: impl FnMut(FunctionID),
+    ) {
+        // Iterative to avoid stack overflows on deep call graphs.
+        let mut stack: Vec<(FunctionID, usize)> = vec![];
+        for root in roots {
+            if visited[root.0] {
+                continue;
+            }
+            visited[root.0] = true;
+            stack.push((root, 0));
+            while let Some((function, next_neighbor_i)) = stack.last_mut() {
+                let function = *function;
+                let neighbors = self.neighbors(function, direction);
+                if let Some(neighbor) = neighbors.get(*next_neighbor_i) {
+                    *next_neighbor_i += 1;
+                    if !visited[neighbor.0] {
+                        visited[neighbor.0] = true;
+                        stack.push((*neighbor, 0));
+                    }
+                } else {
+                    stack.pop();
+                    post_visit(function);
+                }
+            }
+        }
+    }
+
+    /// Returns all functions reachable from the start function in breadth-first order,
+    /// together with their distance from the start. The start itself is not included.
+    /// Functions further away than `max_depth` are not visited.
+    pub fn bfs(
+        &self,
+        start: FunctionID,
+        direction: Direction,
+        max_depth: Option<usize>,
+    ) -> TraversalResult<Vec<(FunctionID, usize)>> {
+        let mut visited = vec![false; self.nodes_num()];
+        let mut queue = VecDeque::from([(start, 0)]);
+        let mut result = vec![];
+        let mut is_truncated = false;
+        visited[start.0] = true;
+        while let Some((function, distance)) = queue.pop_front() {
+            for neighbor in self.neighbors(function, direction) {
+                if !visited[neighbor.0] {
+                    if max_depth.is_some_and(|max_depth| distance >= max_depth) {
+                        is_truncated = true;
+                        break;
+                    }
+                    visited[neighbor.0] = true;
+                    queue.push_back((*neighbor, distance + 1));
+                    result.push((*neighbor, distance + 1));
+                }
+            }
+        }
+        TraversalResult::new(result, is_truncated)
+    }
+
+    /// Returns the functions on the shortest call chain from `from` to `to`, including both
+    /// ends, or `None` if `to` is not reachable within `max_depth` calls.
+    pub fn shortest_call_path(
+        &self,
+        from: FunctionID,
+        to: FunctionID,
+        max_depth: Option<usize>,
+    ) -> TraversalResult<Option<Vec<FunctionID>>> {
+        self.shortest_call_path_to_any(from, |function| function == to, max_depth)
+    }
+
+    /// Like `shortest_call_path`, but ends at the closest function for which `is_target`
+    /// returns true.
+    pub fn shortest_call_path_to_any(
+        &self,
+        from: FunctionID,
+        is_target: impl Fn(FunctionID) -> bool,
+        max_depth: Option<usize>,
+    ) -> TraversalResult<Option<Vec<FunctionID>>> {
+        let mut parent: Vec<Option<FunctionID>> = vec![None; self.nodes_num()];
+        let mut queue = VecDeque::from([(from, 0)]);
+        let mut visited = vec![false; self.nodes_num()];
+        let mut is_truncated = false;
+        visited[from.0] = true;
+        while let Some((function, distance)) = queue.pop_front() {
+            if is_target(function) {
+                let mut path = vec![function];
+                let mut current = function;
+                while let Some(previous) = parent[current.0] {
+                    path.push(previous);
+                    current = previous;
+                }
+                path.reverse();
+                return TraversalResult::Complete(Some(path));
+            }
+            if max_depth.is_some_and(|max_depth| distance >= max_depth) {
+                is_truncated = true;
+                continue;
+            }
+            for callee in self.neighbors(function, Direction::Callees) {
+                if !visited[callee.0] {
+                    visited[callee.0] = true;
+                    parent[callee.0] = Some(function);
+                    queue.push_back((*callee, distance + 1));
+                }
+            }
+        }
+        TraversalResult::new(None, is_truncated)
+    }
+
+    /// Functions that may be called directly or indirectly by the given function.
+    pub fn reachable_callees(
+        &self,
+        function: FunctionID,
+        max_depth: Option<usize>,
+    ) -> TraversalResult<Vec<FunctionID>> {
+        self.reachable(function, Direction::Callees, max_depth)
+    }
+
+    /// Functions that may directly or indirectly call the given function.
+    pub fn reachable_callers(
+        &self,
+        function: FunctionID,
+        max_depth: Option<usize>,
+    ) -> TraversalResult<Vec<FunctionID>> {
+        self.reachable(function, Direction::Callers, max_depth)
+    }
+
+    fn reachable(
+        &self,
+        function: FunctionID,
+        direction: Direction,
+        max_depth: Option<usize>,
+    ) -> TraversalResult<Vec<FunctionID>> {
+        let result = self.bfs(function, direction, max_depth);
+        let is_truncated = result.is_truncated();
+        let functions = result
+            .into_inner()
+            .into_iter()
+            .map(|(function, _)| function)
+            .collect();
+        TraversalResult::new(functions, is_truncated)
+    }
+
+    /// Computes the shortest distances between all pairs of functions with one breadth-first
+    /// search per function, which is enough because every call has the same weight. Needs
+    /// quadratic memory, so it should only be used for small graphs.
+    pub fn shortest_distance_matrix(&self) -> DistanceMatrix {
+        (0..self.nodes_num())
+            .map(|start| {
+                let mut distances = vec![None; self.nodes_num()];
+                distances[start] = Some(0);
+                for (function, distance) in self
+                    .bfs(FunctionID(start), Direction::Callees, None)
+                    .into_inner()
+                {
+                    distances[function.0] = Some(distance);
+                }
+                distances
+            })
+            .collect()
+    }
+
+    /// Orders all functions so that callees come before their callers. Cycles can't be
+    /// ordered like that and are broken at an arbitrary call.
+    pub fn topological_sort(&self) -> Vec<FunctionID> {
+        let mut visited = vec![false; self.nodes_num()];
+        let mut order = Vec::with_capacity(self.nodes_num());
+        let roots = (0..self.nodes_num()).map(FunctionID);
+        self.dfs(roots, Direction::Callees, &mut visited, |function| {
+            order.push(function)
+        });
+        order
+    }
+
+    /// Finds strongly connected components with Tarjan's algorithm. Components are
+    /// returned in reverse topological order, i.e. callees before callers.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<FunctionID>> {
+        self.strongly_connected_components_from((0..self.nodes_num()).map(FunctionID))
+    }
+
+    /// Same as `strongly_connected_components`, but only for the functions that are
+    /// reachable from the roots.
+    pub fn strongly_connected_components_from(
+        &self,
+        roots: impl IntoIterator<Item = FunctionID>,
+    ) -> Vec<Vec<FunctionID>> {
+        const UNVISITED: usize = usize::MAX;
+        let nodes_num = self.nodes_num();
+        let mut index = vec![UNVISITED; nodes_num];
+        let mut lowlink = vec![0; nodes_num];
+        let mut on_stack = vec![false; nodes_num];
+        let mut component_stack: Vec<FunctionID> = vec![];
+        let mut components = vec![];
+        let mut next_index = 0;
+
+        for FunctionID(root) in roots {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            // Emulates the recursive formulation with an explicit stack of
+            // (function, index of next callee to look at).
+            let mut call_stack = vec![(root, 0)];
+            index[root] = next_index;
+            lowlink[root] = next_index;
+            next_index += 1;
+            component_stack.push(FunctionID(root));
+            on_stack[root] = true;
+
+            while let Some(&mut (node, ref mut next_callee_i)) = call_stack.last_mut() {
+                if let Some(callee) = self.adjacency[node].get(*next_callee_i) {
+                    *next_callee_i += 1;
+                    let callee = callee.0;
+                    if index[callee] == UNVISITED {
+                        index[callee] = next_index;
+                        lowlink[callee] = next_index;
+                        next_index += 1;
+                        component_stack.push(FunctionID(callee));
+                        on_stack[callee] = true;
+                        call_stack.push((callee, 0));
+                    } else if on_stack[callee] {
+                        lowlink[node] = lowlink[node].min(index[callee]);
+                    }
+                    continue;
+                }
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[node]);
+                }
+                if lowlink[node] == index[node] {
+                    let mut component = vec![];
+                    loop {
+                        let function = component_stack.pop().unwrap();
+                        on_stack[function.0] = false;
+                        component.push(function);
+                        if function.0 == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    /// Sum of the weights of every function that is reachable from the roots and of
+    /// everything it calls directly or indirectly. This is the transitive closure over the
+    /// strongly connected components. It is computed for 64 components at a time, as the bits
+    /// of a word, so that it only needs linear memory and callees that are shared by many
+    /// callers are not traversed again for every caller.
+    pub fn reachable_weight_sums(
+        &self,
+        roots: impl IntoIterator<Item = FunctionID>,
+        weight: impl Fn(FunctionID) -> usize,
+    ) -> HashMap<FunctionID, usize> {
+        let components = self.strongly_connected_components_from(roots);
+        let mut component_by_function: HashMap<FunctionID, usize> = HashMap::new();
+        for (component_i, component) in components.iter().enumerate() {
+            for function in component {
+                component_by_function.insert(*function, component_i);
+            }
+        }
+        // Callees come first, so their components always have smaller indices.
+        let callee_components: Vec<Vec<usize>> = components
+            .iter()
+            .enumerate()
+            .map(|(component_i, component)| {
+                let mut callees: Vec<usize> = component
+                    .iter()
+                    .flat_map(|function| self.neighbors(*function, Direction::Callees))
+                    .map(|callee| component_by_function[callee])
+                    .filter(|callee_i| *callee_i != component_i)
+                    .collect();
+                callees.sort();
+                callees.dedup();
+                callees
+            })
+            .collect();
+        let component_weights: Vec<usize> = components
+            .iter()
+            .map(|component| component.iter().map(|function| weight(*function)).sum())
+            .collect();
+
+        let mut sums = vec![0; components.len()];
+        let mut reachable_bits = vec![0u64; components.len()];
+        for chunk_start in (0..components.len()).step_by(64) {
+            // Sums of the weights for every value of every byte of the bits, so that the sum
+            // of a word only needs eight lookups.
+            let byte_sums: Vec<[usize; 256]> = (0..8)
+                .map(|byte_i| {
+                    let mut table = [0; 256];
+                    for bits in 1..256usize {
+                        let component_i = chunk_start + byte_i * 8 + bits.trailing_zeros() as usize;
+                        table[bits] = table[bits & (bits - 1)]
+                            + component_weights
+                                .get(component_i)
+                                .copied()
+                                .unwrap_or_default();
+                    }
+                    table
+                })
+                .collect();
+            // Components before the chunk can't reach it, so their bits are not updated.
+            for component_i in chunk_start..components.len() {
+                let mut bits = match component_i - chunk_start {
+                    bit if bit < 64 => 1 << bit,
+                    _ => 0,
+                };
+                for callee_i in &callee_components[component_i] {
+                    if *callee_i >= chunk_start {
+                        bits |= reachable_bits[*callee_i];
+                    }
+                }
+                reachable_bits[component_i] = bits;
+                sums[component_i] += byte_sums
+                    .iter()
+                    .enumerate()
+                    .map(|(byte_i, table)| table[(bits >> (byte_i * 8)) as usize & 0xff])
+                    .sum::<usize>();
+            }
+        }
+        components
+            .iter()
+            .zip(sums)
+            .flat_map(|(component, sum)| component.iter().map(move |function| (*function, sum)))
+            .collect()
+    }
+}
+
+/// Average over all pairs of different functions where one is reachable from the other.
+/// Returns zero if there are no such pairs.
+pub fn average_shortest_path_length(matrix: &DistanceMatrix) -> f64 {
+    let (sum, pairs_num) = reachable_distances(matrix)
+        .fold((0, 0), |(sum, pairs_num), distance| {
+            (sum + distance, pairs_num + 1)
+        });
+    if pairs_num == 0 {
+        0.0
+    } else {
+        sum as f64 / pairs_num as f64
+    }
+}
+
+/// Longest of all shortest paths between reachable pairs of functions.
+pub fn diameter(matrix: &DistanceMatrix) -> usize {
+    reachable_distances(matrix).max().unwrap_or_default()
+}
+
+fn reachable_distances(matrix: &DistanceMatrix) -> impl Iterator<Item = usize> + '_ {
+    matrix.iter().enumerate().flat_map(|(i, row)| {
+        row.iter()
+            .enumerate()
+            .filter(move |(j, _)| i != *j)
+            .filter_map(|(_, distance)| *distance)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(indices: &[usize]) -> Vec<FunctionID> {
+        indices.iter().copied().map(FunctionID).collect()
+    }
+
+    /// `4 -> 0 -> 1 -> 2 -> 3` where `1` and `2` call each other, and `5` is isolated.
+    fn graph_with_cycle() -> CallGraph {
+        let edges = [(4, 0), (0, 1), (1, 2), (2, 1), (2, 3)];
+        let mut graph = CallGraph {
+            adjacency: vec![vec![]; 6],
+            reverse_adjacency: vec![vec![]; 6],
+        };
+        for (caller, callee) in edges {
+            graph.adjacency[caller].push(FunctionID(callee));
+            graph.reverse_adjacency[callee].push(FunctionID(caller));
+        }
+        graph
+    }
+
+    #[test]
+    fn dfs_visits_callees_in_post_order() {
+        let graph = graph_with_cycle();
+        let mut visited = vec![false; graph.nodes_num()];
+        let mut order = vec![];
+        graph.dfs(ids(&[0]), Direction::Callees, &mut visited, |function| {
+            order.push(function)
+        });
+        assert_eq!(order, ids(&[3, 2, 1, 0]));
+
+        // Already visited functions are skipped when the traversal is continued.
+        order.clear();
+        graph.dfs(ids(&[1, 4]), Direction::Callees, &mut visited, |function| {
+            order.push(function)
+        });
+        assert_eq!(order, ids(&[4]));
+    }
+
+    #[test]
+    fn bfs_reports_distances_and_truncation() {
+        let graph = graph_with_cycle();
+        let result = graph.bfs(FunctionID(0), Direction::Callees, None);
+        assert!(!result.is_truncated());
+        assert_eq!(
+            result.into_inner(),
+            vec![(FunctionID(1), 1), (FunctionID(2), 2), (FunctionID(3), 3)]
+        );
+
+        let result = graph.bfs(FunctionID(0), Direction::Callees, Some(2));
+        assert!(matches!(result, TraversalResult::Truncated(_)));
+        assert_eq!(
+            result.into_inner(),
+            vec![(FunctionID(1), 1), (FunctionID(2), 2)]
+        );
+
+        // Reaching the last function exactly at the limit is not a truncation.
+        assert!(matches!(
+            graph.bfs(FunctionID(0), Direction::Callees, Some(3)),
+            TraversalResult::Complete(_)
+        ));
+
+        assert_eq!(
+            graph.reachable_callers(FunctionID(3), None).into_inner(),
+            ids(&[2, 1, 0, 4])
+        );
+    }
+
+    #[test]
+    fn shortest_call_path_respects_max_depth() {
+        let graph = graph_with_cycle();
+        assert_eq!(
+            graph
+                .shortest_call_path(FunctionID(4), FunctionID(3), None)
+                .into_inner(),
+            Some(ids(&[4, 0, 1, 2, 3]))
+        );
+        let result = graph.shortest_call_path(FunctionID(4), FunctionID(3), Some(2));
+        assert!(result.is_truncated());
+        assert_eq!(result.into_inner(), None);
+        let result = graph.shortest_call_path(FunctionID(3), FunctionID(4), None);
+        assert!(!result.is_truncated());
+        assert_eq!(result.into_inner(), None);
+    }
+
+    #[test]
+    fn strongly_connected_components_contain_cycles() {
+        let graph = graph_with_cycle();
+        let mut components = graph.strongly_connected_components();
+        for component in components.iter_mut() {
+            component.sort();
+        }
+        assert_eq!(
+            components,
+            vec![ids(&[3]), ids(&[1, 2]), ids(&[0]), ids(&[4]), ids(&[5])]
+        );
+    }
+
+    #[test]
+    fn reachable_weight_sums_count_shared_callees_once() {
+        // 0 calls 1 and 2, which both call 3 and 2 calls itself through 4.
+        let edges = [(0, 1), (0, 2), (1, 3), (2, 3), (2, 4), (4, 2), (5, 0)];
+        let mut graph = CallGraph {
+            adjacency: vec![vec![]; 6],
+            reverse_adjacency: vec![vec![]; 6],
+        };
+        for (caller, callee) in edges {
+            graph.adjacency[caller].push(FunctionID(callee));
+            graph.reverse_adjacency[callee].push(FunctionID(caller));
+        }
+        let weight = |function: FunctionID| 1 << function.0;
+
+        let sums = graph.reachable_weight_sums(ids(&[0]), weight);
+        assert_eq!(
+            sums,
+            HashMap::from([
+                (FunctionID(0), 0b11111),
+                (FunctionID(1), 0b01010),
+                (FunctionID(2), 0b11100),
+                (FunctionID(3), 0b01000),
+                (FunctionID(4), 0b11100),
+            ])
+        );
+        assert_eq!(
+            graph.reachable_weight_sums(ids(&[5]), weight)[&FunctionID(5)],
+            0b111111
+        );
+    }
+
+    #[test]
+    fn reachable_weight_sums_match_traversals_in_large_graphs() {
+        // More components than bits in a word, with callees in earlier and later chunks.
+        let nodes_num = 200;
+        let mut graph = CallGraph {
+            adjacency: vec![vec![]; nodes_num],
+            reverse_adjacency: vec![vec![]; nodes_num],
+        };
+        for caller in 0..nodes_num {
+            for callee in [caller * 7 % nodes_num, (caller + 1) % 150, caller / 2] {
+                if callee != caller {
+                    graph.adjacency[caller].push(FunctionID(callee));
+                    graph.reverse_adjacency[callee].push(FunctionID(caller));
+                }
+            }
+        }
+        let weight = |function: FunctionID| function.0 + 1;
+
+        let sums = graph.reachable_weight_sums((0..nodes_num).map(FunctionID), weight);
+        for function in (0..nodes_num).map(FunctionID) {
+            let expected: usize = graph
+                .reachable_callees(function, None)
+                .into_inner()
+                .into_iter()
+                .chain([function])
+                .map(weight)
+                .sum();
+            assert_eq!(sums[&function], expected, "{:?}", function);
+        }
+    }
+
+    #[test]
+    fn topological_sort_puts_callees_first() {
+        let graph = graph_with_cycle();
+        assert_eq!(graph.topological_sort(), ids(&[3, 2, 1, 0, 4, 5]));
+    }
+
+    #[test]
+    fn distance_matrix_contains_shortest_distances() {
+        let graph = graph_with_cycle();
+        let matrix = graph.shortest_distance_matrix();
+        assert_eq!(matrix[4][3], Some(4));
+        assert_eq!(matrix[2][1], Some(1));
+        assert_eq!(matrix[1][1], Some(0));
+        assert_eq!(matrix[3][0], None);
+        assert_eq!(matrix[5][0], None);
+        assert_eq!(diameter(&matrix), 4);
+        // 4 reaches four functions, 0 three, 1 and 2 two and the other ones none. 2 calls
+        // both functions it reaches directly.
+        let expected = (1 + 2 + 3 + 4) + (1 + 2 + 3) + (1 + 2) + (1 + 1);
+        assert_eq!(
+            average_shortest_path_length(&matrix),
+            expected as f64 / 11.0
+        );
+    }
+}
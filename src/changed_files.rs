@@ -0,0 +1,73 @@
+use crate::AssemblyGenerationCommand;
+use eyre::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where the list of changed files comes from.
+#[derive(Clone)]
+pub enum ChangedFiles {
+    /// File with the output of `git diff --name-only`.
+    DiffOutput(PathBuf),
+    /// Runs `git diff --name-only HEAD~1` in the current directory.
+    LastCommit,
+}
+
+fn run_git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|err| eyre::eyre!("Failed to run git: {}", err))?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "`git {}` failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Paths are compared after resolving symlinks, because compile commands often reference
+/// sources through a symlinked build or source directory.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+/// Absolute paths of the changed files. `git diff --name-only` prints paths relative to the
+/// root of the repository, which is found from the current directory.
+fn load_changed_files(changed_files: &ChangedFiles) -> Result<HashSet<PathBuf>> {
+    let diff_output = match changed_files {
+        ChangedFiles::DiffOutput(path) => std::fs::read_to_string(path)
+            .map_err(|err| eyre::eyre!("Can't read {}: {}", path.display(), err))?,
+        ChangedFiles::LastCommit => run_git(&["diff", "--name-only", "HEAD~1"])?,
+    };
+    let root = PathBuf::from(run_git(&["rev-parse", "--show-toplevel"])?.trim());
+    Ok(diff_output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| canonical(&root.join(line)))
+        .collect())
+}
+
+/// Keeps the commands whose source file is one of the changed files.
+pub fn retain_changed_files(
+    commands: &mut Vec<AssemblyGenerationCommand>,
+    changed_files: &ChangedFiles,
+) -> Result<()> {
+    let changed_files = load_changed_files(changed_files)?;
+    let commands_num = commands.len();
+    commands.retain(|command| {
+        command
+            .source_file()
+            .is_some_and(|source_file| changed_files.contains(&canonical(&source_file)))
+    });
+    eprintln!(
+        "{} of {} compile commands compile changed files.",
+        commands.len(),
+        commands_num
+    );
+    Ok(())
+}
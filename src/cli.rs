@@ -0,0 +1,1432 @@
+use eyre::Result;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Usage: assembly_analysis [global options] <command> [options]
+
+Commands:
+  analyze   Generate and parse assembly, then write the parsed data as JSON.
+  report    Run one of the analyses on parsed data.
+  diff      Compare the instruction counts of two JSON files or build configurations.
+  query     Print information about a single function.
+  baseline  Save parsed data as named baseline and compare against it later.
+  stats     Print the size of the project without compiling it.
+  merge-shards
+            Combine the parsed data of shards that were analyzed separately.
+  compare-optimization
+            Analyze twice to show which functions get smaller or larger with more optimization.
+
+Global options:
+  --show-full-paths, -v  Print the full paths of object files instead of their file names.
+  --format-function-names <format>
+                         `demangled` (default), `mangled` or `short`, which truncates
+                         demangled names to 60 characters.
+  --strip-prefix <ns>    Remove a namespace from demangled names, e.g. `blender::`. Can be
+                         repeated.
+
+Run `assembly_analysis <command> --help` for the options of a command.";
+
+const SOURCE_OPTIONS: &str = "  --compile-commands <path>  The compile_commands.json to use (default: ./compile_commands.json).
+  --object <path>            Object file to analyze, as in the `output` field of the compile
+                             commands. Can be repeated. Defaults to all objects. With
+                             --from-json, objects whose path ends with it are kept.
+  --rust-rlib <path>         Read the LLVM bitcode embedded in a Rust rlib instead of compiling
+                             objects. The bitcode is disassembled with an external llvm-dis, the
+                             ThinLTO summary is not used. Needs a crate built with
+                             -C embed-bitcode=yes. Instructions are counted in LLVM IR.
+                             Also available as --rust-rmeta, which only reads the .rlib next to
+                             the given .rmeta file.
+  --llvm-dis <path>          The llvm-dis to use with --rust-rlib (default: llvm-dis). Its LLVM
+                             version must match the one of rustc, see `rustc -vV`.
+  --bazel-aquery <path>      Use the compile actions from the output of
+                             `bazel aquery --output=jsonproto` instead of compile_commands.json.
+  --bazel-execroot <path>    Directory Bazel runs the actions in (default: current directory).
+  --makefile-commands <path> Use the compiler invocations from the output of `make -n`, e.g.
+                             written with `make -n > commands.txt`.
+  --extra-flag <flag>        Compiler flag to append when generating assembly, e.g. -fno-inline.
+                             Can be repeated. Also available as --add-flag.
+  --remove-flag <flag>       Compiler flag to remove when generating assembly. Can be repeated.
+  --replace-flag <old> <new> Compiler flag to replace when generating assembly, e.g.
+                             `--replace-flag -O3 -O2`. Can be repeated.
+  --strip-debug-flags        Remove -g, -gdwarf-<n>, -fdebug-prefix-map and similar flags. The
+                             instructions stay the same, but compilation and parsing are faster.
+  --exclude-ifdefs           Ignore the code in `.ifdef`/`.ifndef`/`.if` branches, e.g.
+                             sanitizer instrumentation, but keep their `.else` branches.
+                             Includes all branches by default.
+  --keep-raw-assembly        Store the instructions of every function in the parsed data, so
+                             that they can be compared with `diff --diff-assembly-for`.
+  --validate-assembly        Report assembly lines that are neither directives, instructions,
+                             labels nor comments, which might be missed by the parser.
+  --slowest-compilations <n> Print the n objects that took longest to compile.
+  --analyze-binary <path>    Disassemble a linked ELF binary with objdump instead of compiling
+                             objects. Includes the effects of LTO and linker garbage collection.
+  --objdump <path>           The objdump to use with --analyze-binary (default: objdump).
+  --analyze-wat <path>       Parse a WebAssembly module in the text format instead of compiling
+                             objects, e.g. the output of wasm2wat.
+  --verbose-errors           Print compiler warnings also for files that compile successfully.
+                             Errors of failed compilations are always printed.
+  --pipe-mode                Only read the assembly from the compiler's stdout. By default, a
+                             temporary file is used when the compiler writes nothing to stdout.
+                             Compilers other than GCC and Clang always use files.
+  --assembly-dir <path>      Read the assembly of every object from <path>/<object>.s instead
+                             of compiling, e.g. generated with the commands written by
+                             `analyze --emit-compile-commands-for-assembly`.
+  --memory-limit <MB>        Abort the analysis when the parsed data is estimated to use more
+                             memory than this.
+  --shard-index <i>          Only analyze every n-th object starting at i, to distribute the
+  --shard-count <n>          analysis over n machines. Combine the results with merge-shards.
+  --sample <n>               Only analyze n random objects, chosen proportionally from every
+                             directory, and estimate the total instruction count.
+  --seed <n>                 Seed of --sample, to select the same objects again.
+  --max-object-files <n>     Abort before compiling if more objects would be analyzed
+                             (default: 100). Use `unlimited` to analyze any number of objects.
+  --all-objects              Same as `--max-object-files unlimited`.
+  --only-changed-files <path>
+                             Only analyze objects whose source file is listed in the output of
+                             `git diff --name-only`, with paths relative to the repository root.
+  --git-diff                 Only analyze objects whose source file changed in the last commit,
+                             according to `git diff --name-only HEAD~1`.";
+
+const RANKING_OPTIONS: &str =
+    "  --top <n>                  Only show the first n entries of sorted lists.
+  --bottom <n>               Only show the last n entries of sorted lists. Can't be combined
+                             with --top.
+  --min-instructions <n>     Skip entries with fewer instructions. Applied before --top.";
+
+const COMPRESSION_OPTIONS: &str =
+    "  --compress-output <format>  `zstd`, `lz4` or `none`. Compressed files are detected when
+                             reading them, independent of the extension.
+  --compress-level <n>       Compression level of zstd (default: 3).";
+
+const FUNCTION_REGEX_REPLACE_OPTION: &str =
+    "--function-regex-replace <pattern> <replacement>  Replace matches in the demangled \
+     function\n                             names before the versions are compared, e.g. \
+     `'h[0-9a-f]{16}$' HASH`\n                             for Rust symbol hashes. Can be \
+     repeated, they are applied in order.";
+
+const DEFAULT_BASELINE_DIR: &str = ".assembly_analysis_baselines";
+
+pub enum Subcommand {
+    Analyze(AnalyzeArgs),
+    Report(ReportArgs),
+    Diff(DiffArgs),
+    Query(QueryArgs),
+    Baseline(BaselineArgs),
+    Stats(StatsArgs),
+    MergeShards(MergeShardsArgs),
+    CompareOptimization(CompareOptimizationArgs),
+    Help(String),
+}
+
+/// Describes which objects should be compiled and parsed.
+#[derive(Clone)]
+pub struct SourceArgs {
+    pub compile_commands: PathBuf,
+    pub objects: Vec<String>,
+    /// Rust rlib whose embedded bitcode is parsed instead of compiling objects.
+    pub rust_rlib: Option<PathBuf>,
+    pub llvm_dis: PathBuf,
+    pub bazel_aquery: Option<PathBuf>,
+    pub bazel_execution_root: PathBuf,
+    /// File with the output of `make -n`.
+    pub makefile_commands: Option<PathBuf>,
+    /// Appended to every compiler invocation that generates assembly.
+    pub extra_flags: Vec<String>,
+    pub removed_flags: Vec<String>,
+    /// Pairs of (old, new) flags.
+    pub replaced_flags: Vec<(String, String)>,
+    pub parse_options: crate::ParseOptions,
+    /// Number of objects with the longest compilation time to print after the analysis.
+    pub slowest_compilations: Option<usize>,
+    /// Linked binary to disassemble instead of compiling objects.
+    pub binary: Option<PathBuf>,
+    /// WebAssembly text module to parse instead of compiling objects.
+    pub wat: Option<PathBuf>,
+    pub objdump: PathBuf,
+    /// Print the compiler output also for successful compilations.
+    pub verbose_errors: bool,
+    /// Don't fall back to a temporary file when the compiler writes no assembly to stdout.
+    pub pipe_mode: bool,
+    /// Directory with the pre-generated assembly of the objects.
+    pub assembly_dir: Option<PathBuf>,
+    /// Remove `-g` and related flags before generating assembly.
+    pub strip_debug_flags: bool,
+    /// Abort the analysis when the estimated memory usage of the parsed data exceeds it.
+    pub memory_limit_mb: Option<usize>,
+    /// Only the objects whose index modulo `shard_count` is `shard_index` are analyzed.
+    pub shard_index: usize,
+    pub shard_count: usize,
+    /// Only analyze this many randomly selected objects.
+    pub sample: Option<usize>,
+    /// Makes the sample reproducible.
+    pub seed: Option<u64>,
+    /// Protects against compiling a whole project by accident. `None` means unlimited.
+    pub max_object_files: Option<usize>,
+    /// Only analyze the objects whose source file changed according to git.
+    pub changed_files: Option<crate::changed_files::ChangedFiles>,
+}
+
+/// How written parsed data is compressed.
+#[derive(Clone, Copy, Default)]
+pub struct CompressionArgs {
+    /// Chosen by the caller if not specified.
+    pub format: Option<crate::compression::Compression>,
+    /// Default of the compression format if not specified.
+    pub level: Option<i32>,
+}
+
+impl CompressionArgs {
+    /// Returns false if the flag is not a compression option.
+    fn parse_flag(&mut self, flag: &str, args: &mut ArgStream) -> Result<bool> {
+        match flag {
+            "--compress-output" => self.format = Some(args.value(flag)?.parse()?),
+            "--compress-level" => self.level = Some(args.value(flag)?.parse()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+pub struct MergeShardsArgs {
+    pub shards: Vec<PathBuf>,
+    pub output_json: PathBuf,
+    pub deterministic_output: bool,
+}
+
+pub struct CompareOptimizationArgs {
+    pub source: SourceArgs,
+    /// Added to the flags of the base analysis for the second analysis.
+    pub optimization_flags: Vec<String>,
+    pub ranking: crate::Ranking,
+}
+
+pub struct AnalyzeArgs {
+    pub source: SourceArgs,
+    pub output_json: PathBuf,
+    pub compression: CompressionArgs,
+    /// Keep running and update the output whenever a source file changes.
+    pub watch: bool,
+    /// Write the JSON with sorted keys so that it can be diffed textually.
+    pub deterministic_output: bool,
+    /// Analyze every target architecture separately.
+    pub split_by_arch: bool,
+    /// Port of the HTTP server that serves the results as Prometheus metrics.
+    pub metrics_port: Option<u16>,
+    /// Only write the assembly generation commands as compile database instead of analyzing.
+    pub emit_compile_commands: Option<PathBuf>,
+}
+
+/// Operations that run in order after the data has been parsed once.
+pub enum AnalysisOp {
+    Report(ReportKind),
+    FunctionInfo(String),
+    /// Name of the function whose callers are grouped by object.
+    WhoUses(String),
+    /// Name of the function whose role should be described in sentences.
+    Explain(String),
+    /// Root function and depth of a callee tree.
+    CallTree(String, usize),
+    /// Function and number of its most expensive callers to show.
+    TopCallersOf(String, usize),
+    /// Suffix of the paths of the objects.
+    ObjectInfo(String),
+    OutputJson(PathBuf),
+    OutputAdjacencyMatrix(PathBuf),
+    OutputCallgrind(PathBuf),
+    /// Markdown summary for pull request comments.
+    ReportCard(PathBuf),
+}
+
+pub struct ReportArgs {
+    pub from_json: Option<PathBuf>,
+    pub source: SourceArgs,
+    pub ops: Vec<AnalysisOp>,
+    pub output_format: OutputFormat,
+    pub inlining_costs: crate::InliningCosts,
+    pub ranking: crate::Ranking,
+    pub sort_by: SortBy,
+    /// Order of the functions in per-object output like `--object-info`.
+    pub sort_functions_by: FunctionOrder,
+    /// Call ratio above which functions are reported as dispatchers.
+    pub dispatcher_threshold: f64,
+    /// Hide functions of the standard library and runtime.
+    pub exclude_system_functions: bool,
+    /// Show all functions that are not defined in the analyzed objects as one function.
+    pub omit_library_callees: bool,
+    pub test_functions: TestFunctions,
+    /// Replaces the built-in patterns of test functions if not empty.
+    pub test_patterns: crate::filter::FilterSet,
+    pub max_depth: Option<usize>,
+    /// All-pairs shortest paths need quadratic memory, so they are skipped for larger graphs.
+    pub max_functions_for_apsp: usize,
+    /// Pairs of (function pattern, maximum number of instructions).
+    pub max_instructions_assertions: Vec<(String, usize)>,
+    /// Name of a baseline that no function may grow compared to.
+    pub assert_no_increase_vs: Option<String>,
+    /// TOML file with the layers of the project and which of them may call each other.
+    pub check_layering: Option<PathBuf>,
+    /// Patterns of functions that may not call an allocator, also not indirectly.
+    pub no_alloc_checks: Vec<String>,
+    pub baseline_dir: PathBuf,
+    /// Chosen by the number of functions if not specified.
+    pub adjacency_format: Option<crate::export::AdjacencyFormat>,
+    /// Regular expressions of the functions that are included in the adjacency matrix, all
+    /// functions are included if it is empty.
+    pub filter_regex: crate::filter::FilterSet,
+}
+
+/// Which functions are analyzed depending on whether they belong to tests.
+#[derive(Clone, Copy)]
+pub enum TestFunctions {
+    Include,
+    Exclude,
+    /// E.g. to monitor the size of the test suite.
+    Only,
+}
+
+#[derive(Clone, Copy)]
+pub enum SortBy {
+    Instructions,
+    /// Instructions weighted by their approximate throughput.
+    WeightedInstructions,
+    /// Backward jumps within the function, which usually close a loop.
+    Loops,
+    /// Number of `ret` instructions.
+    ReturnCount,
+}
+
+impl std::str::FromStr for SortBy {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "instructions" => SortBy::Instructions,
+            "weighted-instructions" => SortBy::WeightedInstructions,
+            "loops" => SortBy::Loops,
+            "return-count" => SortBy::ReturnCount,
+            _ => return Err(eyre::eyre!("Unknown sort order: {}", s)),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum FunctionOrder {
+    /// Largest functions first.
+    Size,
+    /// Callees before their callers.
+    CallOrder,
+}
+
+impl std::str::FromStr for FunctionOrder {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "size" => FunctionOrder::Size,
+            "call-order" => FunctionOrder::CallOrder,
+            _ => return Err(eyre::eyre!("Unknown function order: {}", s)),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum OutputFormat {
+    Text,
+    Ndjson,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "text" => OutputFormat::Text,
+            "ndjson" => OutputFormat::Ndjson,
+            _ => return Err(eyre::eyre!("Unknown output format: {}", s)),
+        })
+    }
+}
+
+pub struct DiffArgs {
+    pub inputs: DiffInputs,
+    pub ranking: crate::Ranking,
+    /// Show the changed instructions of this function instead of the instruction counts.
+    pub assembly_for: Option<String>,
+    /// Markdown summary of the difference for pull request comments.
+    pub report_card: Option<PathBuf>,
+    /// Applied to the function names of both versions before they are matched.
+    pub normalization: crate::normalize::NameNormalization,
+}
+
+pub enum DiffInputs {
+    Json {
+        before_json: PathBuf,
+        after_json: PathBuf,
+    },
+    /// Analyzes the same sources twice, with the flag overrides of each config file.
+    Configs {
+        configs: [PathBuf; 2],
+        source: Box<SourceArgs>,
+    },
+}
+
+pub struct BaselineArgs {
+    /// Directory that contains all baselines.
+    pub baseline_dir: PathBuf,
+    pub action: BaselineAction,
+}
+
+pub enum BaselineAction {
+    /// Store the current data under the given name.
+    Save {
+        name: String,
+        from_json: Option<PathBuf>,
+        source: SourceArgs,
+        compression: CompressionArgs,
+    },
+    /// Diff the given baseline against the current data.
+    Compare {
+        name: String,
+        from_json: Option<PathBuf>,
+        source: SourceArgs,
+        ranking: crate::Ranking,
+        /// Also list the functions that did not change.
+        report_unchanged: bool,
+        normalization: crate::normalize::NameNormalization,
+        /// Markdown summary of the difference for pull request comments.
+        report_card: Option<PathBuf>,
+    },
+    List,
+    Delete(String),
+}
+
+pub struct StatsArgs {
+    pub source: SourceArgs,
+    /// Compile one file to estimate the analysis time instead of using a fixed rate.
+    pub estimate_time: bool,
+    /// Group the objects by their compiler flags to find misconfigured ones.
+    pub report_flag_groups: bool,
+}
+
+pub struct QueryArgs {
+    pub from_json: Option<PathBuf>,
+    pub source: SourceArgs,
+    pub function: Option<String>,
+    /// Suffix of the path of the objects to look up.
+    pub object_info: Option<String>,
+    pub sort_functions_by: FunctionOrder,
+    pub who_uses: Option<String>,
+    pub explain: Option<String>,
+    /// Root function and depth of a callee tree.
+    pub call_tree: Option<(String, usize)>,
+    /// Function and number of its most expensive callers to show.
+    pub top_callers_of: Option<(String, usize)>,
+    /// Start and end of a call chain that should be drawn as sequence diagram.
+    pub plantuml_chain: Option<(String, String)>,
+    /// Maximum number of calls that transitive analyses follow. `None` means unlimited.
+    pub max_depth: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+pub enum ReportKind {
+    TopFunctions,
+    CallGraph,
+    Objects,
+    Histogram,
+    InAllObjects,
+    ImmediateHeavy,
+    Recursive,
+    InliningAnalysis,
+    Topological,
+    ExceptionHeavy,
+    ObjectShares,
+    StringHeavy,
+    NamespaceSummary,
+    CallbackFunctions,
+    CalleeSaves,
+    WindowsAbi,
+    TlsUsers,
+    LtoDiscarded,
+    InlinedFunctions,
+    ReturnPaths,
+    Dispatchers,
+    InlineMemoryOps,
+    Deoptimized,
+    CodeOwnership,
+    DeprecatedCallers,
+    EmptyFunctions,
+    HotCallPairs,
+    GraphStats,
+    Alignment,
+}
+
+const REPORT_KINDS: &str = "  top-functions    Functions sorted by their instruction count.
+  call-graph       All callees of every function.
+  objects          Number of functions and instructions per object.
+  histogram        Distribution of function sizes.
+  in-all-objects   Functions that are defined in every object.
+  immediate-heavy  Functions where most instructions have an immediate operand.
+  recursive        Functions that call themselves, including tail recursion.
+  inlining-analysis Functions that are likely worth inlining or marking noinline.
+  topological      All functions ordered so that callees come before their callers.
+  eh-functions     Functions that reference a C++ exception personality routine.
+  object-shares    Share of every object in the total instruction count.
+  string-heavy     Functions sorted by the number of string literals they use.
+  namespace-summary Instructions per C++ namespace of the demangled function names.
+  callback-functions Uncalled global functions, split by whether their address is stored in tables.
+  callee-saves     Functions sorted by the number of callee-saved registers they save.
+  windows-abi      Functions using the Windows x64 calling convention and their callers.
+  tls-users        Functions that access thread-local storage.
+  lto-discarded    Functions that LTO discarded or internalized, according to GCC's directives.
+  inlined-functions Declared functions that are never defined, and callers whose copies differ
+                   by about the size of a callee.
+  return-paths     Functions with more than 3 `ret` instructions, and functions without `ret`
+                   that still return through tail calls.
+  dispatchers      Functions with many calls compared to their instructions (default ratio: 5).
+  inline-memory-ops Functions with inline expansions of memcpy and memset, i.e. runs of moves
+                   and `rep movs`/`rep stos`.
+  deoptimized      Functions that look like they were compiled without optimizations.
+  code-ownership   Share of the instructions per last git author of the functions' source files.
+  deprecated-callers Functions that call functions with a `.gnu.warning` link warning.
+  empty-functions  Functions that are defined without any instructions.
+  hot-call-pairs   Callers that call the same function from multiple call sites (default: top 20).
+  graph-stats      Size, average shortest call path length and diameter of the call graph.
+  alignment        Functions sorted by the worst case padding that their alignment inserts.";
+
+impl std::str::FromStr for ReportKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "top-functions" => ReportKind::TopFunctions,
+            "call-graph" => ReportKind::CallGraph,
+            "objects" => ReportKind::Objects,
+            "histogram" => ReportKind::Histogram,
+            "in-all-objects" => ReportKind::InAllObjects,
+            "immediate-heavy" => ReportKind::ImmediateHeavy,
+            "recursive" => ReportKind::Recursive,
+            "inlining-analysis" => ReportKind::InliningAnalysis,
+            "topological" => ReportKind::Topological,
+            "eh-functions" => ReportKind::ExceptionHeavy,
+            "object-shares" => ReportKind::ObjectShares,
+            "string-heavy" => ReportKind::StringHeavy,
+            "namespace-summary" => ReportKind::NamespaceSummary,
+            "callback-functions" => ReportKind::CallbackFunctions,
+            "callee-saves" => ReportKind::CalleeSaves,
+            "windows-abi" => ReportKind::WindowsAbi,
+            "tls-users" => ReportKind::TlsUsers,
+            "lto-discarded" => ReportKind::LtoDiscarded,
+            "inlined-functions" => ReportKind::InlinedFunctions,
+            "return-paths" => ReportKind::ReturnPaths,
+            "dispatchers" => ReportKind::Dispatchers,
+            "inline-memory-ops" => ReportKind::InlineMemoryOps,
+            "deoptimized" => ReportKind::Deoptimized,
+            "code-ownership" => ReportKind::CodeOwnership,
+            "deprecated-callers" => ReportKind::DeprecatedCallers,
+            "empty-functions" => ReportKind::EmptyFunctions,
+            "hot-call-pairs" => ReportKind::HotCallPairs,
+            "graph-stats" => ReportKind::GraphStats,
+            "alignment" => ReportKind::Alignment,
+            _ => return Err(eyre::eyre!("Unknown report kind: {}", s)),
+        })
+    }
+}
+
+impl ReportKind {
+    /// Whether the report is a list of records that can be written in any output format.
+    pub fn supports_records(&self) -> bool {
+        matches!(
+            self,
+            ReportKind::TopFunctions | ReportKind::CallGraph | ReportKind::Objects
+        )
+    }
+}
+
+struct ArgStream {
+    args: std::vec::IntoIter<String>,
+}
+
+impl ArgStream {
+    fn next(&mut self) -> Option<String> {
+        self.args.next()
+    }
+
+    fn value(&mut self, flag: &str) -> Result<String> {
+        self.args
+            .next()
+            .ok_or(eyre::eyre!("Missing value for {}.", flag))
+    }
+
+    /// Consumes the next argument only if it can be parsed, for optional values.
+    fn optional_value<T: std::str::FromStr>(&mut self) -> Option<T> {
+        let value = self.args.as_slice().first()?.parse().ok()?;
+        self.args.next();
+        Some(value)
+    }
+}
+
+/// Depth of `--call-tree` when none is given.
+const DEFAULT_CALL_TREE_DEPTH: usize = 5;
+/// Number of callers of `--top-callers-of` when none is given.
+const DEFAULT_TOP_CALLERS_NUM: usize = 10;
+/// Call ratio above which `--find-dispatchers` reports a function when none is given.
+const DEFAULT_DISPATCHER_THRESHOLD: f64 = 5.0;
+
+impl SourceArgs {
+    fn new() -> Self {
+        SourceArgs {
+            compile_commands: PathBuf::from("compile_commands.json"),
+            objects: vec![],
+            rust_rlib: None,
+            llvm_dis: PathBuf::from("llvm-dis"),
+            bazel_aquery: None,
+            bazel_execution_root: PathBuf::from("."),
+            makefile_commands: None,
+            extra_flags: vec![],
+            removed_flags: vec![],
+            replaced_flags: vec![],
+            parse_options: crate::ParseOptions::default(),
+            slowest_compilations: None,
+            binary: None,
+            wat: None,
+            objdump: PathBuf::from("objdump"),
+            verbose_errors: false,
+            pipe_mode: false,
+            assembly_dir: None,
+            strip_debug_flags: false,
+            memory_limit_mb: None,
+            shard_index: 0,
+            shard_count: 1,
+            sample: None,
+            seed: None,
+            max_object_files: Some(100),
+            changed_files: None,
+        }
+    }
+
+    /// Returns false if the flag is not a source option.
+    fn parse_flag(&mut self, flag: &str, args: &mut ArgStream) -> Result<bool> {
+        match flag {
+            "--compile-commands" => self.compile_commands = args.value(flag)?.into(),
+            "--object" => self.objects.push(args.value(flag)?),
+            "--rust-rlib" | "--rust-rmeta" => self.rust_rlib = Some(args.value(flag)?.into()),
+            "--llvm-dis" => self.llvm_dis = args.value(flag)?.into(),
+            "--bazel-aquery" => self.bazel_aquery = Some(args.value(flag)?.into()),
+            "--bazel-execroot" => self.bazel_execution_root = args.value(flag)?.into(),
+            "--makefile-commands" => self.makefile_commands = Some(args.value(flag)?.into()),
+            "--extra-flag" | "--add-flag" => self.extra_flags.push(args.value(flag)?),
+            "--remove-flag" => self.removed_flags.push(args.value(flag)?),
+            "--replace-flag" => {
+                let old = args.value(flag)?;
+                let new = args.value(flag)?;
+                self.replaced_flags.push((old, new));
+            }
+            "--exclude-ifdefs" => self.parse_options.exclude_conditional_blocks = true,
+            "--keep-raw-assembly" => self.parse_options.keep_raw_assembly = true,
+            "--validate-assembly" => self.parse_options.validate_assembly = true,
+            "--analyze-binary" => self.binary = Some(args.value(flag)?.into()),
+            "--analyze-wat" => self.wat = Some(args.value(flag)?.into()),
+            "--objdump" => self.objdump = args.value(flag)?.into(),
+            "--verbose-errors" => self.verbose_errors = true,
+            "--pipe-mode" => self.pipe_mode = true,
+            "--assembly-dir" => self.assembly_dir = Some(args.value(flag)?.into()),
+            "--strip-debug-flags" => self.strip_debug_flags = true,
+            "--memory-limit" => self.memory_limit_mb = Some(args.value(flag)?.parse()?),
+            "--shard-index" => self.shard_index = args.value(flag)?.parse()?,
+            "--shard-count" => self.shard_count = args.value(flag)?.parse()?,
+            "--sample" => self.sample = Some(args.value(flag)?.parse()?),
+            "--seed" => self.seed = Some(args.value(flag)?.parse()?),
+            "--max-object-files" => {
+                self.max_object_files = match args.value(flag)?.as_str() {
+                    "unlimited" => None,
+                    value => Some(value.parse()?),
+                }
+            }
+            "--all-objects" => self.max_object_files = None,
+            "--only-changed-files" => {
+                self.changed_files = Some(crate::changed_files::ChangedFiles::DiffOutput(
+                    args.value(flag)?.into(),
+                ))
+            }
+            "--git-diff" => {
+                self.changed_files = Some(crate::changed_files::ChangedFiles::LastCommit)
+            }
+            "--slowest-compilations" => {
+                self.slowest_compilations = Some(args.value(flag)?.parse()?)
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl crate::Ranking {
+    /// Returns false if the flag is not a ranking option.
+    fn parse_flag(&mut self, flag: &str, args: &mut ArgStream) -> Result<bool> {
+        match flag {
+            "--top" => self.top = Some(args.value(flag)?.parse()?),
+            "--bottom" => self.bottom = Some(args.value(flag)?.parse()?),
+            "--min-instructions" => self.min_instructions = args.value(flag)?.parse()?,
+            _ => return Ok(false),
+        }
+        if self.top.is_some() && self.bottom.is_some() {
+            return Err(eyre::eyre!("--top and --bottom can't be combined."));
+        }
+        Ok(true)
+    }
+}
+
+fn is_help_flag(arg: &str) -> bool {
+    arg == "-h" || arg == "--help"
+}
+
+fn unexpected_argument(arg: &str) -> eyre::Report {
+    eyre::eyre!("Unexpected argument: {}", arg)
+}
+
+pub fn parse_args(args: impl Iterator<Item = String>) -> Result<Subcommand> {
+    let mut args = ArgStream {
+        args: args.collect::<Vec<_>>().into_iter(),
+    };
+    let mut display_options = crate::display::DisplayOptions::default();
+    let mut command = args.next();
+    while let Some(flag) = command.as_deref().filter(|arg| arg.starts_with('-')) {
+        match flag {
+            "--show-full-paths" | "-v" => display_options.show_full_paths = true,
+            "--format-function-names" => match args.value(flag)?.as_str() {
+                "demangled" => {
+                    display_options.demangle = true;
+                    display_options.max_length = None;
+                }
+                "mangled" => display_options.demangle = false,
+                "short" => {
+                    display_options.demangle = true;
+                    display_options.max_length = Some(crate::display::SHORT_NAME_LENGTH);
+                }
+                format => {
+                    return Err(eyre::eyre!(
+                        "Unknown function name format: {}. Expected mangled, demangled or \
+                         short.",
+                        format
+                    ))
+                }
+            },
+            "--strip-prefix" => display_options.strip_prefixes.push(args.value(flag)?),
+            _ if is_help_flag(flag) => return Ok(Subcommand::Help(USAGE.to_owned())),
+            _ => return Err(unexpected_argument(flag)),
+        }
+        command = args.next();
+    }
+    crate::display::set_display_options(display_options);
+    let Some(command) = command else {
+        return Ok(Subcommand::Help(USAGE.to_owned()));
+    };
+    match command.as_str() {
+        "analyze" => parse_analyze_args(&mut args),
+        "report" => parse_report_args(&mut args),
+        "diff" => parse_diff_args(&mut args),
+        "query" => parse_query_args(&mut args),
+        "baseline" => parse_baseline_args(&mut args),
+        "stats" => parse_stats_args(&mut args),
+        "merge-shards" => parse_merge_shards_args(&mut args),
+        "compare-optimization" => parse_compare_optimization_args(&mut args),
+        _ if is_help_flag(&command) => Ok(Subcommand::Help(USAGE.to_owned())),
+        _ => Err(eyre::eyre!("Unknown command: {}\n\n{}", command, USAGE)),
+    }
+}
+
+fn parse_analyze_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut source = SourceArgs::new();
+    let mut output_json = PathBuf::from("assembly_analysis.json");
+    let mut watch = false;
+    let mut deterministic_output = false;
+    let mut split_by_arch = false;
+    let mut metrics_port = None;
+    let mut emit_compile_commands = None;
+    let mut compression = CompressionArgs::default();
+    while let Some(arg) = args.next() {
+        if source.parse_flag(&arg, args)? || compression.parse_flag(&arg, args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--output-json" => output_json = args.value(&arg)?.into(),
+            "--watch" => watch = true,
+            "--deterministic-output" => deterministic_output = true,
+            "--split-by-arch" => split_by_arch = true,
+            "--output-metrics-prometheus" => metrics_port = Some(args.value(&arg)?.parse()?),
+            "--emit-compile-commands-for-assembly" => {
+                emit_compile_commands = Some(args.value(&arg)?.into())
+            }
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(format!(
+                    "Usage: assembly_analysis analyze [options]\n\nOptions:\n{}\n  \
+                     --output-json <path>       Where to write the parsed data \
+                     (default: ./assembly_analysis.json).\n  \
+                     --watch                    Keep running and re-parse objects whose \
+                     source file changed.\n  \
+                     --deterministic-output     Sort all keys in the written JSON.\n  \
+                     --split-by-arch            Analyze every target architecture separately and \
+                     write one JSON file per architecture, e.g. assembly_analysis.aarch64.json.\n  \
+                     --output-metrics-prometheus <port>  Serve the instruction counts at \
+                     /metrics for Prometheus,\n                             updated on every \
+                     rebuild with --watch. Requires the prometheus feature.\n  \
+                     --emit-compile-commands-for-assembly <path>  Write the commands that \
+                     generate the assembly\n                             as \
+                     compile_commands.json instead of analyzing, to run them \
+                     separately.\n                             Their output is read with \
+                     --assembly-dir, which is required.\n\
+                     {}\n  \
+                     Without --compress-output, files ending with .zst or .lz4 are \
+                     compressed.",
+                    SOURCE_OPTIONS, COMPRESSION_OPTIONS
+                )))
+            }
+            _ => return Err(unexpected_argument(&arg)),
+        }
+    }
+    Ok(Subcommand::Analyze(AnalyzeArgs {
+        source,
+        output_json,
+        compression,
+        watch,
+        deterministic_output,
+        split_by_arch,
+        metrics_port,
+        emit_compile_commands,
+    }))
+}
+
+fn parse_report_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut from_json = None;
+    let mut source = SourceArgs::new();
+    let mut ops = vec![];
+    let mut max_depth = Some(50);
+    let mut max_functions_for_apsp = 500;
+    let mut output_format = OutputFormat::Text;
+    let mut inlining_costs = crate::InliningCosts::default();
+    let mut ranking = crate::Ranking::default();
+    let mut sort_by = SortBy::Instructions;
+    let mut sort_functions_by = FunctionOrder::Size;
+    let mut dispatcher_threshold = DEFAULT_DISPATCHER_THRESHOLD;
+    let mut exclude_system_functions = true;
+    let mut omit_library_callees = false;
+    let mut test_functions = TestFunctions::Include;
+    let mut test_patterns = crate::filter::FilterSet::default();
+    let mut max_instructions_assertions = vec![];
+    let mut assert_no_increase_vs = None;
+    let mut check_layering = None;
+    let mut no_alloc_checks = vec![];
+    let mut baseline_dir = PathBuf::from(DEFAULT_BASELINE_DIR);
+    let mut adjacency_format = None;
+    let mut filter_regex = crate::filter::FilterSet::default();
+    while let Some(arg) = args.next() {
+        if source.parse_flag(&arg, args)? || ranking.parse_flag(&arg, args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--sort-by" => sort_by = args.value(&arg)?.parse()?,
+            "--sort-functions-by" => sort_functions_by = args.value(&arg)?.parse()?,
+            "--function-info" => ops.push(AnalysisOp::FunctionInfo(args.value(&arg)?)),
+            "--object-info" => ops.push(AnalysisOp::ObjectInfo(args.value(&arg)?)),
+            "--who-uses" => ops.push(AnalysisOp::WhoUses(args.value(&arg)?)),
+            "--explain" => ops.push(AnalysisOp::Explain(args.value(&arg)?)),
+            "--call-tree" => {
+                let function = args.value(&arg)?;
+                let depth = args.optional_value().unwrap_or(DEFAULT_CALL_TREE_DEPTH);
+                ops.push(AnalysisOp::CallTree(function, depth));
+            }
+            "--top-callers-of" => {
+                let function = args.value(&arg)?;
+                let n = args.optional_value().unwrap_or(DEFAULT_TOP_CALLERS_NUM);
+                ops.push(AnalysisOp::TopCallersOf(function, n));
+            }
+            "--output-json" => ops.push(AnalysisOp::OutputJson(args.value(&arg)?.into())),
+            "--output-adjacency-matrix" => {
+                ops.push(AnalysisOp::OutputAdjacencyMatrix(args.value(&arg)?.into()))
+            }
+            "--generate-report-card" => ops.push(AnalysisOp::ReportCard(args.value(&arg)?.into())),
+            "--output-callgrind" => ops.push(AnalysisOp::OutputCallgrind(args.value(&arg)?.into())),
+            "--adjacency-format" => adjacency_format = Some(args.value(&arg)?.parse()?),
+            "--filter-regex" => filter_regex.add_regex(&args.value(&arg)?)?,
+            "--max-depth" => max_depth = Some(args.value(&arg)?.parse()?),
+            "--no-depth-limit" => max_depth = None,
+            "--max-functions-for-apsp" => max_functions_for_apsp = args.value(&arg)?.parse()?,
+            "--exclude-system-functions" => exclude_system_functions = true,
+            "--include-system-functions" => exclude_system_functions = false,
+            "--omit-library-callees" => omit_library_callees = true,
+            "--exclude-test-functions" => test_functions = TestFunctions::Exclude,
+            "--only-test-functions" => test_functions = TestFunctions::Only,
+            "--test-pattern" => test_patterns.add_regex(&args.value(&arg)?)?,
+            "--from-json" => from_json = Some(args.value(&arg)?.into()),
+            "--output-format" => output_format = args.value(&arg)?.parse()?,
+            "--ndjson" => output_format = OutputFormat::Ndjson,
+            "--assert-max-instructions" => {
+                let pattern = args.value(&arg)?;
+                let limit = args.value(&arg)?.parse()?;
+                max_instructions_assertions.push((pattern, limit));
+            }
+            "--assert-no-increase-vs" => assert_no_increase_vs = Some(args.value(&arg)?),
+            "--check-layering" => check_layering = Some(args.value(&arg)?.into()),
+            "--check-no-alloc" => no_alloc_checks.push(args.value(&arg)?),
+            "--baseline-dir" => baseline_dir = args.value(&arg)?.into(),
+            "--find-tls-users" => ops.push(AnalysisOp::Report(ReportKind::TlsUsers)),
+            "--reduce-alignment" => ops.push(AnalysisOp::Report(ReportKind::Alignment)),
+            "--find-lto-discarded" => ops.push(AnalysisOp::Report(ReportKind::LtoDiscarded)),
+            "--find-inlined" => ops.push(AnalysisOp::Report(ReportKind::InlinedFunctions)),
+            "--find-multiple-returns" => ops.push(AnalysisOp::Report(ReportKind::ReturnPaths)),
+            "--find-dispatchers" => {
+                if let Some(threshold) = args.optional_value() {
+                    dispatcher_threshold = threshold;
+                }
+                ops.push(AnalysisOp::Report(ReportKind::Dispatchers));
+            }
+            "--find-inline-memcpy" => ops.push(AnalysisOp::Report(ReportKind::InlineMemoryOps)),
+            "--find-deoptimized" => ops.push(AnalysisOp::Report(ReportKind::Deoptimized)),
+            "--git-ownership" => ops.push(AnalysisOp::Report(ReportKind::CodeOwnership)),
+            "--find-deprecated-callers" => {
+                ops.push(AnalysisOp::Report(ReportKind::DeprecatedCallers))
+            }
+            "--call-overhead-savings" => {
+                inlining_costs.call_overhead_savings = args.value(&arg)?.parse()?
+            }
+            "--code-size-penalty" => {
+                inlining_costs.code_size_penalty = args.value(&arg)?.parse()?
+            }
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(format!(
+                    "Usage: assembly_analysis report [options] <kind>...\n\n\
+                     Multiple kinds and operations can be given, they run in order after the \
+                     data is parsed once.\n\nKinds:\n{}\n\n\
+                     Operations:\n  --function-info <name>     Show callers, callees and \
+                     objects of a function.\n  \
+                     --object-info <suffix>     Show information about the objects whose path \
+                     ends with the suffix.\n  \
+                     --who-uses <name>          Show the objects that call a function.\n  \
+                     --explain <name>           Describe the role of a function in sentences.\n  \
+                     --call-tree <name> [depth] Print the callees of a function as tree \
+                     (default depth: 5).\n  \
+                     --top-callers-of <name> [n]  Show the n callers with the most \
+                     instructions, where\n                             inlining the function \
+                     has the biggest effect (default: 10).\n  \
+                     --output-json <path>       Write the parsed data.\n  \
+                     --output-adjacency-matrix <path>  Write the call graph as adjacency \
+                     matrix.\n  \
+                     --adjacency-format <format>  `dense` or `sparse` (default: sparse for \
+                     more than 1000 functions).\n  \
+                     --filter-regex <regex>     Only include the functions whose mangled or \
+                     demangled name matches\n                             the regex in the \
+                     adjacency matrix. Can be repeated.\n  \
+                     --output-callgrind <path>  Write the call graph in the Callgrind format \
+                     for KCachegrind, with\n                             instruction counts \
+                     as cost.\n  \
+                     --generate-report-card <path>  Write a Markdown summary for pull request \
+                     comments.\n  \
+                     --find-tls-users           Shorthand for the tls-users kind.\n  \
+                     --reduce-alignment         Shorthand for the alignment kind, which \
+                     suggests\n                             -falign-functions=1 when much \
+                     space is spent on padding.\n  \
+                     --find-lto-discarded       Shorthand for the lto-discarded kind.\n  \
+                     --find-inlined             Shorthand for the inlined-functions kind.\n  \
+                     --find-multiple-returns    Shorthand for the return-paths kind.\n  \
+                     --find-dispatchers [ratio] Shorthand for the dispatchers kind, optionally \
+                     with another ratio.\n  \
+                     --find-inline-memcpy       Shorthand for the inline-memory-ops kind.\n  \
+                     --find-deoptimized         Shorthand for the deoptimized kind.\n  \
+                     --git-ownership            Shorthand for the code-ownership kind. \
+                     Compile with -g to\n                             attribute inline \
+                     functions to their headers.\n  \
+                     --find-deprecated-callers  Shorthand for the deprecated-callers kind.\n\n\
+                     Assertions, which exit with an error if any of them fails:\n  \
+                     --assert-max-instructions <pattern> <n>  Functions matching the pattern \
+                     may have at most n\n                             instructions. Can be \
+                     repeated.\n  \
+                     --assert-no-increase-vs <name>  No function may have more instructions \
+                     than in the baseline.\n  \
+                     --check-layering <path>    Objects may only call objects of the layers \
+                     they are allowed to,\n                             as defined by \
+                     `[[layer]]` tables with `name`, `object_patterns`\n                             \
+                     and `allowed_to_call`.\n  \
+                     --check-no-alloc <pattern> Functions matching the pattern may not call \
+                     malloc, operator new\n                             etc., also not \
+                     indirectly. Can be repeated.\n  \
+                     --baseline-dir <path>      Where baselines are stored \
+                     (default: ./.assembly_analysis_baselines).\n\n\
+                     Options:\n  --from-json <path>         Use previously written \
+                     parsed data instead of compiling.\n  \
+                     --output-format <format>   `text` (default) or `ndjson` with one JSON \
+                     object per line.\n                             Supported by \
+                     top-functions, call-graph and objects.\n  \
+                     --ndjson                   Shorthand for `--output-format ndjson`.\n  \
+                     --call-overhead-savings <n>  Instructions saved per inlined call \
+                     (default: 5).\n  \
+                     --code-size-penalty <x>    Cost per duplicated instruction (default: 1).\n  \
+                     --sort-by <order>          Order of top-functions: `instructions` \
+                     (default),\n                             `weighted-instructions` \
+                     which approximates cycles, `loops`\n                             or \
+                     `return-count`.\n  \
+                     --sort-functions-by <order>  Order of the functions of --object-info: \
+                     `size` (default)\n                             shows the largest, \
+                     `call-order` all functions with callees before callers.\n  \
+                     --include-system-functions Also show functions of the standard library \
+                     and runtime.\n  \
+                     --exclude-system-functions Hide them (default).\n  \
+                     --omit-library-callees     Collapse all functions that are not defined \
+                     in the analyzed\n                             objects into a single \
+                     `[external]` function.\n  \
+                     --exclude-test-functions   Hide test cases, fixtures and benchmarks, \
+                     e.g. `test_*`, `*Test`,\n                             `TestBody` of \
+                     googletest and `tests::` of Rust.\n  \
+                     --only-test-functions      Only show these functions.\n  \
+                     --test-pattern <regex>     Use this instead of the built-in patterns of \
+                     test functions.\n                             Can be repeated.\n  \
+                     --max-depth <n>            Maximum number of calls followed by transitive \
+                     analyses (default: 50).\n  \
+                     --no-depth-limit           Follow calls without limit.\n  \
+                     --max-functions-for-apsp <n>  Skip the shortest path statistics of \
+                     graph-stats for larger\n                             graphs \
+                     (default: 500).\n{}\n{}",
+                    REPORT_KINDS, RANKING_OPTIONS, SOURCE_OPTIONS
+                )))
+            }
+            _ if !arg.starts_with('-') => ops.push(AnalysisOp::Report(arg.parse()?)),
+            _ => return Err(unexpected_argument(&arg)),
+        }
+    }
+    let has_assertions = !max_instructions_assertions.is_empty()
+        || assert_no_increase_vs.is_some()
+        || check_layering.is_some()
+        || !no_alloc_checks.is_empty();
+    if ops.is_empty() && !has_assertions {
+        return Err(eyre::eyre!(
+            "Missing report kind.\n\nKinds:\n{}",
+            REPORT_KINDS
+        ));
+    }
+    if matches!(output_format, OutputFormat::Ndjson) {
+        let supports_records = ops.iter().all(|op| match op {
+            AnalysisOp::Report(kind) => kind.supports_records(),
+            _ => false,
+        });
+        if !supports_records {
+            return Err(eyre::eyre!(
+                "ndjson output is only supported by the report kinds top-functions, \
+                 call-graph and objects."
+            ));
+        }
+    }
+    Ok(Subcommand::Report(ReportArgs {
+        from_json,
+        source,
+        output_format,
+        inlining_costs,
+        ranking,
+        sort_by,
+        sort_functions_by,
+        dispatcher_threshold,
+        exclude_system_functions,
+        omit_library_callees,
+        test_functions,
+        test_patterns,
+        max_depth,
+        max_functions_for_apsp,
+        ops,
+        max_instructions_assertions,
+        assert_no_increase_vs,
+        check_layering,
+        no_alloc_checks,
+        baseline_dir,
+        adjacency_format,
+        filter_regex,
+    }))
+}
+
+fn parse_diff_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut paths = vec![];
+    let mut configs = None;
+    let mut assembly_for = None;
+    let mut report_card = None;
+    let mut source = SourceArgs::new();
+    let mut ranking = crate::Ranking::default();
+    let mut normalization = crate::normalize::NameNormalization::default();
+    while let Some(arg) = args.next() {
+        if source.parse_flag(&arg, args)? || ranking.parse_flag(&arg, args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--function-regex-replace" => {
+                let pattern = args.value(&arg)?;
+                let replacement = args.value(&arg)?;
+                normalization.add(&pattern, &replacement)?;
+            }
+            "--compare-configs" => {
+                let a = args.value(&arg)?.into();
+                let b = args.value(&arg)?.into();
+                configs = Some([a, b]);
+            }
+            "--diff-assembly-for" => assembly_for = Some(args.value(&arg)?),
+            "--generate-report-card" => report_card = Some(args.value(&arg)?.into()),
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(format!(
+                    "Usage: assembly_analysis diff [options] <before.json> <after.json>\n       \
+                     assembly_analysis diff [options] --compare-configs <a.toml> <b.toml>\n\n\
+                     With --compare-configs, the sources are analyzed once per config. A config \
+                     file contains\n`extra_flags = [\"-flto\"]` and \
+                     `replace_flags = [[\"-O2\", \"-O3\"]]` arrays which are applied\non top of \
+                     the flags given on the command line. Its `function_regex_replace` pairs \
+                     are\nadded to the ones of --function-regex-replace.\n\n\
+                     Options:\n  \
+                     {}\n  \
+                     --diff-assembly-for <name> Show the changed instructions of a function \
+                     as unified diff.\n                             Requires data that was \
+                     analyzed with --keep-raw-assembly.\n  \
+                     --generate-report-card <path>  Write a Markdown summary of the difference \
+                     for pull request\n                             comments.\n{}\n{}",
+                    FUNCTION_REGEX_REPLACE_OPTION, RANKING_OPTIONS, SOURCE_OPTIONS
+                )))
+            }
+            _ if paths.len() < 2 && !arg.starts_with('-') => paths.push(PathBuf::from(arg)),
+            _ => return Err(unexpected_argument(&arg)),
+        }
+    }
+    let inputs = match configs {
+        Some(configs) => {
+            if !paths.is_empty() {
+                return Err(eyre::eyre!(
+                    "JSON files can't be compared together with --compare-configs."
+                ));
+            }
+            // The instructions can only be compared if they are stored during the analysis.
+            if assembly_for.is_some() {
+                source.parse_options.keep_raw_assembly = true;
+            }
+            DiffInputs::Configs {
+                configs,
+                source: Box::new(source),
+            }
+        }
+        None => {
+            let [before_json, after_json]: [PathBuf; 2] = paths
+                .try_into()
+                .map_err(|_| eyre::eyre!("Expected two JSON files to compare."))?;
+            DiffInputs::Json {
+                before_json,
+                after_json,
+            }
+        }
+    };
+    Ok(Subcommand::Diff(DiffArgs {
+        inputs,
+        ranking,
+        assembly_for,
+        report_card,
+        normalization,
+    }))
+}
+
+fn parse_query_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut from_json = None;
+    let mut source = SourceArgs::new();
+    let mut function = None;
+    let mut object_info = None;
+    let mut sort_functions_by = FunctionOrder::Size;
+    let mut who_uses = None;
+    let mut explain = None;
+    let mut call_tree = None;
+    let mut top_callers_of = None;
+    let mut chain_from = None;
+    let mut chain_to = None;
+    let mut max_depth = Some(50);
+    while let Some(arg) = args.next() {
+        if source.parse_flag(&arg, args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--from-json" => from_json = Some(args.value(&arg)?.into()),
+            "--function" => function = Some(args.value(&arg)?),
+            "--object-info" => object_info = Some(args.value(&arg)?),
+            "--sort-functions-by" => sort_functions_by = args.value(&arg)?.parse()?,
+            "--who-uses" => who_uses = Some(args.value(&arg)?),
+            "--explain" => explain = Some(args.value(&arg)?),
+            "--call-tree" => {
+                let function = args.value(&arg)?;
+                let depth = args.optional_value().unwrap_or(DEFAULT_CALL_TREE_DEPTH);
+                call_tree = Some((function, depth));
+            }
+            "--top-callers-of" => {
+                let function = args.value(&arg)?;
+                let n = args.optional_value().unwrap_or(DEFAULT_TOP_CALLERS_NUM);
+                top_callers_of = Some((function, n));
+            }
+            "--plantuml-chain-from" => chain_from = Some(args.value(&arg)?),
+            "--plantuml-chain-to" => chain_to = Some(args.value(&arg)?),
+            "--max-depth" => max_depth = Some(args.value(&arg)?.parse()?),
+            "--no-depth-limit" => max_depth = None,
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(format!(
+                    "Usage: assembly_analysis query [options] --function <name>\n\n\
+                     Options:\n  --function <name>          Name of the function to \
+                     look up.\n  \
+                     --object-info <suffix>     Show information about the objects whose path \
+                     ends with the suffix.\n  \
+                     --sort-functions-by <order> Order of the functions of --object-info: \
+                     `size` (default)\n                             shows the largest, \
+                     `call-order` all functions with callees before callers.\n  \
+                     --who-uses <name>          Show the objects that call a function.\n  \
+                     --explain <name>           Describe the role of a function in sentences.\n  \
+                     --call-tree <name> [depth] Print the callees of a function as tree \
+                     (default depth: 5).\n  \
+                     --top-callers-of <name> [n]  Show the n callers with the most \
+                     instructions, where\n                             inlining the function \
+                     has the biggest effect (default: 10).\n  \
+                     --plantuml-chain-from <name>  Print a PlantUML sequence diagram of the \
+                     shortest\n  \
+                     --plantuml-chain-to <name>    call chain between two functions.\n  \
+                     --max-depth <n>            Maximum number of calls followed by transitive \
+                     analyses (default: 50).\n  \
+                     --no-depth-limit           Follow calls without limit, which can be slow \
+                     on large call graphs.\n  \
+                     --from-json <path>         Use previously written \
+                     parsed data instead of compiling.\n{}",
+                    SOURCE_OPTIONS
+                )))
+            }
+            _ => return Err(unexpected_argument(&arg)),
+        }
+    }
+    let plantuml_chain = match (chain_from, chain_to) {
+        (Some(from), Some(to)) => Some((from, to)),
+        (None, None) => None,
+        _ => {
+            return Err(eyre::eyre!(
+                "--plantuml-chain-from and --plantuml-chain-to have to be used together."
+            ))
+        }
+    };
+    if function.is_none()
+        && object_info.is_none()
+        && who_uses.is_none()
+        && explain.is_none()
+        && call_tree.is_none()
+        && top_callers_of.is_none()
+        && plantuml_chain.is_none()
+    {
+        return Err(eyre::eyre!(
+            "Missing --function, --object-info, --who-uses, --explain, --call-tree, \
+             --top-callers-of or --plantuml-chain-from/--plantuml-chain-to."
+        ));
+    }
+    Ok(Subcommand::Query(QueryArgs {
+        from_json,
+        source,
+        function,
+        plantuml_chain,
+        max_depth,
+        object_info,
+        sort_functions_by,
+        who_uses,
+        explain,
+        call_tree,
+        top_callers_of,
+    }))
+}
+
+fn parse_baseline_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut baseline_dir = PathBuf::from(DEFAULT_BASELINE_DIR);
+    let mut from_json = None;
+    let mut source = SourceArgs::new();
+    let mut ranking = crate::Ranking::default();
+    let mut save = None;
+    let mut compare = None;
+    let mut list = false;
+    let mut delete = None;
+    let mut report_unchanged = false;
+    let mut report_card = None;
+    let mut compression = CompressionArgs::default();
+    let mut normalization = crate::normalize::NameNormalization::default();
+    while let Some(arg) = args.next() {
+        if source.parse_flag(&arg, args)?
+            || ranking.parse_flag(&arg, args)?
+            || compression.parse_flag(&arg, args)?
+        {
+            continue;
+        }
+        match arg.as_str() {
+            "--baseline-dir" => baseline_dir = args.value(&arg)?.into(),
+            "--from-json" => from_json = Some(args.value(&arg)?.into()),
+            "--save" => save = Some(args.value(&arg)?),
+            "--compare" => compare = Some(args.value(&arg)?),
+            "--list-baselines" => list = true,
+            "--delete-baseline" => delete = Some(args.value(&arg)?),
+            "--report-unchanged" => report_unchanged = true,
+            "--function-regex-replace" => {
+                let pattern = args.value(&arg)?;
+                let replacement = args.value(&arg)?;
+                normalization.add(&pattern, &replacement)?;
+            }
+            "--generate-report-card" => report_card = Some(args.value(&arg)?.into()),
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(format!(
+                    "Usage: assembly_analysis baseline [options] <action>\n\n\
+                     Actions:\n  \
+                     --save <name>              Analyze and store the data as baseline.\n  \
+                     --compare <name>           Analyze and print the difference to the \
+                     baseline.\n  \
+                     --list-baselines           Print all baselines with their metadata.\n  \
+                     --delete-baseline <name>   Remove a baseline.\n\n\
+                     Options:\n  \
+                     --baseline-dir <path>      Where baselines are stored \
+                     (default: ./.assembly_analysis_baselines).\n  \
+                     --from-json <path>         Use previously written \
+                     parsed data instead of compiling.\n  \
+                     --report-unchanged         Also list the unchanged functions with \
+                     --compare, to check\n                             that the right \
+                     builds are compared.\n  \
+                     --generate-report-card <path>  Write a Markdown summary of the \
+                     difference with --compare,\n                             e.g. for pull \
+                     request comments.\n  \
+                     {}\n{}\n{}\n{}\n  \
+                     Baselines are saved with zstd by default.",
+                    FUNCTION_REGEX_REPLACE_OPTION,
+                    SOURCE_OPTIONS,
+                    RANKING_OPTIONS,
+                    COMPRESSION_OPTIONS
+                )))
+            }
+            _ => return Err(unexpected_argument(&arg)),
+        }
+    }
+    let action = match (save, compare, list, delete) {
+        (Some(name), None, false, None) => BaselineAction::Save {
+            name,
+            from_json,
+            source,
+            compression,
+        },
+        (None, Some(name), false, None) => BaselineAction::Compare {
+            name,
+            from_json,
+            source,
+            ranking,
+            report_unchanged,
+            report_card,
+            normalization,
+        },
+        (None, None, true, None) => BaselineAction::List,
+        (None, None, false, Some(name)) => BaselineAction::Delete(name),
+        _ => {
+            return Err(eyre::eyre!(
+                "Expected exactly one of --save, --compare, --list-baselines and \
+                 --delete-baseline."
+            ))
+        }
+    };
+    Ok(Subcommand::Baseline(BaselineArgs {
+        baseline_dir,
+        action,
+    }))
+}
+
+fn parse_stats_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut source = SourceArgs::new();
+    let mut estimate_time = false;
+    let mut report_flag_groups = false;
+    while let Some(arg) = args.next() {
+        if source.parse_flag(&arg, args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--estimate-time" => estimate_time = true,
+            "--report-flag-groups" => report_flag_groups = true,
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(format!(
+                    "Usage: assembly_analysis stats [options]\n\n\
+                     Prints the number of translation units, source files and lines and the \
+                     used compiler flags.\n\n\
+                     Options:\n  \
+                     --estimate-time            Analyze the median source file to estimate \
+                     the total analysis time\n                             instead of \
+                     assuming a fixed number of lines per second.\n  \
+                     --report-flag-groups       Group the objects by their compiler flags and \
+                     list the objects\n                             whose flags differ from \
+                     all others.\n{}",
+                    SOURCE_OPTIONS
+                )))
+            }
+            _ => return Err(unexpected_argument(&arg)),
+        }
+    }
+    Ok(Subcommand::Stats(StatsArgs {
+        source,
+        estimate_time,
+        report_flag_groups,
+    }))
+}
+
+fn parse_merge_shards_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut shards = vec![];
+    let mut output_json = PathBuf::from("assembly_analysis.json");
+    let mut deterministic_output = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output-json" => output_json = args.value(&arg)?.into(),
+            "--deterministic-output" => deterministic_output = true,
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(
+                    "Usage: assembly_analysis merge-shards [options] <shard.json>...\n\n\
+                     Combines the parsed data that was written by `analyze --shard-index i \
+                     --shard-count n`.\n\n\
+                     Options:\n  \
+                     --output-json <path>       Where to write the merged data \
+                     (default: ./assembly_analysis.json).\n  \
+                     --deterministic-output     Sort all keys in the written JSON."
+                        .to_owned(),
+                ))
+            }
+            _ if arg.starts_with("--") => return Err(unexpected_argument(&arg)),
+            _ => shards.push(arg.into()),
+        }
+    }
+    if shards.is_empty() {
+        return Err(eyre::eyre!("Missing shard JSON files."));
+    }
+    Ok(Subcommand::MergeShards(MergeShardsArgs {
+        shards,
+        output_json,
+        deterministic_output,
+    }))
+}
+
+fn parse_compare_optimization_args(args: &mut ArgStream) -> Result<Subcommand> {
+    let mut source = SourceArgs::new();
+    let mut optimization_flags = vec![];
+    let mut ranking = crate::Ranking::default();
+    while let Some(arg) = args.next() {
+        if source.parse_flag(&arg, args)? || ranking.parse_flag(&arg, args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--add-optimization-flag" => optimization_flags.push(args.value(&arg)?),
+            _ if is_help_flag(&arg) => {
+                return Ok(Subcommand::Help(format!(
+                    "Usage: assembly_analysis compare-optimization --add-optimization-flag \
+                     <flag> [options]\n\n\
+                     Analyzes the sources with their own flags and again with the additional \
+                     flags, e.g. -O3,\nand lists the functions that got smaller or larger.\n\n\
+                     Options:\n  \
+                     --add-optimization-flag <flag>  Compiler flag to append for the second \
+                     analysis. Can be\n                             repeated.\n{}\n{}",
+                    RANKING_OPTIONS, SOURCE_OPTIONS
+                )))
+            }
+            _ => return Err(unexpected_argument(&arg)),
+        }
+    }
+    if optimization_flags.is_empty() {
+        return Err(eyre::eyre!("Missing --add-optimization-flag."));
+    }
+    Ok(Subcommand::CompareOptimization(CompareOptimizationArgs {
+        source,
+        optimization_flags,
+        ranking,
+    }))
+}
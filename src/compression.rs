@@ -0,0 +1,116 @@
+use eyre::Result;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const LZ4_FRAME_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Zstd,
+    Lz4,
+}
+
+impl std::str::FromStr for Compression {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "none" => Compression::None,
+            "zstd" => Compression::Zstd,
+            "lz4" => Compression::Lz4,
+            _ => return Err(eyre::eyre!("Unknown compression: {}", s)),
+        })
+    }
+}
+
+impl Compression {
+    /// Chosen by the extension, e.g. `data.json.zst`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("zst") => Compression::Zstd,
+            Some("lz4") => Compression::Lz4,
+            _ => Compression::None,
+        }
+    }
+
+    /// Extension of JSON files with this compression.
+    pub fn json_extension(self) -> &'static str {
+        match self {
+            Compression::None => "json",
+            Compression::Zstd => "json.zst",
+            Compression::Lz4 => "json.lz4",
+        }
+    }
+}
+
+/// Creates the file and passes a writer to `write` that compresses everything. The level is
+/// only used by zstd, lz4 has a single level.
+pub fn write_file(
+    path: &Path,
+    compression: Compression,
+    level: Option<i32>,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    match compression {
+        Compression::None => {
+            let mut file = file;
+            write(&mut file)?;
+            file.flush()?;
+        }
+        Compression::Zstd => {
+            let mut encoder = zstd::Encoder::new(file, level.unwrap_or(DEFAULT_ZSTD_LEVEL))?;
+            write(&mut encoder)?;
+            encoder.finish()?.flush()?;
+        }
+        Compression::Lz4 => {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(file);
+            write(&mut encoder)?;
+            encoder.finish()?.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens the file and decompresses it if it starts with the magic bytes of zstd or lz4,
+/// independent of its extension.
+pub fn open_file(path: &Path) -> Result<Box<dyn Read>> {
+    let mut file = BufReader::new(std::fs::File::open(path)?);
+    let magic = file.fill_buf()?;
+    Ok(if magic.starts_with(&ZSTD_MAGIC) {
+        Box::new(zstd::Decoder::with_buffer(file)?)
+    } else if magic.starts_with(&LZ4_FRAME_MAGIC) {
+        Box::new(BufReader::new(lz4_flex::frame::FrameDecoder::new(file)))
+    } else {
+        Box::new(file)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_is_detected_by_magic_bytes() {
+        let dir = std::env::temp_dir().join(format!("compression-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = "{\"functions\": []}\n".repeat(100);
+        for compression in [Compression::None, Compression::Zstd, Compression::Lz4] {
+            // The extension does not match the compression on purpose.
+            let path = dir.join("data.json");
+            write_file(&path, compression, None, |writer| {
+                Ok(writer.write_all(text.as_bytes())?)
+            })
+            .unwrap();
+            let is_plain = std::fs::read(&path).unwrap() == text.as_bytes();
+            assert_eq!(is_plain, compression == Compression::None);
+            let mut read = String::new();
+            open_file(&path).unwrap().read_to_string(&mut read).unwrap();
+            assert_eq!(read, text);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+use eyre::Result;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Compiler flag overrides of a build configuration, which are applied on top of the compile
+/// commands. They are read from a TOML file like:
+///
+/// ```toml
+/// extra_flags = ["-flto"]
+/// replace_flags = [["-O2", "-O3"]]
+/// function_regex_replace = [["h[0-9a-f]{16}$", "HASH"]]
+/// ```
+#[derive(Default)]
+pub struct BuildConfig {
+    pub extra_flags: Vec<String>,
+    /// Pairs of (old, new) flags.
+    pub replaced_flags: Vec<(String, String)>,
+    /// Applied to the function names before the builds are compared.
+    pub normalization: crate::normalize::NameNormalization,
+}
+
+pub fn load_build_config(path: &Path) -> Result<BuildConfig> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("Can't read {}: {}", path.display(), err))?;
+    parse_build_config(&content).map_err(|err| eyre::eyre!("{}: {}", path.display(), err))
+}
+
+/// Architectural layers of a project, which are read from a TOML file like:
+///
+/// ```toml
+/// [[layer]]
+/// name = "platform"
+/// object_patterns = ["*/platform/*"]
+/// allowed_to_call = []
+///
+/// [[layer]]
+/// name = "app"
+/// object_patterns = ["*/app/*"]
+/// allowed_to_call = ["platform"]
+/// ```
+pub struct LayerConfig {
+    pub layers: Vec<Layer>,
+}
+
+pub struct Layer {
+    pub name: String,
+    /// Wildcard patterns of the object paths that belong to the layer, see
+    /// [`crate::filter::wildcard_regex`].
+    pub object_patterns: Vec<regex::Regex>,
+    /// Names of the other layers that objects of this layer may call into.
+    pub allowed_to_call: Vec<String>,
+}
+
+pub fn load_layer_config(path: &Path) -> Result<LayerConfig> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("Can't read {}: {}", path.display(), err))?;
+    parse_layer_config(&content).map_err(|err| eyre::eyre!("{}: {}", path.display(), err))
+}
+
+/// Layout of the layer config file, which is converted to `LayerConfig` after validation.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerConfigFile {
+    #[serde(default)]
+    layer: Vec<LayerFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerFile {
+    name: String,
+    #[serde(default)]
+    object_patterns: Vec<String>,
+    #[serde(default)]
+    allowed_to_call: Vec<String>,
+}
+
+fn parse_layer_config(content: &str) -> Result<LayerConfig> {
+    let file: LayerConfigFile = toml::from_str(content)?;
+    for layer in file.layer.iter() {
+        if layer.name.is_empty() {
+            return Err(eyre::eyre!("Every layer needs a name."));
+        }
+        for allowed in layer.allowed_to_call.iter() {
+            if !file.layer.iter().any(|other| other.name == *allowed) {
+                return Err(eyre::eyre!(
+                    "Layer {} may call the unknown layer {}.",
+                    layer.name,
+                    allowed
+                ));
+            }
+        }
+    }
+    let layers = file
+        .layer
+        .into_iter()
+        .map(|layer| {
+            Ok(Layer {
+                name: layer.name,
+                object_patterns: layer
+                    .object_patterns
+                    .iter()
+                    .map(|pattern| crate::filter::wildcard_regex(pattern))
+                    .collect::<Result<_>>()?,
+                allowed_to_call: layer.allowed_to_call,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(LayerConfig { layers })
+}
+
+/// Layout of the build config file, see `BuildConfig`.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct BuildConfigFile {
+    extra_flags: Vec<String>,
+    replace_flags: Vec<(String, String)>,
+    function_regex_replace: Vec<(String, String)>,
+}
+
+fn parse_build_config(content: &str) -> Result<BuildConfig> {
+    let file: BuildConfigFile = toml::from_str(content)?;
+    let mut config = BuildConfig {
+        extra_flags: file.extra_flags,
+        replaced_flags: file.replace_flags,
+        ..Default::default()
+    };
+    for (pattern, replacement) in file.function_regex_replace {
+        config.normalization.add(&pattern, &replacement)?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_config_is_parsed() {
+        let config = parse_build_config(
+            "# Comparison with link time optimization.\n\
+             extra_flags = ['-flto', \"-DNAME=\\\"x\\\"\"]\n\
+             replace_flags = [\n\
+             \x20   [\"-O2\", \"-O3\"], # Trailing comma.\n\
+             ]\n",
+        )
+        .unwrap();
+        assert_eq!(config.extra_flags, vec!["-flto", "-DNAME=\"x\""]);
+        assert_eq!(
+            config.replaced_flags,
+            vec![("-O2".to_owned(), "-O3".to_owned())]
+        );
+    }
+
+    #[test]
+    fn config_errors_name_the_line() {
+        let error = parse_build_config("extra_flags = []\nreplace_flags = [[\"-O2\"]]\n")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("line 2"), "{}", error);
+        let error = parse_build_config("unknown = []\n")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("unknown field `unknown`"), "{}", error);
+    }
+
+    #[test]
+    fn layer_config_is_parsed() {
+        let config = parse_layer_config(
+            "[[layer]]\n\
+             name = \"platform\"\n\
+             object_patterns = [\"*/platform/*\"]\n\
+             \n\
+             [[layer]]\n\
+             name = \"app\"\n\
+             allowed_to_call = [\"platform\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.layers.len(), 2);
+        assert!(config.layers[0].object_patterns[0].is_match("/src/platform/io.o"));
+        assert_eq!(config.layers[1].allowed_to_call, vec!["platform"]);
+
+        let error = parse_layer_config("[[layer]]\nname = \"app\"\nallowed_to_call = [\"ui\"]\n")
+            .err()
+            .unwrap()
+            .to_string();
+        assert_eq!(error, "Layer app may call the unknown layer ui.");
+    }
+}
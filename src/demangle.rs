@@ -0,0 +1,200 @@
+use eyre::Result;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+/// Demangled names are needed when formatting names, where spawning a process per name would
+/// be much too slow. Therefore, all names are demangled in one batch upfront.
+fn demangled_name_cache() -> &'static Mutex<HashMap<String, String>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Demangles all names that are not cached yet. When `c++filt` is not available, names are
+/// displayed mangled.
+pub fn prepare<'a>(names: impl IntoIterator<Item = &'a str>) {
+    let mut cache = demangled_name_cache().lock().unwrap();
+    let missing_names: Vec<&str> = names
+        .into_iter()
+        .filter(|name| !cache.contains_key(*name))
+        .collect();
+    if missing_names.is_empty() {
+        return;
+    }
+    match demangle_names(&missing_names) {
+        Ok(demangled_names) => {
+            for (name, demangled_name) in missing_names.into_iter().zip(demangled_names) {
+                cache.insert(name.to_owned(), demangled_name);
+            }
+        }
+        Err(err) => {
+            eprintln!("Names are not demangled: {:?}", err);
+            // Otherwise every displayed name would try again.
+            for name in missing_names {
+                cache.insert(name.to_owned(), name.to_owned());
+            }
+        }
+    }
+}
+
+/// Symbols of C++ and Rust, other names are the same when demangled.
+fn is_mangled(name: &str) -> bool {
+    name.starts_with("_Z") || name.starts_with("__Z") || name.starts_with("_R")
+}
+
+/// Names that have not been prepared are demangled on their own.
+pub fn demangled(name: &str) -> String {
+    if let Some(demangled) = demangled_name_cache().lock().unwrap().get(name) {
+        return demangled.clone();
+    }
+    if !is_mangled(name) {
+        return name.to_owned();
+    }
+    prepare([name]);
+    demangled_name_cache()
+        .lock()
+        .unwrap()
+        .get(name)
+        .cloned()
+        .unwrap_or_else(|| name.to_owned())
+}
+
+/// Demangles all names with a single `c++filt` invocation. The result has the same order as
+/// the input. Names that are not mangled are returned unchanged.
+pub fn demangle_names(names: &[&str]) -> Result<Vec<String>> {
+    let mut child = Command::new("c++filt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| eyre::eyre!("Failed to run c++filt: {}", err))?;
+    // Write from a separate thread, otherwise both processes can block on full pipes.
+    let mut stdin = child.stdin.take().unwrap();
+    let input = names.join("\n");
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer
+        .join()
+        .map_err(|_| eyre::eyre!("Failed to pass names to c++filt."))??;
+    let demangled: Vec<String> = String::from_utf8(output.stdout)?
+        .lines()
+        .map(|line| line.to_owned())
+        .collect();
+    if demangled.len() != names.len() {
+        return Err(eyre::eyre!("Unexpected output of c++filt."));
+    }
+    Ok(demangled)
+}
+
+/// Returns the namespace (including enclosing classes) of a demangled function name, i.e.
+/// everything before the last `::` that is not part of template or function parameters.
+/// `blender::nodes::Node::update(int)` has the namespace `blender::nodes::Node`.
+pub fn namespace(demangled: &str) -> Option<&str> {
+    const ANONYMOUS_NAMESPACE: &str = "(anonymous namespace)";
+    let mut depth = 0;
+    let mut start = 0;
+    let mut last_separator = None;
+    let bytes = demangled.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(operator_len) = operator_len(demangled, i) {
+            i += operator_len;
+            continue;
+        }
+        match bytes[i] {
+            b'(' if demangled[i..].starts_with(ANONYMOUS_NAMESPACE) => {
+                i += ANONYMOUS_NAMESPACE.len();
+                continue;
+            }
+            // Everything after this, e.g. the parameters or `[clone .cold]`, is irrelevant.
+            b'(' if depth == 0 => break,
+            b'<' | b'(' => depth += 1,
+            b'>' | b')' => depth -= 1,
+            // Skips the return type of template functions like `void ns::f<int>(int)`.
+            b' ' if depth == 0 && last_separator.is_none() => start = i + 1,
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => {
+                last_separator = Some(i);
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    last_separator.map(|end| &demangled[start..end])
+}
+
+/// Length of the operator name like `operator<=` or `operator()` that starts at `i`, so that
+/// its brackets are not mistaken for template or function parameters.
+fn operator_len(demangled: &str, i: usize) -> Option<usize> {
+    const OPERATOR: &str = "operator";
+    let is_identifier = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+    let bytes = demangled.as_bytes();
+    if !demangled[i..].starts_with(OPERATOR) || i > 0 && is_identifier(bytes[i - 1]) {
+        return None;
+    }
+    let symbol = &demangled[i + OPERATOR.len()..];
+    if symbol.bytes().next().is_some_and(is_identifier) {
+        return None;
+    }
+    let symbol_len = if symbol.starts_with("()") || symbol.starts_with("[]") {
+        2
+    } else {
+        symbol
+            .bytes()
+            .take_while(|c| b"<>=-!+*/%^&|~,".contains(c))
+            .count()
+    };
+    Some(OPERATOR.len() + symbol_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespace_of_functions_and_templates() {
+        assert_eq!(
+            namespace("blender::nodes::Node::update(int)"),
+            Some("blender::nodes::Node")
+        );
+        assert_eq!(
+            namespace("void ns::f<std::pair<int, int>>(int)"),
+            Some("ns")
+        );
+        assert_eq!(
+            namespace("(anonymous namespace)::helper()"),
+            Some("(anonymous namespace)")
+        );
+        assert_eq!(namespace("main"), None);
+    }
+
+    #[test]
+    fn namespace_of_operators_with_brackets() {
+        assert_eq!(
+            namespace("ns::Vec::operator>(ns::Vec const&) const"),
+            Some("ns::Vec")
+        );
+        assert_eq!(
+            namespace("ns::Vec::operator>=(ns::Vec const&) const"),
+            Some("ns::Vec")
+        );
+        assert_eq!(namespace("ns::Ptr::operator->() const"), Some("ns::Ptr"));
+        assert_eq!(namespace("ns::Log::operator<<(int)"), Some("ns::Log"));
+        assert_eq!(
+            namespace("bool ns::operator< <int>(ns::Set<int> const&)"),
+            Some("ns")
+        );
+        assert_eq!(
+            namespace("ns::Functor::operator()(int)"),
+            Some("ns::Functor")
+        );
+        assert_eq!(
+            namespace("ns::Map::operator[](unsigned long)"),
+            Some("ns::Map")
+        );
+        assert_eq!(
+            namespace("ns::operators::apply(int)"),
+            Some("ns::operators")
+        );
+    }
+}
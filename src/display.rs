@@ -0,0 +1,132 @@
+use crate::{demangle, FunctionName, ObjectName, ParsedData};
+use std::sync::{OnceLock, RwLock, RwLockReadGuard};
+
+/// Maximum length of demangled names with `--format-function-names short`.
+pub const SHORT_NAME_LENGTH: usize = 60;
+
+/// How names are printed. Set once from the global command line options, because names are
+/// formatted with `Display` everywhere.
+#[derive(Clone)]
+pub struct DisplayOptions {
+    pub demangle: bool,
+    /// Longer demangled names are truncated and end with `...`.
+    pub max_length: Option<usize>,
+    /// Namespaces like `blender::` that are removed from demangled names.
+    pub strip_prefixes: Vec<String>,
+    /// Print the full paths of objects instead of only their file names.
+    pub show_full_paths: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            demangle: true,
+            max_length: None,
+            strip_prefixes: vec![],
+            show_full_paths: false,
+        }
+    }
+}
+
+fn display_options_lock() -> &'static RwLock<DisplayOptions> {
+    static OPTIONS: OnceLock<RwLock<DisplayOptions>> = OnceLock::new();
+    OPTIONS.get_or_init(Default::default)
+}
+
+pub fn display_options() -> RwLockReadGuard<'static, DisplayOptions> {
+    display_options_lock().read().unwrap()
+}
+
+pub fn set_display_options(options: DisplayOptions) {
+    *display_options_lock().write().unwrap() = options;
+}
+
+/// Local functions also show the object they are defined in, which is looked up in the parsed
+/// data.
+pub fn format_function_name(
+    parsed: &ParsedData,
+    name: &FunctionName,
+    options: &DisplayOptions,
+) -> String {
+    match name {
+        FunctionName::Global(global) => format_symbol(&global.name, options),
+        FunctionName::Local(local) => {
+            let name = format_symbol(&local.name, options);
+            match parsed.name_by_object_id.get(&local.object) {
+                Some(object) => format!(
+                    "{} (local in {})",
+                    name,
+                    format_object_name(object, options)
+                ),
+                None => format!("{} (local in object {})", name, local.object.0),
+            }
+        }
+    }
+}
+
+/// Name that does not depend on the formatting options, for when names are used as keys.
+/// Local functions include the full path of their object, so that they stay distinct.
+#[cfg(feature = "python")]
+pub fn unformatted_function_name(parsed: &ParsedData, name: &FunctionName) -> String {
+    let options = DisplayOptions {
+        show_full_paths: true,
+        ..Default::default()
+    };
+    format_function_name(parsed, name, &options)
+}
+
+/// Formats a mangled symbol name, which is demangled unless disabled.
+pub fn format_symbol(symbol: &str, options: &DisplayOptions) -> String {
+    if !options.demangle {
+        return symbol.to_owned();
+    }
+    let mut name = demangle::demangled(symbol);
+    for prefix in &options.strip_prefixes {
+        name = strip_namespace(&name, prefix);
+    }
+    match options.max_length {
+        Some(max_length) => truncate(name, max_length),
+        None => name,
+    }
+}
+
+pub fn format_object_name(object: &ObjectName, options: &DisplayOptions) -> String {
+    if options.show_full_paths {
+        object.path.display().to_string()
+    } else {
+        object.basename().to_string_lossy().into_owned()
+    }
+}
+
+/// Removes every occurrence of the namespace that is not the end of a longer name, so that
+/// it is also removed from template and function parameters.
+fn strip_namespace(name: &str, prefix: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(index) = rest.find(prefix) {
+        let is_name_start = !result
+            .chars()
+            .chain(rest[..index].chars())
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == ':');
+        result.push_str(&rest[..index]);
+        if !is_name_start {
+            result.push_str(prefix);
+        }
+        rest = &rest[index + prefix.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn truncate(name: String, max_length: usize) -> String {
+    const ELLIPSIS: &str = "...";
+    if name.chars().count() <= max_length {
+        return name;
+    }
+    let kept_length = max_length.saturating_sub(ELLIPSIS.len());
+    name.chars()
+        .take(kept_length)
+        .chain(ELLIPSIS.chars())
+        .collect()
+}
@@ -0,0 +1,213 @@
+use crate::{FunctionID, ParsedData};
+use eyre::Result;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Above this number of functions, a dense matrix gets too large to be useful.
+const MAX_DENSE_ADJACENCY_FUNCTIONS: usize = 1000;
+
+#[derive(Clone, Copy)]
+pub enum AdjacencyFormat {
+    /// One row of space separated zeros and ones per caller.
+    Dense,
+    /// One `row col value` line per call, i.e. the coordinate (COO) format.
+    Sparse,
+}
+
+impl std::str::FromStr for AdjacencyFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "dense" => AdjacencyFormat::Dense,
+            "sparse" => AdjacencyFormat::Sparse,
+            _ => return Err(eyre::eyre!("Unknown adjacency matrix format: {}", s)),
+        })
+    }
+}
+
+/// Writes the call graph between the given functions as an adjacency matrix in which rows
+/// are callers and columns are callees. The functions are listed in `#` comment lines first,
+/// which are skipped by numpy's `loadtxt` and R's `read.table`. Without an explicit format,
+/// large graphs are written as sparse matrix.
+pub fn write_adjacency_matrix(
+    parsed: &ParsedData,
+    writer: &mut dyn Write,
+    functions: &[FunctionID],
+    format: Option<AdjacencyFormat>,
+) -> Result<()> {
+    let format = format.unwrap_or(if functions.len() > MAX_DENSE_ADJACENCY_FUNCTIONS {
+        AdjacencyFormat::Sparse
+    } else {
+        AdjacencyFormat::Dense
+    });
+    let index_by_function: HashMap<FunctionID, usize> = functions
+        .iter()
+        .enumerate()
+        .map(|(i, function_id)| (*function_id, i))
+        .collect();
+    for (i, function_id) in functions.iter().enumerate() {
+        writeln!(writer, "# {} {}", i, parsed.display(*function_id))?;
+    }
+    for (row, caller) in functions.iter().enumerate() {
+        let mut columns: Vec<usize> = parsed
+            .callees_by_caller
+            .get(caller)
+            .into_iter()
+            .flatten()
+            .filter_map(|callee| index_by_function.get(callee).copied())
+            .collect();
+        columns.sort();
+        match format {
+            AdjacencyFormat::Dense => {
+                let mut values = vec!["0"; functions.len()];
+                for column in columns {
+                    values[column] = "1";
+                }
+                writeln!(writer, "{}", values.join(" "))?;
+            }
+            AdjacencyFormat::Sparse => {
+                for column in columns {
+                    writeln!(writer, "{} {} 1", row, column)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// PlantUML can't escape quotes in quoted labels, so they become apostrophes, e.g. in
+/// `operator"" _km`. Backslashes are doubled so that they are not read as `\n`.
+fn plantuml_label(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "'")
+}
+
+/// Writes a PlantUML sequence diagram in which every function of the call chain is a
+/// participant. Recursive functions get an additional self call in a `loop` block.
+pub fn write_plantuml_sequence(
+    parsed: &ParsedData,
+    chain: &[FunctionID],
+    writer: &mut dyn Write,
+) -> Result<()> {
+    writeln!(writer, "@startuml")?;
+    for (i, function_id) in chain.iter().enumerate() {
+        let function = plantuml_label(&parsed.display(*function_id).to_string());
+        let instructions_num = crate::function_instructions_num(parsed, *function_id);
+        writeln!(
+            writer,
+            "participant \"{}\\n({} instructions)\" as f{}",
+            function, instructions_num, i
+        )?;
+    }
+    for (i, function_id) in chain.iter().enumerate().skip(1) {
+        writeln!(writer, "f{} -> f{}: call", i - 1, i)?;
+        writeln!(writer, "activate f{}", i)?;
+        let is_recursive = parsed
+            .callees_by_caller
+            .get(function_id)
+            .is_some_and(|callees| callees.contains(function_id))
+            || parsed.tail_recursive_functions.contains(function_id);
+        if is_recursive {
+            writeln!(writer, "loop recursion")?;
+            writeln!(writer, "  f{} -> f{}: call", i, i)?;
+            writeln!(writer, "end")?;
+        }
+    }
+    for i in (1..chain.len()).rev() {
+        writeln!(writer, "f{} --> f{}", i, i - 1)?;
+        writeln!(writer, "deactivate f{}", i)?;
+    }
+    writeln!(writer, "@enduml")?;
+    Ok(())
+}
+
+/// Writes the call graph in the Callgrind format, so that it can be browsed in KCachegrind.
+/// There is no profiling data, so the cost of every function is its instruction count and
+/// calls have no inclusive cost. Names are compressed to `(id)` after their first use.
+pub fn write_callgrind(parsed: &ParsedData, writer: &mut dyn Write) -> Result<()> {
+    writeln!(writer, "# callgrind format")?;
+    writeln!(writer, "version: 1")?;
+    writeln!(writer, "creator: assembly_analysis")?;
+    writeln!(writer, "events: Instructions")?;
+
+    let mut functions: Vec<(FunctionID, String)> = parsed
+        .name_by_function_id
+        .keys()
+        .map(|function_id| (*function_id, parsed.display(*function_id).to_string()))
+        .collect();
+    functions.sort_by(|a, b| a.1.cmp(&b.1));
+    let name_by_function: HashMap<FunctionID, &str> = functions
+        .iter()
+        .map(|(function_id, name)| (*function_id, name.as_str()))
+        .collect();
+    let mut compressed_ids: HashMap<FunctionID, usize> = HashMap::new();
+    let mut compressed_name = |function_id: FunctionID| -> String {
+        if let Some(id) = compressed_ids.get(&function_id) {
+            return format!("({})", id);
+        }
+        let id = compressed_ids.len() + 1;
+        compressed_ids.insert(function_id, id);
+        format!("({}) {}", id, name_by_function[&function_id])
+    };
+
+    for (function_id, _) in functions.iter() {
+        writeln!(writer)?;
+        writeln!(writer, "fn={}", compressed_name(*function_id))?;
+        let instructions_num = crate::function_instructions_num(parsed, *function_id);
+        writeln!(writer, "0 {}", instructions_num)?;
+        let mut callees: Vec<FunctionID> = parsed
+            .callees_by_caller
+            .get(function_id)
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        callees.sort_by_key(|callee| name_by_function[callee]);
+        for callee in callees {
+            let call_sites_num = parsed
+                .call_sites_by_caller
+                .get(function_id)
+                .and_then(|call_sites| call_sites.get(&callee))
+                .copied()
+                .unwrap_or(1);
+            writeln!(writer, "cfn={}", compressed_name(callee))?;
+            writeln!(writer, "calls={} 0", call_sites_num)?;
+            writeln!(writer, "0 0")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FunctionName;
+
+    #[test]
+    fn plantuml_sequence_labels_are_sanitized() {
+        let mut parsed = ParsedData::default();
+        let main = parsed.get_or_add_function(FunctionName::global("main"));
+        let literal = parsed.get_or_add_function(FunctionName::global("operator\"\" _km"));
+        parsed
+            .callees_by_caller
+            .insert(literal, [literal].into_iter().collect());
+
+        let mut output = vec![];
+        write_plantuml_sequence(&parsed, &[main, literal], &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "@startuml\n\
+             participant \"main\\n(0 instructions)\" as f0\n\
+             participant \"operator'' _km\\n(0 instructions)\" as f1\n\
+             f0 -> f1: call\n\
+             activate f1\n\
+             loop recursion\n\
+             \x20 f1 -> f1: call\n\
+             end\n\
+             f1 --> f0\n\
+             deactivate f1\n\
+             @enduml\n"
+        );
+    }
+}
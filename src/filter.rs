@@ -0,0 +1,215 @@
+use crate::{demangle, FunctionName};
+use eyre::Result;
+use regex::Regex;
+
+/// Set of name patterns. A function matches if its mangled or demangled name matches any of
+/// the patterns.
+#[derive(Default)]
+pub struct FilterSet {
+    prefixes: Vec<String>,
+    names: Vec<String>,
+    /// Match anywhere in the name.
+    regexes: Vec<Regex>,
+}
+
+/// Functions of the standard library and the C++ runtime.
+const SYSTEM_FUNCTION_PREFIXES: &[&str] = &[
+    "std::",
+    "__gnu_cxx::",
+    "_ZN9__gnu_cxx",
+    "_ZNSt",
+    "_ZSt",
+    "operator new",
+    "operator delete",
+    "__cxa_",
+    "__gxx_",
+    "_Unwind_",
+];
+
+/// Functions of libc. These are matched exactly, because their names are common prefixes.
+const SYSTEM_FUNCTION_NAMES: &[&str] = &[
+    "malloc",
+    "calloc",
+    "realloc",
+    "free",
+    "memcpy",
+    "memmove",
+    "memset",
+    "memcmp",
+    "strlen",
+    "strcmp",
+    "__stack_chk_fail",
+];
+
+/// Test cases, test fixtures and benchmarks of common frameworks, e.g. `test_parse`,
+/// `FooTest_Bar_Test::TestBody` of googletest and `crate::tests::parse` in Rust.
+const TEST_FUNCTION_PATTERNS: &[&str] = &[
+    r"\btest_",
+    r"_test\b",
+    r"_TEST\b",
+    r"\bTest|Test\b",
+    r"Google_?[Tt]est",
+    r"^testing::",
+    r"_ZN.*TestBody",
+    r"::TestBody\b",
+    r"\btests::",
+    r"\bBM_",
+    r"\b[Bb]ench(mark)?_",
+    r"^benchmark::",
+];
+
+impl FilterSet {
+    pub fn system_functions() -> Self {
+        let mut filter = FilterSet::default();
+        for prefix in SYSTEM_FUNCTION_PREFIXES {
+            filter.add_prefix(prefix);
+        }
+        for name in SYSTEM_FUNCTION_NAMES {
+            filter.add_name(name);
+        }
+        filter
+    }
+
+    pub fn test_functions() -> Self {
+        let mut filter = FilterSet::default();
+        for pattern in TEST_FUNCTION_PATTERNS {
+            filter.add_regex(pattern).unwrap();
+        }
+        filter
+    }
+
+    pub fn add_regex(&mut self, pattern: &str) -> Result<()> {
+        let regex = Regex::new(pattern)
+            .map_err(|err| eyre::eyre!("Invalid pattern {}: {}", pattern, err))?;
+        self.regexes.push(regex);
+        Ok(())
+    }
+
+    pub fn add_prefix(&mut self, prefix: &str) {
+        self.prefixes.push(prefix.to_owned());
+    }
+
+    pub fn add_name(&mut self, name: &str) {
+        self.names.push(name.to_owned());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty() && self.names.is_empty() && self.regexes.is_empty()
+    }
+
+    /// Matching all functions is faster when their names have been prepared for demangling.
+    pub fn matches(&self, function: &FunctionName) -> bool {
+        let name = function.name();
+        let demangled_name = demangle::demangled(name);
+        let demangled_name = demangled_name.split('(').next().unwrap_or_default();
+        self.regexes
+            .iter()
+            .any(|regex| regex.is_match(name) || regex.is_match(demangled_name))
+            || self
+                .names
+                .iter()
+                .any(|pattern| name == pattern || demangled_name == pattern)
+            || self
+                .prefixes
+                .iter()
+                .any(|prefix| name.starts_with(prefix) || demangled_name.starts_with(prefix))
+    }
+}
+
+/// How a function matches a pattern given on the command line, better matches come first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PatternMatch {
+    Mangled,
+    /// The demangled name with or without parameters.
+    Demangled,
+    /// Case-insensitive substring of the demangled name.
+    Substring,
+    /// Regular expression that matches anywhere in the demangled or mangled name.
+    Regex,
+}
+
+/// Pattern given on the command line. It is prepared once, because it is usually matched
+/// against all functions.
+pub struct NamePattern {
+    pattern: String,
+    lowercase_pattern: String,
+    /// Only patterns with `*` or `[` are used as regular expressions. Names like
+    /// `operator[]` are not valid regular expressions and are only matched literally.
+    regex: Option<Regex>,
+}
+
+impl NamePattern {
+    pub fn new(pattern: &str) -> Self {
+        NamePattern {
+            pattern: pattern.to_owned(),
+            lowercase_pattern: pattern.to_lowercase(),
+            regex: pattern
+                .contains(['*', '['])
+                .then(|| Regex::new(pattern).ok())
+                .flatten(),
+        }
+    }
+
+    /// Checks the kinds of matches in order and returns the first one.
+    pub fn matches(&self, function: &FunctionName) -> Option<PatternMatch> {
+        let name = function.name();
+        if name == self.pattern {
+            return Some(PatternMatch::Mangled);
+        }
+        let demangled_name = demangle::demangled(name);
+        let demangled_name_without_params = demangled_name.split('(').next().unwrap_or_default();
+        if demangled_name == self.pattern || demangled_name_without_params == self.pattern {
+            return Some(PatternMatch::Demangled);
+        }
+        if demangled_name
+            .to_lowercase()
+            .contains(&self.lowercase_pattern)
+        {
+            return Some(PatternMatch::Substring);
+        }
+        let regex = self.regex.as_ref()?;
+        (regex.is_match(&demangled_name) || regex.is_match(name)).then_some(PatternMatch::Regex)
+    }
+}
+
+/// Wildcard pattern that matches a whole text, e.g. an object path. It supports `*`, `?`
+/// and `[...]` with ranges like `a-z` and negation with `!`. Unlike in shells, `*` also
+/// matches `/`.
+pub fn wildcard_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let mut class = String::new();
+                let negated = chars.next_if_eq(&'!').is_some();
+                // A `]` directly after the opening bracket belongs to the class.
+                if let Some(c) = chars.next_if_eq(&']') {
+                    class.push(c);
+                }
+                while let Some(c) = chars.next_if(|c| *c != ']') {
+                    class.push(c);
+                }
+                if chars.next().is_none() {
+                    return Err(eyre::eyre!("Missing ']' in {}.", pattern));
+                }
+                regex.push('[');
+                if negated {
+                    regex.push('^');
+                }
+                for c in class.chars() {
+                    match c {
+                        '-' => regex.push('-'),
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).map_err(|err| eyre::eyre!("Invalid pattern {}: {}", pattern, err))
+}
@@ -0,0 +1,71 @@
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Ids that are used as indices, e.g. in the call graph.
+pub trait Id: Copy {
+    fn from_index(index: usize) -> Self;
+    fn index(self) -> usize;
+}
+
+/// Hands out dense ids that start at zero. Freed ids are reused before new ones are created,
+/// so there are no gaps when objects are removed and added again. Can be shared between
+/// threads.
+pub struct IdAllocator<T: Id> {
+    next: AtomicUsize,
+    free: Mutex<Vec<usize>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Id> IdAllocator<T> {
+    pub fn allocate(&self) -> T {
+        if let Some(index) = self.free.lock().unwrap().pop() {
+            return T::from_index(index);
+        }
+        T::from_index(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The id can be handed out again by the next `allocate`.
+    pub fn free(&self, id: T) {
+        self.free.lock().unwrap().push(id.index());
+    }
+
+    /// Makes sure that an id that is used already, e.g. after loading, is not handed out.
+    pub fn mark_used(&self, id: T) {
+        self.next.fetch_max(id.index() + 1, Ordering::Relaxed);
+    }
+
+    /// Forgets all allocated ids, the next id is zero again.
+    pub fn reset(&self) {
+        self.next.store(0, Ordering::Relaxed);
+        self.free.lock().unwrap().clear();
+    }
+}
+
+impl<T: Id> Default for IdAllocator<T> {
+    fn default() -> Self {
+        IdAllocator {
+            next: AtomicUsize::new(0),
+            free: Mutex::new(vec![]),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Id> Clone for IdAllocator<T> {
+    fn clone(&self) -> Self {
+        IdAllocator {
+            next: AtomicUsize::new(self.next.load(Ordering::Relaxed)),
+            free: Mutex::new(self.free.lock().unwrap().clone()),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Id> std::fmt::Debug for IdAllocator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdAllocator")
+            .field("next", &self.next.load(Ordering::Relaxed))
+            .finish()
+    }
+}
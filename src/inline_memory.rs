@@ -0,0 +1,270 @@
+use crate::{FunctionID, FunctionName, ParsedData, Ranking};
+
+/// Minimum number of consecutive stores that are counted as an inline expansion. Single
+/// stores are ordinary assignments.
+const MIN_RUN_LENGTH: usize = 2;
+
+/// Move instructions that compilers use for inline expansions of `memcpy` and `memset`.
+const MOVE_MNEMONICS: &[&str] = &[
+    "movq", "movl", "movdqu", "movdqa", "movups", "movaps", "movupd", "movapd", "vmovdqu",
+    "vmovdqa", "vmovups", "vmovaps",
+];
+
+/// Base register and offset of memory operands like `16(%rsi)`. Indexed and `%rip` relative
+/// operands are ignored, the latter are loads of constants.
+fn parse_memory_operand(operand: &str) -> Option<(&str, i64)> {
+    let (offset, base) = operand.strip_suffix(')')?.split_once('(')?;
+    if base.contains(',') || base == "%rip" || !base.starts_with('%') {
+        return None;
+    }
+    let offset = if offset.is_empty() {
+        0
+    } else {
+        offset.parse().ok()?
+    };
+    Some((base, offset))
+}
+
+/// Source and destination of a store, `source_offset` is only known for copies.
+#[derive(Clone, Copy)]
+struct Store<'a> {
+    source: &'a str,
+    source_offset: Option<i64>,
+    destination: &'a str,
+    destination_offset: i64,
+}
+
+impl Store<'_> {
+    /// Both the source and the destination offsets grow.
+    fn continues(&self, previous: &Store) -> bool {
+        self.source == previous.source
+            && self.destination == previous.destination
+            && self.destination_offset > previous.destination_offset
+            && match (self.source_offset, previous.source_offset) {
+                (Some(offset), Some(previous_offset)) => offset > previous_offset,
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+/// Detects the instruction sequences that compilers generate instead of calls to `memcpy` and
+/// `memset` with small known sizes on x86: consecutive stores to growing offsets, either of
+/// values that were loaded from growing offsets or of the same value, and `rep movs` and
+/// `rep stos`.
+#[derive(Default)]
+pub struct InlineMemoryScan<'a> {
+    /// Registers that were loaded from memory since the last store sequence started.
+    loaded: Vec<(&'a str, &'a str, i64)>,
+    last_store: Option<Store<'a>>,
+    run_length: usize,
+    pub memcpy_num: usize,
+    pub memset_num: usize,
+}
+
+impl<'a> InlineMemoryScan<'a> {
+    pub fn add_instruction(&mut self, instruction: &'a str) {
+        let mut words = instruction.split_whitespace();
+        let mnemonic = words.next().unwrap_or_default();
+        if mnemonic == "rep" {
+            match words.next() {
+                Some(string_op) if string_op.starts_with("movs") => self.memcpy_num += 1,
+                Some(string_op) if string_op.starts_with("stos") => self.memset_num += 1,
+                _ => {}
+            }
+            self.reset();
+            return;
+        }
+        let operands = instruction
+            .split_once('\t')
+            .and_then(|(_, operands)| operands.split_once(", "));
+        let (true, Some((source, destination))) = (MOVE_MNEMONICS.contains(&mnemonic), operands)
+        else {
+            self.reset();
+            return;
+        };
+        if let Some((base, offset)) = parse_memory_operand(source) {
+            self.loaded
+                .retain(|(register, _, _)| *register != destination);
+            self.loaded.push((destination, base, offset));
+            return;
+        }
+        let destination = parse_memory_operand(destination);
+        let Some((destination_base, destination_offset)) = destination else {
+            self.reset();
+            return;
+        };
+        let store = match self
+            .loaded
+            .iter()
+            .find(|(register, _, _)| *register == source)
+        {
+            Some((_, source_base, source_offset)) => Store {
+                source: source_base,
+                source_offset: Some(*source_offset),
+                destination: destination_base,
+                destination_offset,
+            },
+            None => Store {
+                source,
+                source_offset: None,
+                destination: destination_base,
+                destination_offset,
+            },
+        };
+        match self.last_store {
+            Some(previous) if store.continues(&previous) => self.run_length += 1,
+            _ => self.run_length = 1,
+        }
+        // Counted once when the run becomes long enough.
+        if self.run_length == MIN_RUN_LENGTH {
+            match store.source_offset {
+                Some(_) => self.memcpy_num += 1,
+                None => self.memset_num += 1,
+            }
+        }
+        self.last_store = Some(store);
+    }
+
+    fn reset(&mut self) {
+        self.loaded.clear();
+        self.last_store = None;
+        self.run_length = 0;
+    }
+}
+
+fn count(parsed: &ParsedData, function_id: FunctionID) -> (usize, usize) {
+    (
+        parsed
+            .inline_memcpy_count_by_function
+            .get(&function_id)
+            .copied()
+            .unwrap_or_default(),
+        parsed
+            .inline_memset_count_by_function
+            .get(&function_id)
+            .copied()
+            .unwrap_or_default(),
+    )
+}
+
+/// Functions with many inline expansions might be faster and smaller with explicit calls,
+/// because `memcpy` and `memset` of the C library use the widest available SIMD registers.
+pub fn print_inline_memory_operations(parsed: &ParsedData, ranking: &Ranking) {
+    let mut functions: Vec<(&FunctionName, FunctionID, usize)> = parsed
+        .inline_memcpy_count_by_function
+        .keys()
+        .chain(parsed.inline_memset_count_by_function.keys())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .map(|function_id| {
+            let (memcpy_num, memset_num) = count(parsed, *function_id);
+            (
+                &parsed.name_by_function_id[function_id],
+                *function_id,
+                memcpy_num + memset_num,
+            )
+        })
+        .collect();
+    functions.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.name().cmp(b.0.name())));
+    let functions = ranking.apply(functions, |(_, function_id, _)| {
+        crate::function_instructions_num(parsed, *function_id)
+    });
+    for (function, function_id, _) in functions {
+        let (memcpy_num, memset_num) = count(parsed, function_id);
+        println!(
+            "{}: {} memcpy, {} memset, {} instructions",
+            parsed.display_name(function),
+            memcpy_num,
+            memset_num,
+            crate::function_instructions_num(parsed, function_id)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(instructions: &[&'static str]) -> (usize, usize) {
+        let mut scan = InlineMemoryScan::default();
+        for instruction in instructions {
+            scan.add_instruction(instruction);
+        }
+        (scan.memcpy_num, scan.memset_num)
+    }
+
+    #[test]
+    fn rep_string_instructions_are_counted() {
+        assert_eq!(scan(&["rep movsq"]), (1, 0));
+        assert_eq!(scan(&["rep stosb %al, %es:(%rdi)"]), (0, 1));
+        assert_eq!(scan(&["rep movsb", "rep stosq"]), (1, 1));
+        assert_eq!(scan(&["rep ret"]), (0, 0));
+    }
+
+    #[test]
+    fn unrolled_copies_are_counted_once() {
+        assert_eq!(
+            scan(&[
+                "movdqu\t(%rsi), %xmm0",
+                "movups\t%xmm0, (%rdi)",
+                "movdqu\t16(%rsi), %xmm0",
+                "movups\t%xmm0, 16(%rdi)",
+                "movq\t32(%rsi), %rax",
+                "movq\t%rax, 32(%rdi)",
+            ]),
+            (1, 0)
+        );
+        // Loads from unrelated places are ordinary assignments.
+        assert_eq!(
+            scan(&[
+                "movq\t8(%rsi), %rax",
+                "movq\t%rax, (%rdi)",
+                "movq\t(%rsi), %rax",
+                "movq\t%rax, 8(%rdi)",
+            ]),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn unrolled_stores_are_counted_once() {
+        assert_eq!(
+            scan(&[
+                "movq\t$0, (%rdi)",
+                "movq\t$0, 8(%rdi)",
+                "movq\t$0, 16(%rdi)",
+            ]),
+            (0, 1)
+        );
+        assert_eq!(
+            scan(&[
+                "pxor\t%xmm0, %xmm0",
+                "movups\t%xmm0, 32(%rsp)",
+                "movups\t%xmm0, 48(%rsp)",
+            ]),
+            (0, 1)
+        );
+    }
+
+    #[test]
+    fn interrupted_or_single_stores_are_not_counted() {
+        assert_eq!(scan(&["movq\t$0, (%rdi)"]), (0, 0));
+        assert_eq!(
+            scan(&["movq\t$0, (%rdi)", "addq\t$1, %rax", "movq\t$0, 8(%rdi)"]),
+            (0, 0)
+        );
+        assert_eq!(scan(&["movq\t$0, 8(%rdi)", "movq\t$0, (%rdi)"]), (0, 0));
+        assert_eq!(scan(&["movq\t$0, (%rdi)", "movq\t$0, 8(%rsi)"]), (0, 0));
+        // Constants are loaded relative to `%rip`.
+        assert_eq!(
+            scan(&[
+                "movq\t.LC0(%rip), %rax",
+                "movq\t%rax, (%rdi)",
+                "movq\t.LC1(%rip), %rax",
+                "movq\t%rax, 8(%rdi)",
+            ]),
+            (0, 0)
+        );
+    }
+}
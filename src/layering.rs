@@ -0,0 +1,251 @@
+use crate::config::LayerConfig;
+use crate::{FunctionID, ObjectID, ParsedData};
+use eyre::Result;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Calls between objects. Inline functions are compiled into every object that uses them,
+/// so calls through headers are attributed to the objects that include them.
+pub struct ObjectCallGraph {
+    /// Calls as (caller, callee) from functions of the caller object to functions defined in
+    /// the callee object.
+    pub calls_by_edge: BTreeMap<(ObjectID, ObjectID), BTreeSet<(FunctionID, FunctionID)>>,
+    pub path_by_object: HashMap<ObjectID, String>,
+}
+
+/// A call from an object to another object in a layer that it may not call.
+pub struct LayeringViolation {
+    pub caller_object: ObjectID,
+    pub callee_object: ObjectID,
+    pub caller_layer: String,
+    pub callee_layer: String,
+    pub call_site_function: FunctionID,
+}
+
+/// Calls to functions that the caller object defines itself, e.g. its own copy of an inline
+/// function, stay within the object.
+pub fn build_object_call_graph(parsed: &ParsedData) -> ObjectCallGraph {
+    let mut calls_by_edge: BTreeMap<(ObjectID, ObjectID), BTreeSet<(FunctionID, FunctionID)>> =
+        BTreeMap::new();
+    for (caller_object, functions) in parsed.functions_by_object.iter() {
+        for caller in functions {
+            for callee in parsed.callees_by_caller.get(caller).into_iter().flatten() {
+                if functions.binary_search(callee).is_ok() {
+                    continue;
+                }
+                for callee_object in parsed.objects_by_function.get(callee).into_iter().flatten() {
+                    calls_by_edge
+                        .entry((*caller_object, *callee_object))
+                        .or_default()
+                        .insert((*caller, *callee));
+                }
+            }
+        }
+    }
+    let path_by_object = parsed
+        .name_by_object_id
+        .iter()
+        .map(|(object_id, object)| (*object_id, object.path.to_string_lossy().into_owned()))
+        .collect();
+    ObjectCallGraph {
+        calls_by_edge,
+        path_by_object,
+    }
+}
+
+/// Objects belong to the first layer with a matching pattern. Objects without layer and calls
+/// within a layer are not restricted. Callees that are defined in several objects, like
+/// inline functions, may be called if any of their copies may be called. Otherwise the call
+/// is reported once, for the first object that defines the callee.
+pub fn check_layering(
+    object_graph: &ObjectCallGraph,
+    layers: &LayerConfig,
+) -> Vec<LayeringViolation> {
+    let layer_by_object: HashMap<ObjectID, &crate::config::Layer> = object_graph
+        .path_by_object
+        .iter()
+        .filter_map(|(object_id, path)| {
+            let layer = layers.layers.iter().find(|layer| {
+                layer
+                    .object_patterns
+                    .iter()
+                    .any(|pattern| pattern.is_match(path))
+            })?;
+            Some((*object_id, layer))
+        })
+        .collect();
+
+    // Edges are ordered by object, so the callee objects of every call are sorted.
+    let mut callee_objects_by_call: BTreeMap<(ObjectID, FunctionID, FunctionID), Vec<ObjectID>> =
+        BTreeMap::new();
+    for ((caller_object, callee_object), calls) in object_graph.calls_by_edge.iter() {
+        for (caller, callee) in calls {
+            callee_objects_by_call
+                .entry((*caller_object, *caller, *callee))
+                .or_default()
+                .push(*callee_object);
+        }
+    }
+    // Different callees in the same object are only reported once per calling function.
+    let mut violating_calls: BTreeSet<(ObjectID, ObjectID, FunctionID)> = BTreeSet::new();
+    for ((caller_object, caller, _), callee_objects) in callee_objects_by_call {
+        let Some(caller_layer) = layer_by_object.get(&caller_object) else {
+            continue;
+        };
+        let may_call = |callee_object: &ObjectID| match layer_by_object.get(callee_object) {
+            Some(callee_layer) => {
+                caller_layer.name == callee_layer.name
+                    || caller_layer.allowed_to_call.contains(&callee_layer.name)
+            }
+            None => true,
+        };
+        if !callee_objects.iter().any(may_call) {
+            violating_calls.insert((caller_object, callee_objects[0], caller));
+        }
+    }
+    violating_calls
+        .into_iter()
+        .map(
+            |(caller_object, callee_object, call_site_function)| LayeringViolation {
+                caller_object,
+                callee_object,
+                caller_layer: layer_by_object[&caller_object].name.clone(),
+                callee_layer: layer_by_object[&callee_object].name.clone(),
+                call_site_function,
+            },
+        )
+        .collect()
+}
+
+/// Prints all violations and returns an error if there are any.
+pub fn report(parsed: &ParsedData, violations: &[LayeringViolation]) -> Result<()> {
+    if violations.is_empty() {
+        eprintln!("No layering violations found.");
+        return Ok(());
+    }
+    for violation in violations {
+        println!(
+            "Layering violation: {} ({}) calls {} ({}) in {}",
+            parsed.name_by_object_id[&violation.caller_object],
+            violation.caller_layer,
+            parsed.name_by_object_id[&violation.callee_object],
+            violation.callee_layer,
+            parsed.display(violation.call_site_function)
+        );
+    }
+    Err(eyre::eyre!("{} layering violations.", violations.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Layer;
+    use crate::{ObjectName, ParseOptions};
+    use std::path::PathBuf;
+
+    fn function(name: &str, body: &str) -> String {
+        format!(
+            "\t.weak\t{name}\n\
+             \t.type\t{name}, @function\n\
+             {name}:\n\
+             {body}\
+             \tret\n\
+             \t.size\t{name}, .-{name}\n"
+        )
+    }
+
+    fn layer(name: &str, pattern: &str, allowed_to_call: &[&str]) -> Layer {
+        Layer {
+            name: name.to_owned(),
+            object_patterns: vec![crate::filter::wildcard_regex(pattern).unwrap()],
+            allowed_to_call: allowed_to_call
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn only_calls_into_forbidden_layers_are_violations() {
+        let objects = [
+            (
+                "/src/platform/p.o",
+                function("platform_function", "") + &function("shared_inline", ""),
+            ),
+            (
+                "/src/app/a.o",
+                function("app_function", "\tcall\tplatform_function\n")
+                    + &function("app_inline", ""),
+            ),
+            (
+                "/src/app/b.o",
+                function("app_inline", "") + &function("shared_inline", ""),
+            ),
+            (
+                "/src/platform/q.o",
+                function("calls_app", "\tcall\tapp_function\n")
+                    + &function("calls_app_inline", "\tcall\tapp_inline\n")
+                    + &function("calls_shared_inline", "\tcall\tshared_inline\n"),
+            ),
+        ];
+        let mut parsed = ParsedData::default();
+        for (path, assembly) in &objects {
+            let object = parsed.get_or_add_object(ObjectName {
+                path: PathBuf::from(path),
+            });
+            crate::parse_data(object, assembly, ParseOptions::default(), &mut parsed);
+        }
+        let layers = LayerConfig {
+            layers: vec![
+                layer("platform", "*/platform/*", &[]),
+                layer("app", "*/app/*", &["platform"]),
+            ],
+        };
+
+        let object_graph = build_object_call_graph(&parsed);
+        // The copy of the inline function in b.o is a second edge into the app layer.
+        let edges_from_q = object_graph
+            .calls_by_edge
+            .keys()
+            .filter(|(caller_object, _)| {
+                object_graph.path_by_object[caller_object] == "/src/platform/q.o"
+            })
+            .count();
+        assert_eq!(edges_from_q, 3);
+
+        let violations = check_layering(&object_graph, &layers);
+        let mut violations: Vec<_> = violations
+            .iter()
+            .map(|violation| {
+                (
+                    object_graph.path_by_object[&violation.caller_object].as_str(),
+                    object_graph.path_by_object[&violation.callee_object].as_str(),
+                    violation.caller_layer.as_str(),
+                    violation.callee_layer.as_str(),
+                    parsed
+                        .function_name_str(violation.call_site_function)
+                        .unwrap(),
+                )
+            })
+            .collect();
+        violations.sort();
+        assert_eq!(
+            violations,
+            [
+                (
+                    "/src/platform/q.o",
+                    "/src/app/a.o",
+                    "platform",
+                    "app",
+                    "calls_app"
+                ),
+                (
+                    "/src/platform/q.o",
+                    "/src/app/a.o",
+                    "platform",
+                    "app",
+                    "calls_app_inline"
+                ),
+            ]
+        );
+    }
+}
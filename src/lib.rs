@@ -0,0 +1,7509 @@
+//! Everything except the entry point of the command line tool, so that the Python module of
+//! the `python` feature is built from the same sources and parses the same way.
+
+use eyre::Result;
+use internment::ArcIntern;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+mod alloc;
+mod arch;
+mod assembly_diff;
+mod assertions;
+mod baseline;
+mod call_graph;
+mod changed_files;
+mod cli;
+mod compression;
+mod config;
+mod demangle;
+mod display;
+mod export;
+mod filter;
+mod id_allocator;
+mod inline_memory;
+mod layering;
+mod metrics;
+mod normalize;
+mod objdump;
+mod output;
+mod ownership;
+#[cfg(feature = "petgraph")]
+mod petgraph_interop;
+#[cfg(feature = "python")]
+mod python;
+mod report_card;
+mod rlib;
+mod sample;
+mod shard;
+mod stats;
+mod validate;
+mod wat;
+mod watch;
+
+use output::{Formatter, Record};
+
+#[derive(Deserialize, Debug)]
+struct CMakeCompileCommand {
+    directory: String,
+    command: String,
+    output: String,
+}
+
+/// Entry written by `ninja -t compdb`. Older Ninja versions only write the source `file`
+/// but no `output`.
+#[derive(Deserialize, Debug)]
+struct NinjaCompileCommand {
+    directory: String,
+    command: String,
+    file: String,
+    output: Option<String>,
+}
+
+impl From<NinjaCompileCommand> for CMakeCompileCommand {
+    /// Without `output`, the object is taken from the `-o` argument of the command, which
+    /// is the path that is actually written. The source file with an `.o` extension is only
+    /// a guess if that fails.
+    fn from(command: NinjaCompileCommand) -> Self {
+        let output = command.output.unwrap_or_else(|| {
+            shlex::split(&command.command)
+                .and_then(|args| {
+                    let output_index = args.iter().position(|arg| arg == "-o")?;
+                    args.get(output_index + 1).cloned()
+                })
+                .unwrap_or_else(|| {
+                    Path::new(&command.file)
+                        .with_extension("o")
+                        .to_string_lossy()
+                        .into_owned()
+                })
+        });
+        CMakeCompileCommand {
+            directory: command.directory,
+            command: command.command,
+            output,
+        }
+    }
+}
+
+/// Compile databases of CMake always contain `output`, those of Ninja not necessarily.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CompileDatabaseEntry {
+    CMake(CMakeCompileCommand),
+    Ninja(NinjaCompileCommand),
+}
+
+#[derive(Clone)]
+struct AssemblyGenerationCommand {
+    /// Object file that the original command generates.
+    object: String,
+    program: PathBuf,
+    args: Vec<String>,
+    cwd: String,
+    output: PathBuf,
+    /// Only read the assembly from the stdout of the compiler, without falling back to the
+    /// output file when the compiler writes nothing to stdout.
+    pipe_output: bool,
+    /// The output file has been generated before and is read instead of compiling.
+    is_pre_generated: bool,
+}
+
+impl AssemblyGenerationCommand {
+    /// Source file that is compiled, i.e. the argument following `-c`.
+    fn source_file(&self) -> Option<PathBuf> {
+        let c_index = self.args.iter().position(|arg| arg == "-c")?;
+        let source = self.args.get(c_index + 1)?;
+        Some(Path::new(&self.cwd).join(source))
+    }
+
+    /// The object path is relative to the directory the command runs in.
+    fn object_name(&self) -> Result<ObjectName> {
+        ObjectName::from_path(&Path::new(&self.cwd).join(&self.object))
+    }
+
+    /// Writes the assembly to `<dir>/<object>.s` and reads it from there instead of
+    /// compiling. Only the normal components of the object path are used, so that absolute
+    /// paths and `..` stay inside of the directory.
+    fn use_assembly_dir(&mut self, dir: &Path) -> Result<()> {
+        let mut output = std::path::absolute(dir)?;
+        output.extend(
+            Path::new(&self.object)
+                .components()
+                .filter(|component| matches!(component, std::path::Component::Normal(_))),
+        );
+        output.as_mut_os_string().push(".s");
+        if let Some(output_index) = self.args.iter().position(|arg| arg == "-o") {
+            self.args[output_index + 1] = output.to_string_lossy().into_owned();
+        }
+        self.output = output;
+        self.is_pre_generated = true;
+        Ok(())
+    }
+}
+
+/// Entry of the `compile_commands.json` that is written by
+/// `--emit-compile-commands-for-assembly`.
+#[derive(Serialize)]
+struct AssemblyCompileCommand<'a> {
+    directory: &'a str,
+    arguments: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<PathBuf>,
+    output: &'a Path,
+}
+
+/// Writes the commands so that the assembly can be generated by other tools, e.g. in
+/// parallel on a build machine. The output argument is the assembly file in the assembly
+/// directory, whose subdirectories are created because compilers don't create them.
+fn write_assembly_compile_commands(
+    commands: &[AssemblyGenerationCommand],
+    path: &Path,
+) -> Result<()> {
+    for command in commands {
+        if let Some(parent) = command.output.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|err| eyre::eyre!("Can't create {}: {}", parent.display(), err))?;
+        }
+    }
+    let entries: Vec<AssemblyCompileCommand> = commands
+        .iter()
+        .map(|command| AssemblyCompileCommand {
+            directory: &command.cwd,
+            arguments: std::iter::once(command.program.to_string_lossy().into_owned())
+                .chain(command.args.iter().cloned())
+                .collect(),
+            file: command.source_file(),
+            output: &command.output,
+        })
+        .collect();
+    let file = std::fs::File::create(path)
+        .map_err(|err| eyre::eyre!("Can't create {}: {}", path.display(), err))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), &entries)?;
+    eprintln!(
+        "Wrote {} assembly generation commands to {}.",
+        entries.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Output of `bazel aquery --output=jsonproto`.
+#[derive(Deserialize, Debug)]
+struct BazelActionGraph {
+    #[serde(default)]
+    actions: Vec<BazelAction>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BazelAction {
+    #[serde(default)]
+    mnemonic: String,
+    #[serde(default)]
+    arguments: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FunctionID(usize);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectID(usize);
+
+impl id_allocator::Id for FunctionID {
+    fn from_index(index: usize) -> Self {
+        FunctionID(index)
+    }
+
+    fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl id_allocator::Id for ObjectID {
+    fn from_index(index: usize) -> Self {
+        ObjectID(index)
+    }
+
+    fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+pub struct GlobalFunctionName {
+    name: ArcIntern<str>,
+}
+
+/// Local functions with the same name in different objects are different functions.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+pub struct LocalFunctionName {
+    name: ArcIntern<str>,
+    object: ObjectID,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+pub enum FunctionName {
+    Global(GlobalFunctionName),
+    Local(LocalFunctionName),
+}
+
+impl FunctionName {
+    fn global(name: &str) -> Self {
+        FunctionName::Global(GlobalFunctionName {
+            name: ArcIntern::from(name),
+        })
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            FunctionName::Global(global) => &global.name,
+            FunctionName::Local(local) => &local.name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+pub struct ObjectName {
+    path: PathBuf,
+}
+
+impl ObjectName {
+    /// Makes the path absolute and resolves symlinks, `.` and `..`, so that an object that is
+    /// referenced by different paths gets the same name. Objects usually don't exist because
+    /// only assembly is generated, then only the parent directory is canonicalized if
+    /// possible.
+    fn from_path(path: &Path) -> Result<ObjectName> {
+        let absolute = std::path::absolute(path)?;
+        if let Ok(canonical) = absolute.canonicalize() {
+            return Ok(ObjectName { path: canonical });
+        }
+        if let (Some(parent), Some(file_name)) = (absolute.parent(), absolute.file_name()) {
+            if let Ok(parent) = parent.canonicalize() {
+                return Ok(ObjectName {
+                    path: parent.join(file_name),
+                });
+            }
+        }
+        // Collecting the components removes repeated separators and trailing slashes.
+        Ok(ObjectName {
+            path: absolute.components().collect(),
+        })
+    }
+
+    fn basename(&self) -> &std::ffi::OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    /// Falls back to the full path if the object is not in the base directory.
+    fn relative_to(&self, base: &Path) -> PathBuf {
+        self.path
+            .strip_prefix(base)
+            .map_or_else(|_| self.path.clone(), Path::to_path_buf)
+    }
+}
+
+/// Demangles all names of the parsed data at once. Names are also demangled when they are
+/// displayed without this, but one at a time, which is much slower for many names.
+fn prepare_display(parsed: &ParsedData) {
+    demangle::prepare(parsed.name_by_function_id.values().map(FunctionName::name));
+}
+
+/// Formats a function name with the display options. Local functions also show the object
+/// they are defined in, which is looked up in the parsed data that the name belongs to.
+#[derive(Clone, Copy)]
+struct DisplayFunctionName<'a> {
+    parsed: &'a ParsedData,
+    name: &'a FunctionName,
+}
+
+impl std::fmt::Display for DisplayFunctionName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let options = display::display_options();
+        write!(
+            f,
+            "{}",
+            display::format_function_name(self.parsed, self.name, &options)
+        )
+    }
+}
+
+impl std::fmt::Display for ObjectName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let options = display::display_options();
+        write!(f, "{}", display::format_object_name(self, &options))
+    }
+}
+
+impl std::fmt::Display for FunctionID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The derived serde functions are only used by the `Serialize` and `Deserialize` impls below,
+/// which rebuild the lookup maps after loading.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, remote = "Self")]
+pub struct ParsedData {
+    /// Not stored in JSON, they are rebuilt from the ids in use after loading.
+    #[serde(skip)]
+    object_ids: id_allocator::IdAllocator<ObjectID>,
+    #[serde(skip)]
+    function_ids: id_allocator::IdAllocator<FunctionID>,
+
+    /// Maps with non-string keys can't be stored in JSON. They are rebuilt from
+    /// their inverse after loading.
+    #[serde(skip)]
+    object_id_by_name: HashMap<ObjectName, ObjectID>,
+    name_by_object_id: HashMap<ObjectID, ObjectName>,
+
+    #[serde(skip)]
+    function_id_by_name: HashMap<FunctionName, FunctionID>,
+    name_by_function_id: HashMap<FunctionID, FunctionName>,
+
+    /// The functions are sorted by id, so that they can be borrowed as a slice.
+    functions_by_object: HashMap<ObjectID, Vec<FunctionID>>,
+    objects_by_function: HashMap<FunctionID, HashSet<ObjectID>>,
+
+    callers_by_callee: HashMap<FunctionID, HashSet<FunctionID>>,
+    callees_by_caller: HashMap<FunctionID, HashSet<FunctionID>>,
+    /// Number of call instructions from a caller to a callee. For functions that are defined
+    /// in multiple objects, the maximum is used.
+    call_sites_by_caller: HashMap<FunctionID, HashMap<FunctionID, usize>>,
+
+    /// Instructions of a function in every object that defines it. Inline functions are
+    /// compiled separately in every object that uses them, so their instruction count can
+    /// differ between objects, e.g. because of different inlining decisions. Also lists the
+    /// defined functions without instructions. See `function_instructions_num` and
+    /// `total_instructions`.
+    instructions_by_object_by_function: HashMap<FunctionID, HashMap<ObjectID, usize>>,
+    instructions_with_immediate_by_function: HashMap<FunctionID, usize>,
+    /// Fraction of instructions with an immediate operand, multiplied by 1000.
+    immediate_density_by_function: HashMap<FunctionID, usize>,
+
+    tail_recursive_functions: HashSet<FunctionID>,
+    /// Functions whose unwind info references a C++ personality routine.
+    has_unwind_personality: HashSet<FunctionID>,
+    /// Functions whose address is stored in read-only data, e.g. in vtables or callback
+    /// tables.
+    function_pointer_referenced: HashSet<FunctionID>,
+    /// Functions that likely use the Windows x64 calling convention.
+    windows_abi_functions: HashSet<FunctionID>,
+    /// Functions that access thread-local variables.
+    uses_tls: HashSet<FunctionID>,
+    /// Newest x86 vector extension used by the function, only recorded for AVX and later.
+    simd_extension_by_function: HashMap<FunctionID, SimdExtension>,
+    /// Functions listed in `.lto_discard` directives, which GCC emits with `-flto` for
+    /// symbols that may be missing in the final binary.
+    lto_discarded: HashSet<FunctionID>,
+    /// Functions listed in `.lto_private_extern` directives, i.e. symbols that LTO made
+    /// internal to the binary.
+    lto_private: HashSet<FunctionID>,
+    /// Link-time warnings of functions from `.gnu.warning.<function>` sections, which glibc
+    /// uses to mark deprecated functions like `gets`.
+    deprecated_functions: HashMap<FunctionID, String>,
+    /// Functions that look like they were compiled without optimizations, e.g. because of
+    /// `__attribute__((optimize("O0")))` or `#pragma GCC optimize`.
+    possibly_deoptimized: HashSet<FunctionID>,
+    /// Symbols that are declared with `.globl` but neither typed nor defined in the object,
+    /// i.e. functions that were probably inlined at every call site. Only meaningful for
+    /// functions that have no instructions in any object.
+    fully_inlined_functions: HashSet<FunctionID>,
+    /// Only known for functions that are defined in one of the objects.
+    link_type_by_function: HashMap<FunctionID, LinkType>,
+    /// Functions in the slots of the primary vtable, keyed by the vtable symbol like
+    /// `_ZTV3Foo`. Filtered out functions are `None`, so that slots keep their position.
+    vtable_entries: HashMap<String, Vec<Option<FunctionID>>>,
+    /// Vtable slots that a function calls through, detected heuristically from a load of the
+    /// vtable pointer followed by an indirect call.
+    virtual_call_slots_by_function: HashMap<FunctionID, BTreeSet<usize>>,
+    /// Callee-saved registers that a function stores on the stack, in the order they are saved.
+    saved_registers_by_function: HashMap<FunctionID, Vec<String>>,
+    /// Largest distance between the stack pointer and the canonical frame address in bytes.
+    frame_size_by_function: HashMap<FunctionID, usize>,
+    /// File that a function is written in, according to the `.loc` directives of debug info,
+    /// or otherwise the compiled source file. For functions that are defined in multiple
+    /// objects, the first one is kept.
+    source_file_by_function: HashMap<FunctionID, PathBuf>,
+    /// Alignment in bytes that is requested before the function starts, e.g. with
+    /// `.p2align 4`.
+    alignment_overhead_by_function: HashMap<FunctionID, usize>,
+    /// Worst case number of padding bytes that the alignment inserts before the function.
+    estimated_padding_by_function: HashMap<FunctionID, usize>,
+    /// Number of distinct string literals whose address is loaded by a function.
+    string_literal_refs_by_function: HashMap<FunctionID, usize>,
+    /// Jumps to an earlier local label of the same function, which usually close a loop. For
+    /// functions that are defined in multiple objects, the maximum is used.
+    loop_count_by_function: HashMap<FunctionID, usize>,
+    /// Jumps to a later local label of the same function, i.e. the branches of conditions.
+    branch_count_by_function: HashMap<FunctionID, usize>,
+    /// Number of `ret` instructions, zero for functions that only exit through tail calls or
+    /// never return. For functions that are defined in multiple objects, the maximum is used.
+    return_count_by_function: HashMap<FunctionID, usize>,
+    /// Sequences of moves and `rep movs` that the compiler generated instead of calling
+    /// `memcpy`. For functions that are defined in multiple objects, the maximum is used.
+    inline_memcpy_count_by_function: HashMap<FunctionID, usize>,
+    /// Like `inline_memcpy_count_by_function`, but for stores of the same value and
+    /// `rep stos`.
+    inline_memset_count_by_function: HashMap<FunctionID, usize>,
+    /// Calls through function pointers and vtables, only stored if there are any. For
+    /// functions that are defined in multiple objects, the maximum is used.
+    indirect_call_count_by_function: HashMap<FunctionID, usize>,
+
+    /// Sum of the approximate reciprocal throughputs of all instructions in cycles, for every
+    /// object that defines the function like `instructions_by_object_by_function`. See
+    /// `function_weighted_instructions`.
+    weighted_instructions_by_object_by_function: HashMap<FunctionID, HashMap<ObjectID, f64>>,
+
+    /// Instruction lines of every function, only stored with `--keep-raw-assembly`. For
+    /// functions that are defined in multiple objects, the first one is kept.
+    raw_assembly_by_function: HashMap<FunctionID, Vec<String>>,
+}
+
+impl Serialize for ParsedData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ParsedData::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ParsedData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut parsed = ParsedData::deserialize(deserializer)?;
+        parsed.rebuild_lookup_maps();
+        Ok(parsed)
+    }
+}
+
+impl ParsedData {
+    fn rebuild_lookup_maps(&mut self) {
+        self.object_id_by_name = self
+            .name_by_object_id
+            .iter()
+            .map(|(id, name)| (name.clone(), *id))
+            .collect();
+        self.function_id_by_name = self
+            .name_by_function_id
+            .iter()
+            .map(|(id, name)| (name.clone(), *id))
+            .collect();
+        self.object_ids.reset();
+        for object_id in self.name_by_object_id.keys() {
+            self.object_ids.mark_used(*object_id);
+        }
+        self.function_ids.reset();
+        for function_id in self.name_by_function_id.keys() {
+            self.function_ids.mark_used(*function_id);
+        }
+        // Data that was written without --deterministic-output has arbitrary order.
+        for function_ids in self.functions_by_object.values_mut() {
+            function_ids.sort();
+            function_ids.dedup();
+        }
+    }
+
+    /// Name of the function for printing.
+    fn display(&self, function_id: FunctionID) -> DisplayFunctionName<'_> {
+        self.display_name(&self.name_by_function_id[&function_id])
+    }
+
+    fn display_name<'a>(&'a self, name: &'a FunctionName) -> DisplayFunctionName<'a> {
+        DisplayFunctionName { parsed: self, name }
+    }
+
+    /// Returns the id of the object, a new one is allocated if it's not known yet.
+    fn get_or_add_object(&mut self, object: ObjectName) -> ObjectID {
+        if let Some(object_id) = self.object_id_by_name.get(&object) {
+            return *object_id;
+        }
+        let object_id = self.object_ids.allocate();
+        self.object_id_by_name.insert(object.clone(), object_id);
+        self.name_by_object_id.insert(object_id, object);
+        object_id
+    }
+
+    /// Returns the id of the function, a new one is allocated if it's not known yet.
+    fn get_or_add_function(&mut self, function: FunctionName) -> FunctionID {
+        if let Some(function_id) = self.function_id_by_name.get(&function) {
+            return *function_id;
+        }
+        let function_id = self.function_ids.allocate();
+        self.function_id_by_name
+            .insert(function.clone(), function_id);
+        self.name_by_function_id.insert(function_id, function);
+        function_id
+    }
+
+    /// Looks up a global function by its mangled name.
+    pub fn function_id_by_global_name(&self, name: &str) -> Option<FunctionID> {
+        let name = ArcIntern::from(name);
+        self.function_id_by_name
+            .get(&FunctionName::Global(GlobalFunctionName { name }))
+            .copied()
+    }
+
+    /// Looks up a function that is local to the object by its mangled name.
+    pub fn function_id_by_local_name(&self, object: ObjectID, name: &str) -> Option<FunctionID> {
+        let name = ArcIntern::from(name);
+        self.function_id_by_name
+            .get(&FunctionName::Local(LocalFunctionName { name, object }))
+            .copied()
+    }
+
+    /// Mangled name of the function, without the object of local functions.
+    pub fn function_name_str(&self, function_id: FunctionID) -> Option<&str> {
+        self.name_by_function_id
+            .get(&function_id)
+            .map(FunctionName::name)
+    }
+
+    /// Approximate number of bytes used by the data. Hash tables are assumed to be about 60%
+    /// full, allocator overhead is ignored.
+    fn memory_usage_estimate(&self) -> usize {
+        fn table<T>(len: usize) -> usize {
+            len * std::mem::size_of::<T>() * 17 / 10
+        }
+        fn map<K, V>(map: &HashMap<K, V>) -> usize {
+            table::<(K, V)>(map.len())
+        }
+        fn set<T>(set: &HashSet<T>) -> usize {
+            table::<T>(set.len())
+        }
+        fn vec<T>(vec: &Vec<T>) -> usize {
+            vec.capacity() * std::mem::size_of::<T>()
+        }
+        fn nested_sets<K, T>(sets: &HashMap<K, HashSet<T>>) -> usize {
+            map(sets) + sets.values().map(set).sum::<usize>()
+        }
+        fn nested_maps<K, K2, V>(maps: &HashMap<K, HashMap<K2, V>>) -> usize {
+            map(maps) + maps.values().map(map).sum::<usize>()
+        }
+        fn strings(strings: &[String]) -> usize {
+            std::mem::size_of_val(strings) + strings.iter().map(String::len).sum::<usize>()
+        }
+
+        // Object paths are stored in both directions, function names are interned once.
+        let object_names: usize = self
+            .name_by_object_id
+            .values()
+            .map(|object| 2 * object.path.as_os_str().len())
+            .sum();
+        let function_names: usize = self
+            .name_by_function_id
+            .values()
+            .map(|function| function.name().len())
+            .sum();
+
+        object_names
+            + function_names
+            + map(&self.object_id_by_name)
+            + map(&self.name_by_object_id)
+            + map(&self.function_id_by_name)
+            + map(&self.name_by_function_id)
+            + map(&self.functions_by_object)
+            + self.functions_by_object.values().map(vec).sum::<usize>()
+            + nested_sets(&self.objects_by_function)
+            + nested_sets(&self.callers_by_callee)
+            + nested_sets(&self.callees_by_caller)
+            + nested_maps(&self.call_sites_by_caller)
+            + nested_maps(&self.instructions_by_object_by_function)
+            + map(&self.instructions_with_immediate_by_function)
+            + map(&self.immediate_density_by_function)
+            + set(&self.tail_recursive_functions)
+            + set(&self.has_unwind_personality)
+            + set(&self.function_pointer_referenced)
+            + set(&self.windows_abi_functions)
+            + set(&self.uses_tls)
+            + map(&self.simd_extension_by_function)
+            + set(&self.lto_discarded)
+            + set(&self.lto_private)
+            + set(&self.possibly_deoptimized)
+            + set(&self.fully_inlined_functions)
+            + map(&self.deprecated_functions)
+            + self
+                .deprecated_functions
+                .values()
+                .map(String::len)
+                .sum::<usize>()
+            + map(&self.link_type_by_function)
+            + map(&self.vtable_entries)
+            + self
+                .vtable_entries
+                .iter()
+                .map(|(vtable, entries)| vtable.len() + std::mem::size_of_val(&entries[..]))
+                .sum::<usize>()
+            + map(&self.virtual_call_slots_by_function)
+            + self
+                .virtual_call_slots_by_function
+                .values()
+                .map(|slots| table::<usize>(slots.len()))
+                .sum::<usize>()
+            + map(&self.saved_registers_by_function)
+            + self
+                .saved_registers_by_function
+                .values()
+                .map(|registers| strings(registers))
+                .sum::<usize>()
+            + map(&self.frame_size_by_function)
+            + map(&self.source_file_by_function)
+            + self
+                .source_file_by_function
+                .values()
+                .map(|path| path.as_os_str().len())
+                .sum::<usize>()
+            + map(&self.alignment_overhead_by_function)
+            + map(&self.estimated_padding_by_function)
+            + map(&self.string_literal_refs_by_function)
+            + map(&self.loop_count_by_function)
+            + map(&self.branch_count_by_function)
+            + map(&self.return_count_by_function)
+            + map(&self.inline_memcpy_count_by_function)
+            + map(&self.inline_memset_count_by_function)
+            + map(&self.indirect_call_count_by_function)
+            + nested_maps(&self.weighted_instructions_by_object_by_function)
+            + map(&self.raw_assembly_by_function)
+            + self
+                .raw_assembly_by_function
+                .values()
+                .map(|lines| strings(lines))
+                .sum::<usize>()
+    }
+}
+
+/// Iterates over all functions with the instruction count of their largest copy. The count
+/// is `None` for functions that are only called but not defined in any of the objects.
+pub struct FunctionIter<'a> {
+    parsed: &'a ParsedData,
+    names: std::collections::hash_map::Iter<'a, FunctionID, FunctionName>,
+}
+
+impl<'a> Iterator for FunctionIter<'a> {
+    type Item = (FunctionID, &'a FunctionName, Option<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (function_id, function) = self.names.next()?;
+        let instructions_num = self
+            .parsed
+            .instructions_by_object_by_function
+            .contains_key(function_id)
+            .then(|| function_instructions_num(self.parsed, *function_id));
+        Some((*function_id, function, instructions_num))
+    }
+}
+
+impl<'a> IntoIterator for &'a ParsedData {
+    type Item = (FunctionID, &'a FunctionName, Option<usize>);
+    type IntoIter = FunctionIter<'a>;
+
+    fn into_iter(self) -> FunctionIter<'a> {
+        FunctionIter {
+            parsed: self,
+            names: self.name_by_function_id.iter(),
+        }
+    }
+}
+
+impl ParsedData {
+    pub fn functions(&self) -> FunctionIter<'_> {
+        self.into_iter()
+    }
+
+    /// All objects with the functions they define. The functions are sorted by id.
+    pub fn objects(&self) -> impl Iterator<Item = (ObjectID, &ObjectName, &[FunctionID])> {
+        self.name_by_object_id.iter().map(|(object_id, object)| {
+            let functions = self
+                .functions_by_object
+                .get(object_id)
+                .map_or(&[][..], Vec::as_slice);
+            (*object_id, object, functions)
+        })
+    }
+}
+
+impl ParsedData {
+    /// Removes all functions for which `predicate` returns false, including the calls to and
+    /// from them.
+    pub fn retain_functions(&mut self, predicate: impl Fn(FunctionID, &FunctionName) -> bool) {
+        let kept: HashSet<FunctionID> = self
+            .name_by_function_id
+            .iter()
+            .filter(|(function_id, function)| predicate(**function_id, function))
+            .map(|(function_id, _)| *function_id)
+            .collect();
+        let keep = |function_id: FunctionID| kept.contains(&function_id);
+        fn retain_map<V>(map: &mut HashMap<FunctionID, V>, keep: &impl Fn(FunctionID) -> bool) {
+            map.retain(|function_id, _| keep(*function_id));
+        }
+        fn retain_graph(
+            map: &mut HashMap<FunctionID, HashSet<FunctionID>>,
+            keep: &impl Fn(FunctionID) -> bool,
+        ) {
+            map.retain(|function_id, _| keep(*function_id));
+            for function_ids in map.values_mut() {
+                function_ids.retain(|function_id| keep(*function_id));
+            }
+            map.retain(|_, function_ids| !function_ids.is_empty());
+        }
+
+        self.function_id_by_name
+            .retain(|_, function_id| keep(*function_id));
+        retain_map(&mut self.name_by_function_id, &keep);
+        for function_ids in self.functions_by_object.values_mut() {
+            function_ids.retain(|function_id| keep(*function_id));
+        }
+        self.functions_by_object
+            .retain(|_, function_ids| !function_ids.is_empty());
+        retain_map(&mut self.objects_by_function, &keep);
+        retain_graph(&mut self.callers_by_callee, &keep);
+        retain_graph(&mut self.callees_by_caller, &keep);
+        retain_map(&mut self.call_sites_by_caller, &keep);
+        for call_sites in self.call_sites_by_caller.values_mut() {
+            call_sites.retain(|callee, _| keep(*callee));
+        }
+        self.call_sites_by_caller
+            .retain(|_, call_sites| !call_sites.is_empty());
+        retain_map(&mut self.instructions_by_object_by_function, &keep);
+        retain_map(&mut self.instructions_with_immediate_by_function, &keep);
+        retain_map(&mut self.immediate_density_by_function, &keep);
+        self.tail_recursive_functions
+            .retain(|function_id| keep(*function_id));
+        self.has_unwind_personality
+            .retain(|function_id| keep(*function_id));
+        self.function_pointer_referenced
+            .retain(|function_id| keep(*function_id));
+        self.windows_abi_functions
+            .retain(|function_id| keep(*function_id));
+        self.uses_tls.retain(|function_id| keep(*function_id));
+        retain_map(&mut self.simd_extension_by_function, &keep);
+        self.lto_discarded.retain(|function_id| keep(*function_id));
+        self.lto_private.retain(|function_id| keep(*function_id));
+        self.possibly_deoptimized
+            .retain(|function_id| keep(*function_id));
+        self.fully_inlined_functions
+            .retain(|function_id| keep(*function_id));
+        retain_map(&mut self.deprecated_functions, &keep);
+        retain_map(&mut self.link_type_by_function, &keep);
+        for entries in self.vtable_entries.values_mut() {
+            for entry in entries.iter_mut() {
+                if entry.is_some_and(|function_id| !keep(function_id)) {
+                    *entry = None;
+                }
+            }
+        }
+        retain_map(&mut self.virtual_call_slots_by_function, &keep);
+        retain_map(&mut self.saved_registers_by_function, &keep);
+        retain_map(&mut self.frame_size_by_function, &keep);
+        retain_map(&mut self.source_file_by_function, &keep);
+        retain_map(&mut self.alignment_overhead_by_function, &keep);
+        retain_map(&mut self.estimated_padding_by_function, &keep);
+        retain_map(&mut self.raw_assembly_by_function, &keep);
+        retain_map(&mut self.string_literal_refs_by_function, &keep);
+        retain_map(&mut self.loop_count_by_function, &keep);
+        retain_map(&mut self.branch_count_by_function, &keep);
+        retain_map(&mut self.return_count_by_function, &keep);
+        retain_map(&mut self.inline_memcpy_count_by_function, &keep);
+        retain_map(&mut self.inline_memset_count_by_function, &keep);
+        retain_map(&mut self.indirect_call_count_by_function, &keep);
+        retain_map(&mut self.weighted_instructions_by_object_by_function, &keep);
+    }
+
+    /// All functions that the data refers to apart from the name maps. Has to cover the same
+    /// maps as `retain_functions`.
+    fn referenced_functions(&self) -> HashSet<FunctionID> {
+        let mut functions: HashSet<FunctionID> = HashSet::new();
+        functions.extend(self.functions_by_object.values().flatten());
+        functions.extend(self.objects_by_function.keys());
+        for graph in [&self.callers_by_callee, &self.callees_by_caller] {
+            for (function_id, function_ids) in graph {
+                functions.insert(*function_id);
+                functions.extend(function_ids);
+            }
+        }
+        for (caller, call_sites) in &self.call_sites_by_caller {
+            functions.insert(*caller);
+            functions.extend(call_sites.keys());
+        }
+        functions.extend(self.instructions_by_object_by_function.keys());
+        functions.extend(self.instructions_with_immediate_by_function.keys());
+        functions.extend(self.immediate_density_by_function.keys());
+        for set in [
+            &self.tail_recursive_functions,
+            &self.has_unwind_personality,
+            &self.function_pointer_referenced,
+            &self.windows_abi_functions,
+            &self.uses_tls,
+            &self.lto_discarded,
+            &self.lto_private,
+            &self.possibly_deoptimized,
+            &self.fully_inlined_functions,
+        ] {
+            functions.extend(set);
+        }
+        functions.extend(self.deprecated_functions.keys());
+        functions.extend(self.link_type_by_function.keys());
+        functions.extend(self.simd_extension_by_function.keys());
+        functions.extend(self.vtable_entries.values().flatten().flatten());
+        functions.extend(self.virtual_call_slots_by_function.keys());
+        functions.extend(self.saved_registers_by_function.keys());
+        functions.extend(self.frame_size_by_function.keys());
+        functions.extend(self.source_file_by_function.keys());
+        functions.extend(self.alignment_overhead_by_function.keys());
+        functions.extend(self.estimated_padding_by_function.keys());
+        functions.extend(self.raw_assembly_by_function.keys());
+        for counts in [
+            &self.string_literal_refs_by_function,
+            &self.loop_count_by_function,
+            &self.branch_count_by_function,
+            &self.return_count_by_function,
+            &self.inline_memcpy_count_by_function,
+            &self.inline_memset_count_by_function,
+            &self.indirect_call_count_by_function,
+        ] {
+            functions.extend(counts.keys());
+        }
+        functions.extend(self.weighted_instructions_by_object_by_function.keys());
+        functions
+    }
+
+    /// Removes all objects for which `predicate` returns false. Functions that are only
+    /// defined in removed objects are removed as well, and so are external functions that
+    /// were only called from them. Other functions lose the instructions of the removed
+    /// objects, data that is not stored per object is kept.
+    pub fn retain_objects(&mut self, predicate: impl Fn(ObjectID, &ObjectName) -> bool) {
+        let removed: HashSet<ObjectID> = self
+            .name_by_object_id
+            .iter()
+            .filter(|(object_id, object)| !predicate(**object_id, object))
+            .map(|(object_id, _)| *object_id)
+            .collect();
+        if removed.is_empty() {
+            return;
+        }
+        let removed_functions: HashSet<FunctionID> = self
+            .objects_by_function
+            .iter()
+            .filter(|(_, objects)| objects.iter().all(|object| removed.contains(object)))
+            .map(|(function_id, _)| *function_id)
+            .collect();
+        let callees_of_removed: HashSet<FunctionID> = removed_functions
+            .iter()
+            .filter_map(|function_id| self.callees_by_caller.get(function_id))
+            .flatten()
+            .copied()
+            .collect();
+        self.retain_functions(|function_id, function| {
+            let is_local_to_removed_object = matches!(
+                function,
+                FunctionName::Local(local) if removed.contains(&local.object)
+            );
+            !removed_functions.contains(&function_id) && !is_local_to_removed_object
+        });
+        let orphaned: HashSet<FunctionID> = callees_of_removed
+            .into_iter()
+            .filter(|function_id| {
+                !self.objects_by_function.contains_key(function_id)
+                    && !self.callers_by_callee.contains_key(function_id)
+            })
+            .collect();
+        if !orphaned.is_empty() {
+            self.retain_functions(|function_id, _| !orphaned.contains(&function_id));
+        }
+
+        self.object_id_by_name
+            .retain(|_, object_id| !removed.contains(object_id));
+        self.name_by_object_id
+            .retain(|object_id, _| !removed.contains(object_id));
+        self.functions_by_object
+            .retain(|object_id, _| !removed.contains(object_id));
+        for objects in self.objects_by_function.values_mut() {
+            objects.retain(|object_id| !removed.contains(object_id));
+        }
+        for nums in self.instructions_by_object_by_function.values_mut() {
+            nums.retain(|object_id, _| !removed.contains(object_id));
+        }
+        self.instructions_by_object_by_function
+            .retain(|_, nums| !nums.is_empty());
+        for weights in self
+            .weighted_instructions_by_object_by_function
+            .values_mut()
+        {
+            weights.retain(|object_id, _| !removed.contains(object_id));
+        }
+        self.weighted_instructions_by_object_by_function
+            .retain(|_, weights| !weights.is_empty());
+    }
+
+    /// Removes all functions that match the filter.
+    fn exclude_functions(&mut self, filter: &filter::FilterSet) {
+        if filter.is_empty() {
+            return;
+        }
+        let excluded: HashSet<FunctionID> = self
+            .name_by_function_id
+            .iter()
+            .filter(|(_, function)| filter.matches(function))
+            .map(|(function_id, _)| *function_id)
+            .collect();
+        self.retain_functions(|function_id, _| !excluded.contains(&function_id));
+    }
+
+    /// Makes the source files that were parsed relative to the directory of the compiler
+    /// invocation absolute. Functions of the object without debug info are attributed to the
+    /// compiled file. Only the functions of the given object are visited, all other paths have
+    /// been resolved when their object was added.
+    fn resolve_source_files(&mut self, object: ObjectID, command: &AssemblyGenerationCommand) {
+        let compiled_file = command.source_file();
+        for function_id in self.functions_by_object.get(&object).into_iter().flatten() {
+            match self.source_file_by_function.get_mut(function_id) {
+                Some(path) => {
+                    if path.is_relative() {
+                        *path = Path::new(&command.cwd).join(&*path);
+                    }
+                }
+                None => {
+                    if let Some(compiled_file) = &compiled_file {
+                        self.source_file_by_function
+                            .insert(*function_id, compiled_file.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes all functions that don't match the filter.
+    fn retain_matching_functions(&mut self, filter: &filter::FilterSet) {
+        let retained: HashSet<FunctionID> = self
+            .name_by_function_id
+            .iter()
+            .filter(|(_, function)| filter.matches(function))
+            .map(|(function_id, _)| *function_id)
+            .collect();
+        self.retain_functions(|function_id, _| retained.contains(&function_id));
+    }
+
+    /// Replaces all functions that are not defined in any analyzed object by a single
+    /// `[external]` function, so that calls to `memcpy`, `malloc` etc. don't clutter call
+    /// graphs. Returns the number of collapsed functions.
+    fn collapse_library_callees(&mut self) -> usize {
+        let external: HashSet<FunctionID> = self
+            .name_by_function_id
+            .keys()
+            .copied()
+            .filter(|function_id| !is_defined_function(self, *function_id))
+            .collect();
+        if external.is_empty() {
+            return 0;
+        }
+        // Callers of external functions with the number of their call sites.
+        let mut external_call_sites: HashMap<FunctionID, usize> = HashMap::new();
+        for callee in external.iter() {
+            for caller in self.callers_by_callee.get(callee).into_iter().flatten() {
+                let call_sites_num = self
+                    .call_sites_by_caller
+                    .get(caller)
+                    .and_then(|call_sites| call_sites.get(callee))
+                    .copied()
+                    .unwrap_or_default();
+                *external_call_sites.entry(*caller).or_default() += call_sites_num;
+            }
+        }
+        self.retain_functions(|function_id, _| !external.contains(&function_id));
+
+        let external_id = self.get_or_add_function(FunctionName::global(EXTERNAL_FUNCTION_NAME));
+        for (caller, call_sites_num) in external_call_sites {
+            self.callees_by_caller
+                .entry(caller)
+                .or_default()
+                .insert(external_id);
+            self.callers_by_callee
+                .entry(external_id)
+                .or_default()
+                .insert(caller);
+            if call_sites_num > 0 {
+                self.call_sites_by_caller
+                    .entry(caller)
+                    .or_default()
+                    .insert(external_id, call_sites_num);
+            }
+        }
+        external.len()
+    }
+}
+
+/// Name of the function that stands for all collapsed library callees.
+const EXTERNAL_FUNCTION_NAME: &str = "[external]";
+
+/// Functions that are only called are not defined in any of the analyzed objects.
+fn is_defined_function(parsed: &ParsedData, func: FunctionID) -> bool {
+    parsed.objects_by_function.contains_key(&func)
+}
+
+/// Same as `ParsedData` but with a stable iteration order, so that the written JSON does not
+/// change between runs. It has the same JSON layout, so it can be loaded as `ParsedData`.
+#[derive(Serialize)]
+struct DeterministicParsedData {
+    name_by_object_id: BTreeMap<ObjectID, ObjectName>,
+    name_by_function_id: BTreeMap<FunctionID, FunctionName>,
+
+    functions_by_object: BTreeMap<ObjectID, Vec<FunctionID>>,
+    objects_by_function: BTreeMap<FunctionID, BTreeSet<ObjectID>>,
+
+    callers_by_callee: BTreeMap<FunctionID, BTreeSet<FunctionID>>,
+    callees_by_caller: BTreeMap<FunctionID, BTreeSet<FunctionID>>,
+    call_sites_by_caller: BTreeMap<FunctionID, BTreeMap<FunctionID, usize>>,
+
+    instructions_by_object_by_function: BTreeMap<FunctionID, BTreeMap<ObjectID, usize>>,
+    instructions_with_immediate_by_function: BTreeMap<FunctionID, usize>,
+    immediate_density_by_function: BTreeMap<FunctionID, usize>,
+
+    tail_recursive_functions: BTreeSet<FunctionID>,
+    has_unwind_personality: BTreeSet<FunctionID>,
+    function_pointer_referenced: BTreeSet<FunctionID>,
+    windows_abi_functions: BTreeSet<FunctionID>,
+    uses_tls: BTreeSet<FunctionID>,
+    simd_extension_by_function: BTreeMap<FunctionID, SimdExtension>,
+    lto_discarded: BTreeSet<FunctionID>,
+    lto_private: BTreeSet<FunctionID>,
+    possibly_deoptimized: BTreeSet<FunctionID>,
+    fully_inlined_functions: BTreeSet<FunctionID>,
+    deprecated_functions: BTreeMap<FunctionID, String>,
+    link_type_by_function: BTreeMap<FunctionID, LinkType>,
+    vtable_entries: BTreeMap<String, Vec<Option<FunctionID>>>,
+    virtual_call_slots_by_function: BTreeMap<FunctionID, BTreeSet<usize>>,
+    saved_registers_by_function: BTreeMap<FunctionID, Vec<String>>,
+    frame_size_by_function: BTreeMap<FunctionID, usize>,
+    source_file_by_function: BTreeMap<FunctionID, PathBuf>,
+    alignment_overhead_by_function: BTreeMap<FunctionID, usize>,
+    estimated_padding_by_function: BTreeMap<FunctionID, usize>,
+    raw_assembly_by_function: BTreeMap<FunctionID, Vec<String>>,
+    string_literal_refs_by_function: BTreeMap<FunctionID, usize>,
+    loop_count_by_function: BTreeMap<FunctionID, usize>,
+    branch_count_by_function: BTreeMap<FunctionID, usize>,
+    return_count_by_function: BTreeMap<FunctionID, usize>,
+    inline_memcpy_count_by_function: BTreeMap<FunctionID, usize>,
+    inline_memset_count_by_function: BTreeMap<FunctionID, usize>,
+    indirect_call_count_by_function: BTreeMap<FunctionID, usize>,
+
+    weighted_instructions_by_object_by_function: BTreeMap<FunctionID, BTreeMap<ObjectID, f64>>,
+}
+
+fn to_deterministic(parsed: ParsedData) -> DeterministicParsedData {
+    fn sorted_sets<K: Ord, V: Ord>(map: HashMap<K, HashSet<V>>) -> BTreeMap<K, BTreeSet<V>> {
+        map.into_iter()
+            .map(|(key, values)| (key, values.into_iter().collect()))
+            .collect()
+    }
+    DeterministicParsedData {
+        name_by_object_id: parsed.name_by_object_id.into_iter().collect(),
+        name_by_function_id: parsed.name_by_function_id.into_iter().collect(),
+        functions_by_object: parsed.functions_by_object.into_iter().collect(),
+        objects_by_function: sorted_sets(parsed.objects_by_function),
+        callers_by_callee: sorted_sets(parsed.callers_by_callee),
+        callees_by_caller: sorted_sets(parsed.callees_by_caller),
+        call_sites_by_caller: parsed
+            .call_sites_by_caller
+            .into_iter()
+            .map(|(caller, call_sites)| (caller, call_sites.into_iter().collect()))
+            .collect(),
+        instructions_by_object_by_function: parsed
+            .instructions_by_object_by_function
+            .into_iter()
+            .map(|(function_id, nums)| (function_id, nums.into_iter().collect()))
+            .collect(),
+        instructions_with_immediate_by_function: parsed
+            .instructions_with_immediate_by_function
+            .into_iter()
+            .collect(),
+        immediate_density_by_function: parsed.immediate_density_by_function.into_iter().collect(),
+        tail_recursive_functions: parsed.tail_recursive_functions.into_iter().collect(),
+        has_unwind_personality: parsed.has_unwind_personality.into_iter().collect(),
+        function_pointer_referenced: parsed.function_pointer_referenced.into_iter().collect(),
+        windows_abi_functions: parsed.windows_abi_functions.into_iter().collect(),
+        uses_tls: parsed.uses_tls.into_iter().collect(),
+        simd_extension_by_function: parsed.simd_extension_by_function.into_iter().collect(),
+        lto_discarded: parsed.lto_discarded.into_iter().collect(),
+        lto_private: parsed.lto_private.into_iter().collect(),
+        possibly_deoptimized: parsed.possibly_deoptimized.into_iter().collect(),
+        fully_inlined_functions: parsed.fully_inlined_functions.into_iter().collect(),
+        deprecated_functions: parsed.deprecated_functions.into_iter().collect(),
+        link_type_by_function: parsed.link_type_by_function.into_iter().collect(),
+        vtable_entries: parsed.vtable_entries.into_iter().collect(),
+        virtual_call_slots_by_function: parsed.virtual_call_slots_by_function.into_iter().collect(),
+        saved_registers_by_function: parsed.saved_registers_by_function.into_iter().collect(),
+        frame_size_by_function: parsed.frame_size_by_function.into_iter().collect(),
+        source_file_by_function: parsed.source_file_by_function.into_iter().collect(),
+        alignment_overhead_by_function: parsed.alignment_overhead_by_function.into_iter().collect(),
+        estimated_padding_by_function: parsed.estimated_padding_by_function.into_iter().collect(),
+        raw_assembly_by_function: parsed.raw_assembly_by_function.into_iter().collect(),
+        string_literal_refs_by_function: parsed
+            .string_literal_refs_by_function
+            .into_iter()
+            .collect(),
+        loop_count_by_function: parsed.loop_count_by_function.into_iter().collect(),
+        branch_count_by_function: parsed.branch_count_by_function.into_iter().collect(),
+        return_count_by_function: parsed.return_count_by_function.into_iter().collect(),
+        inline_memcpy_count_by_function: parsed
+            .inline_memcpy_count_by_function
+            .into_iter()
+            .collect(),
+        inline_memset_count_by_function: parsed
+            .inline_memset_count_by_function
+            .into_iter()
+            .collect(),
+        indirect_call_count_by_function: parsed
+            .indirect_call_count_by_function
+            .into_iter()
+            .collect(),
+        weighted_instructions_by_object_by_function: parsed
+            .weighted_instructions_by_object_by_function
+            .into_iter()
+            .map(|(function_id, weights)| (function_id, weights.into_iter().collect()))
+            .collect(),
+    }
+}
+
+fn load_cmake_compile_commands(path: &std::path::Path) -> Result<Vec<CMakeCompileCommand>> {
+    let file = std::fs::File::open(path)?;
+    let entries: Vec<CompileDatabaseEntry> = serde_json::from_reader(file)?;
+    let compile_commands = entries
+        .into_iter()
+        .map(|entry| match entry {
+            CompileDatabaseEntry::CMake(command) => command,
+            CompileDatabaseEntry::Ninja(command) => command.into(),
+        })
+        .collect();
+    let database_dir = std::path::absolute(path)?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    Ok(normalize_compile_commands(compile_commands, &database_dir))
+}
+
+/// Makes relative directories absolute by resolving them against the directory of the
+/// compile database, so that the commands don't depend on the current directory. Outputs
+/// stay relative to the directory because `--object` refers to them as they are written in
+/// the database.
+fn normalize_compile_commands(
+    commands: Vec<CMakeCompileCommand>,
+    database_dir: &Path,
+) -> Vec<CMakeCompileCommand> {
+    commands
+        .into_iter()
+        .map(|command| CMakeCompileCommand {
+            directory: database_dir
+                .join(&command.directory)
+                .to_string_lossy()
+                .into_owned(),
+            ..command
+        })
+        .collect()
+}
+
+impl TryFrom<&CMakeCompileCommand> for ObjectName {
+    type Error = eyre::Report;
+
+    fn try_from(command: &CMakeCompileCommand) -> Result<Self> {
+        if command.output.is_empty() {
+            return Err(eyre::eyre!(
+                "Compile command in {} has no output.",
+                command.directory
+            ));
+        }
+        ObjectName::from_path(&Path::new(&command.directory).join(&command.output))
+    }
+}
+
+impl TryFrom<&CMakeCompileCommand> for AssemblyGenerationCommand {
+    type Error = eyre::Report;
+
+    fn try_from(command: &CMakeCompileCommand) -> Result<Self> {
+        // Validates the output, the original string is kept so that it matches `--object`.
+        ObjectName::try_from(command)?;
+        let mut args =
+            shlex::split(&command.command).ok_or(eyre::eyre!("Can't split cmake command."))?;
+        // Compilers write `<source>.o` without `-o`, which Ninja databases don't always state.
+        if !args.iter().any(|arg| arg == "-o") {
+            args.extend(["-o".to_owned(), command.output.clone()]);
+        }
+        Ok(AssemblyGenerationCommand {
+            object: command.output.clone(),
+            ..adapt_args_to_generate_assembly(args, &command.directory)?
+        })
+    }
+}
+
+/// Turns the arguments of a compiler invocation that generates an object file into one
+/// that generates assembly instead.
+fn adapt_args_to_generate_assembly(
+    mut args: Vec<String>,
+    cwd: &str,
+) -> Result<AssemblyGenerationCommand> {
+    let output_index = args
+        .iter()
+        .position(|x| x == "-o")
+        .ok_or(eyre::eyre!("Can't find -o in the command."))?;
+    let object = args
+        .get(output_index + 1)
+        .ok_or(eyre::eyre!("Missing output path after -o."))?
+        .clone();
+    let mut assembly_file_path = std::path::Path::new(cwd).join(&object);
+    assembly_file_path.set_extension("txt");
+    args[output_index + 1] = assembly_file_path
+        .to_str()
+        .ok_or(eyre::eyre!("Failed to create assembly output path."))?
+        .to_owned();
+    args.insert(output_index, "-S".to_owned());
+    Ok(AssemblyGenerationCommand {
+        object,
+        program: std::path::PathBuf::from(args[0].clone()),
+        args: args[1..].to_owned(),
+        cwd: cwd.to_owned(),
+        output: assembly_file_path,
+        pipe_output: false,
+        is_pre_generated: false,
+    })
+}
+
+/// Extracts the compiler invocations from Bazel's action graph, e.g. generated with
+/// `bazel aquery --output=jsonproto 'mnemonic("CppCompile", //...)'`. Bazel runs the
+/// commands in its execution root which has to be passed in.
+fn load_bazel_aquery(
+    aquery_json_path: &Path,
+    execution_root: &Path,
+) -> Result<Vec<AssemblyGenerationCommand>> {
+    let file = std::io::BufReader::new(std::fs::File::open(aquery_json_path)?);
+    let action_graph: BazelActionGraph = serde_json::from_reader(file)?;
+    let cwd = execution_root
+        .to_str()
+        .ok_or(eyre::eyre!("Invalid execution root."))?;
+    action_graph
+        .actions
+        .into_iter()
+        .filter(|action| action.mnemonic.is_empty() || action.mnemonic == "CppCompile")
+        .filter(|action| action.arguments.iter().any(|arg| arg == "-c"))
+        .map(|action| adapt_args_to_generate_assembly(action.arguments, cwd))
+        .collect()
+}
+
+/// Whether the program is a C or C++ compiler, including cross compilers like
+/// `aarch64-linux-gnu-g++` and versioned ones like `clang-16`.
+fn is_compiler_program(program: &str) -> bool {
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let name = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-');
+    ["cc", "c++", "gcc", "g++", "clang", "clang++"]
+        .iter()
+        .any(|compiler| name == *compiler || name.ends_with(&format!("-{}", compiler)))
+}
+
+/// Extracts the compiler invocations from the output of a dry run like `make -n` or
+/// `make --print-data-base -n`. Commands are run in the directory that make reports with
+/// `Entering directory`, or in the current directory.
+fn load_makefile_commands(make_output: &str) -> Result<Vec<AssemblyGenerationCommand>> {
+    let mut commands = vec![];
+    let mut directories = vec![".".to_owned()];
+    let mut pending_line = String::new();
+    for line in make_output.lines() {
+        // Long commands are often split with a trailing backslash.
+        if let Some(continued) = line.strip_suffix('\\') {
+            pending_line.push_str(continued);
+            continue;
+        }
+        pending_line.push_str(line);
+        let line = std::mem::take(&mut pending_line);
+        if let Some((_, directory)) = line.split_once(": Entering directory '") {
+            directories.push(directory.trim_end_matches('\'').to_owned());
+            continue;
+        }
+        if line.contains(": Leaving directory '") {
+            if directories.len() > 1 {
+                directories.pop();
+            }
+            continue;
+        }
+        let Some(args) = shlex::split(line.trim().trim_start_matches('@')) else {
+            continue;
+        };
+        let is_compile_command = args
+            .first()
+            .is_some_and(|program| is_compiler_program(program))
+            && args.iter().any(|arg| arg == "-c")
+            && args.iter().any(|arg| arg == "-o");
+        if is_compile_command {
+            commands.push(adapt_args_to_generate_assembly(
+                args,
+                directories.last().unwrap(),
+            )?);
+        }
+    }
+    Ok(commands)
+}
+
+fn load_assembly_generation_commands(
+    source: &cli::SourceArgs,
+) -> Result<Vec<AssemblyGenerationCommand>> {
+    let commands = load_modified_commands(source)?;
+    check_object_files_limit(&commands, source)?;
+    Ok(commands)
+}
+
+/// Selects the commands and applies the flag modifications, without the limit of the number
+/// of objects that only matters if they are compiled.
+fn load_modified_commands(source: &cli::SourceArgs) -> Result<Vec<AssemblyGenerationCommand>> {
+    let mut commands = select_assembly_generation_commands(source)?;
+    if let Some(changed_files) = &source.changed_files {
+        changed_files::retain_changed_files(&mut commands, changed_files)?;
+    }
+    if source.shard_index >= source.shard_count {
+        return Err(eyre::eyre!(
+            "The shard index has to be smaller than the shard count ({}).",
+            source.shard_count
+        ));
+    }
+    if source.shard_count > 1 {
+        commands = commands
+            .into_iter()
+            .skip(source.shard_index)
+            .step_by(source.shard_count)
+            .collect();
+    }
+    for command in commands.iter_mut() {
+        apply_flag_modifications(
+            &mut command.args,
+            &source.removed_flags,
+            &source.replaced_flags,
+            &source.extra_flags,
+        );
+        if source.strip_debug_flags {
+            strip_debug_flags(&mut command.args);
+        }
+        command.pipe_output = source.pipe_mode;
+        if let Some(assembly_dir) = &source.assembly_dir {
+            command.use_assembly_dir(assembly_dir)?;
+        }
+    }
+    Ok(commands)
+}
+
+/// Runs before any compilation, so that analyzing a whole project has to be requested
+/// explicitly.
+fn check_object_files_limit(
+    commands: &[AssemblyGenerationCommand],
+    source: &cli::SourceArgs,
+) -> Result<()> {
+    if source.assembly_dir.is_some() {
+        return Ok(());
+    }
+    let objects_num = source
+        .sample
+        .map_or(commands.len(), |n| n.min(commands.len()));
+    eprintln!("{} objects match.", objects_num);
+    match source.max_object_files {
+        Some(max_object_files) if objects_num > max_object_files => Err(eyre::eyre!(
+            "{} objects would be analyzed, more than the limit of {}. Select fewer objects \
+             with --object or --sample, or raise the limit with --max-object-files <n> or \
+             --all-objects.",
+            objects_num,
+            max_object_files
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Removes the flags that generate debug information. It does not change the instructions,
+/// but the `.loc` directives make compilation and parsing slower.
+fn strip_debug_flags(args: &mut Vec<String>) {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        let is_debug_level = arg
+            .strip_prefix("-g")
+            .is_some_and(|level| level.chars().all(|c| c.is_ascii_digit()));
+        if arg == "-fdebug-compilation-dir" {
+            // The directory is passed as separate argument.
+            args.drain(i..(i + 2).min(args.len()));
+        } else if is_debug_level
+            || arg == "-gline-tables-only"
+            || arg == "-gno-column-info"
+            || arg.starts_with("-gdwarf")
+            || arg.starts_with("-fdebug-compilation-dir=")
+            || arg.starts_with("-fdebug-prefix-map=")
+        {
+            args.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Levels of `-O` like `2`, `s` or `fast`.
+fn is_optimization_level(arg: &str) -> bool {
+    matches!(arg, "s" | "z" | "fast" | "g") || arg.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// Removes and replaces flags and then appends the additions. Optimization flags match in
+/// both the merged (`-O2`) and the split (`-O 2`) style.
+fn apply_flag_modifications(
+    args: &mut Vec<String>,
+    removals: &[String],
+    replacements: &[(String, String)],
+    additions: &[String],
+) {
+    let mut modified_args = Vec::with_capacity(args.len() + additions.len());
+    let mut i = 0;
+    while i < args.len() {
+        let (flag, consumed_num) = match args.get(i + 1) {
+            Some(level) if args[i] == "-O" && is_optimization_level(level) => {
+                (format!("-O{}", level), 2)
+            }
+            _ => (args[i].clone(), 1),
+        };
+        if removals.contains(&flag) {
+            // Skip the flag.
+        } else if let Some((_, new)) = replacements.iter().find(|(old, _)| *old == flag) {
+            modified_args.push(new.clone());
+        } else {
+            modified_args.extend_from_slice(&args[i..i + consumed_num]);
+        }
+        i += consumed_num;
+    }
+    modified_args.extend_from_slice(additions);
+    *args = modified_args;
+}
+
+fn select_assembly_generation_commands(
+    source: &cli::SourceArgs,
+) -> Result<Vec<AssemblyGenerationCommand>> {
+    let commands = if let Some(aquery_path) = &source.bazel_aquery {
+        load_bazel_aquery(aquery_path, &source.bazel_execution_root)?
+    } else if let Some(make_output_path) = &source.makefile_commands {
+        load_makefile_commands(&std::fs::read_to_string(make_output_path)?)?
+    } else {
+        let compile_commands = load_cmake_compile_commands(&source.compile_commands)?;
+        if source.objects.is_empty() {
+            return compile_commands
+                .iter()
+                .map(AssemblyGenerationCommand::try_from)
+                .collect();
+        }
+        // Only adapt the commands that are actually used.
+        compile_commands
+            .iter()
+            .filter(|command| source.objects.contains(&command.output))
+            .map(AssemblyGenerationCommand::try_from)
+            .collect::<Result<Vec<_>>>()?
+    };
+    if source.objects.is_empty() {
+        return Ok(commands);
+    }
+    let mut command_by_object: HashMap<String, AssemblyGenerationCommand> = commands
+        .into_iter()
+        .map(|command| (command.object.clone(), command))
+        .collect();
+    source
+        .objects
+        .iter()
+        .map(|object| {
+            command_by_object
+                .remove(object)
+                .ok_or(eyre::eyre!("Can't find compile command for {}.", object))
+        })
+        .collect()
+}
+
+/// Returns the stdout and stderr of the compiler. The stderr can contain warnings even if it
+/// succeeded.
+fn run_assembly_generation(
+    command: &AssemblyGenerationCommand,
+    args: &[String],
+) -> Result<(Vec<u8>, String)> {
+    let output = Command::new(&command.program)
+        .args(args)
+        .current_dir(&command.cwd)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "Generating assembly for {} failed ({}):\n{}",
+            command.object,
+            output.status,
+            stderr.trim_end()
+        ));
+    }
+    Ok((output.stdout, stderr))
+}
+
+/// With `verbose_errors`, the compiler output is printed also when it succeeded.
+fn get_assembly(asm_command: &AssemblyGenerationCommand, verbose_errors: bool) -> Result<String> {
+    if asm_command.is_pre_generated {
+        return std::fs::read_to_string(&asm_command.output).map_err(|err| {
+            eyre::eyre!(
+                "Can't read the assembly of {} from {}: {}",
+                asm_command.object,
+                asm_command.output.display(),
+                err
+            )
+        });
+    }
+    get_assembly_via_pipe(asm_command, verbose_errors)
+}
+
+fn print_compiler_warnings(asm_command: &AssemblyGenerationCommand, stderr: &str) {
+    if !stderr.trim().is_empty() {
+        eprintln!("{}:\n{}", asm_command.object, stderr.trim_end());
+    }
+}
+
+fn get_assembly_via_file(
+    asm_command: &AssemblyGenerationCommand,
+    verbose_errors: bool,
+) -> Result<String> {
+    let (_, stderr) = run_assembly_generation(asm_command, &asm_command.args)?;
+    if verbose_errors {
+        print_compiler_warnings(asm_command, &stderr);
+    }
+
+    let assembly = std::fs::read_to_string(&asm_command.output)?;
+    // The assembly has been read already, so a leftover file is not worth failing for.
+    if let Err(err) = std::fs::remove_file(&asm_command.output) {
+        eprintln!(
+            "Warning: Can't remove {}: {}",
+            asm_command.output.display(),
+            err
+        );
+    }
+
+    Ok(assembly)
+}
+
+/// Lets the compiler write the assembly to stdout with `-o -`, so that no temporary file is
+/// needed, e.g. on read-only file systems. Only GCC and Clang support this, other compilers
+/// like MSVC write the file instead. When the compiler succeeds without writing anything to
+/// stdout, it did not understand `-o -` and the file is used as well, unless pipe mode is
+/// forced. Compilation errors are returned as they are, because they would happen again.
+fn get_assembly_via_pipe(
+    asm_command: &AssemblyGenerationCommand,
+    verbose_errors: bool,
+) -> Result<String> {
+    let program = asm_command.program.to_str().unwrap_or_default();
+    let output_index = asm_command.args.iter().position(|arg| arg == "-o");
+    let (true, Some(output_index)) = (is_compiler_program(program), output_index) else {
+        return get_assembly_via_file(asm_command, verbose_errors);
+    };
+    let mut args = asm_command.args.clone();
+    args[output_index + 1] = "-".to_owned();
+    let (stdout, stderr) = run_assembly_generation(asm_command, &args)?;
+    if stdout.is_empty() && !asm_command.pipe_output {
+        return get_assembly_via_file(asm_command, verbose_errors);
+    }
+    if verbose_errors {
+        print_compiler_warnings(asm_command, &stderr);
+    }
+    String::from_utf8(stdout)
+        .map_err(|_| eyre::eyre!("The assembly of {} is not valid UTF-8.", asm_command.object))
+}
+
+/// Settings that change how assembly is interpreted.
+#[derive(Default, Clone, Copy)]
+struct ParseOptions {
+    /// Skip the branches between `.if*` and `.else` or `.endif`, but keep the `.else`
+    /// branches. Sanitizers guard some of their instrumentation this way, and the `.else`
+    /// branch is the code without it.
+    exclude_conditional_blocks: bool,
+    /// Store the instruction lines of every function, so that they can be diffed later.
+    keep_raw_assembly: bool,
+    /// Report lines that the parser does not recognize after the analysis.
+    validate_assembly: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+enum LinkType {
+    Local,
+    Weak,
+    Global,
+}
+
+fn parse_data(object: ObjectID, assembly: &str, options: ParseOptions, parsed: &mut ParsedData) {
+    let mut link_type_by_name: HashMap<&str, LinkType> = HashMap::new();
+    // Ordered so that function ids are assigned the same way in every run.
+    let mut function_names: BTreeSet<&str> = BTreeSet::new();
+    let mut aliases: HashMap<&str, &str> = HashMap::new();
+    let mut lto_discarded_names: Vec<&str> = vec![];
+    let mut lto_private_names: Vec<&str> = vec![];
+    let mut source_file_by_number: HashMap<usize, PathBuf> = HashMap::new();
+    // Symbols with any `.type` and labels, to find declared symbols that are not defined.
+    let mut typed_names: HashSet<&str> = HashSet::new();
+    let mut labels: HashSet<&str> = HashSet::new();
+
+    for line in assembly.lines() {
+        let trimmed_line = line.trim();
+        if trimmed_line.starts_with(".type") && trimmed_line.ends_with("@function") {
+            let function_name =
+                &trimmed_line[".type\t".len()..(trimmed_line.len() - ", @function".len())];
+            function_names.insert(function_name);
+        } else if let Some(type_args) = trimmed_line.strip_prefix(".type\t") {
+            typed_names.extend(type_args.split(',').next().map(str::trim));
+        } else if let Some(label) = line.strip_suffix(':') {
+            labels.insert(label);
+        } else if let Some(function_name) = trimmed_line.strip_prefix(".weak\t") {
+            link_type_by_name.insert(function_name, LinkType::Weak);
+        } else if let Some(function_name) = trimmed_line.strip_prefix(".globl\t") {
+            link_type_by_name.insert(function_name, LinkType::Global);
+        } else if let Some(set_args) = trimmed_line.strip_prefix(".set\t") {
+            if let Some((old_name, new_name)) = set_args.split_once(',') {
+                aliases.insert(old_name.trim(), new_name.trim());
+            }
+        } else if let Some(names) = trimmed_line.strip_prefix(".lto_discard") {
+            lto_discarded_names.extend(parse_symbol_list(names));
+        } else if let Some(names) = trimmed_line.strip_prefix(".lto_private_extern") {
+            lto_private_names.extend(parse_symbol_list(names));
+        } else if let Some((number, path)) = parse_file_directive(trimmed_line) {
+            source_file_by_number.insert(number, path);
+        }
+    }
+
+    let mut id_by_function_name: HashMap<&str, FunctionID> = HashMap::new();
+
+    for &function_name in function_names.iter() {
+        let link_type = link_type_by_name
+            .get(function_name)
+            .copied()
+            .unwrap_or(LinkType::Local);
+        let function = match link_type {
+            LinkType::Local => FunctionName::Local(LocalFunctionName {
+                object,
+                name: ArcIntern::from(function_name),
+            }),
+            _ => FunctionName::global(function_name),
+        };
+        let function_id = parsed.get_or_add_function(function);
+        parsed.link_type_by_function.insert(function_id, link_type);
+        id_by_function_name.insert(function_name, function_id);
+    }
+
+    let mut function_id_of = |name: &str| match id_by_function_name.get(name) {
+        Some(function_id) => *function_id,
+        None => parsed.get_or_add_function(FunctionName::global(name)),
+    };
+    let lto_discarded: Vec<FunctionID> = lto_discarded_names
+        .into_iter()
+        .map(&mut function_id_of)
+        .collect();
+    let lto_private: Vec<FunctionID> = lto_private_names
+        .into_iter()
+        .map(&mut function_id_of)
+        .collect();
+    // Sorted, so that function ids are assigned the same way in every run.
+    let mut fully_inlined_names: Vec<&str> = link_type_by_name
+        .iter()
+        .filter(|(name, link_type)| {
+            **link_type == LinkType::Global
+                && !function_names.contains(*name)
+                && !typed_names.contains(*name)
+                && !labels.contains(*name)
+                && !aliases.contains_key(*name)
+        })
+        .map(|(name, _)| *name)
+        .collect();
+    fully_inlined_names.sort();
+    let fully_inlined: Vec<FunctionID> = fully_inlined_names
+        .into_iter()
+        .map(&mut function_id_of)
+        .collect();
+    parsed.lto_discarded.extend(lto_discarded);
+    parsed.lto_private.extend(lto_private);
+    parsed.fully_inlined_functions.extend(fully_inlined);
+
+    let mut current_function: Option<FunctionID> = None;
+    let mut string_literals_by_function: HashMap<FunctionID, HashSet<&str>> = HashMap::new();
+    let mut saved_registers_by_function: HashMap<FunctionID, Vec<String>> = HashMap::new();
+    let mut frame_size_by_function: HashMap<FunctionID, usize> = HashMap::new();
+    let mut source_file_by_function: HashMap<FunctionID, PathBuf> = HashMap::new();
+    let mut raw_assembly_by_function: HashMap<FunctionID, Vec<String>> = HashMap::new();
+    let mut abi_scan_by_function: HashMap<FunctionID, WindowsAbiScan> = HashMap::new();
+    let mut deoptimization_scan_by_function: HashMap<FunctionID, DeoptimizationScan> =
+        HashMap::new();
+    let mut inline_memory_scan_by_function: HashMap<FunctionID, inline_memory::InlineMemoryScan> =
+        HashMap::new();
+    let mut vtable_entries: HashMap<&str, Vec<Option<FunctionID>>> = HashMap::new();
+    let mut call_sites_by_caller: HashMap<FunctionID, HashMap<FunctionID, usize>> = HashMap::new();
+    // Local labels are numbered per object, so their line is enough to tell whether a jump
+    // goes backward. Jumps are classified after all labels of the function are known.
+    let mut local_label_lines: HashMap<&str, (FunctionID, usize)> = HashMap::new();
+    let mut local_jumps: Vec<(FunctionID, usize, &str)> = vec![];
+    let mut returns_num_by_function: HashMap<FunctionID, usize> = HashMap::new();
+    let mut indirect_calls_num_by_function: HashMap<FunctionID, usize> = HashMap::new();
+    // Assemblers start in the `.text` section when there is no section directive.
+    let mut current_section: Option<&str> = None;
+    let mut previous_section: Option<&str> = None;
+    // Whether the current branch of every nested `.if*` directive is skipped.
+    let mut conditional_branches_excluded: Vec<bool> = vec![];
+    // Vtable whose primary entries are currently parsed.
+    let mut current_vtable: Option<&str> = None;
+    let mut vtable_rtti_seen = false;
+    // Register that contains a vtable pointer that was loaded from an object.
+    let mut vptr_register: Option<&str> = None;
+    // Alignment directive between functions, which applies to the next function.
+    let mut pending_alignment: Option<usize> = None;
+    for (line_index, line) in assembly.lines().enumerate() {
+        let trimmed_line = line.trim();
+        if options.exclude_conditional_blocks {
+            let directive = trimmed_line.split_whitespace().next().unwrap_or_default();
+            match directive {
+                ".else" => {
+                    if let Some(is_excluded) = conditional_branches_excluded.last_mut() {
+                        *is_excluded = !*is_excluded;
+                    }
+                    continue;
+                }
+                ".elseif" => {
+                    if let Some(is_excluded) = conditional_branches_excluded.last_mut() {
+                        *is_excluded = true;
+                    }
+                    continue;
+                }
+                ".endif" => {
+                    conditional_branches_excluded.pop();
+                    continue;
+                }
+                _ if directive.starts_with(".if") => {
+                    conditional_branches_excluded.push(true);
+                    continue;
+                }
+                _ => {}
+            }
+            if conditional_branches_excluded.contains(&true) {
+                continue;
+            }
+        }
+        if trimmed_line == ".previous" {
+            std::mem::swap(&mut current_section, &mut previous_section);
+            continue;
+        }
+        if let Some(section) = parse_section_directive(trimmed_line) {
+            previous_section = current_section;
+            current_section = Some(section);
+            pending_alignment = None;
+            continue;
+        }
+        if let Some(function_name) = current_section.and_then(deprecated_function_of_section) {
+            if let Some(message) = parse_string_directive(trimmed_line) {
+                let function_id = match id_by_function_name.get(function_name) {
+                    Some(function_id) => *function_id,
+                    None => parsed.get_or_add_function(FunctionName::global(function_name)),
+                };
+                parsed.deprecated_functions.insert(function_id, message);
+            }
+            continue;
+        }
+        if current_section.is_some_and(is_read_only_data_section) {
+            if let Some(label) = line.strip_suffix(':') {
+                current_vtable = label.starts_with("_ZTV").then_some(label);
+                vtable_rtti_seen = false;
+                continue;
+            }
+            // Function pointer tables and vtables contain the addresses of functions.
+            if let Some(mut symbol) = parse_data_symbol_reference(trimmed_line) {
+                if let Some(alias) = aliases.get(symbol) {
+                    symbol = alias;
+                }
+                if let Some(function_id) = id_by_function_name.get(symbol) {
+                    parsed.function_pointer_referenced.insert(*function_id);
+                }
+                if let Some(vtable) = current_vtable {
+                    // The primary vtable starts after the offset to top and the type info.
+                    // Secondary vtables of base classes start with another type info.
+                    if symbol.starts_with("_ZTI") {
+                        if vtable_rtti_seen {
+                            current_vtable = None;
+                        }
+                        vtable_rtti_seen = true;
+                    } else if vtable_rtti_seen {
+                        let function_id = match id_by_function_name.get(symbol) {
+                            Some(function_id) => *function_id,
+                            None => parsed.get_or_add_function(FunctionName::global(symbol)),
+                        };
+                        vtable_entries
+                            .entry(vtable)
+                            .or_default()
+                            .push(Some(function_id));
+                    }
+                }
+            }
+            continue;
+        }
+        // Data sections can contain labels and instruction-like strings which must not be
+        // mistaken for functions.
+        if !current_section.is_none_or(is_text_section) {
+            continue;
+        }
+        if let Some(function_id) = current_function {
+            if trimmed_line.starts_with(".size\t") {
+                // Functions without instructions are recorded explicitly, so that they can be
+                // distinguished from functions that are only called.
+                parsed
+                    .instructions_by_object_by_function
+                    .entry(function_id)
+                    .or_default()
+                    .entry(object)
+                    .or_insert(0);
+                current_function = None;
+                vptr_register = None;
+                continue;
+            }
+            if let Some(label) = trimmed_line.strip_suffix(':') {
+                if label.starts_with(".L") {
+                    local_label_lines.insert(label, (function_id, line_index));
+                }
+            }
+            if trimmed_line.starts_with(".") {
+                if is_unwind_personality_directive(trimmed_line) {
+                    parsed.has_unwind_personality.insert(function_id);
+                }
+                // The first location is usually the line of the function signature.
+                if let Some(path) = parse_loc_file_number(trimmed_line)
+                    .and_then(|number| source_file_by_number.get(&number))
+                {
+                    source_file_by_function
+                        .entry(function_id)
+                        .or_insert_with(|| path.clone());
+                }
+                match parse_cfi_directive(trimmed_line) {
+                    Some(CfiDirective::DefCfaOffset(offset)) => {
+                        let frame_size = frame_size_by_function.entry(function_id).or_default();
+                        *frame_size = (*frame_size).max(offset);
+                    }
+                    Some(CfiDirective::SavedRegister(register)) => {
+                        let registers = saved_registers_by_function.entry(function_id).or_default();
+                        if !registers.contains(&register) {
+                            registers.push(register);
+                        }
+                    }
+                    None => {}
+                }
+                continue;
+            }
+            *parsed
+                .instructions_by_object_by_function
+                .entry(function_id)
+                .or_default()
+                .entry(object)
+                .or_default() += 1;
+            if options.keep_raw_assembly {
+                raw_assembly_by_function
+                    .entry(function_id)
+                    .or_default()
+                    .push(trimmed_line.to_owned());
+            }
+            if let Some(mnemonic) = trimmed_line.split_whitespace().next() {
+                *parsed
+                    .weighted_instructions_by_object_by_function
+                    .entry(function_id)
+                    .or_default()
+                    .entry(object)
+                    .or_default() += x86_throughput_weight(mnemonic);
+            }
+            scan_windows_abi_prologue(
+                trimmed_line,
+                abi_scan_by_function.entry(function_id).or_default(),
+            );
+            deoptimization_scan_by_function
+                .entry(function_id)
+                .or_default()
+                .add_instruction(trimmed_line);
+            inline_memory_scan_by_function
+                .entry(function_id)
+                .or_default()
+                .add_instruction(trimmed_line);
+            if accesses_thread_local_storage(trimmed_line) {
+                parsed.uses_tls.insert(function_id);
+            }
+            if let Some(extension) = simd_extension(trimmed_line) {
+                let newest = parsed
+                    .simd_extension_by_function
+                    .entry(function_id)
+                    .or_insert(extension);
+                *newest = (*newest).max(extension);
+            }
+            if let Some(label) = local_jump_target(trimmed_line) {
+                local_jumps.push((function_id, line_index, label));
+            }
+            *returns_num_by_function.entry(function_id).or_default() +=
+                is_return_instruction(trimmed_line) as usize;
+            if is_indirect_call(trimmed_line) {
+                *indirect_calls_num_by_function
+                    .entry(function_id)
+                    .or_default() += 1;
+            }
+            if let Some(label) = string_literal_label(trimmed_line) {
+                string_literals_by_function
+                    .entry(function_id)
+                    .or_default()
+                    .insert(label);
+            }
+            if has_immediate_operand(trimmed_line) {
+                *parsed
+                    .instructions_with_immediate_by_function
+                    .entry(function_id)
+                    .or_default() += 1;
+            }
+            if let Some((register, offset)) = parse_indirect_memory_branch(trimmed_line) {
+                if vptr_register == Some(register) {
+                    parsed
+                        .virtual_call_slots_by_function
+                        .entry(function_id)
+                        .or_default()
+                        .insert(offset / 8);
+                }
+            }
+            match parse_vtable_pointer_load(trimmed_line) {
+                Some(register) => vptr_register = Some(register),
+                None => {
+                    // Calls clobber the register and other writes overwrite it.
+                    let is_overwritten = trimmed_line.starts_with("call")
+                        || vptr_register.is_some_and(|register| {
+                            trimmed_line.ends_with(&format!(", {}", register))
+                        });
+                    if is_overwritten {
+                        vptr_register = None;
+                    }
+                }
+            }
+            let mut callee = trimmed_line.strip_prefix("call\t");
+            if let Some(mut target) = trimmed_line.strip_prefix("jmp\t") {
+                if let Some(alias) = aliases.get(target) {
+                    target = alias;
+                }
+                // A jump back to the own entry label is a self call that was turned into a
+                // loop by tail call optimization.
+                if id_by_function_name.get(target) == Some(&function_id) {
+                    parsed.tail_recursive_functions.insert(function_id);
+                } else if !target.starts_with(".L")
+                    && !target.starts_with(|c: char| c.is_ascii_digit())
+                    && !is_cold_part_of(target, parsed.name_by_function_id[&function_id].name())
+                {
+                    // Jumps to other functions are tail calls, local labels and the cold part
+                    // stay within the function.
+                    callee = Some(target);
+                }
+            }
+            if let Some(mut callee) = callee {
+                if !callee.starts_with('*') {
+                    if let Some(stripped) = callee.strip_suffix("@PLT") {
+                        callee = stripped;
+                    }
+                    if let Some(alias) = aliases.get(callee) {
+                        callee = alias;
+                    }
+                    let callee_id = if let Some(callee_id) = id_by_function_name.get(callee) {
+                        *callee_id
+                    } else {
+                        parsed.get_or_add_function(FunctionName::global(callee))
+                    };
+                    *call_sites_by_caller
+                        .entry(function_id)
+                        .or_default()
+                        .entry(callee_id)
+                        .or_default() += 1;
+                    parsed
+                        .callees_by_caller
+                        .entry(function_id)
+                        .or_default()
+                        .insert(callee_id);
+                    parsed
+                        .callers_by_callee
+                        .entry(callee_id)
+                        .or_default()
+                        .insert(function_id);
+                }
+            }
+        } else {
+            if let Some(alignment) = parse_alignment_directive(trimmed_line) {
+                pending_alignment = Some(alignment);
+                continue;
+            }
+            if line.starts_with("\t") {
+                continue;
+            }
+            if !line.ends_with(":") {
+                continue;
+            }
+            let label_name = &line[..line.len() - 1];
+            let label_name = if let Some(alias) = aliases.get(label_name) {
+                alias
+            } else {
+                label_name
+            };
+            if let Some(function_id) = id_by_function_name.get(label_name).copied() {
+                current_function = Some(function_id);
+                insert_sorted(
+                    parsed.functions_by_object.entry(object).or_default(),
+                    function_id,
+                );
+                parsed
+                    .objects_by_function
+                    .entry(function_id)
+                    .or_default()
+                    .insert(object);
+                // Functions defined in multiple objects are usually aligned the same way, the
+                // first alignment is kept.
+                if let Some(alignment) = pending_alignment.take() {
+                    parsed
+                        .alignment_overhead_by_function
+                        .entry(function_id)
+                        .or_insert(alignment);
+                    parsed
+                        .estimated_padding_by_function
+                        .entry(function_id)
+                        .or_insert(alignment.saturating_sub(1));
+                }
+            }
+        }
+    }
+
+    // Every object has its own string literals. For functions that are defined in multiple
+    // objects, the maximum is used.
+    for (function_id, labels) in string_literals_by_function {
+        let refs_num = parsed
+            .string_literal_refs_by_function
+            .entry(function_id)
+            .or_default();
+        *refs_num = (*refs_num).max(labels.len());
+    }
+
+    let mut loops_num_by_function: HashMap<FunctionID, usize> = HashMap::new();
+    let mut branches_num_by_function: HashMap<FunctionID, usize> = HashMap::new();
+    for (function_id, jump_line, label) in local_jumps {
+        let Some(&(label_function, label_line)) = local_label_lines.get(label) else {
+            continue;
+        };
+        if label_function != function_id {
+            continue;
+        }
+        if label_line < jump_line {
+            *loops_num_by_function.entry(function_id).or_default() += 1;
+        } else {
+            *branches_num_by_function.entry(function_id).or_default() += 1;
+        }
+    }
+    for (function_id, loops_num) in loops_num_by_function {
+        let merged_num = parsed
+            .loop_count_by_function
+            .entry(function_id)
+            .or_default();
+        *merged_num = (*merged_num).max(loops_num);
+    }
+    for (function_id, branches_num) in branches_num_by_function {
+        let merged_num = parsed
+            .branch_count_by_function
+            .entry(function_id)
+            .or_default();
+        *merged_num = (*merged_num).max(branches_num);
+    }
+    for (function_id, returns_num) in returns_num_by_function {
+        let merged_num = parsed
+            .return_count_by_function
+            .entry(function_id)
+            .or_default();
+        *merged_num = (*merged_num).max(returns_num);
+    }
+    for (function_id, indirect_calls_num) in indirect_calls_num_by_function {
+        let merged_num = parsed
+            .indirect_call_count_by_function
+            .entry(function_id)
+            .or_default();
+        *merged_num = (*merged_num).max(indirect_calls_num);
+    }
+    for (function_id, scan) in inline_memory_scan_by_function {
+        for (counts, num) in [
+            (&mut parsed.inline_memcpy_count_by_function, scan.memcpy_num),
+            (&mut parsed.inline_memset_count_by_function, scan.memset_num),
+        ] {
+            if num > 0 {
+                let merged_num = counts.entry(function_id).or_default();
+                *merged_num = (*merged_num).max(num);
+            }
+        }
+    }
+
+    for (function_id, abi_scan) in abi_scan_by_function {
+        // `%rdi` and `%rsi` are only callee-saved in the Windows ABI.
+        let saves_windows_registers = saved_registers_by_function
+            .get(&function_id)
+            .is_some_and(|registers| registers.iter().any(|r| r == "rdi" || r == "rsi"));
+        if abi_scan.saves_incoming_xmm || saves_windows_registers {
+            parsed.windows_abi_functions.insert(function_id);
+        }
+    }
+
+    parsed.possibly_deoptimized.extend(
+        deoptimization_scan_by_function
+            .into_iter()
+            .filter(|(_, scan)| scan.looks_unoptimized())
+            .map(|(function_id, _)| function_id),
+    );
+
+    // Functions defined in multiple objects are usually compiled the same way everywhere, so
+    // the first frame layout is kept.
+    for (function_id, registers) in saved_registers_by_function {
+        parsed
+            .saved_registers_by_function
+            .entry(function_id)
+            .or_insert(registers);
+    }
+    for (function_id, path) in source_file_by_function {
+        parsed
+            .source_file_by_function
+            .entry(function_id)
+            .or_insert(path);
+    }
+    for (function_id, frame_size) in frame_size_by_function {
+        parsed
+            .frame_size_by_function
+            .entry(function_id)
+            .or_insert(frame_size);
+    }
+    for (function_id, lines) in raw_assembly_by_function {
+        parsed
+            .raw_assembly_by_function
+            .entry(function_id)
+            .or_insert(lines);
+    }
+
+    for (caller, call_sites) in call_sites_by_caller {
+        let merged_call_sites = parsed.call_sites_by_caller.entry(caller).or_default();
+        for (callee, num) in call_sites {
+            let merged_num = merged_call_sites.entry(callee).or_default();
+            *merged_num = (*merged_num).max(num);
+        }
+    }
+
+    // Vtables are emitted in every object that uses them, the first one is kept.
+    for (vtable, entries) in vtable_entries {
+        parsed
+            .vtable_entries
+            .entry(vtable.to_owned())
+            .or_insert(entries);
+    }
+
+    update_immediate_density(parsed, id_by_function_name.values());
+}
+
+/// Returns the alignment in bytes of directives like `.p2align 4` or `.p2align 4,,15`. On
+/// x86, `.align` takes a number of bytes like `.balign`.
+fn parse_alignment_directive(line: &str) -> Option<usize> {
+    let (directive, operands) = line.split_once(char::is_whitespace)?;
+    let value: usize = operands.split(',').next()?.trim().parse().ok()?;
+    match directive {
+        ".p2align" => 1usize.checked_shl(value.try_into().ok()?),
+        ".balign" | ".align" => Some(value),
+        _ => None,
+    }
+}
+
+/// Inserts the id into the sorted ids unless it is already there.
+fn insert_sorted<T: Ord>(ids: &mut Vec<T>, id: T) {
+    if let Err(index) = ids.binary_search(&id) {
+        ids.insert(index, id);
+    }
+}
+
+/// Whether the label is the part of the function that GCC moved to `.text.unlikely`, like
+/// `main.cold` or `main.cold.3`.
+fn is_cold_part_of(label: &str, function_name: &str) -> bool {
+    let Some(suffix) = label
+        .strip_prefix(function_name)
+        .and_then(|rest| rest.strip_prefix(".cold"))
+    else {
+        return false;
+    };
+    suffix.is_empty()
+        || suffix
+            .strip_prefix('.')
+            .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Returns the label of direct jumps like `jmp\t.L3` or `jne\t.L5`. Other jump targets are
+/// functions or addresses in registers.
+fn local_jump_target(instruction: &str) -> Option<&str> {
+    let (mnemonic, target) = instruction.split_once('\t')?;
+    (mnemonic.starts_with('j') && target.starts_with(".L")).then_some(target.trim())
+}
+
+/// Returns the destination register of loads like `movq\t(%rdi), %rax`, which is how the
+/// vtable pointer is loaded from the start of an object.
+fn parse_vtable_pointer_load(instruction: &str) -> Option<&str> {
+    let operands = instruction.strip_prefix("movq\t")?;
+    let (source, destination) = operands.split_once(", ")?;
+    let base = source.strip_prefix("(%")?.strip_suffix(')')?;
+    (!base.contains(',') && destination.starts_with('%')).then_some(destination)
+}
+
+/// Returns the base register and offset of calls and jumps like `call\t*16(%rax)`.
+fn parse_indirect_memory_branch(instruction: &str) -> Option<(&str, usize)> {
+    let operand = instruction
+        .strip_prefix("call\t*")
+        .or_else(|| instruction.strip_prefix("jmp\t*"))?;
+    let (offset, register) = operand.split_once('(')?;
+    let register = register.strip_suffix(')')?;
+    if register.contains(',') {
+        return None;
+    }
+    let offset = if offset.is_empty() {
+        0
+    } else {
+        offset.parse().ok()?
+    };
+    Some((register, offset))
+}
+
+/// State used to detect functions that use the Windows x64 calling convention, e.g. because
+/// of `__attribute__((ms_abi))`. In this convention `%xmm6`-`%xmm15` are callee-saved, while
+/// in the System V ABI they are not. `%xmm6` and `%xmm7` are System V argument registers, so
+/// only `%xmm8`-`%xmm15` are checked.
+#[derive(Default)]
+struct WindowsAbiScan {
+    /// Bit mask of the xmm registers that have been written by the function.
+    written_xmm: u16,
+    /// Registers that are stored after a call may contain the return value.
+    after_call: bool,
+    saves_incoming_xmm: bool,
+}
+
+/// Minimum number of instructions of a function to check whether it is optimized. Tiny
+/// functions access their arguments on the stack even with optimizations in some ABIs.
+const MIN_DEOPTIMIZATION_SCAN_INSTRUCTIONS: usize = 8;
+
+/// Counts the instructions that are typical for code compiled with `-O0`. Without
+/// optimizations, every variable lives on the stack and is loaded and stored relative to the
+/// frame pointer around every use. Optimized code keeps variables in registers.
+#[derive(Default)]
+struct DeoptimizationScan {
+    instructions_num: usize,
+    frame_pointer_accesses_num: usize,
+    nops_num: usize,
+}
+
+impl DeoptimizationScan {
+    fn add_instruction(&mut self, instruction: &str) {
+        self.instructions_num += 1;
+        // `-8(%rbp)` on x86-64 and `[x29, -8]` on AArch64.
+        if instruction.contains("(%rbp)") || instruction.contains("[x29,") {
+            self.frame_pointer_accesses_num += 1;
+        }
+        if instruction.starts_with("nop") {
+            self.nops_num += 1;
+        }
+    }
+
+    /// Either most instructions move variables between registers and the stack or there are
+    /// many nops, e.g. from `-O0` with patchable function entries.
+    fn looks_unoptimized(&self) -> bool {
+        if self.instructions_num < MIN_DEOPTIMIZATION_SCAN_INSTRUCTIONS {
+            return false;
+        }
+        self.frame_pointer_accesses_num * 5 >= self.instructions_num * 2
+            || self.nops_num * 4 >= self.instructions_num
+    }
+}
+
+fn scan_windows_abi_prologue(instruction: &str, scan: &mut WindowsAbiScan) {
+    if scan.after_call || scan.saves_incoming_xmm {
+        return;
+    }
+    let Some((mnemonic, operands)) = instruction.split_once('\t') else {
+        return;
+    };
+    if mnemonic.starts_with("call") || mnemonic.starts_with("jmp") {
+        scan.after_call = true;
+        return;
+    }
+    let operands: Vec<&str> = operands.split(", ").collect();
+    let Some((destination, sources)) = operands.split_last() else {
+        return;
+    };
+    let is_store = destination.ends_with("(%rsp)") || destination.ends_with("(%rbp)");
+    for source in sources {
+        if let Some(register) = xmm_register_number(source) {
+            let is_unwritten = scan.written_xmm & (1 << register) == 0;
+            if is_store && register >= 8 && is_unwritten {
+                scan.saves_incoming_xmm = true;
+            }
+        }
+    }
+    if let Some(register) = xmm_register_number(destination) {
+        scan.written_xmm |= 1 << register;
+    }
+}
+
+fn xmm_register_number(operand: &str) -> Option<u32> {
+    operand
+        .strip_prefix("%xmm")?
+        .parse()
+        .ok()
+        .filter(|number| *number < 16)
+}
+
+enum CfiDirective {
+    /// Distance between the stack pointer and the canonical frame address.
+    DefCfaOffset(usize),
+    /// A callee-saved register is stored on the stack.
+    SavedRegister(String),
+}
+
+/// Parses the call frame information directives that describe the stack frame layout.
+/// `.cfi_restore` is ignored, because the register has been saved anyway.
+fn parse_cfi_directive(line: &str) -> Option<CfiDirective> {
+    if let Some(offset) = line.strip_prefix(".cfi_def_cfa_offset ") {
+        return offset.trim().parse().ok().map(CfiDirective::DefCfaOffset);
+    }
+    let args = line
+        .strip_prefix(".cfi_offset ")
+        .or_else(|| line.strip_prefix(".cfi_rel_offset "))?;
+    let (register, _) = args.split_once(',')?;
+    let register = register.trim();
+    // GCC uses DWARF register numbers, Clang uses register names.
+    let register = match register.parse::<usize>() {
+        Ok(number) => x86_64_dwarf_register_name(number)
+            .map(|name| name.to_owned())
+            .unwrap_or_else(|| format!("dwarf{}", number)),
+        Err(_) => register.trim_start_matches('%').to_owned(),
+    };
+    // The return address is saved by every call and is no overhead of the function itself.
+    if register == "rip" {
+        return None;
+    }
+    Some(CfiDirective::SavedRegister(register))
+}
+
+fn x86_64_dwarf_register_name(number: usize) -> Option<&'static str> {
+    const NAMES: [&str; 17] = [
+        "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12",
+        "r13", "r14", "r15", "rip",
+    ];
+    NAMES.get(number).copied()
+}
+
+/// Recomputes the immediate density of the given functions from their instruction counts.
+fn update_immediate_density<'a>(
+    parsed: &mut ParsedData,
+    function_ids: impl IntoIterator<Item = &'a FunctionID>,
+) {
+    for function_id in function_ids {
+        // Both counts are summed over all copies of the function.
+        let instructions_num = total_instructions(parsed, *function_id);
+        if instructions_num == 0 {
+            continue;
+        }
+        let immediates_num = parsed
+            .instructions_with_immediate_by_function
+            .get(function_id)
+            .copied()
+            .unwrap_or_default();
+        parsed
+            .immediate_density_by_function
+            .insert(*function_id, immediates_num * 1000 / instructions_num);
+    }
+}
+
+/// Functions that reference a C++ personality routine can propagate exceptions and need
+/// unwind tables, even if they never catch anything themselves.
+fn is_unwind_personality_directive(line: &str) -> bool {
+    let Some(directive) = line.split_whitespace().next() else {
+        return false;
+    };
+    matches!(
+        directive,
+        ".cfi_personality" | ".personality" | ".seh_handler"
+    ) && (line.contains("__gxx_personality_v0") || line.contains("__gxx_personality_seh0"))
+}
+
+/// Returns the name of the section that is switched to if the line is a section directive.
+fn parse_section_directive(line: &str) -> Option<&str> {
+    if matches!(line, ".text" | ".data" | ".bss") {
+        return Some(line);
+    }
+    let args = line.strip_prefix(".section")?;
+    if !args.starts_with(char::is_whitespace) {
+        return None;
+    }
+    args.split(|c: char| c == ',' || c.is_whitespace())
+        .find(|part| !part.is_empty())
+}
+
+/// Returns the number and path of the files of debug info like `.file 1 "src" "a.cc"`, which
+/// `.loc` directives refer to. The path is relative to the directory of the compiler
+/// invocation unless it's absolute. The unnumbered `.file "a.cc"` is ignored, because GCC
+/// only writes the file name of the compiled file there.
+fn parse_file_directive(line: &str) -> Option<(usize, PathBuf)> {
+    let args = line.strip_prefix(".file")?;
+    if !args.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let args = args.trim_start();
+    let number = args.split_whitespace().next()?.parse().ok()?;
+    // Quoted strings are at odd positions when splitting by quotes. Escaped quotes are rare
+    // in paths and ignored.
+    let strings: Vec<&str> = args.split('"').skip(1).step_by(2).collect();
+    let path = match strings.as_slice() {
+        [file] => PathBuf::from(file),
+        [directory, file, ..] => Path::new(directory).join(file),
+        [] => return None,
+    };
+    Some((number, path))
+}
+
+/// Returns the file number of a `.loc 1 12 5` directive.
+fn parse_loc_file_number(line: &str) -> Option<usize> {
+    line.strip_prefix(".loc")?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Returns the function of a `.gnu.warning.<function>` section. The linker prints the string
+/// in the section when the function is used.
+fn deprecated_function_of_section(section: &str) -> Option<&str> {
+    section
+        .trim_matches('"')
+        .strip_prefix(".gnu.warning.")
+        .filter(|function| !function.is_empty())
+}
+
+/// Returns the content of `.string "text"`, `.asciz` or `.ascii` with quotes and backslashes
+/// unescaped.
+fn parse_string_directive(line: &str) -> Option<String> {
+    let (directive, args) = line.split_once(char::is_whitespace)?;
+    if !matches!(directive, ".string" | ".asciz" | ".ascii") {
+        return None;
+    }
+    let text = args.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    Some(unescaped)
+}
+
+/// Sections like `.text.hot`, `.text.unlikely` or `.text.startup` contain code as well.
+fn is_text_section(section: &str) -> bool {
+    section == ".text" || section.starts_with(".text.")
+}
+
+/// Sections like `.data.rel.ro` are only written by the dynamic linker for relocations.
+fn is_read_only_data_section(section: &str) -> bool {
+    section.starts_with(".rodata") || section.starts_with(".data.rel.ro")
+}
+
+/// Splits the operands of directives like `.lto_discard foo, bar`.
+fn parse_symbol_list(operands: &str) -> impl Iterator<Item = &str> {
+    operands
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns the symbol of data directives like `.quad\t_Z3cb1i` or `.long\tfoo+16`.
+fn parse_data_symbol_reference(line: &str) -> Option<&str> {
+    let (directive, value) = line.split_once('\t')?;
+    if !matches!(directive, ".quad" | ".long" | ".4byte" | ".8byte") {
+        return None;
+    }
+    let symbol = value.split('+').next()?.trim();
+    symbol
+        .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        .then_some(symbol)
+}
+
+/// `ret` in both syntaxes and with any operand size, also with the `rep` prefix that older
+/// GCC versions emit for the branch predictors of old AMD processors.
+fn is_return_instruction(instruction: &str) -> bool {
+    let mut words = instruction.split_whitespace();
+    let mut mnemonic = words.next();
+    if matches!(mnemonic, Some("rep" | "repz")) {
+        mnemonic = words.next();
+    }
+    matches!(mnemonic, Some("ret" | "retq" | "retl" | "retw"))
+}
+
+/// Calls through a register or memory operand like `call\t*%rax` or `call\t*16(%rax)`.
+fn is_indirect_call(instruction: &str) -> bool {
+    let mut words = instruction.split_whitespace();
+    matches!(words.next(), Some("call" | "callq"))
+        && words.next().is_some_and(|operand| operand.starts_with('*'))
+}
+
+/// Approximate reciprocal throughput in cycles of common x86-64 instructions, roughly based
+/// on Agner Fog's instruction tables for recent Intel and AMD cores. Memory operands and
+/// dependency chains are not taken into account.
+fn x86_throughput_weight(mnemonic: &str) -> f64 {
+    if let Some(weight) = x86_throughput_weight_exact(mnemonic) {
+        return weight;
+    }
+    // AT&T syntax appends the operand size to many mnemonics, e.g. `movl` or `addq`.
+    if let Some(weight) = mnemonic
+        .strip_suffix(['b', 'w', 'l', 'q'])
+        .and_then(x86_throughput_weight_exact)
+    {
+        return weight;
+    }
+    if mnemonic.starts_with("cmov") || mnemonic.starts_with("set") || mnemonic.starts_with('j') {
+        return 0.5;
+    }
+    1.0
+}
+
+fn x86_throughput_weight_exact(mnemonic: &str) -> Option<f64> {
+    Some(match mnemonic {
+        "mov" | "movabs" | "add" | "sub" | "and" | "or" | "xor" | "cmp" | "test" | "inc"
+        | "dec" | "neg" | "not" | "nop" | "cltq" | "cqto" | "cltd" => 0.25,
+        "lea" | "shl" | "shr" | "sar" | "sal" | "rol" | "ror" | "bt" | "pop" | "movzb"
+        | "movzw" | "movsb" | "movsw" | "movsl" | "movslq" | "movzbl" | "movzwl" | "movsbl"
+        | "movswl" => 0.5,
+        "movaps" | "movups" | "movapd" | "movupd" | "movdqa" | "movdqu" | "movss" | "movsd"
+        | "vmovaps" | "vmovups" | "vmovapd" | "vmovupd" | "vmovdqa" | "vmovdqu" | "vmovss"
+        | "vmovsd" | "pxor" | "vpxor" | "xorps" | "vxorps" => 0.33,
+        "addss" | "addsd" | "addps" | "addpd" | "subss" | "subsd" | "subps" | "subpd" | "mulss"
+        | "mulsd" | "mulps" | "mulpd" | "vaddss" | "vaddsd" | "vaddps" | "vaddpd" | "vsubss"
+        | "vsubsd" | "vsubps" | "vsubpd" | "vmulss" | "vmulsd" | "vmulps" | "vmulpd"
+        | "vfmadd132ps" | "vfmadd213ps" | "vfmadd231ps" => 0.5,
+        "imul" | "mul" | "push" | "ret" | "leave" | "popcnt" | "lzcnt" | "tzcnt" | "bsf"
+        | "bsr" => 1.0,
+        "call" => 2.0,
+        "divss" | "divps" | "vdivss" | "vdivps" | "sqrtss" | "sqrtps" | "vsqrtss" | "vsqrtps" => {
+            4.0
+        }
+        "divsd" | "divpd" | "vdivsd" | "vdivpd" | "sqrtsd" | "sqrtpd" | "vsqrtsd" | "vsqrtpd" => {
+            6.0
+        }
+        "div" | "idiv" => 10.0,
+        // String instructions that process a whole buffer.
+        "rep" | "repe" | "repz" | "repne" | "repnz" => 10.0,
+        // Atomic read-modify-write operations.
+        "lock" | "xchg" | "cmpxchg" => 18.0,
+        _ => return None,
+    })
+}
+
+/// Returns the `.LC` label of a string literal if the instruction loads its address, as in
+/// `lea\t.LC0(%rip), %rdi`.
+fn string_literal_label(instruction: &str) -> Option<&str> {
+    let operand = instruction
+        .strip_prefix("lea")?
+        .trim_start_matches(['q', 'l']);
+    let operand = operand.strip_prefix('\t')?;
+    let (label, _) = operand.split_once("(%rip),")?;
+    let number = label.strip_prefix(".LC")?;
+    (!number.is_empty() && number.bytes().all(|c| c.is_ascii_digit())).then_some(label)
+}
+
+/// Thread-local variables are addressed relative to the `%fs` segment on x86-64, or through
+/// `__tls_get_addr` in the general dynamic model. The stack protector canary at `%fs:40` is
+/// also stored in the thread control block, but is not a thread-local variable.
+fn accesses_thread_local_storage(instruction: &str) -> bool {
+    if instruction.contains("__tls_get_addr") {
+        return true;
+    }
+    instruction.split("%fs:").skip(1).any(|operand| {
+        !(operand.starts_with("40,") || operand.starts_with("0x28,") || operand == "40")
+    })
+}
+
+/// x86 vector extensions that are not part of the x86-64 baseline. SSE2 is always available,
+/// and compilers use it for scalar floating point math and small copies, so it is not listed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SimdExtension {
+    Avx,
+    Avx2,
+    Avx512,
+}
+
+impl std::fmt::Display for SimdExtension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SimdExtension::Avx => "AVX",
+            SimdExtension::Avx2 => "AVX2",
+            SimdExtension::Avx512 => "AVX-512",
+        })
+    }
+}
+
+/// VEX encoded instructions on vector registers need AVX. Integer operations on `%ymm`
+/// registers, 128 bit lane transfers, gathers and FMA came with AVX2, and `%zmm` registers or
+/// opmasks need AVX-512.
+fn simd_extension(instruction: &str) -> Option<SimdExtension> {
+    let mnemonic = instruction.split_whitespace().next()?;
+    if !mnemonic.starts_with('v') {
+        return None;
+    }
+    if instruction.contains("%zmm") || instruction.contains("{%k") {
+        return Some(SimdExtension::Avx512);
+    }
+    let uses_ymm = instruction.contains("%ymm");
+    if !uses_ymm && !instruction.contains("%xmm") {
+        return None;
+    }
+    let is_avx2 = mnemonic.starts_with("vfm")
+        || mnemonic.starts_with("vfnm")
+        || mnemonic.contains("gather")
+        || mnemonic.ends_with("i128")
+        || (uses_ymm
+            && mnemonic.starts_with("vp")
+            && !mnemonic.starts_with("vpermil")
+            && mnemonic != "vperm2f128");
+    Some(if is_avx2 {
+        SimdExtension::Avx2
+    } else {
+        SimdExtension::Avx
+    })
+}
+
+/// Checks for AT&T style numeric immediates like `$42`, `$-1` or `$0x10`.
+/// Symbolic immediates such as `$.LC0` are addresses and are not counted.
+fn has_immediate_operand(instruction: &str) -> bool {
+    instruction.match_indices('$').any(|(i, _)| {
+        let value = instruction[i + 1..].trim_start_matches('-');
+        value.starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+fn top_n<T>(items: impl Iterator<Item = T>, n: usize) -> Vec<T> {
+    items.take(n).collect()
+}
+
+fn bottom_n<T>(items: impl Iterator<Item = T>, n: usize) -> Vec<T> {
+    let mut items: Vec<T> = items.collect();
+    items.drain(..items.len().saturating_sub(n));
+    items
+}
+
+/// Limits the output of analyses that produce sorted lists.
+#[derive(Default)]
+struct Ranking {
+    top: Option<usize>,
+    /// Never set together with `top`.
+    bottom: Option<usize>,
+    min_instructions: usize,
+}
+
+impl Ranking {
+    /// Expects the items to be sorted already. The instruction threshold is applied before
+    /// the number of items is limited.
+    fn apply<T>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        instructions_num: impl Fn(&T) -> usize,
+    ) -> Vec<T> {
+        let items = items
+            .into_iter()
+            .filter(|item| instructions_num(item) >= self.min_instructions);
+        let items = match self.top {
+            Some(n) => top_n(items, n),
+            None => items.collect(),
+        };
+        match self.bottom {
+            Some(n) => bottom_n(items.into_iter(), n),
+            None => items,
+        }
+    }
+}
+
+/// Instructions of the function in a single object that defines it.
+fn instructions_in_object(parsed: &ParsedData, function_id: FunctionID, object: ObjectID) -> usize {
+    parsed
+        .instructions_by_object_by_function
+        .get(&function_id)
+        .and_then(|nums| nums.get(&object))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Instructions of the largest copy of the function. Inline functions are compiled into
+/// every object that uses them, possibly with different inlining decisions, so the copies can
+/// differ. This is the size that functions are ranked and compared by.
+fn function_instructions_num(parsed: &ParsedData, function_id: FunctionID) -> usize {
+    parsed
+        .instructions_by_object_by_function
+        .get(&function_id)
+        .and_then(|nums| nums.values().copied().max())
+        .unwrap_or_default()
+}
+
+/// Approximate cycles of the most expensive copy of the function, see
+/// `function_instructions_num`.
+fn function_weighted_instructions(parsed: &ParsedData, function_id: FunctionID) -> f64 {
+    parsed
+        .weighted_instructions_by_object_by_function
+        .get(&function_id)
+        .map_or(0.0, |weights| weights.values().copied().fold(0.0, f64::max))
+}
+
+/// Sum of the instructions of all copies of the function, i.e. the code that it adds to the
+/// objects.
+fn total_instructions(parsed: &ParsedData, function_id: FunctionID) -> usize {
+    parsed
+        .instructions_by_object_by_function
+        .get(&function_id)
+        .map_or(0, |nums| nums.values().sum())
+}
+
+/// Instructions of all copies of all functions.
+fn all_instructions_num(parsed: &ParsedData) -> usize {
+    parsed
+        .instructions_by_object_by_function
+        .values()
+        .flat_map(|nums| nums.values())
+        .sum()
+}
+
+fn print_functions_with_most_instructions(
+    parsed: &ParsedData,
+    sort_by: cli::SortBy,
+    ranking: &Ranking,
+    formatter: &mut dyn Formatter,
+) -> Result<()> {
+    let mut data: Vec<(FunctionID, usize)> = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .map(|function_id| {
+            (
+                *function_id,
+                function_instructions_num(parsed, *function_id),
+            )
+        })
+        .collect();
+    let weighted_instructions =
+        |function_id: &FunctionID| function_weighted_instructions(parsed, *function_id);
+    match sort_by {
+        cli::SortBy::Instructions | cli::SortBy::Loops | cli::SortBy::ReturnCount => {
+            data.sort_by(|a, b| a.1.cmp(&b.1).reverse())
+        }
+        cli::SortBy::WeightedInstructions => data.sort_by(|a, b| {
+            weighted_instructions(&a.0)
+                .total_cmp(&weighted_instructions(&b.0))
+                .reverse()
+        }),
+    }
+    for (function_id, instr_num) in ranking.apply(data, |(_, num)| *num) {
+        let function = parsed.name_by_function_id.get(&function_id).unwrap();
+        let objects = parsed
+            .objects_by_function
+            .get(&function_id)
+            .into_iter()
+            .flatten()
+            .map(|object| parsed.name_by_object_id.get(object).unwrap().path.as_path())
+            .collect();
+        formatter.write_record(&Record::Function {
+            function: parsed.display_name(function),
+            name: function.name(),
+            link_type: parsed.link_type_by_function.get(&function_id).copied(),
+            instruction_count: instr_num,
+            weighted_instructions: match sort_by {
+                cli::SortBy::WeightedInstructions => Some(weighted_instructions(&function_id)),
+                cli::SortBy::Instructions | cli::SortBy::Loops | cli::SortBy::ReturnCount => None,
+            },
+            objects,
+        })?;
+    }
+    Ok(())
+}
+
+/// Functions with many loops are candidates for algorithmic improvements, functions with
+/// many branches may benefit from branch prediction hints.
+fn print_functions_with_most_loops(parsed: &ParsedData, ranking: &Ranking) {
+    let branches_num = |function_id: &FunctionID| {
+        parsed
+            .branch_count_by_function
+            .get(function_id)
+            .copied()
+            .unwrap_or_default()
+    };
+    let mut data: Vec<_> = parsed.loop_count_by_function.iter().collect();
+    data.sort_by(|a, b| {
+        a.1.cmp(b.1)
+            .then_with(|| branches_num(a.0).cmp(&branches_num(b.0)))
+            .reverse()
+    });
+    let data = ranking.apply(data, |(function_id, _)| {
+        function_instructions_num(parsed, **function_id)
+    });
+    for (function_id, loops_num) in data {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        println!(
+            "{}: {} loops, {} branches, {} instructions",
+            parsed.display_name(function),
+            loops_num,
+            branches_num(function_id),
+            function_instructions_num(parsed, *function_id)
+        );
+    }
+}
+
+fn returns_num(parsed: &ParsedData, function_id: FunctionID) -> usize {
+    parsed
+        .return_count_by_function
+        .get(&function_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn print_functions_with_most_returns(parsed: &ParsedData, ranking: &Ranking) {
+    let mut data: Vec<_> = parsed.return_count_by_function.iter().collect();
+    data.sort_by(|a, b| a.1.cmp(b.1).reverse());
+    let data = ranking.apply(data, |(function_id, _)| {
+        function_instructions_num(parsed, **function_id)
+    });
+    for (function_id, returns_num) in data {
+        println!(
+            "{}: {} returns, {} instructions",
+            parsed.display(*function_id),
+            returns_num,
+            function_instructions_num(parsed, *function_id)
+        );
+    }
+}
+
+/// Functions with more `ret` instructions have complex control flow.
+const MAX_SIMPLE_RETURNS_NUM: usize = 3;
+
+/// Functions that never return on their own. Compilers omit the `ret` after calls to them.
+fn is_known_noreturn_function(name: &str) -> bool {
+    matches!(
+        name,
+        "abort"
+            | "exit"
+            | "_exit"
+            | "_Exit"
+            | "quick_exit"
+            | "longjmp"
+            | "siglongjmp"
+            | "__assert_fail"
+            | "__stack_chk_fail"
+            | "__cxa_throw"
+            | "__cxa_rethrow"
+            | "__cxa_bad_cast"
+            | "__cxa_bad_typeid"
+            | "_Unwind_Resume"
+            | "_ZSt9terminatev"
+    ) || name.contains("__throw_")
+}
+
+/// Functions without `ret` that call a function that does not return, starting from the
+/// known ones, e.g. `fatal_error` wrappers around `exit`.
+fn noreturn_functions(parsed: &ParsedData) -> HashSet<FunctionID> {
+    let mut noreturn: HashSet<FunctionID> = parsed
+        .name_by_function_id
+        .iter()
+        .filter(|(_, function)| is_known_noreturn_function(function.name()))
+        .map(|(function_id, _)| *function_id)
+        .collect();
+    let mut candidates: Vec<FunctionID> = parsed
+        .return_count_by_function
+        .iter()
+        .filter(|(function_id, returns_num)| **returns_num == 0 && !noreturn.contains(function_id))
+        .map(|(function_id, _)| *function_id)
+        .collect();
+    loop {
+        let (found, remaining): (Vec<FunctionID>, Vec<FunctionID>) =
+            candidates.into_iter().partition(|function_id| {
+                parsed
+                    .callees_by_caller
+                    .get(function_id)
+                    .into_iter()
+                    .flatten()
+                    .any(|callee| noreturn.contains(callee))
+            });
+        if found.is_empty() {
+            return noreturn;
+        }
+        noreturn.extend(found);
+        candidates = remaining;
+    }
+}
+
+/// Functions with more than `MAX_SIMPLE_RETURNS_NUM` return instructions and their number of
+/// returns, sorted by the number of returns.
+fn multiple_return_functions<'a>(
+    parsed: &'a ParsedData,
+    ranking: &Ranking,
+) -> Vec<(&'a FunctionName, usize)> {
+    let mut multiple: Vec<(FunctionID, usize)> = parsed
+        .return_count_by_function
+        .iter()
+        .filter(|(_, returns_num)| **returns_num > MAX_SIMPLE_RETURNS_NUM)
+        .map(|(function_id, returns_num)| (*function_id, *returns_num))
+        .collect();
+    multiple.sort_by(|a, b| {
+        b.1.cmp(&a.1).then_with(|| {
+            parsed.name_by_function_id[&a.0]
+                .name()
+                .cmp(parsed.name_by_function_id[&b.0].name())
+        })
+    });
+    ranking
+        .apply(multiple, |(function_id, _)| {
+            function_instructions_num(parsed, *function_id)
+        })
+        .into_iter()
+        .map(|(function_id, returns_num)| (&parsed.name_by_function_id[&function_id], returns_num))
+        .collect()
+}
+
+fn print_multiple_return_functions(parsed: &ParsedData, ranking: &Ranking) {
+    let multiple = multiple_return_functions(parsed, ranking);
+    if !multiple.is_empty() {
+        println!(
+            "Functions with more than {} return paths:",
+            MAX_SIMPLE_RETURNS_NUM
+        );
+    }
+    for (function, returns_num) in multiple {
+        println!(
+            "  {}: {} returns",
+            parsed.display_name(function),
+            returns_num
+        );
+    }
+
+    let noreturn = noreturn_functions(parsed);
+    let mut without_return: Vec<(FunctionID, String)> = parsed
+        .return_count_by_function
+        .iter()
+        .filter(|(function_id, returns_num)| **returns_num == 0 && !noreturn.contains(function_id))
+        .map(|(function_id, _)| (*function_id, parsed.display(*function_id).to_string()))
+        .collect();
+    without_return.sort_by(|a, b| a.1.cmp(&b.1));
+    let without_return = ranking.apply(without_return, |(function_id, _)| {
+        function_instructions_num(parsed, *function_id)
+    });
+    if !without_return.is_empty() {
+        println!("Functions without `ret` that exit through tail calls or jumps:");
+    }
+    for (_, function) in without_return {
+        println!("  {}", function);
+    }
+}
+
+/// Weight of every distinct callee and every indirect call in the call ratio, so that a
+/// function whose instructions are mostly calls and their argument setup has a ratio above 1.
+const CALL_RATIO_CALL_WEIGHT: f64 = 10.0;
+
+/// Calls relative to the own instructions of every defined function. Functions with a high
+/// ratio mainly coordinate other functions and have little code of their own. Both are
+/// counted per copy, so that dispatchers in headers are not diluted by the number of objects.
+fn call_ratio_by_function(parsed: &ParsedData) -> HashMap<FunctionID, f64> {
+    parsed
+        .instructions_by_object_by_function
+        .keys()
+        .map(|function_id| {
+            let callees_num = parsed
+                .callees_by_caller
+                .get(function_id)
+                .map_or(0, HashSet::len);
+            let indirect_calls_num = parsed
+                .indirect_call_count_by_function
+                .get(function_id)
+                .copied()
+                .unwrap_or_default();
+            let calls_weight = (callees_num + indirect_calls_num) as f64 * CALL_RATIO_CALL_WEIGHT;
+            (
+                *function_id,
+                calls_weight / function_instructions_num(parsed, *function_id).max(1) as f64,
+            )
+        })
+        .collect()
+}
+
+/// Functions whose call ratio is above the threshold, sorted by the ratio.
+fn dispatcher_functions(
+    parsed: &ParsedData,
+    threshold: f64,
+    ranking: &Ranking,
+) -> Vec<(FunctionID, f64)> {
+    let mut dispatchers: Vec<(FunctionID, f64)> = call_ratio_by_function(parsed)
+        .into_iter()
+        .filter(|(_, call_ratio)| *call_ratio > threshold)
+        .collect();
+    dispatchers.sort_by(|a, b| {
+        b.1.total_cmp(&a.1).then_with(|| {
+            parsed.name_by_function_id[&a.0]
+                .name()
+                .cmp(parsed.name_by_function_id[&b.0].name())
+        })
+    });
+    ranking.apply(dispatchers, |(function_id, _)| {
+        function_instructions_num(parsed, *function_id)
+    })
+}
+
+/// Thin dispatchers are cheap themselves, but every call costs an additional call overhead.
+/// Small ones are candidates for inlining at their call sites.
+fn print_dispatcher_functions(parsed: &ParsedData, threshold: f64, ranking: &Ranking) {
+    for (function_id, call_ratio) in dispatcher_functions(parsed, threshold, ranking) {
+        println!(
+            "{}: call ratio {:.1}, {} callees, {} indirect calls, {} instructions",
+            parsed.display(function_id),
+            call_ratio,
+            parsed
+                .callees_by_caller
+                .get(&function_id)
+                .map_or(0, HashSet::len),
+            parsed
+                .indirect_call_count_by_function
+                .get(&function_id)
+                .copied()
+                .unwrap_or_default(),
+            function_instructions_num(parsed, function_id)
+        );
+    }
+}
+
+/// Only instructions are counted, so the code size is estimated from a typical x86
+/// instruction length.
+const AVERAGE_INSTRUCTION_BYTES: usize = 4;
+/// Above this share of the code size, reducing the function alignment is suggested.
+const MAX_ALIGNMENT_PADDING_SHARE: f64 = 0.05;
+
+/// The actual padding depends on where the previous function ends, so the worst case is an
+/// upper bound.
+fn print_alignment_padding(parsed: &ParsedData, ranking: &Ranking) {
+    let mut data: Vec<_> = parsed
+        .estimated_padding_by_function
+        .iter()
+        .filter(|(_, padding)| **padding > 0)
+        .collect();
+    data.sort_by(|a, b| a.1.cmp(b.1).reverse());
+    let data = ranking.apply(data, |(function_id, _)| {
+        function_instructions_num(parsed, **function_id)
+    });
+    for (function_id, padding) in data {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        println!(
+            "{}: up to {} bytes (aligned to {} bytes, {} instructions)",
+            parsed.display_name(function),
+            padding,
+            parsed.alignment_overhead_by_function[function_id],
+            function_instructions_num(parsed, *function_id)
+        );
+    }
+    let padding_bytes: usize = parsed.estimated_padding_by_function.values().sum();
+    let code_bytes = all_instructions_num(parsed) * AVERAGE_INSTRUCTION_BYTES;
+    let share = if code_bytes == 0 {
+        0.0
+    } else {
+        padding_bytes as f64 / code_bytes as f64
+    };
+    println!(
+        "Total: up to {} bytes of padding, {:.1}% of about {} bytes of code",
+        padding_bytes,
+        share * 100.0,
+        code_bytes
+    );
+    if share > MAX_ALIGNMENT_PADDING_SHARE {
+        println!(
+            "Consider compiling with -falign-functions=1 if code size matters more than the \
+             speed of aligned function entries."
+        );
+    }
+}
+
+/// Functions with a larger share of instructions with immediates are listed.
+const IMMEDIATE_HEAVY_DENSITY: usize = 500; // 50% in per-mille
+
+fn print_immediate_heavy_functions(parsed: &ParsedData, ranking: &Ranking) {
+    let mut data: Vec<_> = parsed
+        .immediate_density_by_function
+        .iter()
+        .filter(|(_, density)| **density > IMMEDIATE_HEAVY_DENSITY)
+        .collect();
+    data.sort_by(|a, b| a.1.cmp(b.1).reverse());
+    let data = ranking.apply(data, |(function_id, _)| {
+        function_instructions_num(parsed, **function_id)
+    });
+    for (function_id, density) in data {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        println!(
+            "{}: {:.1}%",
+            parsed.display_name(function),
+            *density as f64 / 10.0
+        );
+    }
+}
+
+fn print_string_heavy_functions(parsed: &ParsedData, ranking: &Ranking) {
+    let mut data: Vec<_> = parsed.string_literal_refs_by_function.iter().collect();
+    data.sort_by(|a, b| a.1.cmp(b.1).reverse());
+    let data = ranking.apply(data, |(function_id, _)| {
+        function_instructions_num(parsed, **function_id)
+    });
+    for (function_id, refs_num) in data {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        println!(
+            "{}: {} string literals",
+            parsed.display_name(function),
+            refs_num
+        );
+    }
+}
+
+/// Groups all functions that have instructions by the namespace of their demangled name.
+/// Functions in the global namespace use an empty string as key.
+fn group_functions_by_namespace(parsed: &ParsedData) -> Result<HashMap<String, Vec<FunctionID>>> {
+    let function_ids: Vec<FunctionID> = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .copied()
+        .collect();
+    let names: Vec<&str> = function_ids
+        .iter()
+        .map(|function_id| parsed.function_name_str(*function_id).unwrap())
+        .collect();
+    let demangled_names = demangle::demangle_names(&names)?;
+    let mut functions_by_namespace: HashMap<String, Vec<FunctionID>> = HashMap::new();
+    for (function_id, demangled_name) in function_ids.into_iter().zip(demangled_names.iter()) {
+        let namespace = demangle::namespace(demangled_name).unwrap_or_default();
+        functions_by_namespace
+            .entry(namespace.to_owned())
+            .or_default()
+            .push(function_id);
+    }
+    Ok(functions_by_namespace)
+}
+
+fn print_namespace_summary(parsed: &ParsedData, ranking: &Ranking) -> Result<()> {
+    let total: usize = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .map(|function_id| function_instructions_num(parsed, *function_id))
+        .sum();
+    let mut data: Vec<(String, usize)> = group_functions_by_namespace(parsed)?
+        .into_iter()
+        .map(|(namespace, function_ids)| {
+            let instructions_num = function_ids
+                .into_iter()
+                .map(|function_id| function_instructions_num(parsed, function_id))
+                .sum();
+            (namespace, instructions_num)
+        })
+        .collect();
+    data.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+    for (namespace, instructions_num) in ranking.apply(data, |item| item.1) {
+        let namespace = if namespace.is_empty() {
+            "(global namespace)"
+        } else {
+            &namespace
+        };
+        println!(
+            "{}: {} instructions ({:.1}%)",
+            namespace,
+            instructions_num,
+            instructions_num as f64 * 100.0 / total.max(1) as f64
+        );
+    }
+    Ok(())
+}
+
+fn print_high_callee_save_overhead(parsed: &ParsedData, ranking: &Ranking) {
+    let mut data: Vec<_> = parsed.saved_registers_by_function.iter().collect();
+    data.sort_by(|a, b| a.1.len().cmp(&b.1.len()).reverse());
+    let data = ranking.apply(data, |(function_id, _)| {
+        function_instructions_num(parsed, **function_id)
+    });
+    for (function_id, registers) in data {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        let frame_size = parsed
+            .frame_size_by_function
+            .get(function_id)
+            .copied()
+            .unwrap_or_default();
+        println!(
+            "{}: {} saved registers ({}), {} byte frame",
+            parsed.display_name(function),
+            registers.len(),
+            registers.join(", "),
+            frame_size
+        );
+    }
+}
+
+/// Constants of the simple cost model used to judge whether inlining is beneficial.
+struct InliningCosts {
+    /// Instructions saved for every call that is removed by inlining.
+    call_overhead_savings: f64,
+    /// Cost of every instruction that is duplicated into a caller.
+    code_size_penalty: f64,
+}
+
+impl Default for InliningCosts {
+    fn default() -> Self {
+        InliningCosts {
+            call_overhead_savings: 5.0,
+            code_size_penalty: 1.0,
+        }
+    }
+}
+
+/// Positive scores indicate that inlining is likely beneficial, negative scores indicate
+/// that the function should rather not be inlined. Sorted by descending score.
+fn compute_inlining_scores(parsed: &ParsedData, costs: &InliningCosts) -> Vec<(FunctionID, f64)> {
+    let mut scores: Vec<_> = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .filter_map(|function_id| {
+            let callers_num = parsed.callers_by_callee.get(function_id)?.len() as f64;
+            let instructions_num = function_instructions_num(parsed, *function_id);
+            let score = callers_num * costs.call_overhead_savings
+                - instructions_num as f64 * callers_num * costs.code_size_penalty;
+            Some((*function_id, score))
+        })
+        .collect();
+    scores.sort_by(|a, b| a.1.total_cmp(&b.1).reverse());
+    scores
+}
+
+fn print_inlining_candidates(parsed: &ParsedData, costs: &InliningCosts, ranking: &Ranking) {
+    let scores = compute_inlining_scores(parsed, costs);
+    let instructions_num =
+        |(function_id, _): &&(FunctionID, f64)| function_instructions_num(parsed, *function_id);
+    println!("Inlining candidates:");
+    let candidates = scores.iter().filter(|(_, score)| *score > 0.0);
+    for (function_id, score) in ranking.apply(candidates, instructions_num) {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        println!("  {}: {:.1}", parsed.display_name(function), score);
+    }
+    println!("Candidates for [[noinline]]:");
+    let candidates = scores.iter().rev().filter(|(_, score)| *score < 0.0);
+    for (function_id, score) in ranking.apply(candidates, instructions_num) {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        println!("  {}: {:.1}", parsed.display_name(function), score);
+    }
+}
+
+fn print_recursive_functions(parsed: &ParsedData) {
+    for (function_id, function) in parsed.name_by_function_id.iter() {
+        let calls_itself = parsed
+            .callees_by_caller
+            .get(function_id)
+            .is_some_and(|callees| callees.contains(function_id));
+        let is_tail_recursive = parsed.tail_recursive_functions.contains(function_id);
+        match (calls_itself, is_tail_recursive) {
+            (true, true) => println!(
+                "{} (recursive and tail-recursive, TCO applied)",
+                parsed.display_name(function)
+            ),
+            (true, false) => println!("{}", parsed.display_name(function)),
+            (false, true) => println!(
+                "{} (tail-recursive, TCO applied)",
+                parsed.display_name(function)
+            ),
+            (false, false) => {}
+        }
+    }
+
+    let call_graph = call_graph::build_call_graph(parsed);
+    let groups: Vec<_> = call_graph
+        .strongly_connected_components()
+        .into_iter()
+        .filter(|component| component.len() > 1)
+        .collect();
+    if !groups.is_empty() {
+        println!("Mutually recursive groups:");
+        for group in groups {
+            println!("  Group of {} functions:", group.len());
+            for function_id in group {
+                println!("    {}", parsed.display(function_id));
+            }
+        }
+    }
+}
+
+/// Functions with exception handling cleanup code, largest first.
+fn exception_heavy_functions<'a>(
+    parsed: &'a ParsedData,
+    ranking: &Ranking,
+) -> Vec<(&'a FunctionName, usize)> {
+    let mut functions: Vec<(&FunctionName, usize)> = parsed
+        .has_unwind_personality
+        .iter()
+        .map(|function_id| {
+            (
+                &parsed.name_by_function_id[function_id],
+                function_instructions_num(parsed, *function_id),
+            )
+        })
+        .collect();
+    functions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name().cmp(b.0.name())));
+    ranking.apply(functions, |(_, num)| *num)
+}
+
+fn print_exception_heavy_functions(parsed: &ParsedData, ranking: &Ranking) {
+    for (function, instructions_num) in exception_heavy_functions(parsed, ranking) {
+        println!("{}: {}", parsed.display_name(function), instructions_num);
+    }
+}
+
+/// Global functions without direct callers are either unused, entry points of the public API
+/// or only called indirectly. The latter are detected by their address being stored in
+/// read-only data.
+fn print_callback_functions(parsed: &ParsedData) {
+    let mut with_references = vec![];
+    let mut without_references = vec![];
+    for function_id in parsed.instructions_by_object_by_function.keys() {
+        let function = parsed.name_by_function_id.get(function_id).unwrap();
+        if !matches!(function, FunctionName::Global(_))
+            || parsed.callers_by_callee.contains_key(function_id)
+        {
+            continue;
+        }
+        if parsed.function_pointer_referenced.contains(function_id) {
+            with_references.push(function);
+        } else {
+            without_references.push(function);
+        }
+    }
+    with_references.sort_by_key(|function| function.name());
+    without_references.sort_by_key(|function| function.name());
+
+    println!("Uncalled functions referenced from read-only data (likely callbacks or virtual):");
+    for function in with_references {
+        println!("  {}", parsed.display_name(function));
+    }
+    println!("Uncalled functions without references (API entry points or dead code):");
+    for function in without_references {
+        println!("  {}", parsed.display_name(function));
+    }
+}
+
+/// Calls between the two calling conventions are only correct if the compiler knows the
+/// convention of the callee, e.g. from the declaration. Mismatched declarations in different
+/// translation units are a common source of bugs when interfacing with UEFI or Windows code.
+fn print_windows_abi_functions(parsed: &ParsedData) {
+    let mut functions: Vec<_> = parsed
+        .windows_abi_functions
+        .iter()
+        .map(|function_id| {
+            (
+                function_id,
+                parsed.name_by_function_id.get(function_id).unwrap(),
+            )
+        })
+        .collect();
+    functions.sort_by_key(|(_, function)| function.name());
+    println!("Functions using the Windows x64 ABI:");
+    for (_, function) in functions.iter() {
+        println!("  {}", parsed.display_name(function));
+    }
+    for (function_id, function) in functions {
+        let sysv_callers = parsed
+            .callers_by_callee
+            .get(function_id)
+            .into_iter()
+            .flatten()
+            .filter(|caller| !parsed.windows_abi_functions.contains(caller));
+        for caller in sysv_callers {
+            println!(
+                "Warning: {} is called from the System V ABI function {}, make sure the \
+                 declaration used there has the same calling convention.",
+                parsed.display_name(function),
+                parsed.display(*caller)
+            );
+        }
+    }
+}
+
+/// Discarded functions may be missing in the final binary, internalized ones can't be called
+/// from other link units.
+fn print_lto_discarded_functions(parsed: &ParsedData) {
+    for (label, functions) in [
+        ("Discarded by LTO", &parsed.lto_discarded),
+        ("Internalized by LTO", &parsed.lto_private),
+    ] {
+        if functions.is_empty() {
+            continue;
+        }
+        let mut names: Vec<String> = functions
+            .iter()
+            .map(|function_id| parsed.display(*function_id).to_string())
+            .collect();
+        names.sort();
+        println!("{}:", label);
+        for name in names {
+            println!("  {}", name);
+        }
+    }
+}
+
+/// Declared functions that are not defined in any object.
+fn is_fully_inlined(parsed: &ParsedData, function_id: FunctionID) -> bool {
+    parsed.fully_inlined_functions.contains(&function_id)
+        && !parsed
+            .instructions_by_object_by_function
+            .contains_key(&function_id)
+}
+
+/// Smaller callees are within the noise of other optimizations.
+const MIN_INLINED_CALLEE_INSTRUCTIONS: usize = 4;
+
+/// Callers whose copies in different objects differ by about the size of one of their callees.
+/// The larger copies probably inlined the callee that the smaller copies still call. The size
+/// of a callee is its smallest copy.
+fn suspected_inlined_callees(parsed: &ParsedData) -> HashMap<FunctionID, Vec<FunctionID>> {
+    let smallest_copy = |function_id: &FunctionID| {
+        parsed
+            .instructions_by_object_by_function
+            .get(function_id)?
+            .values()
+            .min()
+            .copied()
+    };
+    let mut suspected: HashMap<FunctionID, Vec<FunctionID>> = HashMap::new();
+    for (caller, nums) in parsed.instructions_by_object_by_function.iter() {
+        let (Some(min), Some(max)) = (nums.values().min(), nums.values().max()) else {
+            continue;
+        };
+        let difference = max - min;
+        if difference == 0 {
+            continue;
+        }
+        for callee in parsed.callees_by_caller.get(caller).into_iter().flatten() {
+            let Some(callee_num) = smallest_copy(callee) else {
+                continue;
+            };
+            if callee != caller
+                && callee_num >= MIN_INLINED_CALLEE_INSTRUCTIONS
+                && difference * 4 >= callee_num * 3
+            {
+                suspected.entry(*caller).or_default().push(*callee);
+            }
+        }
+    }
+    suspected
+}
+
+fn print_inlined_functions(parsed: &ParsedData) {
+    let mut fully_inlined: Vec<String> = parsed
+        .fully_inlined_functions
+        .iter()
+        .filter(|function_id| is_fully_inlined(parsed, **function_id))
+        .map(|function_id| parsed.display(*function_id).to_string())
+        .collect();
+    fully_inlined.sort();
+    if !fully_inlined.is_empty() {
+        println!("Declared but never defined, probably inlined at every call site:");
+        for name in fully_inlined {
+            println!("  {}", name);
+        }
+    }
+
+    let mut suspected: Vec<(String, FunctionID, Vec<FunctionID>)> =
+        suspected_inlined_callees(parsed)
+            .into_iter()
+            .map(|(caller, callees)| (parsed.display(caller).to_string(), caller, callees))
+            .collect();
+    suspected.sort_by(|a, b| a.0.cmp(&b.0));
+    if !suspected.is_empty() {
+        println!("Callers whose larger copies probably inlined a callee:");
+    }
+    for (caller_name, caller, callees) in suspected {
+        let nums = &parsed.instructions_by_object_by_function[&caller];
+        println!(
+            "  {} ({} to {} instructions)",
+            caller_name,
+            nums.values().min().unwrap(),
+            nums.values().max().unwrap()
+        );
+        let mut callees: Vec<(String, usize)> = callees
+            .iter()
+            .map(|callee| {
+                (
+                    parsed.display(*callee).to_string(),
+                    parsed.instructions_by_object_by_function[callee]
+                        .values()
+                        .min()
+                        .copied()
+                        .unwrap_or_default(),
+                )
+            })
+            .collect();
+        callees.sort();
+        for (callee_name, callee_num) in callees {
+            println!("    {} ({} instructions)", callee_name, callee_num);
+        }
+    }
+}
+
+/// Functions sorted by their instruction count, because enabling optimizations for large
+/// functions helps most.
+fn print_deoptimized_functions(parsed: &ParsedData, ranking: &Ranking) {
+    let mut functions: Vec<(&FunctionName, usize)> = parsed
+        .possibly_deoptimized
+        .iter()
+        .map(|function_id| {
+            (
+                &parsed.name_by_function_id[function_id],
+                function_instructions_num(parsed, *function_id),
+            )
+        })
+        .collect();
+    functions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name().cmp(b.0.name())));
+    for (function, instructions_num) in ranking.apply(functions, |(_, num)| *num) {
+        println!("{}: {}", parsed.display_name(function), instructions_num);
+    }
+}
+
+/// Shown next to the callers of deprecated functions, so that they know what to use instead.
+fn print_deprecation_warning(parsed: &ParsedData, function_id: FunctionID) {
+    if let Some(message) = parsed.deprecated_functions.get(&function_id) {
+        println!("  Deprecated: {}", message);
+    }
+}
+
+/// Every deprecated function that is called, with its warning and callers.
+fn print_deprecated_callers(parsed: &ParsedData) {
+    let mut deprecated: Vec<(&FunctionName, FunctionID)> = parsed
+        .deprecated_functions
+        .keys()
+        .map(|function_id| (&parsed.name_by_function_id[function_id], *function_id))
+        .collect();
+    deprecated.sort_by_key(|(function, _)| function.name());
+    let mut callers_num = 0;
+    for (function, function_id) in deprecated {
+        let mut callers: Vec<&FunctionName> = parsed
+            .callers_by_callee
+            .get(&function_id)
+            .into_iter()
+            .flatten()
+            .map(|caller| &parsed.name_by_function_id[caller])
+            .collect();
+        if callers.is_empty() {
+            continue;
+        }
+        callers.sort_by_key(|caller| caller.name());
+        callers_num += callers.len();
+        println!("{}:", parsed.display_name(function));
+        print_deprecation_warning(parsed, function_id);
+        for caller in callers {
+            println!("  {}", parsed.display_name(caller));
+        }
+    }
+    if callers_num == 0 {
+        eprintln!("No deprecated function is called.");
+    }
+}
+
+/// TLS is not available on every platform, e.g. on many embedded systems, and its first
+/// access in a thread can be expensive.
+fn print_tls_using_functions(parsed: &ParsedData) {
+    let mut functions: Vec<_> = parsed
+        .uses_tls
+        .iter()
+        .map(|function_id| {
+            (
+                parsed.name_by_function_id.get(function_id).unwrap(),
+                function_instructions_num(parsed, *function_id),
+            )
+        })
+        .collect();
+    functions.sort_by_key(|(function, _)| function.name());
+    for (function, instructions_num) in functions {
+        println!("{}: {}", parsed.display_name(function), instructions_num);
+    }
+}
+
+/// Functions that are defined without any instructions, e.g. pure virtual stubs or
+/// functions whose body was optimized away. They take no code space but still have a symbol.
+fn print_empty_functions(parsed: &ParsedData) {
+    let mut functions: Vec<_> = parsed
+        .functions()
+        .filter(|(_, _, instructions_num)| *instructions_num == Some(0))
+        .map(|(_, function, _)| function)
+        .collect();
+    functions.sort_by_key(|function| function.name());
+    for function in functions {
+        println!("{}", parsed.display_name(function));
+    }
+}
+
+fn count_call_sites(parsed: &ParsedData) -> HashMap<(FunctionID, FunctionID), usize> {
+    parsed
+        .call_sites_by_caller
+        .iter()
+        .flat_map(|(caller, call_sites)| {
+            call_sites
+                .iter()
+                .map(|(callee, num)| ((*caller, *callee), *num))
+        })
+        .collect()
+}
+
+/// Number of hot call pairs that are shown when neither `--top` nor `--bottom` is given.
+const DEFAULT_HOT_CALL_PAIRS_NUM: usize = 20;
+
+/// Without a profile, calling the same function from many call sites is used as a proxy for
+/// hot call pairs. Such callers may benefit from inlining the callee or caching its result.
+/// The instruction threshold of the ranking applies to the callers.
+fn hot_call_pairs(
+    parsed: &ParsedData,
+    ranking: &Ranking,
+) -> Vec<((FunctionID, FunctionID), usize)> {
+    let mut pairs: Vec<_> = count_call_sites(parsed)
+        .into_iter()
+        .filter(|(_, num)| *num > 1)
+        .collect();
+    pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let ranking = Ranking {
+        top: ranking.top.or(ranking
+            .bottom
+            .is_none()
+            .then_some(DEFAULT_HOT_CALL_PAIRS_NUM)),
+        bottom: ranking.bottom,
+        min_instructions: ranking.min_instructions,
+    };
+    ranking.apply(pairs, |((caller, _), _)| {
+        function_instructions_num(parsed, *caller)
+    })
+}
+
+fn print_hot_call_pairs(parsed: &ParsedData, ranking: &Ranking) {
+    for ((caller, callee), num) in hot_call_pairs(parsed, ranking) {
+        println!(
+            "{} -> {}: {} call sites",
+            parsed.display(caller),
+            parsed.display(callee),
+            num
+        );
+    }
+}
+
+fn print_call_graph_stats(parsed: &ParsedData, max_functions_for_apsp: usize) {
+    let call_graph = call_graph::build_call_graph(parsed);
+    let calls_num: usize = parsed.callees_by_caller.values().map(HashSet::len).sum();
+    println!("Functions: {}", parsed.name_by_function_id.len());
+    println!("Calls: {}", calls_num);
+    if call_graph.nodes_num() > max_functions_for_apsp {
+        println!(
+            "Shortest paths skipped because there are more than {} functions, see \
+             --max-functions-for-apsp.",
+            max_functions_for_apsp
+        );
+        return;
+    }
+    let matrix = call_graph.shortest_distance_matrix();
+    println!(
+        "Average shortest path length: {:.2}",
+        call_graph::average_shortest_path_length(&matrix)
+    );
+    println!("Diameter: {}", call_graph::diameter(&matrix));
+    #[cfg(feature = "petgraph")]
+    {
+        print_virtual_call_cycles(parsed);
+        print_most_important_functions(parsed);
+    }
+}
+
+/// Recursion through virtual calls is not visible in the direct call graph, e.g. a visitor
+/// that calls `accept` on child nodes.
+#[cfg(feature = "petgraph")]
+fn print_virtual_call_cycles(parsed: &ParsedData) {
+    let call_graph = petgraph_interop::from_petgraph(petgraph_interop::to_petgraph(parsed), parsed);
+    let cycles_num = call_graph
+        .strongly_connected_components()
+        .into_iter()
+        .filter(|component| component.len() > 1)
+        .count();
+    println!("Call cycles including virtual calls: {}", cycles_num);
+}
+
+/// Functions that are called, directly or indirectly, by many other important functions.
+#[cfg(feature = "petgraph")]
+fn print_most_important_functions(parsed: &ParsedData) {
+    const SHOWN_NUM: usize = 10;
+    let graph = petgraph_interop::to_petgraph(parsed);
+    let ranks = petgraph::algo::page_rank(&graph, 0.85, 50);
+    let mut ranked: Vec<(FunctionID, f64)> = graph
+        .node_indices()
+        .map(|node| (graph[node], ranks[node.index()]))
+        .collect();
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1).reverse());
+    println!("Highest PageRank:");
+    for (function_id, rank) in ranked.into_iter().take(SHOWN_NUM) {
+        println!("  {}: {:.4}", parsed.display(function_id), rank);
+    }
+}
+
+fn print_functions_in_topological_order(parsed: &ParsedData) {
+    let call_graph = call_graph::build_call_graph(parsed);
+    for function_id in call_graph.topological_sort() {
+        if let Some(function) = parsed.name_by_function_id.get(&function_id) {
+            println!("{}", parsed.display_name(function));
+        }
+    }
+}
+
+fn print_functions_in_all_objects(parsed: &ParsedData) {
+    let objects_num = parsed.object_id_by_name.len();
+    for (function_id, objects) in parsed.objects_by_function.iter() {
+        if objects.len() == objects_num {
+            let function = parsed.name_by_function_id.get(function_id).unwrap();
+            println!("{}", parsed.display_name(function));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CallEdgeKind {
+    Direct,
+    /// The callee is in the called slot of a vtable. Since the class of the object is not
+    /// known, every vtable with a function in that slot is a candidate.
+    Virtual,
+}
+
+fn callees_with_kind(parsed: &ParsedData, caller: FunctionID) -> Vec<(FunctionID, CallEdgeKind)> {
+    let direct_callees = parsed.callees_by_caller.get(&caller).into_iter().flatten();
+    let mut callees: Vec<_> = direct_callees
+        .map(|callee| (*callee, CallEdgeKind::Direct))
+        .collect();
+    let Some(slots) = parsed.virtual_call_slots_by_function.get(&caller) else {
+        return callees;
+    };
+    let mut virtual_callees = BTreeSet::new();
+    for entries in parsed.vtable_entries.values() {
+        for slot in slots {
+            if let Some(Some(callee)) = entries.get(*slot) {
+                virtual_callees.insert(*callee);
+            }
+        }
+    }
+    callees.extend(
+        virtual_callees
+            .into_iter()
+            .map(|callee| (callee, CallEdgeKind::Virtual)),
+    );
+    callees
+}
+
+fn print_function_info(
+    parsed: &ParsedData,
+    function: &FunctionName,
+    max_depth: Option<usize>,
+) -> Result<()> {
+    let function_id = parsed
+        .function_id_by_name
+        .get(function)
+        .ok_or(eyre::eyre!("Can't find function."))?;
+    let objects = parsed
+        .objects_by_function
+        .get(function_id)
+        .cloned()
+        .unwrap_or_default();
+    let callers = parsed
+        .callers_by_callee
+        .get(function_id)
+        .cloned()
+        .unwrap_or_default();
+    let callees = callees_with_kind(parsed, *function_id);
+    println!("Function: {}", parsed.display_name(function));
+    if let Some(link_type) = parsed.link_type_by_function.get(function_id) {
+        println!("  Link type: {:?}", link_type);
+    }
+    if parsed
+        .instructions_by_object_by_function
+        .contains_key(function_id)
+        && function_instructions_num(parsed, *function_id) == 0
+    {
+        println!("  Empty: defined without instructions");
+    }
+    if parsed.lto_discarded.contains(function_id) {
+        println!("  LTO: discarded, may be missing in the final binary");
+    }
+    if parsed.lto_private.contains(function_id) {
+        println!("  LTO: internalized");
+    }
+    if parsed.possibly_deoptimized.contains(function_id) {
+        println!("  Optimization: possibly disabled");
+    }
+    if is_fully_inlined(parsed, *function_id) {
+        println!("  Inlining: probably inlined at every call site");
+    }
+    if parsed.return_count_by_function.contains_key(function_id) {
+        println!("  Returns: {}", returns_num(parsed, *function_id));
+    }
+    print_deprecation_warning(parsed, *function_id);
+    let call_graph = call_graph::build_call_graph(parsed);
+    print_call_chain_from_main(parsed, &call_graph, *function_id, max_depth);
+    println!("  Objects:");
+    for object in objects {
+        println!(
+            "    {} ({} instructions)",
+            parsed.name_by_object_id.get(&object).unwrap(),
+            instructions_in_object(parsed, *function_id, object)
+        );
+    }
+    println!("  Callers:");
+    for caller in callers {
+        println!("    {}", parsed.display(caller));
+    }
+    println!("  Callees:");
+    for (callee, kind) in callees {
+        let callee = parsed.name_by_function_id.get(&callee).unwrap();
+        match kind {
+            CallEdgeKind::Direct => println!("    {}", parsed.display_name(callee)),
+            CallEdgeKind::Virtual => println!("    {} (virtual)", parsed.display_name(callee)),
+        }
+    }
+    let transitive_callees = call_graph.reachable_callees(*function_id, max_depth);
+    let transitive_callers = call_graph.reachable_callers(*function_id, max_depth);
+    for (label, result) in [
+        ("callees", transitive_callees),
+        ("callers", transitive_callers),
+    ] {
+        if result.is_truncated() {
+            eprintln!(
+                "Warning: Transitive {} are incomplete because the maximum depth of {} was reached.",
+                label,
+                max_depth.unwrap_or_default()
+            );
+        }
+        println!("  Transitive {}: {}", label, result.into_inner().len());
+    }
+    Ok(())
+}
+
+/// Shows how the function is reached from the program entry, if the analyzed code has one.
+fn print_call_chain_from_main(
+    parsed: &ParsedData,
+    call_graph: &call_graph::CallGraph,
+    function_id: FunctionID,
+    max_depth: Option<usize>,
+) {
+    let Some(main_id) = parsed.function_id_by_global_name("main") else {
+        return;
+    };
+    let chain = call_graph.shortest_call_path(main_id, function_id, max_depth);
+    let is_truncated = chain.is_truncated();
+    match chain.into_inner() {
+        Some(chain) if chain.len() == 1 => {}
+        Some(chain) => {
+            let names: Vec<String> = chain
+                .iter()
+                .map(|function_id| parsed.display(*function_id).to_string())
+                .collect();
+            println!(
+                "  Called from main at depth {}: {}",
+                chain.len() - 1,
+                names.join(" → ")
+            );
+        }
+        None if is_truncated => println!(
+            "  Not reachable from main within the maximum depth of {} calls",
+            max_depth.unwrap_or_default()
+        ),
+        None => println!("  Not reachable from main through known calls"),
+    }
+}
+
+/// Instructions of the function and everything it calls directly or indirectly, up to
+/// `max_depth` calls away.
+fn subtree_instructions_num(
+    parsed: &ParsedData,
+    call_graph: &call_graph::CallGraph,
+    function_id: FunctionID,
+    max_depth: Option<usize>,
+) -> call_graph::TraversalResult<usize> {
+    let callees = call_graph.reachable_callees(function_id, max_depth);
+    let is_truncated = callees.is_truncated();
+    let instructions_num = callees
+        .into_inner()
+        .into_iter()
+        .map(|callee| function_instructions_num(parsed, callee))
+        .sum::<usize>()
+        + function_instructions_num(parsed, function_id);
+    if is_truncated {
+        call_graph::TraversalResult::Truncated(instructions_num)
+    } else {
+        call_graph::TraversalResult::Complete(instructions_num)
+    }
+}
+
+/// Defined functions that are traversed at most to rank a subtree size with a maximum depth.
+const SUBTREE_SIZE_SAMPLE_NUM: usize = 1000;
+
+/// Whether `defined_subtree_sizes` only traverses a sample of the defined functions, so that
+/// rankings against them are approximate.
+fn defined_subtree_sizes_are_sampled(parsed: &ParsedData, max_depth: Option<usize>) -> bool {
+    max_depth.is_some() && parsed.instructions_by_object_by_function.len() > SUBTREE_SIZE_SAMPLE_NUM
+}
+
+/// Subtree sizes of the defined functions that other subtree sizes are ranked against.
+/// Without a maximum depth, they are computed together from the transitive closure. Sizes
+/// with a maximum depth can't be derived from each other, so only an evenly spaced sample of
+/// the functions is traversed when there are more than `SUBTREE_SIZE_SAMPLE_NUM`.
+fn defined_subtree_sizes(
+    parsed: &ParsedData,
+    call_graph: &call_graph::CallGraph,
+    max_depth: Option<usize>,
+) -> call_graph::TraversalResult<Vec<usize>> {
+    let mut defined_functions: Vec<FunctionID> = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .copied()
+        .collect();
+    defined_functions.sort();
+    if max_depth.is_none() {
+        let sums = call_graph.reachable_weight_sums(defined_functions.iter().copied(), |f| {
+            function_instructions_num(parsed, f)
+        });
+        return call_graph::TraversalResult::Complete(
+            defined_functions
+                .iter()
+                .map(|function_id| sums[function_id])
+                .collect(),
+        );
+    }
+    let step = defined_functions
+        .len()
+        .div_ceil(SUBTREE_SIZE_SAMPLE_NUM)
+        .max(1);
+    let mut is_truncated = false;
+    let sizes = defined_functions
+        .iter()
+        .step_by(step)
+        .map(|function_id| {
+            let size = subtree_instructions_num(parsed, call_graph, *function_id, max_depth);
+            is_truncated |= size.is_truncated();
+            size.into_inner()
+        })
+        .collect();
+    if is_truncated {
+        call_graph::TraversalResult::Truncated(sizes)
+    } else {
+        call_graph::TraversalResult::Complete(sizes)
+    }
+}
+
+/// The callers of the target with the most own instructions, where inlining the target
+/// changes the largest functions.
+fn top_callers_by_weight(
+    parsed: &ParsedData,
+    target: FunctionID,
+    n: usize,
+) -> Vec<(FunctionID, usize)> {
+    let mut callers: Vec<(FunctionID, usize)> = parsed
+        .callers_by_callee
+        .get(&target)
+        .into_iter()
+        .flatten()
+        .map(|caller| (*caller, function_instructions_num(parsed, *caller)))
+        .collect();
+    callers.sort_by_key(|(caller, num)| (std::cmp::Reverse(*num), *caller));
+    callers.truncate(n);
+    callers
+}
+
+/// Same as `top_callers_by_weight`, but weighted by everything the callers call directly or
+/// indirectly, up to `max_depth` calls away.
+fn top_callers_by_subtree_size(
+    parsed: &ParsedData,
+    target: FunctionID,
+    n: usize,
+    max_depth: Option<usize>,
+) -> call_graph::TraversalResult<Vec<(FunctionID, usize)>> {
+    let call_graph = call_graph::build_call_graph(parsed);
+    let mut is_truncated = false;
+    let mut callers: Vec<(FunctionID, usize)> = parsed
+        .callers_by_callee
+        .get(&target)
+        .into_iter()
+        .flatten()
+        .map(|caller| {
+            let size = subtree_instructions_num(parsed, &call_graph, *caller, max_depth);
+            is_truncated |= size.is_truncated();
+            (*caller, size.into_inner())
+        })
+        .collect();
+    callers.sort_by_key(|(caller, num)| (std::cmp::Reverse(*num), *caller));
+    callers.truncate(n);
+    if is_truncated {
+        call_graph::TraversalResult::Truncated(callers)
+    } else {
+        call_graph::TraversalResult::Complete(callers)
+    }
+}
+
+/// Shows where inlining the target would have the biggest effect.
+fn print_top_callers(parsed: &ParsedData, target: FunctionID, n: usize, max_depth: Option<usize>) {
+    let callers_num = parsed
+        .callers_by_callee
+        .get(&target)
+        .map_or(0, HashSet::len);
+    println!("{} has {} callers.", parsed.display(target), callers_num);
+    print_deprecation_warning(parsed, target);
+    println!("By own instructions:");
+    for (caller, num) in top_callers_by_weight(parsed, target, n) {
+        println!("  {}: {}", parsed.display(caller), num);
+    }
+    println!("By subtree instructions:");
+    let callers = top_callers_by_subtree_size(parsed, target, n, max_depth);
+    if callers.is_truncated() {
+        eprintln!(
+            "Warning: Subtree sizes are incomplete because the maximum depth of {} was reached.",
+            max_depth.unwrap_or_default()
+        );
+    }
+    for (caller, num) in callers.into_inner() {
+        println!("  {}: {}", parsed.display(caller), num);
+    }
+}
+
+/// Prints the callees of the root as indented tree. Every function is expanded only once, so
+/// that the output does not grow exponentially, and calls back into the current path are
+/// not followed. The cumulative count includes everything the function calls transitively, up
+/// to `max_depth` calls away from that function.
+fn print_call_tree(parsed: &ParsedData, root: FunctionID, depth: usize, max_depth: Option<usize>) {
+    let call_graph = call_graph::build_call_graph(parsed);
+    // Without a maximum depth, all cumulative counts come from the same transitive closure.
+    let cumulative_nums = match max_depth {
+        None => call_graph.reachable_weight_sums([root], |function_id| {
+            function_instructions_num(parsed, function_id)
+        }),
+        Some(_) => HashMap::new(),
+    };
+    let mut is_truncated = false;
+    let mut shown: HashSet<FunctionID> = HashSet::new();
+    let mut path: Vec<FunctionID> = vec![];
+    // Functions with their level that still have to be printed, in reverse order.
+    let mut stack: Vec<(FunctionID, usize)> = vec![(root, 0)];
+    while let Some((function_id, level)) = stack.pop() {
+        path.truncate(level);
+        let indent = "  ".repeat(level);
+        let function = &parsed.name_by_function_id[&function_id];
+        if path.contains(&function_id) {
+            println!("{}{} [recursive]", indent, parsed.display_name(function));
+            continue;
+        }
+        if !shown.insert(function_id) {
+            println!(
+                "{}{} [already shown]",
+                indent,
+                parsed.display_name(function)
+            );
+            continue;
+        }
+        let cumulative_num = match max_depth {
+            None => cumulative_nums[&function_id],
+            Some(_) => {
+                let size = subtree_instructions_num(parsed, &call_graph, function_id, max_depth);
+                is_truncated |= size.is_truncated();
+                size.into_inner()
+            }
+        };
+        println!(
+            "{}{} ({} instructions, {} cumulative)",
+            indent,
+            parsed.display_name(function),
+            function_instructions_num(parsed, function_id),
+            cumulative_num
+        );
+        if level == depth {
+            continue;
+        }
+        path.push(function_id);
+        let mut callees: Vec<FunctionID> = call_graph
+            .neighbors(function_id, call_graph::Direction::Callees)
+            .to_vec();
+        callees.sort_by_key(|callee| parsed.display(*callee).to_string());
+        stack.extend(callees.into_iter().rev().map(|callee| (callee, level + 1)));
+    }
+    if is_truncated {
+        eprintln!(
+            "Warning: Cumulative counts are incomplete because the maximum depth of {} was reached.",
+            max_depth.unwrap_or_default()
+        );
+    }
+}
+
+/// Functions with at least this many callers are described as widely used.
+const HIGH_USAGE_CALLERS_NUM: usize = 10;
+
+/// Describes the role of a function in the call graph in plain sentences, for readers who are
+/// not used to the raw numbers of `print_function_info`.
+fn explain_function(
+    parsed: &ParsedData,
+    function_id: FunctionID,
+    max_depth: Option<usize>,
+) -> String {
+    let function = &parsed.name_by_function_id[&function_id];
+    let plural = |num: usize, word: &str| {
+        if num == 1 {
+            format!("1 {}", word)
+        } else {
+            format!("{} {}s", num, word)
+        }
+    };
+    let mut sentences = vec![];
+
+    let objects_num = parsed
+        .objects_by_function
+        .get(&function_id)
+        .map_or(0, HashSet::len);
+    if objects_num == 0 {
+        sentences.push(format!(
+            "Function `{}` is not defined in any analyzed object, it is only called.",
+            parsed.display_name(function)
+        ));
+    } else {
+        let link_type = match parsed.link_type_by_function.get(&function_id) {
+            Some(LinkType::Local) => " It is local to its object.",
+            Some(LinkType::Weak) => " It is a weak symbol, so the linker keeps only one copy.",
+            _ => "",
+        };
+        sentences.push(format!(
+            "Function `{}` is defined in {}.{}",
+            parsed.display_name(function),
+            plural(objects_num, "object file"),
+            link_type
+        ));
+        let instructions_num = function_instructions_num(parsed, function_id);
+        sentences.push(if instructions_num == 0 {
+            "It does not contain any instructions.".to_owned()
+        } else {
+            format!("It has {}.", plural(instructions_num, "instruction"))
+        });
+    }
+
+    let callers_num = parsed
+        .callers_by_callee
+        .get(&function_id)
+        .map_or(0, HashSet::len);
+    let is_address_taken = parsed.function_pointer_referenced.contains(&function_id);
+    sentences.push(match callers_num {
+        0 if is_address_taken => "It is not called directly, but its address is stored, e.g. \
+                                  in a vtable or callback table."
+            .to_owned(),
+        0 => "It is not called by any analyzed function.".to_owned(),
+        1 => format!(
+            "It is only called from `{}`, so inlining it would not duplicate code.",
+            parsed.display(
+                *parsed.callers_by_callee[&function_id]
+                    .iter()
+                    .next()
+                    .unwrap()
+            )
+        ),
+        n if n >= HIGH_USAGE_CALLERS_NUM => format!(
+            "It is called from {} other functions, making it a high-usage utility.",
+            n
+        ),
+        n => format!("It is called from {} other functions.", n),
+    });
+
+    let callees = callees_with_kind(parsed, function_id);
+    let virtual_callees_num = callees
+        .iter()
+        .filter(|(_, kind)| matches!(kind, CallEdgeKind::Virtual))
+        .count();
+    sentences.push(match (callees.len(), virtual_callees_num) {
+        (0, _) => "It is a leaf function that does not call other functions.".to_owned(),
+        (n, 0) => format!("It calls {}.", plural(n, "other function")),
+        (n, v) => format!(
+            "It calls {}, {} of them through virtual calls.",
+            plural(n, "other function"),
+            v
+        ),
+    });
+
+    let call_graph = call_graph::build_call_graph(parsed);
+    let calls_itself = parsed
+        .callees_by_caller
+        .get(&function_id)
+        .is_some_and(|callees| callees.contains(&function_id))
+        || parsed.tail_recursive_functions.contains(&function_id);
+    let is_mutually_recursive = call_graph
+        .strongly_connected_components()
+        .into_iter()
+        .any(|component| component.len() > 1 && component.contains(&function_id));
+    sentences.push(
+        match (calls_itself, is_mutually_recursive) {
+            (true, _) => "It is recursive.",
+            (false, true) => "It is mutually recursive with other functions.",
+            (false, false) => "It is not recursive.",
+        }
+        .to_owned(),
+    );
+    if parsed.uses_tls.contains(&function_id) {
+        sentences.push("It accesses thread-local storage.".to_owned());
+    }
+    if parsed.has_unwind_personality.contains(&function_id) {
+        sentences.push("It has exception handling cleanup code.".to_owned());
+    }
+    if let Some(extension) = parsed.simd_extension_by_function.get(&function_id) {
+        sentences.push(format!("It uses {} SIMD instructions.", extension));
+    }
+
+    let subtree_size = subtree_instructions_num(parsed, &call_graph, function_id, max_depth);
+    let mut is_truncated = subtree_size.is_truncated();
+    let subtree_size = subtree_size.into_inner();
+    if subtree_size > 0 {
+        let transitive_callees_num = call_graph
+            .reachable_callees(function_id, max_depth)
+            .into_inner()
+            .len();
+        let subtree = match transitive_callees_num {
+            0 => "Its subtree".to_owned(),
+            n => format!(
+                "Together with the {} it calls directly or indirectly, its subtree",
+                plural(n, "function")
+            ),
+        };
+        let other_sizes = defined_subtree_sizes(parsed, &call_graph, max_depth);
+        is_truncated |= other_sizes.is_truncated();
+        let other_sizes = other_sizes.into_inner();
+        let larger_num = other_sizes
+            .iter()
+            .filter(|other_size| **other_size > subtree_size)
+            .count();
+        let top_percent = ((larger_num + 1) * 100).div_ceil(other_sizes.len().max(1));
+        let approximately = if defined_subtree_sizes_are_sampled(parsed, max_depth) {
+            "approximately "
+        } else {
+            ""
+        };
+        sentences.push(if top_percent <= 50 {
+            format!(
+                "{} size is {}, placing it {}in the top {}% of subtree sizes.",
+                subtree,
+                plural(subtree_size, "instruction"),
+                approximately,
+                top_percent
+            )
+        } else {
+            format!(
+                "{} size is {}, placing it {}in the lower half of subtree sizes.",
+                subtree,
+                plural(subtree_size, "instruction"),
+                approximately
+            )
+        });
+    }
+    if is_truncated {
+        eprintln!(
+            "Warning: Subtree sizes are incomplete because the maximum depth of {} was reached.",
+            max_depth.unwrap_or_default()
+        );
+    }
+    sentences.join(" ")
+}
+
+/// Functions of the object with callees before their callers, as far as possible, like in a
+/// source file that defines helpers first. Calls to other objects are ignored and recursive
+/// calls are broken at the first call that closes the cycle. Ties are ordered by name.
+fn functions_in_call_order(parsed: &ParsedData, object: ObjectID) -> Vec<FunctionID> {
+    let functions: &[FunctionID] = parsed
+        .functions_by_object
+        .get(&object)
+        .map_or(&[], Vec::as_slice);
+    let sorted_by_name = |function_ids: &mut Vec<FunctionID>| {
+        function_ids.sort_by_key(|function_id| parsed.name_by_function_id[function_id].name())
+    };
+    let mut roots = functions.to_vec();
+    sorted_by_name(&mut roots);
+    let mut visited: HashSet<FunctionID> = HashSet::new();
+    let mut order = Vec::with_capacity(functions.len());
+    for root in roots {
+        if !visited.insert(root) {
+            continue;
+        }
+        // Pairs of (function, callees that are not visited yet), in reverse order.
+        let mut stack: Vec<(FunctionID, Vec<FunctionID>)> = vec![];
+        let local_callees = |function_id: FunctionID| {
+            let mut callees: Vec<FunctionID> = parsed
+                .callees_by_caller
+                .get(&function_id)
+                .into_iter()
+                .flatten()
+                .filter(|callee| functions.binary_search(callee).is_ok())
+                .copied()
+                .collect();
+            sorted_by_name(&mut callees);
+            callees.reverse();
+            callees
+        };
+        stack.push((root, local_callees(root)));
+        while let Some((function_id, callees)) = stack.last_mut() {
+            match callees.pop() {
+                Some(callee) => {
+                    if visited.insert(callee) {
+                        stack.push((callee, local_callees(callee)));
+                    }
+                }
+                None => {
+                    order.push(*function_id);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    order
+}
+
+fn print_object_info(
+    parsed: &ParsedData,
+    object: &ObjectName,
+    function_order: cli::FunctionOrder,
+) -> Result<()> {
+    let object_id = parsed
+        .object_id_by_name
+        .get(object)
+        .ok_or(eyre::eyre!("Can't find object."))?;
+    let functions: &[FunctionID] = parsed
+        .functions_by_object
+        .get(object_id)
+        .map_or(&[], Vec::as_slice);
+    let mut external_callees = HashSet::new();
+    let mut internal_callees = HashSet::new();
+    let mut calling_objects = HashSet::new();
+    for function_id in functions.iter() {
+        for callee in parsed
+            .callees_by_caller
+            .get(function_id)
+            .into_iter()
+            .flatten()
+        {
+            if functions.binary_search(callee).is_ok() {
+                internal_callees.insert(*callee);
+            } else {
+                external_callees.insert(*callee);
+            }
+        }
+        for caller in parsed
+            .callers_by_callee
+            .get(function_id)
+            .into_iter()
+            .flatten()
+        {
+            for caller_object in parsed.objects_by_function.get(caller).into_iter().flatten() {
+                if caller_object != object_id {
+                    calling_objects.insert(*caller_object);
+                }
+            }
+        }
+    }
+    let mut largest_functions: Vec<_> = functions
+        .iter()
+        .map(|function_id| {
+            (
+                *function_id,
+                instructions_in_object(parsed, *function_id, *object_id),
+            )
+        })
+        .collect();
+    largest_functions.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+    let print_functions = |function_ids: HashSet<FunctionID>| {
+        let mut names: Vec<String> = function_ids
+            .iter()
+            .map(|function_id| parsed.display(*function_id).to_string())
+            .collect();
+        names.sort();
+        for name in names {
+            println!("    {}", name);
+        }
+    };
+
+    println!("Object: {}", object);
+    println!(
+        "  Path: {}",
+        object.relative_to(&std::env::current_dir()?).display()
+    );
+    println!(
+        "  Instructions: {}",
+        largest_functions.iter().map(|(_, num)| num).sum::<usize>()
+    );
+    println!("  Functions: {}", functions.len());
+    match function_order {
+        cli::FunctionOrder::Size => {
+            println!("  Largest functions:");
+            for (function_id, instructions_num) in top_n(largest_functions.into_iter(), 5) {
+                println!("    {}: {}", parsed.display(function_id), instructions_num);
+            }
+        }
+        cli::FunctionOrder::CallOrder => {
+            println!("  Functions in call order:");
+            for function_id in functions_in_call_order(parsed, *object_id) {
+                println!(
+                    "    {}: {}",
+                    parsed.display(function_id),
+                    instructions_in_object(parsed, function_id, *object_id)
+                );
+            }
+        }
+    }
+    println!("  External callees:");
+    print_functions(external_callees);
+    println!("  Internal callees:");
+    print_functions(internal_callees);
+    println!("  Called from objects:");
+    let mut calling_objects: Vec<_> = calling_objects
+        .iter()
+        .map(|object_id| parsed.name_by_object_id.get(object_id).unwrap())
+        .collect();
+    calling_objects.sort_by_key(|object| &object.path);
+    for calling_object in calling_objects {
+        println!("    {}", calling_object);
+    }
+    Ok(())
+}
+
+/// Contains every object once for every call site of the target in it. The call sites of a
+/// caller are the maximum over its copies, so every copy counts with that number.
+fn objects_referencing_function(parsed: &ParsedData, target: FunctionID) -> Vec<ObjectID> {
+    let mut objects = vec![];
+    for caller in parsed.callers_by_callee.get(&target).into_iter().flatten() {
+        // Every edge has call sites, the fallback only guards against inconsistent data.
+        let call_sites_num = parsed
+            .call_sites_by_caller
+            .get(caller)
+            .and_then(|call_sites| call_sites.get(&target))
+            .copied()
+            .unwrap_or(1);
+        for object_id in parsed.objects_by_function.get(caller).into_iter().flatten() {
+            objects.extend(std::iter::repeat_n(*object_id, call_sites_num));
+        }
+    }
+    objects
+}
+
+fn print_function_usage_by_object(parsed: &ParsedData, function: &FunctionName) -> Result<()> {
+    let function_id = parsed
+        .function_id_by_name
+        .get(function)
+        .ok_or(eyre::eyre!("Can't find function."))?;
+    let mut call_sites_by_object: HashMap<ObjectID, usize> = HashMap::new();
+    for object_id in objects_referencing_function(parsed, *function_id) {
+        *call_sites_by_object.entry(object_id).or_default() += 1;
+    }
+    let mut objects: Vec<_> = call_sites_by_object
+        .into_iter()
+        .map(|(object_id, num)| (parsed.name_by_object_id.get(&object_id).unwrap(), num))
+        .collect();
+    objects.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path)));
+    print_deprecation_warning(parsed, *function_id);
+    println!("Objects using {}:", parsed.display_name(function));
+    for (object, num) in objects {
+        println!("  {}: {}", object, num);
+    }
+    Ok(())
+}
+
+/// Objects whose path ends with the given suffix, e.g. `nodes/node_geo_mesh.cc.o`.
+fn find_objects_by_path_suffix(parsed: &ParsedData, suffix: &str) -> Vec<ObjectName> {
+    let mut objects: Vec<ObjectName> = parsed
+        .object_id_by_name
+        .keys()
+        .filter(|object| object.path.to_string_lossy().ends_with(suffix))
+        .cloned()
+        .collect();
+    objects.sort_by(|a, b| a.path.cmp(&b.path));
+    objects
+}
+
+fn print_call_graph(parsed: &ParsedData, formatter: &mut dyn Formatter) -> Result<()> {
+    for (caller, callees) in parsed.callees_by_caller.iter() {
+        let caller_function = parsed.name_by_function_id.get(caller).unwrap();
+        for callee in callees {
+            let callee_function = parsed.name_by_function_id.get(callee).unwrap();
+            formatter.write_record(&Record::Call {
+                caller_function: parsed.display_name(caller_function),
+                callee_function: parsed.display_name(callee_function),
+                caller: caller_function.name(),
+                callee: callee_function.name(),
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn print_objects(
+    parsed: &ParsedData,
+    ranking: &Ranking,
+    formatter: &mut dyn Formatter,
+) -> Result<()> {
+    let mut data: Vec<_> = parsed
+        .objects()
+        .map(|(object_id, object, functions)| {
+            let instructions_num: usize = functions
+                .iter()
+                .map(|function_id| instructions_in_object(parsed, *function_id, object_id))
+                .sum();
+            (object, functions.len(), instructions_num)
+        })
+        .collect();
+    data.sort_by(|a, b| a.2.cmp(&b.2).reverse());
+    for (object, functions_num, instructions_num) in ranking.apply(data, |item| item.2) {
+        formatter.write_record(&Record::Object {
+            object,
+            path: &object.path,
+            function_count: functions_num,
+            instruction_count: instructions_num,
+        })?;
+    }
+    Ok(())
+}
+
+fn object_instructions_num(parsed: &ParsedData, object_id: &ObjectID) -> usize {
+    parsed
+        .functions_by_object
+        .get(object_id)
+        .into_iter()
+        .flatten()
+        .map(|function_id| instructions_in_object(parsed, *function_id, *object_id))
+        .sum()
+}
+
+/// Share of every object in the total number of instructions of all objects. Functions that
+/// are defined in multiple objects count for each of them.
+fn compute_object_weight_fractions(parsed: &ParsedData) -> HashMap<ObjectID, f64> {
+    let instructions_by_object: HashMap<ObjectID, usize> = parsed
+        .name_by_object_id
+        .keys()
+        .map(|object_id| (*object_id, object_instructions_num(parsed, object_id)))
+        .collect();
+    let total: usize = instructions_by_object.values().sum();
+    instructions_by_object
+        .into_iter()
+        .map(|(object_id, instructions_num)| {
+            let fraction = if total == 0 {
+                0.0
+            } else {
+                instructions_num as f64 / total as f64
+            };
+            (object_id, fraction)
+        })
+        .collect()
+}
+
+fn print_object_pie_chart(parsed: &ParsedData, ranking: &Ranking) {
+    const CHART_WIDTH: usize = 60;
+    const SYMBOLS: &[u8] = b"#=*+%@&$~o";
+
+    let mut fractions: Vec<_> = compute_object_weight_fractions(parsed)
+        .into_iter()
+        .collect();
+    fractions.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let fractions = ranking.apply(fractions, |(object_id, _)| {
+        object_instructions_num(parsed, object_id)
+    });
+
+    // A pie chart flattened into a single bar, every object gets its own symbol.
+    let mut chart = String::new();
+    for (i, (_, fraction)) in fractions.iter().enumerate() {
+        let symbol = SYMBOLS[i % SYMBOLS.len()] as char;
+        let width = (fraction * CHART_WIDTH as f64).round() as usize;
+        chart.extend(std::iter::repeat_n(symbol, width));
+    }
+    println!("[{:<width$}]", chart, width = CHART_WIDTH);
+
+    let mut cumulative = 0.0;
+    for (i, (object_id, fraction)) in fractions.iter().enumerate() {
+        cumulative += fraction;
+        println!(
+            "{} {:>6.2}% (cumulative {:>6.2}%) {}",
+            SYMBOLS[i % SYMBOLS.len()] as char,
+            fraction * 100.0,
+            cumulative * 100.0,
+            parsed.name_by_object_id.get(object_id).unwrap()
+        );
+    }
+}
+
+fn print_instructions_histogram(parsed: &ParsedData) {
+    // Bucket `i` contains the functions with less than `2^i` instructions.
+    let mut buckets: Vec<usize> = vec![];
+    for function_id in parsed.instructions_by_object_by_function.keys() {
+        let instructions_num = function_instructions_num(parsed, *function_id);
+        let bucket = (usize::BITS - instructions_num.leading_zeros()) as usize;
+        if buckets.len() <= bucket {
+            buckets.resize(bucket + 1, 0);
+        }
+        buckets[bucket] += 1;
+    }
+    let max_count = buckets.iter().copied().max().unwrap_or_default().max(1);
+    for (bucket, count) in buckets.iter().enumerate() {
+        let bar = "#".repeat(count * 50 / max_count);
+        println!("< {:>8}: {:>6} {}", 1usize << bucket, count, bar);
+    }
+}
+
+/// Key that identifies a function independent of the ids assigned during parsing.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+enum StableFunctionName {
+    Global {
+        name: String,
+    },
+    /// The object path is relative to [`common_object_dirs`], so that the names match between
+    /// checkouts and build directories.
+    Local {
+        name: String,
+        object: PathBuf,
+    },
+}
+
+/// Deepest directory that contains all objects.
+fn common_object_dir(parsed: &ParsedData) -> Vec<std::path::Component<'_>> {
+    let mut dirs = parsed
+        .name_by_object_id
+        .values()
+        .map(|object| object.path.parent().unwrap_or(Path::new("")));
+    let Some(first_dir) = dirs.next() else {
+        return Vec::new();
+    };
+    let mut common: Vec<std::path::Component> = first_dir.components().collect();
+    for dir in dirs {
+        let common_len = common_prefix_len(&common, dir.components());
+        common.truncate(common_len);
+    }
+    common
+}
+
+fn common_prefix_len<'a>(
+    components: &[std::path::Component<'a>],
+    other: impl IntoIterator<Item = std::path::Component<'a>>,
+) -> usize {
+    components
+        .iter()
+        .zip(other)
+        .take_while(|(a, b)| **a == *b)
+        .count()
+}
+
+/// Directories of the two versions that the object paths are made relative to, so that the
+/// same object has the same relative path in both. Objects with the same file name are aligned
+/// by their common path suffix, which finds the roots of the two build directories even if
+/// they are in different checkouts. The roots are then extended by the directories that all
+/// objects of both versions share below them, so that both versions use one base directory
+/// and adding an object elsewhere to only one of them does not rename all other objects.
+fn common_object_dirs(before: &ParsedData, after: &ParsedData) -> (PathBuf, PathBuf) {
+    let before_dir = common_object_dir(before);
+    let after_dir = common_object_dir(after);
+    let mut after_by_file_name: HashMap<&std::ffi::OsStr, Vec<&Path>> = HashMap::new();
+    for object in after.name_by_object_id.values() {
+        if let Some(file_name) = object.path.file_name() {
+            after_by_file_name
+                .entry(file_name)
+                .or_default()
+                .push(&object.path);
+        }
+    }
+    // How many objects agree on the lengths of the two roots.
+    let mut votes: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for object in before.name_by_object_id.values() {
+        let Some(after_paths) = object
+            .path
+            .file_name()
+            .and_then(|file_name| after_by_file_name.get(file_name))
+        else {
+            continue;
+        };
+        let before_len = object.path.components().count();
+        for after_path in after_paths {
+            let suffix_len = object
+                .path
+                .components()
+                .rev()
+                .zip(after_path.components().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let after_len = after_path.components().count();
+            *votes
+                .entry((before_len - suffix_len, after_len - suffix_len))
+                .or_default() += 1;
+        }
+    }
+    let Some((before_root_len, after_root_len)) = votes
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(root_lens, _)| root_lens)
+    else {
+        // Nothing can be matched, so each version is relative to its own objects.
+        return (
+            before_dir.into_iter().collect(),
+            after_dir.into_iter().collect(),
+        );
+    };
+    // The roots come from single objects, so they have to be shortened by the same amount
+    // until they contain all objects of their version.
+    let shorten_by = before_root_len
+        .saturating_sub(before_dir.len())
+        .max(after_root_len.saturating_sub(after_dir.len()));
+    let before_root_len = before_root_len.saturating_sub(shorten_by);
+    let after_root_len = after_root_len.saturating_sub(shorten_by);
+    let shared_len = common_prefix_len(
+        &before_dir[before_root_len..],
+        after_dir[after_root_len..].iter().copied(),
+    );
+    (
+        before_dir[..before_root_len + shared_len].iter().collect(),
+        after_dir[..after_root_len + shared_len].iter().collect(),
+    )
+}
+
+impl std::fmt::Display for StableFunctionName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let options = display::display_options();
+        match self {
+            StableFunctionName::Global { name } => {
+                write!(f, "{}", display::format_symbol(name, &options))
+            }
+            StableFunctionName::Local { name, object } => write!(
+                f,
+                "{} (local in {})",
+                display::format_symbol(name, &options),
+                display::format_object_name(
+                    &ObjectName {
+                        path: object.clone()
+                    },
+                    &options
+                )
+            ),
+        }
+    }
+}
+
+/// `object_dir` is the directory of the parsed data returned by [`common_object_dirs`].
+fn function_id_by_stable_name(
+    parsed: &ParsedData,
+    object_dir: &Path,
+    name: &StableFunctionName,
+) -> Option<FunctionID> {
+    match name {
+        StableFunctionName::Global { name } => parsed.function_id_by_global_name(name),
+        StableFunctionName::Local { name, object } => {
+            let object_id = parsed.object_id_by_name.get(&ObjectName {
+                path: object_dir.join(object),
+            })?;
+            parsed.function_id_by_local_name(*object_id, name)
+        }
+    }
+}
+
+/// `object_dir` is the directory of the parsed data returned by [`common_object_dirs`].
+fn stable_function_name(
+    parsed: &ParsedData,
+    object_dir: &Path,
+    function_id: FunctionID,
+) -> StableFunctionName {
+    match parsed.name_by_function_id.get(&function_id).unwrap() {
+        FunctionName::Global(global) => StableFunctionName::Global {
+            name: global.name.to_string(),
+        },
+        FunctionName::Local(local) => StableFunctionName::Local {
+            name: local.name.to_string(),
+            object: parsed
+                .name_by_object_id
+                .get(&local.object)
+                .unwrap()
+                .relative_to(object_dir),
+        },
+    }
+}
+
+#[derive(Debug, Default)]
+struct ParsedDataDiff {
+    added: Vec<(StableFunctionName, usize)>,
+    removed: Vec<(StableFunctionName, usize)>,
+    changed: Vec<(StableFunctionName, usize, usize)>,
+    unchanged: Vec<(StableFunctionName, usize)>,
+}
+
+impl ParsedDataDiff {
+    fn unchanged_count(&self) -> usize {
+        self.unchanged.len()
+    }
+
+    /// Fraction of all functions of both versions that were changed, added or removed.
+    fn change_rate(&self) -> f64 {
+        let different_num = self.changed.len() + self.added.len() + self.removed.len();
+        let total_num = different_num + self.unchanged.len();
+        if total_num == 0 {
+            0.0
+        } else {
+            different_num as f64 / total_num as f64
+        }
+    }
+}
+
+/// Functions are compared by the size of their largest copy. Functions whose normalized names
+/// are the same are compared as one function with the sum of their sizes.
+fn diff_parsed_data(
+    before: &ParsedData,
+    after: &ParsedData,
+    normalization: &normalize::NameNormalization,
+) -> ParsedDataDiff {
+    let instructions_by_name =
+        |parsed: &ParsedData, object_dir: &Path| -> HashMap<StableFunctionName, usize> {
+            let mut instructions_by_name: HashMap<StableFunctionName, usize> = HashMap::new();
+            for function_id in parsed.instructions_by_object_by_function.keys() {
+                let num = function_instructions_num(parsed, *function_id);
+                let mut name = stable_function_name(parsed, object_dir, *function_id);
+                if !normalization.is_empty() {
+                    match &mut name {
+                        StableFunctionName::Global { name }
+                        | StableFunctionName::Local { name, .. } => {
+                            *name = normalization.apply(&demangle::demangled(name));
+                        }
+                    }
+                }
+                *instructions_by_name.entry(name).or_default() += num;
+            }
+            instructions_by_name
+        };
+    let (before_dir, after_dir) = common_object_dirs(before, after);
+    let before_instructions = instructions_by_name(before, &before_dir);
+    let after_instructions = instructions_by_name(after, &after_dir);
+
+    let mut diff = ParsedDataDiff::default();
+    for (name, before_num) in before_instructions.iter() {
+        match after_instructions.get(name) {
+            Some(after_num) if after_num != before_num => {
+                diff.changed.push((name.clone(), *before_num, *after_num))
+            }
+            Some(_) => diff.unchanged.push((name.clone(), *before_num)),
+            None => diff.removed.push((name.clone(), *before_num)),
+        }
+    }
+    for (name, after_num) in after_instructions.iter() {
+        if !before_instructions.contains_key(name) {
+            diff.added.push((name.clone(), *after_num));
+        }
+    }
+    diff.added.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+    diff.removed.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+    diff.unchanged.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+    diff.changed
+        .sort_by_key(|(_, before_num, after_num)| before_num.abs_diff(*after_num));
+    diff.changed.reverse();
+    diff
+}
+
+/// Lists the functions that did not change. When nothing is unchanged, the compared data is
+/// probably from unrelated builds.
+fn print_unchanged(diff: &ParsedDataDiff, ranking: &Ranking) {
+    println!("Unchanged:");
+    for (name, num) in ranking.apply(diff.unchanged.iter(), |(_, num)| *num) {
+        println!("  {}: {}", name, num);
+    }
+    println!(
+        "{} functions unchanged, change rate {:.1}%",
+        diff.unchanged_count(),
+        diff.change_rate() * 100.0
+    );
+    if diff.unchanged_count() == 0 && !diff.changed.is_empty() {
+        eprintln!("Warning: no function is unchanged, the data may be from a different build.");
+    }
+}
+
+fn print_diff(diff: &ParsedDataDiff, ranking: &Ranking) {
+    println!("Changed:");
+    let changed = ranking.apply(diff.changed.iter(), |(_, before_num, after_num)| {
+        *before_num.max(after_num)
+    });
+    for (name, before_num, after_num) in changed {
+        let delta = *after_num as i64 - *before_num as i64;
+        println!("  {}: {} -> {} ({:+})", name, before_num, after_num, delta);
+    }
+    println!("Added:");
+    for (name, num) in ranking.apply(diff.added.iter(), |(_, num)| *num) {
+        println!("  {}: {}", name, num);
+    }
+    println!("Removed:");
+    for (name, num) in ranking.apply(diff.removed.iter(), |(_, num)| *num) {
+        println!("  {}: {}", name, num);
+    }
+}
+
+/// Splits the changed functions into the ones that benefit from the additional flags and the
+/// ones that get larger, e.g. because of more aggressive inlining or unrolling.
+fn print_optimization_comparison(diff: &ParsedDataDiff, flags: &[String], ranking: &Ranking) {
+    let flags = flags.join(" ");
+    let total = |get_num: fn(&(StableFunctionName, usize, usize)) -> usize| -> usize {
+        diff.changed.iter().map(get_num).sum::<usize>()
+            + diff.unchanged.iter().map(|(_, num)| num).sum::<usize>()
+    };
+    let base_total = total(|(_, base_num, _)| *base_num)
+        + diff.removed.iter().map(|(_, num)| num).sum::<usize>();
+    let optimized_total = total(|(_, _, optimized_num)| *optimized_num)
+        + diff.added.iter().map(|(_, num)| num).sum::<usize>();
+
+    let print_changes = |changes: Vec<&(StableFunctionName, usize, usize)>| {
+        for (name, base_num, optimized_num) in ranking
+            .apply(changes, |(_, base_num, optimized_num)| {
+                *base_num.max(optimized_num)
+            })
+        {
+            let delta = *optimized_num as i64 - *base_num as i64;
+            println!(
+                "  {}: {} -> {} ({:+})",
+                name, base_num, optimized_num, delta
+            );
+        }
+    };
+    // The changes are sorted by the size of the change already.
+    println!("Smaller with {}:", flags);
+    print_changes(
+        diff.changed
+            .iter()
+            .filter(|(_, base_num, optimized_num)| optimized_num < base_num)
+            .collect(),
+    );
+    println!("Larger with {}:", flags);
+    print_changes(
+        diff.changed
+            .iter()
+            .filter(|(_, base_num, optimized_num)| optimized_num > base_num)
+            .collect(),
+    );
+    println!("Only without {} (e.g. inlined):", flags);
+    for (name, num) in ranking.apply(diff.removed.iter(), |(_, num)| *num) {
+        println!("  {}: {}", name, num);
+    }
+    println!("Only with {}:", flags);
+    for (name, num) in ranking.apply(diff.added.iter(), |(_, num)| *num) {
+        println!("  {}: {}", name, num);
+    }
+    println!(
+        "Total instructions: {} -> {} ({:+}), {} functions unchanged",
+        base_total,
+        optimized_total,
+        optimized_total as i64 - base_total as i64,
+        diff.unchanged_count()
+    );
+}
+
+/// Prints the instructions of the function that changed between the two versions. The
+/// function is looked up in the new version first, so that removed functions can be found
+/// too.
+fn print_assembly_diff(before: &ParsedData, after: &ParsedData, pattern: &str) -> Result<()> {
+    let (parsed, function) = match find_function_by_name(after, pattern) {
+        Ok(function) => (after, function),
+        Err(_) => (before, find_function_by_name(before, pattern)?),
+    };
+    let (before_dir, after_dir) = common_object_dirs(before, after);
+    let object_dir = if std::ptr::eq(parsed, after) {
+        &after_dir
+    } else {
+        &before_dir
+    };
+    let stable_name =
+        stable_function_name(parsed, object_dir, parsed.function_id_by_name[&function]);
+    let raw_assembly = |parsed: &ParsedData, object_dir: &Path| -> Result<Vec<String>> {
+        let function_id = function_id_by_stable_name(parsed, object_dir, &stable_name);
+        if parsed.raw_assembly_by_function.is_empty() {
+            return Err(eyre::eyre!(
+                "The data contains no assembly, analyze with --keep-raw-assembly."
+            ));
+        }
+        Ok(function_id
+            .and_then(|function_id| parsed.raw_assembly_by_function.get(&function_id))
+            .cloned()
+            .unwrap_or_default())
+    };
+    let diff = assembly_diff::diff_function_assembly(
+        &raw_assembly(before, &before_dir)?,
+        &raw_assembly(after, &after_dir)?,
+    );
+    if diff.is_empty() {
+        println!("The instructions of {} did not change.", stable_name);
+        return Ok(());
+    }
+    let label = stable_name.to_string();
+    diff.write_unified(&mut std::io::stdout().lock(), &label, &label)
+}
+
+/// Prints the total instruction change and the change of every object whose instruction
+/// count differs. Objects are matched by their path relative to [`common_object_dirs`].
+fn print_object_deltas(before: &ParsedData, after: &ParsedData, ranking: &Ranking) {
+    let instructions_by_object =
+        |parsed: &ParsedData, object_dir: &Path| -> HashMap<PathBuf, usize> {
+            parsed
+                .objects()
+                .map(|(object_id, object, functions)| {
+                    let instructions_num = functions
+                        .iter()
+                        .map(|function_id| instructions_in_object(parsed, *function_id, object_id))
+                        .sum();
+                    (object.relative_to(object_dir), instructions_num)
+                })
+                .collect()
+        };
+    let (before_dir, after_dir) = common_object_dirs(before, after);
+    let before_instructions = instructions_by_object(before, &before_dir);
+    let after_instructions = instructions_by_object(after, &after_dir);
+    let mut paths: Vec<&PathBuf> = before_instructions
+        .keys()
+        .chain(after_instructions.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let num = |instructions: &HashMap<PathBuf, usize>, path: &PathBuf| {
+        instructions.get(path).copied().unwrap_or_default()
+    };
+    paths.retain(|path| num(&before_instructions, path) != num(&after_instructions, path));
+    paths.sort_by_key(|path| {
+        std::cmp::Reverse(num(&before_instructions, path).abs_diff(num(&after_instructions, path)))
+    });
+    println!("Objects:");
+    let deltas = paths.into_iter().map(|path| {
+        (
+            path,
+            num(&before_instructions, path),
+            num(&after_instructions, path),
+        )
+    });
+    for (path, before_num, after_num) in ranking.apply(deltas, |(_, before_num, after_num)| {
+        *before_num.max(after_num)
+    }) {
+        let delta = after_num as i64 - before_num as i64;
+        println!(
+            "  {}: {} -> {} ({:+})",
+            path.display(),
+            before_num,
+            after_num,
+            delta
+        );
+    }
+    let before_total: usize = before_instructions.values().sum();
+    let after_total: usize = after_instructions.values().sum();
+    println!(
+        "Total: {} -> {} ({:+})",
+        before_total,
+        after_total,
+        after_total as i64 - before_total as i64
+    );
+}
+
+/// Functions that match any of the regular expressions, or all functions if there are none,
+/// sorted by their ID.
+fn adjacency_matrix_functions(parsed: &ParsedData, filter: &filter::FilterSet) -> Vec<FunctionID> {
+    let mut functions: Vec<FunctionID> = parsed
+        .functions()
+        .filter(|(_, function, _)| filter.is_empty() || filter.matches(function))
+        .map(|(id, _, _)| id)
+        .collect();
+    functions.sort();
+    functions
+}
+
+/// All functions with the best kind of match for the pattern, sorted by their demangled name.
+fn find_functions_by_pattern(parsed: &ParsedData, pattern: &str) -> Vec<FunctionID> {
+    let pattern = filter::NamePattern::new(pattern);
+    let matches: Vec<(FunctionID, filter::PatternMatch)> = parsed
+        .functions()
+        .filter_map(|(function_id, function, _)| {
+            pattern.matches(function).map(|kind| (function_id, kind))
+        })
+        .collect();
+    let Some(best_kind) = matches.iter().map(|(_, kind)| *kind).min() else {
+        return vec![];
+    };
+    let mut function_ids: Vec<FunctionID> = matches
+        .into_iter()
+        .filter(|(_, kind)| *kind == best_kind)
+        .map(|(function_id, _)| function_id)
+        .collect();
+    function_ids.sort_by_key(|function_id| {
+        let function = &parsed.name_by_function_id[function_id];
+        (
+            demangle::demangled(function.name()),
+            parsed.display_name(function).to_string(),
+        )
+    });
+    function_ids
+}
+
+/// Looks up a function from command line input. When the pattern is ambiguous, the matches
+/// are listed and one of them can be selected by appending `#<index>` to the pattern.
+fn find_function_by_name(parsed: &ParsedData, input: &str) -> Result<FunctionName> {
+    let (pattern, index) = match input.rsplit_once('#') {
+        Some((pattern, index)) if index.parse::<usize>().is_ok() => {
+            (pattern, Some(index.parse::<usize>()?))
+        }
+        _ => (input, None),
+    };
+    let function_ids = find_functions_by_pattern(parsed, pattern);
+    let function_id = match (function_ids.as_slice(), index) {
+        ([], _) => return Err(eyre::eyre!("Can't find function {}.", pattern)),
+        ([function_id], None) => *function_id,
+        (_, Some(index)) => *function_ids.get(index).ok_or(eyre::eyre!(
+            "{} only matches {} functions.",
+            pattern,
+            function_ids.len()
+        ))?,
+        (_, None) => {
+            let mut message = format!(
+                "{} matches {} functions, select one by appending #<index>:",
+                pattern,
+                function_ids.len()
+            );
+            for (i, function_id) in function_ids.iter().enumerate() {
+                message += &format!("\n  #{}: {}", i, parsed.display(*function_id));
+            }
+            return Err(eyre::eyre!(message));
+        }
+    };
+    Ok(parsed.name_by_function_id[&function_id].clone())
+}
+
+fn analyze(source: &cli::SourceArgs) -> Result<ParsedData> {
+    if let Some(rlib_path) = &source.rust_rlib {
+        return rlib::analyze_rlib(rlib_path, &source.llvm_dis);
+    }
+    if let Some(binary_path) = &source.binary {
+        return objdump::analyze_elf_binary(binary_path, &source.objdump);
+    }
+    if let Some(wat_path) = &source.wat {
+        return wat::analyze_wat_file(wat_path);
+    }
+    let mut commands = load_assembly_generation_commands(source)?;
+    let mut strata = None;
+    if let Some(n) = source.sample {
+        let (sampled, sample_strata) = sample::sample_commands(commands, n, source.seed);
+        commands = sampled;
+        strata = Some(sample_strata);
+    }
+    let (parsed, timings) = analyze_commands(
+        commands,
+        source.parse_options,
+        source.verbose_errors,
+        source.memory_limit_mb,
+    )?;
+    print_timings(&parsed, &timings, source.slowest_compilations);
+    warn_about_inconsistent_instruction_counts(&parsed);
+    if let Some(strata) = &strata {
+        sample::print_total_estimate(&parsed, strata);
+    }
+    Ok(parsed)
+}
+
+/// Functions with the same name but different instruction counts in different objects are
+/// usually inline functions with different inlining decisions, but can also indicate ODR
+/// violations, e.g. because of different macro definitions.
+fn warn_about_inconsistent_instruction_counts(parsed: &ParsedData) {
+    let mut inconsistent: Vec<(&FunctionName, Vec<usize>)> = parsed
+        .instructions_by_object_by_function
+        .iter()
+        .filter_map(|(function_id, nums)| {
+            let mut nums: Vec<usize> = nums.values().copied().collect();
+            nums.sort();
+            nums.dedup();
+            (nums.len() > 1).then(|| (&parsed.name_by_function_id[function_id], nums))
+        })
+        .collect();
+    if inconsistent.is_empty() {
+        return;
+    }
+    inconsistent.sort_by_key(|(function, _)| parsed.display_name(function).to_string());
+    eprintln!(
+        "Warning: {} functions have different instruction counts in different objects:",
+        inconsistent.len()
+    );
+    const SHOWN_NUM: usize = 5;
+    for (function, nums) in inconsistent.iter().take(SHOWN_NUM) {
+        let nums: Vec<String> = nums.iter().map(usize::to_string).collect();
+        eprintln!("  {}: {}", parsed.display_name(function), nums.join(", "));
+    }
+    if inconsistent.len() > SHOWN_NUM {
+        eprintln!("  ... and {} more", inconsistent.len() - SHOWN_NUM);
+    }
+}
+
+/// Time spent in the different steps of the analysis.
+#[derive(Default)]
+struct AnalysisTimings {
+    /// Time to generate the assembly of every object.
+    compilation_time_by_object: HashMap<ObjectID, Duration>,
+    /// Sum of the time spent parsing all objects.
+    parse_time: Duration,
+    /// Time of the whole analysis. Without parallel compilation, this is about the sum of
+    /// all other times.
+    wall_time: Duration,
+}
+
+impl AnalysisTimings {
+    /// Sum of the compilation times of all objects.
+    fn compilation_cpu_time(&self) -> Duration {
+        self.compilation_time_by_object.values().sum()
+    }
+}
+
+/// Estimating the memory usage iterates over all data, so it is only done after this many
+/// objects.
+const MEMORY_CHECK_INTERVAL: usize = 32;
+
+fn analyze_commands(
+    commands: Vec<AssemblyGenerationCommand>,
+    options: ParseOptions,
+    verbose_errors: bool,
+    memory_limit_mb: Option<usize>,
+) -> Result<(ParsedData, AnalysisTimings)> {
+    let mut parsed = ParsedData::default();
+    let mut timings = AnalysisTimings::default();
+    let analysis_start = Instant::now();
+
+    let mut suspicious_lines = vec![];
+    let commands_num = commands.len();
+    for (i, command) in commands.into_iter().enumerate() {
+        let now = Instant::now();
+        let assembly = get_assembly(&command, verbose_errors)?;
+        let compilation_time = now.elapsed();
+
+        let object = parsed.get_or_add_object(command.object_name()?);
+        timings
+            .compilation_time_by_object
+            .insert(object, compilation_time);
+
+        let now = Instant::now();
+        parse_data(object, &assembly, options, &mut parsed);
+        parsed.resolve_source_files(object, &command);
+        timings.parse_time += now.elapsed();
+        if options.validate_assembly {
+            suspicious_lines.extend(validate::validate_assembly(object, &assembly));
+        }
+
+        let is_last = i + 1 == commands_num;
+        if let Some(limit_mb) = memory_limit_mb {
+            if (i + 1) % MEMORY_CHECK_INTERVAL == 0 || is_last {
+                let usage = parsed.memory_usage_estimate();
+                if usage > limit_mb * 1024 * 1024 {
+                    return Err(eyre::eyre!(
+                        "Aborting after {} of {} objects, the parsed data uses about {:.1} MB \
+                         which exceeds the memory limit of {} MB.",
+                        i + 1,
+                        commands_num,
+                        usage as f64 / (1024.0 * 1024.0),
+                        limit_mb
+                    ));
+                }
+            }
+        }
+    }
+    timings.wall_time = analysis_start.elapsed();
+    if options.validate_assembly {
+        validate::print_validation_report(&parsed, &suspicious_lines);
+        for problem in validate::validate_parsed_data(&parsed) {
+            eprintln!("Inconsistent parsed data: {}", problem);
+        }
+    }
+    Ok((parsed, timings))
+}
+
+/// Prints a summary of the timings to stderr, so that it does not mix with report output.
+fn print_timings(parsed: &ParsedData, timings: &AnalysisTimings, slowest_num: Option<usize>) {
+    eprintln!(
+        "Analyzed {} objects in {} ms (compilation: {} ms CPU time, parsing: {} ms).",
+        timings.compilation_time_by_object.len(),
+        timings.wall_time.as_millis(),
+        timings.compilation_cpu_time().as_millis(),
+        timings.parse_time.as_millis()
+    );
+    let padding_bytes: usize = parsed.estimated_padding_by_function.values().sum();
+    if padding_bytes > 0 {
+        eprintln!(
+            "Alignment inserts up to {} bytes of padding before {} functions.",
+            padding_bytes,
+            parsed.estimated_padding_by_function.len()
+        );
+    }
+    eprintln!(
+        "The parsed data uses about {:.1} MB of memory.",
+        parsed.memory_usage_estimate() as f64 / (1024.0 * 1024.0)
+    );
+    if let Some(n) = slowest_num {
+        print_slowest_compilations(parsed, timings, n);
+    }
+}
+
+/// Translation units that take long to compile are candidates for reducing includes, e.g.
+/// with the pimpl idiom or precompiled headers.
+fn print_slowest_compilations(parsed: &ParsedData, timings: &AnalysisTimings, top_n: usize) {
+    let mut objects: Vec<_> = timings.compilation_time_by_object.iter().collect();
+    objects.sort_by_key(|(_, time)| std::cmp::Reverse(**time));
+    eprintln!("Slowest compilations:");
+    for (object_id, time) in objects.into_iter().take(top_n) {
+        match parsed.name_by_object_id.get(object_id) {
+            Some(object) => eprintln!("  {}: {} ms", object, time.as_millis()),
+            None => eprintln!("  object {}: {} ms", object_id.0, time.as_millis()),
+        }
+    }
+}
+
+/// Analyzes the objects of every target architecture separately, so that instruction counts
+/// of different instruction sets are not mixed. Writes one JSON file per architecture.
+fn analyze_split_by_arch(args: &cli::AnalyzeArgs) -> Result<()> {
+    let mut commands_by_arch: BTreeMap<arch::Architecture, Vec<AssemblyGenerationCommand>> =
+        BTreeMap::new();
+    for command in load_assembly_generation_commands(&args.source)? {
+        commands_by_arch
+            .entry(arch::detect_target_arch(&command.args))
+            .or_default()
+            .push(command);
+    }
+    for (arch, commands) in commands_by_arch {
+        let (parsed, timings) = analyze_commands(
+            commands,
+            args.source.parse_options,
+            args.source.verbose_errors,
+            args.source.memory_limit_mb,
+        )?;
+        print_timings(&parsed, &timings, args.source.slowest_compilations);
+        let output_path = args
+            .output_json
+            .with_extension(format!("{}.json", arch.name()));
+        write_parsed_data(
+            &parsed,
+            &output_path,
+            args.deterministic_output,
+            args.compression,
+        )?;
+        println!(
+            "{}: {} objects, {} functions, {} instructions -> {}",
+            arch.name(),
+            parsed.name_by_object_id.len(),
+            parsed.instructions_by_object_by_function.len(),
+            all_instructions_num(&parsed),
+            output_path.display()
+        );
+    }
+    Ok(())
+}
+
+fn load_parsed_data(path: &Path) -> Result<ParsedData> {
+    let file = compression::open_file(path)?;
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Without an explicit compression format, it is chosen by the extension of the path.
+fn write_parsed_data(
+    parsed: &ParsedData,
+    path: &Path,
+    deterministic: bool,
+    compression: cli::CompressionArgs,
+) -> Result<()> {
+    let format = compression
+        .format
+        .unwrap_or_else(|| compression::Compression::from_path(path));
+    compression::write_file(path, format, compression.level, |writer| {
+        if deterministic {
+            serde_json::to_writer(writer, &to_deterministic(parsed.clone()))?;
+        } else {
+            serde_json::to_writer(writer, parsed)?;
+        }
+        Ok(())
+    })
+}
+
+/// Analyzes the sources with the flag overrides of the build config.
+fn analyze_config(source: &cli::SourceArgs, config: config::BuildConfig) -> Result<ParsedData> {
+    let mut source = source.clone();
+    source.extra_flags.extend(config.extra_flags);
+    source.replaced_flags.extend(config.replaced_flags);
+    analyze(&source)
+}
+
+fn load_or_analyze(from_json: &Option<PathBuf>, source: &cli::SourceArgs) -> Result<ParsedData> {
+    match from_json {
+        Some(path) => {
+            let mut parsed = load_parsed_data(path)?;
+            // There are no compile commands to look up the objects in, so they are matched
+            // by the end of their path.
+            if !source.objects.is_empty() {
+                parsed.retain_objects(|_, object| {
+                    let path = object.path.to_string_lossy();
+                    source
+                        .objects
+                        .iter()
+                        .any(|suffix| path.ends_with(suffix.as_str()))
+                });
+            }
+            Ok(parsed)
+        }
+        None => analyze(source),
+    }
+}
+
+fn print_report(parsed: &ParsedData, kind: cli::ReportKind, args: &cli::ReportArgs) -> Result<()> {
+    let stdout = std::io::stdout().lock();
+    let mut formatter: Box<dyn Formatter> = match args.output_format {
+        cli::OutputFormat::Text => Box::new(output::TextFormatter::new(stdout)),
+        cli::OutputFormat::Ndjson => Box::new(output::NdjsonFormatter::new(stdout)),
+    };
+    match kind {
+        cli::ReportKind::TopFunctions if matches!(args.sort_by, cli::SortBy::Loops) => {
+            print_functions_with_most_loops(parsed, &args.ranking)
+        }
+        cli::ReportKind::TopFunctions if matches!(args.sort_by, cli::SortBy::ReturnCount) => {
+            print_functions_with_most_returns(parsed, &args.ranking)
+        }
+        cli::ReportKind::TopFunctions => print_functions_with_most_instructions(
+            parsed,
+            args.sort_by,
+            &args.ranking,
+            formatter.as_mut(),
+        )?,
+        cli::ReportKind::CallGraph => print_call_graph(parsed, formatter.as_mut())?,
+        cli::ReportKind::Objects => print_objects(parsed, &args.ranking, formatter.as_mut())?,
+        cli::ReportKind::Histogram => print_instructions_histogram(parsed),
+        cli::ReportKind::InAllObjects => print_functions_in_all_objects(parsed),
+        cli::ReportKind::ImmediateHeavy => print_immediate_heavy_functions(parsed, &args.ranking),
+        cli::ReportKind::Recursive => print_recursive_functions(parsed),
+        cli::ReportKind::InliningAnalysis => {
+            print_inlining_candidates(parsed, &args.inlining_costs, &args.ranking)
+        }
+        cli::ReportKind::Topological => print_functions_in_topological_order(parsed),
+        cli::ReportKind::GraphStats => print_call_graph_stats(parsed, args.max_functions_for_apsp),
+        cli::ReportKind::ExceptionHeavy => print_exception_heavy_functions(parsed, &args.ranking),
+        cli::ReportKind::ObjectShares => print_object_pie_chart(parsed, &args.ranking),
+        cli::ReportKind::StringHeavy => print_string_heavy_functions(parsed, &args.ranking),
+        cli::ReportKind::NamespaceSummary => print_namespace_summary(parsed, &args.ranking)?,
+        cli::ReportKind::CallbackFunctions => print_callback_functions(parsed),
+        cli::ReportKind::CalleeSaves => print_high_callee_save_overhead(parsed, &args.ranking),
+        cli::ReportKind::WindowsAbi => print_windows_abi_functions(parsed),
+        cli::ReportKind::TlsUsers => print_tls_using_functions(parsed),
+        cli::ReportKind::Alignment => print_alignment_padding(parsed, &args.ranking),
+        cli::ReportKind::LtoDiscarded => print_lto_discarded_functions(parsed),
+        cli::ReportKind::InlinedFunctions => print_inlined_functions(parsed),
+        cli::ReportKind::ReturnPaths => print_multiple_return_functions(parsed, &args.ranking),
+        cli::ReportKind::Dispatchers => {
+            print_dispatcher_functions(parsed, args.dispatcher_threshold, &args.ranking)
+        }
+        cli::ReportKind::InlineMemoryOps => {
+            inline_memory::print_inline_memory_operations(parsed, &args.ranking)
+        }
+        cli::ReportKind::Deoptimized => print_deoptimized_functions(parsed, &args.ranking),
+        cli::ReportKind::DeprecatedCallers => print_deprecated_callers(parsed),
+        cli::ReportKind::CodeOwnership => ownership::print_code_ownership(parsed, &args.ranking),
+        cli::ReportKind::EmptyFunctions => print_empty_functions(parsed),
+        cli::ReportKind::HotCallPairs => print_hot_call_pairs(parsed, &args.ranking),
+    }
+    Ok(())
+}
+
+/// Runs the command line tool with the arguments of the process.
+pub fn app() -> Result<()> {
+    match cli::parse_args(std::env::args().skip(1))? {
+        cli::Subcommand::Analyze(cli::AnalyzeArgs {
+            emit_compile_commands: Some(path),
+            source,
+            ..
+        }) => {
+            if source.assembly_dir.is_none() {
+                return Err(eyre::eyre!(
+                    "--emit-compile-commands-for-assembly requires --assembly-dir, where the \
+                     assembly is written."
+                ));
+            }
+            write_assembly_compile_commands(&load_modified_commands(&source)?, &path)?;
+        }
+        cli::Subcommand::Analyze(args) if args.watch => {
+            let commands = load_assembly_generation_commands(&args.source)?;
+            let metrics_server = args
+                .metrics_port
+                .map(metrics::MetricsServer::start)
+                .transpose()?;
+            watch::watch(
+                &commands,
+                args.source.parse_options,
+                args.source.verbose_errors,
+                &args.output_json,
+                args.deterministic_output,
+                args.compression,
+                metrics_server.as_ref(),
+            )?;
+        }
+        cli::Subcommand::Analyze(args) if args.split_by_arch => {
+            analyze_split_by_arch(&args)?;
+        }
+        cli::Subcommand::Analyze(args) => {
+            // Started before the analysis, so that a port that is in use is reported early.
+            let metrics_server = args
+                .metrics_port
+                .map(metrics::MetricsServer::start)
+                .transpose()?;
+            let parsed = analyze(&args.source)?;
+            write_parsed_data(
+                &parsed,
+                &args.output_json,
+                args.deterministic_output,
+                args.compression,
+            )?;
+            if let Some(metrics_server) = metrics_server {
+                metrics_server.update(&parsed);
+                eprintln!("Serving the metrics until the process is terminated.");
+                loop {
+                    std::thread::park();
+                }
+            }
+        }
+        cli::Subcommand::Report(args) => {
+            // Loaded first, so that errors are found before the analysis.
+            let layer_config = args
+                .check_layering
+                .as_deref()
+                .map(config::load_layer_config)
+                .transpose()?;
+            // Also loaded before the parsed data, whose object names should be displayed.
+            let no_increase_baseline = args
+                .assert_no_increase_vs
+                .as_deref()
+                .map(|name| baseline::load(&args.baseline_dir, name).map(|(before, _)| before))
+                .transpose()?;
+            let parsed = load_or_analyze(&args.from_json, &args.source)?;
+            prepare_display(&parsed);
+            let filtered;
+            let displayed = if args.exclude_system_functions
+                || args.omit_library_callees
+                || !matches!(args.test_functions, cli::TestFunctions::Include)
+            {
+                let mut parsed = parsed.clone();
+                if args.exclude_system_functions {
+                    parsed.exclude_functions(&filter::FilterSet::system_functions());
+                }
+                let builtin_test_filter;
+                let test_filter = if args.test_patterns.is_empty() {
+                    builtin_test_filter = filter::FilterSet::test_functions();
+                    &builtin_test_filter
+                } else {
+                    &args.test_patterns
+                };
+                match args.test_functions {
+                    cli::TestFunctions::Include => {}
+                    cli::TestFunctions::Exclude => parsed.exclude_functions(test_filter),
+                    cli::TestFunctions::Only => parsed.retain_matching_functions(test_filter),
+                }
+                if args.omit_library_callees {
+                    let collapsed_num = parsed.collapse_library_callees();
+                    eprintln!(
+                        "Collapsed {} external functions into {}.",
+                        collapsed_num, EXTERNAL_FUNCTION_NAME
+                    );
+                }
+                filtered = parsed;
+                &filtered
+            } else {
+                &parsed
+            };
+            for op in args.ops.iter() {
+                match op {
+                    cli::AnalysisOp::Report(kind) => print_report(displayed, *kind, &args)?,
+                    cli::AnalysisOp::FunctionInfo(name) => {
+                        let function = find_function_by_name(displayed, name)?;
+                        print_function_info(displayed, &function, args.max_depth)?;
+                    }
+                    cli::AnalysisOp::Explain(name) => {
+                        let function = find_function_by_name(displayed, name)?;
+                        let function_id = displayed.function_id_by_name[&function];
+                        println!(
+                            "{}",
+                            explain_function(displayed, function_id, args.max_depth)
+                        );
+                    }
+                    cli::AnalysisOp::TopCallersOf(name, n) => {
+                        let function = find_function_by_name(displayed, name)?;
+                        print_top_callers(
+                            displayed,
+                            displayed.function_id_by_name[&function],
+                            *n,
+                            args.max_depth,
+                        );
+                    }
+                    cli::AnalysisOp::CallTree(name, depth) => {
+                        let function = find_function_by_name(displayed, name)?;
+                        print_call_tree(
+                            displayed,
+                            displayed.function_id_by_name[&function],
+                            *depth,
+                            args.max_depth,
+                        );
+                    }
+                    cli::AnalysisOp::WhoUses(name) => {
+                        let function = find_function_by_name(displayed, name)?;
+                        print_function_usage_by_object(displayed, &function)?;
+                    }
+                    cli::AnalysisOp::ObjectInfo(suffix) => {
+                        let objects = find_objects_by_path_suffix(displayed, suffix);
+                        if objects.is_empty() {
+                            return Err(eyre::eyre!("Can't find object {}.", suffix));
+                        }
+                        for object in objects {
+                            print_object_info(displayed, &object, args.sort_functions_by)?;
+                        }
+                    }
+                    // The unfiltered data is written, so that it can be used for all reports.
+                    cli::AnalysisOp::OutputJson(path) => {
+                        write_parsed_data(&parsed, path, false, Default::default())?
+                    }
+                    cli::AnalysisOp::OutputAdjacencyMatrix(path) => {
+                        let functions = adjacency_matrix_functions(displayed, &args.filter_regex);
+                        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                        export::write_adjacency_matrix(
+                            displayed,
+                            &mut file,
+                            &functions,
+                            args.adjacency_format,
+                        )?;
+                    }
+                    cli::AnalysisOp::ReportCard(path) => {
+                        report_card::write_report_card(displayed, None, path)?;
+                    }
+                    cli::AnalysisOp::OutputCallgrind(path) => {
+                        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                        export::write_callgrind(displayed, &mut file)?;
+                    }
+                }
+            }
+            // Assertions use the unfiltered data, so that every function can be checked.
+            let mut assertion_results = vec![];
+            for (pattern, limit) in args.max_instructions_assertions.iter() {
+                assertion_results.extend(assertions::check_max_instructions(
+                    &parsed, pattern, *limit,
+                )?);
+            }
+            if let Some(before) = &no_increase_baseline {
+                assertion_results.extend(assertions::check_no_increase(before, &parsed));
+            }
+            if !args.max_instructions_assertions.is_empty() || args.assert_no_increase_vs.is_some()
+            {
+                assertions::report(&assertion_results)?;
+            }
+            if let Some(layer_config) = &layer_config {
+                let object_graph = layering::build_object_call_graph(&parsed);
+                let violations = layering::check_layering(&object_graph, layer_config);
+                layering::report(&parsed, &violations)?;
+            }
+            if !args.no_alloc_checks.is_empty() {
+                let call_graph = call_graph::build_call_graph(&parsed);
+                let mut violations = vec![];
+                for pattern in args.no_alloc_checks.iter() {
+                    violations.extend(alloc::check_no_alloc(
+                        &parsed,
+                        &call_graph,
+                        pattern,
+                        args.max_depth,
+                    )?);
+                }
+                alloc::report(&parsed, &violations)?;
+            }
+        }
+        cli::Subcommand::Diff(args) => {
+            let mut normalization = args.normalization.clone();
+            let (before, after) = match &args.inputs {
+                cli::DiffInputs::Json {
+                    before_json,
+                    after_json,
+                } => (
+                    load_parsed_data(before_json)?,
+                    load_parsed_data(after_json)?,
+                ),
+                cli::DiffInputs::Configs { configs, source } => {
+                    // Both configs are loaded first, so that errors are found before the
+                    // first analysis.
+                    let [a, b] = configs;
+                    let (config_a, config_b) =
+                        (config::load_build_config(a)?, config::load_build_config(b)?);
+                    normalization.extend(&config_a.normalization);
+                    normalization.extend(&config_b.normalization);
+                    eprintln!("Analyzing config {}", a.display());
+                    let before = analyze_config(source, config_a)?;
+                    eprintln!("Analyzing config {}", b.display());
+                    (before, analyze_config(source, config_b)?)
+                }
+            };
+            demangle::prepare(
+                before
+                    .name_by_function_id
+                    .values()
+                    .chain(after.name_by_function_id.values())
+                    .map(FunctionName::name),
+            );
+            if let Some(pattern) = &args.assembly_for {
+                print_assembly_diff(&before, &after, pattern)?;
+                return Ok(());
+            }
+            let diff = diff_parsed_data(&before, &after, &normalization);
+            print_diff(&diff, &args.ranking);
+            if let Some(path) = &args.report_card {
+                report_card::write_report_card(&after, Some(&diff), path)?;
+            }
+            if let cli::DiffInputs::Configs { .. } = &args.inputs {
+                print_object_deltas(&before, &after, &args.ranking);
+            }
+        }
+        cli::Subcommand::Query(args) => {
+            let parsed = load_or_analyze(&args.from_json, &args.source)?;
+            prepare_display(&parsed);
+            if let Some(name) = &args.function {
+                let function = find_function_by_name(&parsed, name)?;
+                print_function_info(&parsed, &function, args.max_depth)?;
+            }
+            if let Some(name) = &args.explain {
+                let function = find_function_by_name(&parsed, name)?;
+                let function_id = parsed.function_id_by_name[&function];
+                println!("{}", explain_function(&parsed, function_id, args.max_depth));
+            }
+            if let Some((name, n)) = &args.top_callers_of {
+                let function = find_function_by_name(&parsed, name)?;
+                print_top_callers(
+                    &parsed,
+                    parsed.function_id_by_name[&function],
+                    *n,
+                    args.max_depth,
+                );
+            }
+            if let Some((name, depth)) = &args.call_tree {
+                let function = find_function_by_name(&parsed, name)?;
+                print_call_tree(
+                    &parsed,
+                    parsed.function_id_by_name[&function],
+                    *depth,
+                    args.max_depth,
+                );
+            }
+            if let Some(name) = &args.who_uses {
+                let function = find_function_by_name(&parsed, name)?;
+                print_function_usage_by_object(&parsed, &function)?;
+            }
+            if let Some(suffix) = &args.object_info {
+                let objects = find_objects_by_path_suffix(&parsed, suffix);
+                if objects.is_empty() {
+                    return Err(eyre::eyre!("Can't find object."));
+                }
+                for object in objects {
+                    print_object_info(&parsed, &object, args.sort_functions_by)?;
+                }
+            }
+            if let Some((from, to)) = &args.plantuml_chain {
+                let find_id = |name: &str| {
+                    find_function_by_name(&parsed, name)
+                        .map(|function| parsed.function_id_by_name[&function])
+                };
+                let call_graph = call_graph::build_call_graph(&parsed);
+                let chain = match call_graph.shortest_call_path(
+                    find_id(from)?,
+                    find_id(to)?,
+                    args.max_depth,
+                ) {
+                    call_graph::TraversalResult::Complete(chain) => {
+                        chain.ok_or(eyre::eyre!("{} does not call {}.", from, to))?
+                    }
+                    call_graph::TraversalResult::Truncated(chain) => chain.ok_or(eyre::eyre!(
+                        "{} does not call {} within {} calls, use a larger --max-depth.",
+                        from,
+                        to,
+                        args.max_depth.unwrap_or_default()
+                    ))?,
+                };
+                export::write_plantuml_sequence(&parsed, &chain, &mut std::io::stdout().lock())?;
+            }
+        }
+        cli::Subcommand::Baseline(args) => match args.action {
+            cli::BaselineAction::Save {
+                name,
+                from_json,
+                source,
+                compression,
+            } => {
+                let parsed = load_or_analyze(&from_json, &source)?;
+                let flags = match from_json {
+                    Some(_) => vec![],
+                    None => baseline::analysis_flags(&source),
+                };
+                baseline::save(&args.baseline_dir, &name, &parsed, flags, compression)?;
+            }
+            cli::BaselineAction::Compare {
+                name,
+                from_json,
+                source,
+                ranking,
+                report_unchanged,
+                report_card,
+                normalization,
+            } => {
+                let (before, metadata) = baseline::load(&args.baseline_dir, &name)?;
+                // The flags of data loaded from JSON are unknown.
+                if from_json.is_none() {
+                    baseline::check_flags(&name, &metadata, &baseline::analysis_flags(&source));
+                }
+                let after = load_or_analyze(&from_json, &source)?;
+                demangle::prepare(
+                    before
+                        .name_by_function_id
+                        .values()
+                        .chain(after.name_by_function_id.values())
+                        .map(FunctionName::name),
+                );
+                let diff = diff_parsed_data(&before, &after, &normalization);
+                print_diff(&diff, &ranking);
+                if report_unchanged {
+                    print_unchanged(&diff, &ranking);
+                }
+                if let Some(path) = &report_card {
+                    report_card::write_report_card(&after, Some(&diff), path)?;
+                }
+            }
+            cli::BaselineAction::List => baseline::print_baselines(&args.baseline_dir)?,
+            cli::BaselineAction::Delete(name) => baseline::delete(&args.baseline_dir, &name)?,
+        },
+        cli::Subcommand::Stats(args) => {
+            // Compiles at most one object for --estimate-time, so the limit doesn't apply.
+            let commands = load_modified_commands(&args.source)?;
+            stats::print_stats(
+                &commands,
+                args.source.parse_options,
+                args.source.verbose_errors,
+                args.estimate_time,
+            )?;
+            if args.report_flag_groups {
+                stats::print_flag_groups(&commands);
+            }
+        }
+        cli::Subcommand::MergeShards(args) => {
+            let shards = args
+                .shards
+                .iter()
+                .map(|path| {
+                    load_parsed_data(path)
+                        .map_err(|err| eyre::eyre!("Can't load {}: {}", path.display(), err))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let merged = shard::merge_shards(shards);
+            eprintln!(
+                "Merged {} shards with {} objects and {} functions.",
+                args.shards.len(),
+                merged.name_by_object_id.len(),
+                merged.name_by_function_id.len()
+            );
+            write_parsed_data(
+                &merged,
+                &args.output_json,
+                args.deterministic_output,
+                Default::default(),
+            )?;
+        }
+        cli::Subcommand::CompareOptimization(args) => {
+            eprintln!("Analyzing with the original flags");
+            let base = analyze(&args.source)?;
+            let mut optimized_source = args.source.clone();
+            optimized_source
+                .extra_flags
+                .extend(args.optimization_flags.iter().cloned());
+            eprintln!("Analyzing with {}", args.optimization_flags.join(" "));
+            let optimized = analyze(&optimized_source)?;
+            demangle::prepare(
+                base.name_by_function_id
+                    .values()
+                    .chain(optimized.name_by_function_id.values())
+                    .map(FunctionName::name),
+            );
+            let diff = diff_parsed_data(&base, &optimized, &Default::default());
+            print_optimization_comparison(&diff, &args.optimization_flags, &args.ranking);
+        }
+        cli::Subcommand::Help(text) => println!("{}", text),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(str::to_owned).collect()
+    }
+
+    fn modified(
+        command: &str,
+        removals: &[&str],
+        replacements: &[(&str, &str)],
+        additions: &[&str],
+    ) -> Vec<String> {
+        let mut command = args(command);
+        let to_strings =
+            |flags: &[&str]| -> Vec<String> { flags.iter().map(|flag| flag.to_string()).collect() };
+        let replacements: Vec<(String, String)> = replacements
+            .iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect();
+        apply_flag_modifications(
+            &mut command,
+            &to_strings(removals),
+            &replacements,
+            &to_strings(additions),
+        );
+        command
+    }
+
+    fn parse(assembly: &str) -> ParsedData {
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: PathBuf::from("/build/a.o"),
+        });
+        parse_data(object, assembly, ParseOptions::default(), &mut parsed);
+        parsed
+    }
+
+    /// Assembly of a global function with the given instructions.
+    fn global_function(name: &str, body: &str) -> String {
+        format!(
+            "\t.globl\t{name}\n\
+             \t.type\t{name}, @function\n\
+             {name}:\n\
+             {body}\
+             \t.size\t{name}, .-{name}\n"
+        )
+    }
+
+    #[test]
+    fn lookups_work_after_deserializing() {
+        let parsed = parse(
+            "\t.globl\tglobal\n\
+             \t.type\tglobal, @function\n\
+             global:\n\
+             \tcall\tlocal\n\
+             \tret\n\
+             \t.size\tglobal, .-global\n\
+             \t.type\tlocal, @function\n\
+             local:\n\
+             \tret\n\
+             \t.size\tlocal, .-local\n",
+        );
+        let object = parsed.object_id_by_name[&ObjectName {
+            path: PathBuf::from("/build/a.o"),
+        }];
+        let json = serde_json::to_string(&parsed).unwrap();
+        let loaded: ParsedData = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            loaded.function_id_by_global_name("global"),
+            parsed.function_id_by_global_name("global")
+        );
+        assert!(loaded.function_id_by_global_name("global").is_some());
+        assert_eq!(
+            loaded.function_id_by_local_name(object, "local"),
+            parsed.function_id_by_local_name(object, "local")
+        );
+        assert!(loaded.function_id_by_local_name(object, "local").is_some());
+        assert_eq!(
+            validate::validate_parsed_data(&loaded),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn calls_through_set_alias_go_to_the_target() {
+        let parsed = parse(
+            "\t.globl\tbar\n\
+             \t.type\tbar, @function\n\
+             bar:\n\
+             \tret\n\
+             \t.size\tbar, .-bar\n\
+             \t.globl\tfoo\n\
+             \t.set\tfoo,bar\n\
+             \t.globl\tcaller\n\
+             \t.type\tcaller, @function\n\
+             caller:\n\
+             \tcall\tfoo\n\
+             \tret\n",
+        );
+        let bar = parsed.function_id_by_global_name("bar").unwrap();
+        let caller = parsed.function_id_by_global_name("caller").unwrap();
+        assert_eq!(
+            parsed.callees_by_caller[&caller],
+            HashSet::from([bar]),
+            "the call to the alias should be an edge to its target"
+        );
+    }
+
+    #[test]
+    fn set_alias_with_space_after_comma() {
+        let parsed = parse(
+            "\t.type\tbar, @function\n\
+             bar:\n\
+             \tret\n\
+             \t.size\tbar, .-bar\n\
+             \t.set\tfoo, bar\n\
+             \t.type\tcaller, @function\n\
+             caller:\n\
+             \tcall\tfoo@PLT\n\
+             \tret\n",
+        );
+        let caller = parsed
+            .function_id_by_name
+            .iter()
+            .find_map(|(name, id)| (name.name() == "caller").then_some(*id));
+        let callees = &parsed.callees_by_caller[&caller.unwrap()];
+        assert_eq!(callees.len(), 1);
+        let callee = callees.iter().next().unwrap();
+        assert_eq!(parsed.name_by_function_id[callee].name(), "bar");
+    }
+
+    #[test]
+    fn function_names_are_freed_with_the_parsed_data() {
+        let parsed = parse(
+            "\t.globl\tonly_in_freed_data\n\
+             \t.type\tonly_in_freed_data, @function\n\
+             only_in_freed_data:\n\
+             \tret\n",
+        );
+        assert!(parsed
+            .function_id_by_global_name("only_in_freed_data")
+            .is_some());
+        drop(parsed);
+        assert_eq!(
+            ArcIntern::<str>::from("only_in_freed_data").refcount(),
+            1,
+            "no other reference to the name should be left"
+        );
+    }
+
+    #[test]
+    fn remove_merged_optimization_flag() {
+        assert_eq!(
+            modified("gcc -O2 -c a.c", &["-O2"], &[], &[]),
+            args("gcc -c a.c")
+        );
+    }
+
+    #[test]
+    fn remove_split_optimization_flag() {
+        assert_eq!(
+            modified("gcc -O 2 -c a.c", &["-O2"], &[], &[]),
+            args("gcc -c a.c")
+        );
+        assert_eq!(
+            modified("gcc -O fast -c a.c", &["-Ofast"], &[], &[]),
+            args("gcc -c a.c")
+        );
+    }
+
+    #[test]
+    fn bare_optimization_flag_is_not_joined_with_next_argument() {
+        assert_eq!(
+            modified("gcc -O -c a.c", &["-O"], &[], &[]),
+            args("gcc -c a.c")
+        );
+        assert_eq!(
+            modified("gcc -O -c a.c", &[], &[("-O", "-O3")], &[]),
+            args("gcc -O3 -c a.c")
+        );
+    }
+
+    #[test]
+    fn replace_merged_and_split_optimization_flags() {
+        assert_eq!(
+            modified("gcc -O2 -c a.c", &[], &[("-O2", "-O0")], &[]),
+            args("gcc -O0 -c a.c")
+        );
+        assert_eq!(
+            modified("gcc -O 2 -c a.c", &[], &[("-O2", "-O0")], &[]),
+            args("gcc -O0 -c a.c")
+        );
+    }
+
+    #[test]
+    fn additions_are_appended() {
+        assert_eq!(
+            modified("gcc -O2 -c a.c", &["-O2"], &[], &["-Os", "-g"]),
+            args("gcc -c a.c -Os -g")
+        );
+    }
+
+    #[test]
+    fn unrelated_flags_are_kept() {
+        assert_eq!(
+            modified("gcc -O 2 -Wall -c a.c", &["-O3"], &[("-Wextra", "-w")], &[]),
+            args("gcc -O 2 -Wall -c a.c")
+        );
+    }
+
+    fn parse_objects(assembly_by_object: &[(&str, &str)]) -> ParsedData {
+        let mut parsed = ParsedData::default();
+        for (path, assembly) in assembly_by_object {
+            let object = parsed.get_or_add_object(ObjectName {
+                path: PathBuf::from(path),
+            });
+            parse_data(object, assembly, ParseOptions::default(), &mut parsed);
+        }
+        parsed
+    }
+
+    /// Compiles /build/x.c into the object, relative to /build.
+    fn command(object: &str) -> AssemblyGenerationCommand {
+        let args = ["cc", "-c", "x.c", "-o", object].map(str::to_owned);
+        adapt_args_to_generate_assembly(args.to_vec(), "/build").unwrap()
+    }
+
+    const SHARED_AND_EXTERNAL: &str = "\t.weak\tshared\n\
+         \t.type\tshared, @function\n\
+         shared:\n\
+         \tnop\n\
+         \tret\n\
+         \t.size\tshared, .-shared\n";
+
+    #[test]
+    fn retain_objects_removes_orphaned_external_callees() {
+        let mut parsed = parse_objects(&[
+            (
+                "/build/a.o",
+                "\t.globl\tkeep\n\
+                 \t.type\tkeep, @function\n\
+                 keep:\n\
+                 \tcall\tshared\n\
+                 \tret\n\
+                 \t.size\tkeep, .-keep\n",
+            ),
+            ("/build/a.o", SHARED_AND_EXTERNAL),
+            (
+                "/build/b.o",
+                "\t.globl\tdrop\n\
+                 \t.type\tdrop, @function\n\
+                 drop:\n\
+                 \tcall\texternal\n\
+                 \tcall\tshared\n\
+                 \tret\n\
+                 \t.size\tdrop, .-drop\n",
+            ),
+            ("/build/b.o", SHARED_AND_EXTERNAL),
+        ]);
+        assert_eq!(
+            validate::validate_parsed_data(&parsed),
+            Vec::<String>::new()
+        );
+        let shared = parsed.function_id_by_global_name("shared").unwrap();
+        assert_eq!(total_instructions(&parsed, shared), 4);
+
+        parsed.retain_objects(|_, object| object.path == Path::new("/build/a.o"));
+
+        assert_eq!(
+            validate::validate_parsed_data(&parsed),
+            Vec::<String>::new()
+        );
+        assert!(parsed.function_id_by_global_name("keep").is_some());
+        assert!(parsed.function_id_by_global_name("drop").is_none());
+        assert!(
+            parsed.function_id_by_global_name("external").is_none(),
+            "an external function that was only called from removed objects should be removed"
+        );
+        assert_eq!(total_instructions(&parsed, shared), 2);
+    }
+
+    #[test]
+    fn retain_functions_removes_all_references() {
+        let mut parsed = parse_objects(&[
+            (
+                "/build/a.o",
+                "\t.globl\tkeep\n\
+                 \t.type\tkeep, @function\n\
+                 keep:\n\
+                 \tcall\tdrop\n\
+                 \tret\n\
+                 \t.size\tkeep, .-keep\n",
+            ),
+            (
+                "/build/b.o",
+                "\t.globl\tdrop\n\
+                 \t.type\tdrop, @function\n\
+                 drop:\n\
+                 \tcall\texternal\n\
+                 \tret\n\
+                 \t.size\tdrop, .-drop\n",
+            ),
+        ]);
+        let keep = parsed.function_id_by_global_name("keep").unwrap();
+        let b = parsed.object_id_by_name[&ObjectName {
+            path: PathBuf::from("/build/b.o"),
+        }];
+
+        parsed.retain_functions(|_, function| function.name() != "drop");
+
+        assert_eq!(
+            validate::validate_parsed_data(&parsed),
+            Vec::<String>::new()
+        );
+        assert!(parsed.function_id_by_global_name("drop").is_none());
+        assert!(parsed.function_id_by_global_name("external").is_some());
+        assert!(!parsed.callees_by_caller.contains_key(&keep));
+        assert!(
+            !parsed.call_sites_by_caller.contains_key(&keep),
+            "callers without remaining callees should have no call sites entry"
+        );
+        assert!(
+            !parsed.functions_by_object.contains_key(&b),
+            "objects without remaining functions should have no functions entry"
+        );
+    }
+
+    #[test]
+    fn functions_in_multiple_objects_are_compared_by_their_largest_copy() {
+        let before = parse_objects(&[("/build/a.o", SHARED_AND_EXTERNAL)]);
+        let after = parse_objects(&[
+            ("/build/a.o", SHARED_AND_EXTERNAL),
+            ("/build/b.o", SHARED_AND_EXTERNAL),
+        ]);
+        let shared = after.function_id_by_global_name("shared").unwrap();
+        assert_eq!(function_instructions_num(&after, shared), 2);
+        assert_eq!(total_instructions(&after, shared), 4);
+        assert_eq!(
+            after
+                .functions()
+                .find(|(function_id, ..)| *function_id == shared),
+            Some((shared, &after.name_by_function_id[&shared], Some(2)))
+        );
+
+        let diff = diff_parsed_data(&before, &after, &Default::default());
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.unchanged.len(), 1);
+    }
+
+    const LOCAL_HELPER: &str = "\t.type\thelper, @function\n\
+                                helper:\n\
+                                \tret\n\
+                                \t.size\thelper, .-helper\n";
+
+    #[test]
+    fn adding_an_object_in_another_directory_keeps_local_functions_matched() {
+        let before = parse_objects(&[("/build/src/a/x.o", LOCAL_HELPER)]);
+        let after = parse_objects(&[
+            ("/build/src/a/x.o", LOCAL_HELPER),
+            ("/build/src/b/y.o", SHARED_AND_EXTERNAL),
+        ]);
+        let (before_dir, after_dir) = common_object_dirs(&before, &after);
+        assert_eq!(before_dir, PathBuf::from("/build/src"));
+        assert_eq!(after_dir, PathBuf::from("/build/src"));
+
+        let diff = diff_parsed_data(&before, &after, &Default::default());
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.added,
+            vec![(
+                StableFunctionName::Global {
+                    name: "shared".to_owned()
+                },
+                2
+            )]
+        );
+        assert_eq!(
+            diff.unchanged,
+            vec![(
+                StableFunctionName::Local {
+                    name: "helper".to_owned(),
+                    object: PathBuf::from("a/x.o")
+                },
+                1
+            )]
+        );
+    }
+
+    #[test]
+    fn objects_of_different_checkouts_are_matched_by_their_relative_path() {
+        let before = parse_objects(&[
+            ("/old/build/a/x.o", LOCAL_HELPER),
+            ("/old/build/b/y.o", LOCAL_HELPER),
+        ]);
+        let after = parse_objects(&[
+            ("/new/checkout/build/a/x.o", LOCAL_HELPER),
+            ("/new/checkout/build/b/y.o", LOCAL_HELPER),
+            ("/new/checkout/build/c/z.o", LOCAL_HELPER),
+        ]);
+        let (before_dir, after_dir) = common_object_dirs(&before, &after);
+        assert_eq!(before_dir, PathBuf::from("/old/build"));
+        assert_eq!(after_dir, PathBuf::from("/new/checkout/build"));
+
+        let diff = diff_parsed_data(&before, &after, &Default::default());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.unchanged_count(), 2);
+    }
+
+    #[test]
+    fn weighted_instructions_count_the_most_expensive_copy() {
+        let expensive = "\t.weak\tshared\n\
+             \t.type\tshared, @function\n\
+             shared:\n\
+             \tdivq\t%rcx\n\
+             \tdivq\t%rcx\n\
+             \tret\n\
+             \t.size\tshared, .-shared\n";
+        let mut parsed = parse_objects(&[
+            ("/build/a.o", SHARED_AND_EXTERNAL),
+            ("/build/b.o", expensive),
+            ("/build/c.o", SHARED_AND_EXTERNAL),
+        ]);
+        let shared = parsed.function_id_by_global_name("shared").unwrap();
+        let cheap_weight = x86_throughput_weight("nop") + x86_throughput_weight("ret");
+        let expensive_weight = 2.0 * x86_throughput_weight("divq") + x86_throughput_weight("ret");
+        assert_eq!(
+            function_weighted_instructions(&parsed, shared),
+            expensive_weight
+        );
+
+        parsed.retain_objects(|_, object| !object.path.ends_with("b.o"));
+        assert_eq!(
+            function_weighted_instructions(&parsed, shared),
+            cheap_weight
+        );
+        assert_eq!(
+            parsed.weighted_instructions_by_object_by_function[&shared].len(),
+            2
+        );
+    }
+
+    #[test]
+    fn incremental_analysis_forgets_functions_of_removed_objects() {
+        let keep = "\t.globl\tkeep\n\
+                    \t.type\tkeep, @function\n\
+                    keep:\n\
+                    \tcall\tshared\n\
+                    \tret\n\
+                    \t.size\tkeep, .-keep\n";
+        let drop = "\t.globl\tdrop\n\
+                    \t.type\tdrop, @function\n\
+                    drop:\n\
+                    \tcall\texternal\n\
+                    \tcall\tshared\n\
+                    \tret\n\
+                    \t.size\tdrop, .-drop\n";
+        let mut analysis = watch::IncrementalAnalysis::default();
+        analysis
+            .update_object(&command("a.o"), &format!("{keep}{SHARED_AND_EXTERNAL}"))
+            .unwrap();
+        analysis
+            .update_object(&command("b.o"), &format!("{drop}{SHARED_AND_EXTERNAL}"))
+            .unwrap();
+        let external = analysis
+            .parsed
+            .function_id_by_global_name("external")
+            .unwrap();
+
+        analysis.remove_object(&command("b.o").object_name().unwrap());
+
+        let parsed = &analysis.parsed;
+        assert_eq!(validate::validate_parsed_data(parsed), Vec::<String>::new());
+        assert!(parsed.function_id_by_global_name("drop").is_none());
+        assert!(parsed.function_id_by_global_name("external").is_none());
+        assert!(!parsed.name_by_function_id.contains_key(&external));
+        assert_eq!(parsed.name_by_function_id.len(), 2);
+
+        // Functions that a rebuilt object doesn't refer to anymore are forgotten as well.
+        analysis.update_object(&command("a.o"), keep).unwrap();
+        analysis.update_object(&command("a.o"), "\tnop\n").unwrap();
+        let parsed = &analysis.parsed;
+        assert_eq!(validate::validate_parsed_data(parsed), Vec::<String>::new());
+        assert!(parsed.name_by_function_id.is_empty());
+    }
+
+    #[test]
+    fn incremental_removal_keeps_the_maximum_call_sites_of_remaining_objects() {
+        // An inline function that makes different calls in every object.
+        let caller = |calls: &[&str]| {
+            let calls: String = calls
+                .iter()
+                .map(|callee| format!("\tcall\t{callee}\n"))
+                .collect();
+            format!(
+                "\t.weak\tcaller\n\
+                 \t.type\tcaller, @function\n\
+                 caller:\n\
+                 {calls}\
+                 \tret\n\
+                 \t.size\tcaller, .-caller\n"
+            )
+        };
+        let objects = [
+            ("a.o", caller(&["x", "x"])),
+            ("b.o", caller(&["x", "y", "y", "y"])),
+            ("c.o", caller(&["x", "x", "x", "z"])),
+        ];
+        let call_sites = |analysis: &watch::IncrementalAnalysis| {
+            let parsed = &analysis.parsed;
+            let caller = parsed.function_id_by_global_name("caller").unwrap();
+            let call_sites: BTreeMap<String, usize> = parsed.call_sites_by_caller[&caller]
+                .iter()
+                .map(|(callee, num)| (parsed.function_name_str(*callee).unwrap().to_owned(), *num))
+                .collect();
+            call_sites
+        };
+
+        let mut incremental = watch::IncrementalAnalysis::default();
+        for (object, assembly) in &objects {
+            incremental
+                .update_object(&command(object), assembly)
+                .unwrap();
+        }
+        incremental.remove_object(&command("c.o").object_name().unwrap());
+        let mut full = watch::IncrementalAnalysis::default();
+        for (object, assembly) in &objects[..2] {
+            full.update_object(&command(object), assembly).unwrap();
+        }
+
+        assert_eq!(
+            call_sites(&incremental),
+            BTreeMap::from([("x".to_owned(), 2), ("y".to_owned(), 3)])
+        );
+        assert_eq!(call_sites(&incremental), call_sites(&full));
+        assert_eq!(
+            validate::validate_parsed_data(&incremental.parsed),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn incremental_removal_keeps_the_values_of_the_first_remaining_object() {
+        let inline_function = |frame_size: usize, register: &str| {
+            format!(
+                "\t.weak\tinline_function\n\
+                 \t.type\tinline_function, @function\n\
+                 inline_function:\n\
+                 \t.cfi_startproc\n\
+                 \tpushq\t%{register}\n\
+                 \t.cfi_def_cfa_offset {frame_size}\n\
+                 \t.cfi_offset %{register}, -16\n\
+                 \tret\n\
+                 \t.cfi_endproc\n\
+                 \t.size\tinline_function, .-inline_function\n"
+            )
+        };
+        let registers = ["rbx", "rbp", "r12", "r13", "r14", "r15"];
+        let objects: Vec<(String, String)> = registers
+            .iter()
+            .enumerate()
+            .map(|(i, register)| (format!("{i}.o"), inline_function(16 * (i + 1), register)))
+            .collect();
+        let values = |analysis: &watch::IncrementalAnalysis| {
+            let parsed = &analysis.parsed;
+            let function_id = parsed
+                .function_id_by_global_name("inline_function")
+                .unwrap();
+            (
+                parsed.frame_size_by_function[&function_id],
+                parsed.saved_registers_by_function[&function_id].clone(),
+            )
+        };
+
+        let mut incremental = watch::IncrementalAnalysis::default();
+        for (object, assembly) in &objects {
+            incremental
+                .update_object(&command(object), assembly)
+                .unwrap();
+        }
+        assert_eq!(values(&incremental), (16, vec!["rbx".to_owned()]));
+        incremental.remove_object(&command("0.o").object_name().unwrap());
+        // Parsing an object again merges it last.
+        incremental
+            .update_object(&command("1.o"), &objects[1].1)
+            .unwrap();
+        let mut full = watch::IncrementalAnalysis::default();
+        for (object, assembly) in objects[2..].iter().chain([&objects[1]]) {
+            full.update_object(&command(object), assembly).unwrap();
+        }
+
+        assert_eq!(values(&incremental), (48, vec!["r12".to_owned()]));
+        assert_eq!(values(&incremental), values(&full));
+        assert_eq!(
+            validate::validate_parsed_data(&incremental.parsed),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn jumps_to_the_cold_part_are_not_tail_calls() {
+        let parsed = parse(
+            "\t.section\t.text.startup,\"ax\",@progbits\n\
+             \t.globl\tmain\n\
+             \t.type\tmain, @function\n\
+             main:\n\
+             \ttestl\t%edi, %edi\n\
+             \tjne\t.L2\n\
+             \tjmp\tmain.cold\n\
+             .L2:\n\
+             \tjmp\tfree\n\
+             \t.section\t.text.unlikely\n\
+             \t.type\tmain.cold, @function\n\
+             main.cold:\n\
+             \tcall\tabort\n\
+             \t.section\t.text.startup\n\
+             \t.size\tmain, .-main\n\
+             \t.section\t.text.unlikely\n\
+             \t.size\tmain.cold, .-main.cold\n",
+        );
+        let main = parsed.function_id_by_global_name("main").unwrap();
+        let callees: BTreeSet<&str> = parsed.callees_by_caller[&main]
+            .iter()
+            .map(|callee| parsed.name_by_function_id[callee].name())
+            .collect();
+        assert_eq!(callees, BTreeSet::from(["abort", "free"]));
+    }
+
+    #[test]
+    fn objects_are_referenced_once_per_call_site() {
+        let parsed = parse_objects(&[
+            (
+                "/build/a.o",
+                "\t.globl\tf\n\
+                 \t.type\tf, @function\n\
+                 f:\n\
+                 \tcall\tmalloc\n\
+                 \tcall\tmalloc\n\
+                 \tcall\tmalloc\n\
+                 \tret\n\
+                 \t.size\tf, .-f\n\
+                 \t.globl\tg\n\
+                 \t.type\tg, @function\n\
+                 g:\n\
+                 \tjmp\tmalloc\n\
+                 \t.size\tg, .-g\n",
+            ),
+            (
+                "/build/b.o",
+                "\t.globl\th\n\
+                 \t.type\th, @function\n\
+                 h:\n\
+                 \tcall\tmalloc\n\
+                 \tcall\tmalloc\n\
+                 \tret\n\
+                 \t.size\th, .-h\n",
+            ),
+        ]);
+        let malloc = parsed.function_id_by_global_name("malloc").unwrap();
+        let mut num_by_object: BTreeMap<&Path, usize> = BTreeMap::new();
+        for object_id in objects_referencing_function(&parsed, malloc) {
+            *num_by_object
+                .entry(&parsed.name_by_object_id[&object_id].path)
+                .or_default() += 1;
+        }
+        assert_eq!(
+            num_by_object,
+            BTreeMap::from([(Path::new("/build/a.o"), 4), (Path::new("/build/b.o"), 2)])
+        );
+    }
+
+    #[test]
+    fn validate_parsed_data_reports_one_sided_call_edges() {
+        let mut parsed = parse(
+            "\t.globl\tcaller\n\
+             \t.type\tcaller, @function\n\
+             caller:\n\
+             \tcall\tcallee\n\
+             \tret\n\
+             \t.size\tcaller, .-caller\n",
+        );
+        assert_eq!(
+            validate::validate_parsed_data(&parsed),
+            Vec::<String>::new()
+        );
+        parsed.callers_by_callee.clear();
+        assert_eq!(validate::validate_parsed_data(&parsed).len(), 1);
+    }
+
+    #[test]
+    fn assembly_diff_ignores_labels_and_directives() {
+        let lines =
+            |lines: &[&str]| -> Vec<String> { lines.iter().map(|line| line.to_string()).collect() };
+        let before = lines(&[
+            "\t.p2align 4",
+            ".L3:",
+            "\tmovl\t$1, %eax",
+            "\tjmp\t.L3",
+            "\tret",
+        ]);
+        let after = lines(&[
+            ".L7:",
+            "\tmovl\t$2, %eax",
+            "\tjmp\t.L7",
+            "\t.cfi_endproc",
+            "\tret",
+        ]);
+        let diff = assembly_diff::diff_function_assembly(&before, &after);
+        let mut output = Vec::new();
+        diff.write_unified(&mut output, "f", "f").unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "--- f\n\
+             +++ f\n\
+             @@ -1,3 +1,3 @@\n\
+             -movl $1, %eax\n\
+             +movl $2, %eax\n \
+             jmp .L\n \
+             ret\n"
+        );
+        assert!(assembly_diff::diff_function_assembly(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn wildcard_patterns_match_whole_paths() {
+        let pattern = filter::wildcard_regex("*/platform/[a-c]?.o").unwrap();
+        assert!(pattern.is_match("/build/platform/ab.o"));
+        assert!(!pattern.is_match("/build/platform/db.o"));
+        assert!(!pattern.is_match("/build/platform/ab.o.d"));
+        let pattern = filter::wildcard_regex("[!.]*.o").unwrap();
+        assert!(pattern.is_match("a.o"));
+        assert!(!pattern.is_match(".a.o"));
+        assert!(filter::wildcard_regex("[a.o").is_err());
+        // Backtracking matchers take exponential time for this.
+        let pattern = filter::wildcard_regex(&format!("{}c", "a*".repeat(30))).unwrap();
+        assert!(!pattern.is_match(&format!("{}b", "a".repeat(100))));
+    }
+
+    #[test]
+    fn names_are_displayed_without_preparation() {
+        let parsed = parse(
+            "\t.type\t_ZL6helperi, @function\n\
+             _ZL6helperi:\n\
+             \tret\n\
+             \t.size\t_ZL6helperi, .-_ZL6helperi\n",
+        );
+        let function = parsed.name_by_function_id.values().next().unwrap();
+        assert_eq!(
+            parsed.display_name(function).to_string(),
+            "helper(int) (local in a.o)"
+        );
+    }
+
+    #[test]
+    fn excluded_conditional_blocks_keep_their_else_branches() {
+        let assembly = "\t.globl\tf\n\
+                        \t.type\tf, @function\n\
+                        f:\n\
+                        \tnop\n\
+                        \t.ifdef\tASAN\n\
+                        \tcall\t__asan_load8\n\
+                        \t.ifndef\tX\n\
+                        \tnop\n\
+                        \t.else\n\
+                        \tnop\n\
+                        \t.endif\n\
+                        \t.else\n\
+                        \tmovq\t%rdi, %rax\n\
+                        \t.ifdef\tB\n\
+                        \tnop\n\
+                        \t.else\n\
+                        \taddq\t$1, %rax\n\
+                        \t.endif\n\
+                        \t.endif\n\
+                        \tret\n\
+                        \t.size\tf, .-f\n";
+        let parse_with = |exclude_conditional_blocks: bool| {
+            let mut parsed = ParsedData::default();
+            let object = parsed.get_or_add_object(ObjectName {
+                path: PathBuf::from("/build/a.o"),
+            });
+            let options = ParseOptions {
+                exclude_conditional_blocks,
+                ..Default::default()
+            };
+            parse_data(object, assembly, options, &mut parsed);
+            parsed
+        };
+
+        let all_branches = parse_with(false);
+        let f = all_branches.function_id_by_global_name("f").unwrap();
+        assert_eq!(function_instructions_num(&all_branches, f), 8);
+        assert!(all_branches
+            .function_id_by_global_name("__asan_load8")
+            .is_some());
+
+        let parsed = parse_with(true);
+        let f = parsed.function_id_by_global_name("f").unwrap();
+        assert_eq!(function_instructions_num(&parsed, f), 4);
+        assert!(parsed.function_id_by_global_name("__asan_load8").is_none());
+        assert_eq!(
+            validate::validate_parsed_data(&parsed),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn excluded_conditional_blocks_skip_elseif_branches() {
+        let assembly = "\t.globl\tf\n\
+                        \t.type\tf, @function\n\
+                        f:\n\
+                        \t.if\tA\n\
+                        \tnop\n\
+                        \t.elseif\tB\n\
+                        \tnop\n\
+                        \t.else\n\
+                        \tnop\n\
+                        \t.endif\n\
+                        \tret\n\
+                        \t.size\tf, .-f\n";
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: PathBuf::from("/build/a.o"),
+        });
+        let options = ParseOptions {
+            exclude_conditional_blocks: true,
+            ..Default::default()
+        };
+        parse_data(object, assembly, options, &mut parsed);
+        let f = parsed.function_id_by_global_name("f").unwrap();
+        assert_eq!(function_instructions_num(&parsed, f), 2);
+    }
+
+    #[test]
+    fn local_functions_are_displayed_with_the_object_of_their_own_data() {
+        let before = parse_objects(&[("/build/old.o", LOCAL_HELPER)]);
+        let after = parse_objects(&[("/build/new.o", LOCAL_HELPER)]);
+        let name = |parsed: &ParsedData| {
+            let (function_id, ..) = parsed.functions().next().unwrap();
+            parsed.display(function_id).to_string()
+        };
+
+        assert_eq!(name(&before), "helper (local in old.o)");
+        assert_eq!(name(&after), "helper (local in new.o)");
+        assert_eq!(name(&before), "helper (local in old.o)");
+    }
+
+    #[test]
+    fn relative_directories_are_resolved_against_the_database() {
+        let command = |directory: &str| CMakeCompileCommand {
+            directory: directory.to_owned(),
+            command: "gcc -c a.c -o a.o".to_owned(),
+            output: "a.o".to_owned(),
+        };
+        let commands = normalize_compile_commands(
+            vec![command("build"), command("/abs/build")],
+            Path::new("/project"),
+        );
+        assert_eq!(commands[0].directory, "/project/build");
+        assert_eq!(commands[1].directory, "/abs/build");
+    }
+
+    #[test]
+    fn local_functions_match_between_build_directories() {
+        let local_function = "\t.type\thelper, @function\n\
+             helper:\n\
+             \tret\n\
+             \t.size\thelper, .-helper\n";
+        let before = parse_objects(&[
+            ("/old/build/src/a.o", local_function),
+            ("/old/build/lib/b.o", local_function),
+        ]);
+        let after = parse_objects(&[
+            ("/new/checkout/build/src/a.o", local_function),
+            ("/new/checkout/build/lib/b.o", local_function),
+        ]);
+        let diff = diff_parsed_data(&before, &after, &Default::default());
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.unchanged.len(), 2);
+    }
+
+    #[test]
+    fn explanation_describes_simd_use_and_subtree_size_rank() {
+        let parsed = parse(
+            "\t.globl\tmain\n\
+             \t.type\tmain, @function\n\
+             main:\n\
+             \tcall\tkernel\n\
+             \tcall\tsmall\n\
+             \tret\n\
+             \t.size\tmain, .-main\n\
+             \t.globl\tkernel\n\
+             \t.type\tkernel, @function\n\
+             kernel:\n\
+             \tvpaddd\t%ymm1, %ymm2, %ymm0\n\
+             \tvmovdqu\t%ymm0, (%rdi)\n\
+             \tvzeroupper\n\
+             \tret\n\
+             \t.size\tkernel, .-kernel\n\
+             \t.globl\tsmall\n\
+             \t.type\tsmall, @function\n\
+             small:\n\
+             \tret\n\
+             \t.size\tsmall, .-small\n",
+        );
+        let main = parsed.function_id_by_global_name("main").unwrap();
+        let kernel = parsed.function_id_by_global_name("kernel").unwrap();
+        assert_eq!(
+            explain_function(&parsed, kernel, None),
+            "Function `kernel` is defined in 1 object file. It has 4 instructions. \
+             It is only called from `main`, so inlining it would not duplicate code. \
+             It is a leaf function that does not call other functions. It is not recursive. \
+             It uses AVX2 SIMD instructions. \
+             Its subtree size is 4 instructions, placing it in the lower half of subtree sizes."
+        );
+        assert!(explain_function(&parsed, main, None).ends_with(
+            "Together with the 2 functions it calls directly or indirectly, its subtree size \
+             is 8 instructions, placing it in the top 34% of subtree sizes."
+        ));
+
+        assert_eq!(
+            simd_extension("vaddsd\t%xmm1, %xmm0, %xmm0"),
+            Some(SimdExtension::Avx)
+        );
+        assert_eq!(
+            simd_extension("vaddps\t%zmm1, %zmm0, %zmm0"),
+            Some(SimdExtension::Avx512)
+        );
+        assert_eq!(simd_extension("addps\t%xmm1, %xmm0"), None);
+    }
+
+    #[test]
+    fn exception_heavy_functions_are_ranked() {
+        let function = |name: &str, instructions_num: usize| {
+            global_function(
+                name,
+                &format!(
+                    "\t.cfi_startproc\n\
+                     \t.cfi_personality 0x9b,DW.ref.__gxx_personality_v0\n\
+                     {}\
+                     \t.cfi_endproc\n",
+                    "\tnop\n".repeat(instructions_num)
+                ),
+            )
+        };
+        let parsed =
+            parse(&[function("big", 3), function("mid", 2), function("small", 1)].concat());
+        let names = |ranking: Ranking| {
+            exception_heavy_functions(&parsed, &ranking)
+                .into_iter()
+                .map(|(function, num)| (parsed.display_name(function).to_string(), num))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(Ranking {
+                top: Some(1),
+                ..Default::default()
+            }),
+            vec![("big".to_owned(), 3)]
+        );
+        assert_eq!(
+            names(Ranking {
+                bottom: Some(1),
+                min_instructions: 2,
+                ..Default::default()
+            }),
+            vec![("mid".to_owned(), 2)]
+        );
+    }
+
+    #[test]
+    fn multiple_return_functions_are_ranked() {
+        let function = |name: &str, returns_num: usize, nops_num: usize| {
+            global_function(
+                name,
+                &("\tnop\n".repeat(nops_num) + &"\tret\n".repeat(returns_num)),
+            )
+        };
+        let parsed = parse(
+            &[
+                function("many", 6, 0),
+                function("some", 5, 10),
+                function("few", 4, 0),
+                function("simple", 1, 0),
+            ]
+            .concat(),
+        );
+        let names = |ranking: Ranking| {
+            multiple_return_functions(&parsed, &ranking)
+                .into_iter()
+                .map(|(function, num)| (parsed.display_name(function).to_string(), num))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(Ranking::default()),
+            vec![
+                ("many".to_owned(), 6),
+                ("some".to_owned(), 5),
+                ("few".to_owned(), 4)
+            ]
+        );
+        assert_eq!(
+            names(Ranking {
+                top: Some(2),
+                bottom: Some(1),
+                ..Default::default()
+            }),
+            vec![("some".to_owned(), 5)]
+        );
+        assert_eq!(
+            names(Ranking {
+                min_instructions: 10,
+                ..Default::default()
+            }),
+            vec![("some".to_owned(), 5)]
+        );
+    }
+
+    #[test]
+    fn dispatcher_functions_are_ranked() {
+        let dispatcher = |name: &str, nops_num: usize| {
+            global_function(
+                name,
+                &("\tnop\n".repeat(nops_num) + "\tcall\tfirst\n\tcall\tsecond\n\tret\n"),
+            )
+        };
+        let parsed = parse(&[dispatcher("thin", 0), dispatcher("thick", 7)].concat());
+        let names = |ranking: Ranking| {
+            dispatcher_functions(&parsed, 1.0, &ranking)
+                .into_iter()
+                .map(|(function_id, _)| parsed.display(function_id).to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(Ranking::default()), vec!["thin", "thick"]);
+        assert_eq!(
+            names(Ranking {
+                top: Some(1),
+                ..Default::default()
+            }),
+            vec!["thin"]
+        );
+        assert_eq!(
+            names(Ranking {
+                min_instructions: 5,
+                ..Default::default()
+            }),
+            vec!["thick"]
+        );
+    }
+
+    #[test]
+    fn hot_call_pairs_are_ranked() {
+        let caller = |name: &str, calls_num: usize, nops_num: usize| {
+            global_function(
+                name,
+                &("\tnop\n".repeat(nops_num) + &"\tcall\tcallee\n".repeat(calls_num) + "\tret\n"),
+            )
+        };
+        let parsed = parse(
+            &[
+                caller("often", 4, 0),
+                caller("sometimes", 3, 5),
+                caller("once", 1, 0),
+            ]
+            .concat(),
+        );
+        let callers = |ranking: Ranking| {
+            hot_call_pairs(&parsed, &ranking)
+                .into_iter()
+                .map(|((caller, _), num)| (parsed.display(caller).to_string(), num))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            callers(Ranking::default()),
+            vec![("often".to_owned(), 4), ("sometimes".to_owned(), 3)]
+        );
+        assert_eq!(
+            callers(Ranking {
+                bottom: Some(1),
+                ..Default::default()
+            }),
+            vec![("sometimes".to_owned(), 3)]
+        );
+        assert_eq!(
+            callers(Ranking {
+                min_instructions: 6,
+                ..Default::default()
+            }),
+            vec![("sometimes".to_owned(), 3)]
+        );
+    }
+
+    #[test]
+    fn dependency_flags_are_ignored_in_both_spellings() {
+        let hash = |args: &[&str]| {
+            let args = args.iter().map(|arg| arg.to_string()).collect();
+            stats::hash_compile_flags(&adapt_args_to_generate_assembly(args, "/build").unwrap())
+        };
+        let plain = hash(&["cc", "-O2", "-c", "a.c", "-o", "a.o"]);
+        assert_eq!(
+            hash(&[
+                "cc", "-O2", "-MD", "-MF", "a.d", "-MT", "a.o", "-MQ", "a.o", "-c", "b.c", "-o",
+                "b.o"
+            ]),
+            plain
+        );
+        assert_eq!(
+            hash(&["cc", "-O2", "-MMD", "-MFb.d", "-MTb.o", "-MQb.o", "-c", "b.c", "-o", "b.o"]),
+            plain
+        );
+        assert_ne!(
+            hash(&["cc", "-O3", "-MFb.d", "-c", "b.c", "-o", "b.o"]),
+            plain
+        );
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn petgraph_conversion_round_trips() {
+        let parsed = parse(
+            "\t.globl\tmain\n\
+             \t.type\tmain, @function\n\
+             main:\n\
+             \tcall\tping\n\
+             \tcall\texternal\n\
+             \tret\n\
+             \t.size\tmain, .-main\n\
+             \t.globl\tping\n\
+             \t.type\tping, @function\n\
+             ping:\n\
+             \tcall\tpong\n\
+             \tret\n\
+             \t.size\tping, .-ping\n\
+             \t.globl\tpong\n\
+             \t.type\tpong, @function\n\
+             pong:\n\
+             \tcall\tping\n\
+             \tret\n\
+             \t.size\tpong, .-pong\n",
+        );
+        let expected = call_graph::build_call_graph(&parsed);
+        let graph = petgraph_interop::to_petgraph(&parsed);
+        assert_eq!(graph.node_count(), parsed.name_by_function_id.len());
+        assert_eq!(graph.edge_count(), 4);
+        let round_tripped = petgraph_interop::from_petgraph(graph, &parsed);
+        assert_eq!(round_tripped.adjacency, expected.adjacency);
+        assert_eq!(round_tripped.reverse_adjacency, expected.reverse_adjacency);
+    }
+
+    #[test]
+    fn functions_and_objects_can_be_iterated() {
+        let parsed = parse(SHARED_AND_EXTERNAL);
+        let shared = parsed.function_id_by_global_name("shared").unwrap();
+        let mut functions: Vec<(String, Option<usize>)> = (&parsed)
+            .into_iter()
+            .map(|(_, function, instructions_num)| {
+                (parsed.display_name(function).to_string(), instructions_num)
+            })
+            .collect();
+        functions.sort();
+        assert_eq!(functions, vec![("shared".to_owned(), Some(2))]);
+
+        let objects: Vec<_> = parsed.objects().collect();
+        assert_eq!(objects.len(), 1);
+        let (_, object, functions) = &objects[0];
+        assert_eq!(object.path, Path::new("/build/a.o"));
+        assert_eq!(**functions, [shared]);
+    }
+
+    #[test]
+    fn functions_are_looked_up_by_name() {
+        let parsed = parse_objects(&[
+            (
+                "/build/a.o",
+                "\t.globl\tapi\n\
+                 \t.type\tapi, @function\n\
+                 api:\n\
+                 \tcall\thelper\n\
+                 \tret\n\
+                 \t.size\tapi, .-api\n\
+                 \t.type\thelper, @function\n\
+                 helper:\n\
+                 \tret\n\
+                 \t.size\thelper, .-helper\n",
+            ),
+            (
+                "/build/b.o",
+                "\t.globl\tother\n\
+                 \t.type\tother, @function\n\
+                 other:\n\
+                 \tret\n\
+                 \t.size\tother, .-other\n",
+            ),
+        ]);
+        let object = |path: &str| {
+            parsed
+                .objects()
+                .find(|(_, object, _)| object.path == Path::new(path))
+                .unwrap()
+                .0
+        };
+        let api = parsed.function_id_by_global_name("api").unwrap();
+        let helper = parsed
+            .function_id_by_local_name(object("/build/a.o"), "helper")
+            .unwrap();
+        assert_eq!(parsed.function_name_str(api), Some("api"));
+        assert_eq!(parsed.function_name_str(helper), Some("helper"));
+        assert_eq!(parsed.callees_by_caller[&api], HashSet::from([helper]));
+
+        assert_eq!(parsed.function_id_by_global_name("helper"), None);
+        assert_eq!(
+            parsed.function_id_by_local_name(object("/build/b.o"), "helper"),
+            None
+        );
+        assert_eq!(
+            parsed.function_id_by_global_name("never_interned_name"),
+            None
+        );
+    }
+
+    #[test]
+    fn transitive_analyses_stop_at_the_maximum_depth() {
+        let parsed = parse(
+            "\t.globl\tframe\n\
+             \t.type\tframe, @function\n\
+             frame:\n\
+             \tcall\tupdate\n\
+             \tret\n\
+             \t.size\tframe, .-frame\n\
+             \t.globl\tupdate\n\
+             \t.type\tupdate, @function\n\
+             update:\n\
+             \tcall\tmalloc\n\
+             \tret\n\
+             \t.size\tupdate, .-update\n",
+        );
+        let call_graph = call_graph::build_call_graph(&parsed);
+        let frame = parsed.function_id_by_global_name("frame").unwrap();
+
+        let violations = alloc::check_no_alloc(&parsed, &call_graph, "frame", None).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].call_path.len(), 3);
+        assert!(
+            alloc::check_no_alloc(&parsed, &call_graph, "frame", Some(1))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(alloc::transitively_allocating(
+            &call_graph,
+            &alloc::allocator_functions(&parsed),
+            Some(1)
+        )
+        .is_truncated());
+
+        let subtree_size = subtree_instructions_num(&parsed, &call_graph, frame, Some(1));
+        assert!(subtree_size.is_truncated());
+        assert_eq!(subtree_size.into_inner(), 4);
+        let subtree_size = subtree_instructions_num(&parsed, &call_graph, frame, None);
+        assert!(!subtree_size.is_truncated());
+        assert_eq!(subtree_size.into_inner(), 4);
+        assert!(explain_function(&parsed, frame, Some(1))
+            .contains("Together with the 1 function it calls directly or indirectly"));
+    }
+
+    #[test]
+    fn sampled_subtree_rankings_are_approximate() {
+        let assembly: String = (0..=SUBTREE_SIZE_SAMPLE_NUM)
+            .map(|i| global_function(&format!("f{i}"), "\tret\n"))
+            .collect();
+        let parsed = parse(&assembly);
+        let f0 = parsed.function_id_by_global_name("f0").unwrap();
+        assert!(!explain_function(&parsed, f0, None).contains("approximately"));
+        assert!(
+            explain_function(&parsed, f0, Some(1)).contains("placing it approximately in the top")
+        );
+    }
+
+    /// A directory for files of the test that is removed before the test starts.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes an executable shell script that acts as the compiler `name` and logs every call.
+    #[cfg(unix)]
+    fn fake_compiler(dir: &Path, name: &str, script: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let compiler = dir.join(name);
+        std::fs::write(
+            &compiler,
+            format!(
+                "#!/bin/sh\n\
+                 echo \"$@\" >> calls.txt\n\
+                 while [ $# -gt 0 ]; do [ \"$1\" = -o ] && out=\"$2\"; shift; done\n\
+                 {script}"
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&compiler, std::fs::Permissions::from_mode(0o755)).unwrap();
+        compiler
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn assembly_is_read_from_the_file_when_stdout_stays_empty() {
+        let dir = test_dir("pipe-fallback");
+        // Ignores `-o -` like a compiler that can't write to stdout.
+        let compiler = fake_compiler(
+            &dir,
+            "fake-gcc",
+            "[ \"$out\" = - ] && exit 0\n\
+             printf 'main:\\n\\tret\\n' > \"$out\"\n",
+        );
+        let args = [compiler.to_str().unwrap(), "-c", "x.c", "-o", "x.o"].map(str::to_owned);
+        let mut command =
+            adapt_args_to_generate_assembly(args.to_vec(), dir.to_str().unwrap()).unwrap();
+
+        assert_eq!(get_assembly(&command, false).unwrap(), "main:\n\tret\n");
+        assert!(
+            !command.output.exists(),
+            "the temporary file should be removed"
+        );
+
+        command.pipe_output = true;
+        assert_eq!(get_assembly(&command, false).unwrap(), "");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilation_errors_in_pipe_mode_are_not_retried_with_a_file() {
+        let dir = test_dir("pipe-error");
+        let compiler = fake_compiler(
+            &dir,
+            "fake-gcc",
+            "echo 'x.c:1: error: expected ;' >&2\n\
+             exit 1\n",
+        );
+        let args = [compiler.to_str().unwrap(), "-c", "x.c", "-o", "x.o"].map(str::to_owned);
+        let command =
+            adapt_args_to_generate_assembly(args.to_vec(), dir.to_str().unwrap()).unwrap();
+
+        let err = get_assembly(&command, false).unwrap_err().to_string();
+        assert!(err.contains("expected ;"), "{}", err);
+        let calls = std::fs::read_to_string(dir.join("calls.txt")).unwrap();
+        assert_eq!(calls.lines().count(), 1, "{}", calls);
+        assert!(calls.contains("-o -"), "{}", calls);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_source_files_of_the_resolved_object_change() {
+        let function = |name: &str| global_function(name, "\tret\n");
+        let mut parsed = parse_objects(&[
+            ("/build/a.o", &function("in_a")),
+            (
+                "/build/b.o",
+                &[function("in_b"), function("without_debug_info")].concat(),
+            ),
+        ]);
+        let id = |name: &str| parsed.function_id_by_global_name(name).unwrap();
+        let (in_a, in_b, without_debug_info) = (id("in_a"), id("in_b"), id("without_debug_info"));
+        parsed
+            .source_file_by_function
+            .insert(in_a, PathBuf::from("src/a.c"));
+        parsed
+            .source_file_by_function
+            .insert(in_b, PathBuf::from("src/b.c"));
+        let b = parsed
+            .objects()
+            .find(|(_, object, _)| object.path == Path::new("/build/b.o"))
+            .unwrap()
+            .0;
+
+        parsed.resolve_source_files(b, &command("b.o"));
+
+        assert_eq!(
+            parsed.source_file_by_function[&in_b],
+            Path::new("/build/src/b.c")
+        );
+        assert_eq!(
+            parsed.source_file_by_function[&without_debug_info],
+            Path::new("/build/x.c")
+        );
+        assert_eq!(
+            parsed.source_file_by_function[&in_a],
+            Path::new("src/a.c"),
+            "a.o is resolved relative to the directory of its own command"
+        );
+    }
+
+    #[test]
+    fn object_limit_only_applies_to_compiled_objects() {
+        let dir = test_dir("object-limit");
+        let entries: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "directory": dir,
+                    "command": format!("cc -c {name}.c -o {name}.o"),
+                    "output": format!("{name}.o"),
+                })
+            })
+            .collect();
+        let compile_commands = dir.join("compile_commands.json");
+        std::fs::write(&compile_commands, serde_json::to_string(&entries).unwrap()).unwrap();
+        let args = [
+            "stats",
+            "--compile-commands",
+            compile_commands.to_str().unwrap(),
+            "--max-object-files",
+            "2",
+        ];
+        let Ok(cli::Subcommand::Stats(stats)) =
+            cli::parse_args(args.map(str::to_owned).into_iter())
+        else {
+            panic!("expected the stats subcommand");
+        };
+
+        assert_eq!(load_modified_commands(&stats.source).unwrap().len(), 3);
+        assert!(load_assembly_generation_commands(&stats.source).is_err());
+    }
+
+    #[test]
+    fn top_and_bottom_are_exclusive() {
+        let parse = |args: &[&str]| cli::parse_args(args.iter().map(|arg| arg.to_string()));
+        assert!(parse(&["report", "top-functions", "--top", "10"]).is_ok());
+        assert!(parse(&["report", "top-functions", "--bottom", "3"]).is_ok());
+        assert!(parse(&["report", "top-functions", "--top", "10", "--bottom", "3"]).is_err());
+        assert!(parse(&["report", "top-functions", "--bottom", "3", "--top", "10"]).is_err());
+    }
+
+    #[test]
+    fn only_called_functions_are_not_defined() {
+        let mut parsed = parse_objects(&[
+            (
+                "/build/a.o",
+                "\t.globl\tmain\n\
+                 \t.type\tmain, @function\n\
+                 main:\n\
+                 \tcall\thelper\n\
+                 \tcall\tmalloc\n\
+                 \tret\n\
+                 \t.size\tmain, .-main\n",
+            ),
+            (
+                "/build/b.o",
+                "\t.globl\thelper\n\
+                 \t.type\thelper, @function\n\
+                 helper:\n\
+                 \tret\n\
+                 \t.size\thelper, .-helper\n",
+            ),
+        ]);
+        let id = |name: &str| parsed.function_id_by_global_name(name).unwrap();
+
+        assert!(is_defined_function(&parsed, id("main")));
+        assert!(is_defined_function(&parsed, id("helper")));
+        assert!(!is_defined_function(&parsed, id("malloc")));
+        assert_eq!(parsed.collapse_library_callees(), 1);
+    }
+
+    #[test]
+    fn metric_labels_ignore_the_display_options() {
+        let (one, two) = ("_ZN11metrics_one1fEv", "_ZN11metrics_two1fEv");
+        let parsed = parse(&(global_function(one, "\tret\n") + &global_function(two, "\tret\n")));
+        // Printed names would be the same with these options.
+        let options = display::DisplayOptions {
+            strip_prefixes: vec!["metrics_one::".to_owned(), "metrics_two::".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(display::format_symbol(one, &options), "f()");
+        assert_eq!(display::format_symbol(two, &options), "f()");
+
+        let metrics = metrics::render_metrics(&parsed);
+
+        assert!(metrics.contains("assembly_function_instructions{name=\"metrics_one::f()\"} 1"));
+        assert!(metrics.contains("assembly_function_instructions{name=\"metrics_two::f()\"} 1"));
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn unformatted_names_of_local_functions_include_the_object_path() {
+        let local = "\t.type\thelper, @function\n\
+                     helper:\n\
+                     \tret\n\
+                     \t.size\thelper, .-helper\n";
+        let parsed = parse_objects(&[("/build/x/a.o", local), ("/build/y/a.o", local)]);
+        let names: HashSet<String> = parsed
+            .functions()
+            .map(|(_, function, _)| display::unformatted_function_name(&parsed, function))
+            .collect();
+
+        assert_eq!(
+            names,
+            HashSet::from([
+                "helper (local in /build/x/a.o)".to_owned(),
+                "helper (local in /build/y/a.o)".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn adjacency_matrix_functions_match_any_filter_regex() {
+        let function = |name: &str| global_function(name, "\tret\n");
+        let parsed = parse(
+            &["draw_mesh", "draw_curve", "free_mesh"]
+                .map(function)
+                .concat(),
+        );
+        let id = |name: &str| parsed.function_id_by_global_name(name).unwrap();
+        let args = [
+            "report",
+            "--output-adjacency-matrix",
+            "matrix.csv",
+            "--filter-regex",
+            "^draw_m",
+            "--filter-regex",
+            "curve$",
+        ];
+        let Ok(cli::Subcommand::Report(report)) =
+            cli::parse_args(args.map(str::to_owned).into_iter())
+        else {
+            panic!("expected the report subcommand");
+        };
+
+        let mut expected = vec![id("draw_mesh"), id("draw_curve")];
+        expected.sort();
+        assert_eq!(
+            adjacency_matrix_functions(&parsed, &report.filter_regex),
+            expected
+        );
+        assert_eq!(
+            adjacency_matrix_functions(&parsed, &Default::default()).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn pre_generated_assembly_is_read_from_the_assembly_dir() {
+        let dir = test_dir("assembly-dir");
+        let mut command = command("../out/sub/x.o");
+        command.use_assembly_dir(&dir).unwrap();
+        let output = dir.join("out/sub/x.o.s");
+        assert_eq!(command.output, output);
+        assert!(command
+            .args
+            .contains(&output.to_string_lossy().into_owned()));
+
+        write_assembly_compile_commands(std::slice::from_ref(&command), &dir.join("commands.json"))
+            .unwrap();
+        assert!(output.parent().unwrap().is_dir());
+
+        // Compiling would fail because /build/x.c does not exist.
+        std::fs::write(&output, "main:\n\tret\n").unwrap();
+        assert_eq!(get_assembly(&command, false).unwrap(), "main:\n\tret\n");
+        std::fs::remove_file(&output).unwrap();
+        assert!(get_assembly(&command, false).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn assembly_file_that_cannot_be_removed_is_still_read() {
+        let dir = test_dir("unremovable-assembly");
+        // Not a compiler, so the assembly is read from the output file.
+        let args = ["true", "-c", "x.c", "-o", "x.o"].map(str::to_owned);
+        let mut command =
+            adapt_args_to_generate_assembly(args.to_vec(), dir.to_str().unwrap()).unwrap();
+        // Files in /proc can be read but not removed, even by root.
+        command.output = PathBuf::from("/proc/self/stat");
+
+        assert!(!get_assembly(&command, false).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::ParsedData;
+use eyre::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+/// Serves the latest analysis results at `/metrics` in the Prometheus text format. The HTTP
+/// server runs in a background thread, so the caller can keep updating the metrics.
+pub struct MetricsServer {
+    metrics: Arc<Mutex<String>>,
+}
+
+impl MetricsServer {
+    #[cfg(feature = "prometheus")]
+    pub fn start(port: u16) -> Result<Self> {
+        let server = tiny_http::Server::http(("0.0.0.0", port))
+            .map_err(|err| eyre::eyre!("Can't serve metrics on port {}: {}", port, err))?;
+        let metrics = Arc::new(Mutex::new(String::new()));
+        let served_metrics = metrics.clone();
+        std::thread::spawn(move || {
+            let content_type = tiny_http::Header::from_bytes(
+                "Content-Type",
+                "text/plain; version=0.0.4; charset=utf-8",
+            )
+            .unwrap();
+            for request in server.incoming_requests() {
+                // Errors only mean that the client went away.
+                let _ = if request.url() == "/metrics" {
+                    let text = served_metrics.lock().unwrap().clone();
+                    request.respond(
+                        tiny_http::Response::from_string(text).with_header(content_type.clone()),
+                    )
+                } else {
+                    request.respond(tiny_http::Response::empty(404))
+                };
+            }
+        });
+        eprintln!("Serving metrics at http://localhost:{}/metrics", port);
+        Ok(MetricsServer { metrics })
+    }
+
+    #[cfg(not(feature = "prometheus"))]
+    pub fn start(_port: u16) -> Result<Self> {
+        Err(eyre::eyre!(
+            "--output-metrics-prometheus requires building with `--features prometheus`."
+        ))
+    }
+
+    pub fn update(&self, parsed: &ParsedData) {
+        crate::prepare_display(parsed);
+        *self.metrics.lock().unwrap() = render_metrics(parsed);
+    }
+}
+
+/// Quotes, backslashes and newlines have to be escaped in label values.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_gauge_header(text: &mut String, name: &str, help: &str) {
+    writeln!(text, "# HELP {} {}", name, help).unwrap();
+    writeln!(text, "# TYPE {} gauge", name).unwrap();
+}
+
+/// Local functions with the same name in different objects are summed up, because every
+/// label set may only appear once. The labels are the demangled names without the formatting
+/// options, which could make different names equal, e.g. by truncating them.
+pub fn render_metrics(parsed: &ParsedData) -> String {
+    let mut instructions_by_name: BTreeMap<String, usize> = BTreeMap::new();
+    for (_, function, instructions_num) in parsed.functions() {
+        if let Some(instructions_num) = instructions_num {
+            *instructions_by_name
+                .entry(crate::demangle::demangled(function.name()))
+                .or_default() += instructions_num;
+        }
+    }
+    let defined_functions_num = parsed
+        .functions()
+        .filter(|(_, _, instructions_num)| instructions_num.is_some())
+        .count();
+
+    let mut text = String::new();
+    write_gauge_header(
+        &mut text,
+        "assembly_function_instructions",
+        "Number of instructions of the largest copy of a function.",
+    );
+    for (name, instructions_num) in &instructions_by_name {
+        writeln!(
+            text,
+            "assembly_function_instructions{{name=\"{}\"}} {}",
+            escape_label_value(name),
+            instructions_num
+        )
+        .unwrap();
+    }
+    write_gauge_header(
+        &mut text,
+        "assembly_total_instructions",
+        "Number of instructions of all functions.",
+    );
+    writeln!(
+        text,
+        "assembly_total_instructions {}",
+        instructions_by_name.values().sum::<usize>()
+    )
+    .unwrap();
+    write_gauge_header(
+        &mut text,
+        "assembly_function_count",
+        "Number of defined functions.",
+    );
+    writeln!(text, "assembly_function_count {}", defined_functions_num).unwrap();
+    write_gauge_header(
+        &mut text,
+        "assembly_object_count",
+        "Number of analyzed objects.",
+    );
+    writeln!(
+        text,
+        "assembly_object_count {}",
+        parsed.name_by_object_id.len()
+    )
+    .unwrap();
+    text
+}
@@ -0,0 +1,35 @@
+use eyre::Result;
+use regex::Regex;
+
+/// Regex substitutions that are applied in order to demangled function names before two
+/// builds are compared, e.g. to remove symbol hashes that change between builds.
+#[derive(Default, Clone)]
+pub struct NameNormalization {
+    replacements: Vec<(Regex, String)>,
+}
+
+impl NameNormalization {
+    /// The replacement can refer to groups of the pattern with `$1` or `${name}`.
+    pub fn add(&mut self, pattern: &str, replacement: &str) -> Result<()> {
+        let regex = Regex::new(pattern)
+            .map_err(|err| eyre::eyre!("Invalid pattern {}: {}", pattern, err))?;
+        self.replacements.push((regex, replacement.to_owned()));
+        Ok(())
+    }
+
+    pub fn extend(&mut self, other: &NameNormalization) {
+        self.replacements.extend(other.replacements.iter().cloned());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+
+    pub fn apply(&self, name: &str) -> String {
+        let mut name = name.to_owned();
+        for (regex, replacement) in self.replacements.iter() {
+            name = regex.replace_all(&name, replacement.as_str()).into_owned();
+        }
+        name
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{
+    FunctionID, FunctionName, LinkType, LocalFunctionName, ObjectID, ObjectName, ParsedData,
+};
+use eyre::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Symbol of a function as listed by `objdump --syms`.
+struct FunctionSymbol {
+    link_type: LinkType,
+    /// Size in bytes.
+    size: u64,
+}
+
+fn run_objdump(objdump: &Path, args: &[&str], binary_path: &Path) -> Result<String> {
+    let output = Command::new(objdump)
+        .args(args)
+        .arg(binary_path)
+        .output()
+        .map_err(|err| eyre::eyre!("Can't run {}: {}", objdump.display(), err))?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "{} failed: {}",
+            objdump.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Parses lines like `0000000000001139 g     F .text  000000000000000b  main`. The flag
+/// characters have fixed positions, so the line is split after them.
+fn parse_function_symbols(symbols: &str) -> HashMap<&str, FunctionSymbol> {
+    let mut symbol_by_name = HashMap::new();
+    for line in symbols.lines() {
+        let Some((_address, rest)) = line.split_once(' ') else {
+            continue;
+        };
+        if rest.len() < 8 || !rest.is_char_boundary(7) {
+            continue;
+        }
+        let (flags, rest) = rest.split_at(7);
+        if flags.as_bytes()[6] != b'F' {
+            continue;
+        }
+        let mut fields = rest.split_whitespace();
+        let (Some(section), Some(size), Some(name)) = (fields.next(), fields.next(), fields.last())
+        else {
+            continue;
+        };
+        // Undefined functions are only called, they are not part of the binary.
+        if section == "*UND*" {
+            continue;
+        }
+        let Ok(size) = u64::from_str_radix(size, 16) else {
+            continue;
+        };
+        symbol_by_name.insert(
+            name,
+            FunctionSymbol {
+                link_type: match flags.as_bytes() {
+                    [b'l', ..] => LinkType::Local,
+                    [_, b'w', ..] => LinkType::Weak,
+                    _ => LinkType::Global,
+                },
+                size,
+            },
+        );
+    }
+    symbol_by_name
+}
+
+/// Extracts the symbol name from a branch target like `1030 <printf@plt>`, without the `@plt`
+/// or symbol version suffix. Targets with an offset point into the middle of a function and
+/// are returned as `None`.
+fn branch_target(operands: &str) -> Option<&str> {
+    let target = operands.split_once('<')?.1.strip_suffix('>')?;
+    if target.contains('+') {
+        return None;
+    }
+    target.split('@').next()
+}
+
+/// Returns the address of jumps within the function, like `jne 1150 <main+0x17>`.
+fn local_jump_target(operands: &str, function: &str) -> Option<u64> {
+    let (address, target) = operands.split_once(" <")?;
+    target.strip_prefix(function)?.strip_prefix('+')?;
+    u64::from_str_radix(address.trim(), 16).ok()
+}
+
+fn function_name(
+    name: &str,
+    object: ObjectID,
+    symbol_by_name: &HashMap<&str, FunctionSymbol>,
+) -> FunctionName {
+    match symbol_by_name.get(name) {
+        Some(symbol) if symbol.link_type == LinkType::Local => {
+            FunctionName::Local(LocalFunctionName {
+                object,
+                name: internment::ArcIntern::from(name),
+            })
+        }
+        _ => FunctionName::global(name),
+    }
+}
+
+fn add_call(
+    parsed: &mut ParsedData,
+    caller: FunctionID,
+    callee: &str,
+    object: ObjectID,
+    symbol_by_name: &HashMap<&str, FunctionSymbol>,
+) {
+    let callee_id = parsed.get_or_add_function(function_name(callee, object, symbol_by_name));
+    *parsed
+        .call_sites_by_caller
+        .entry(caller)
+        .or_default()
+        .entry(callee_id)
+        .or_default() += 1;
+    parsed
+        .callees_by_caller
+        .entry(caller)
+        .or_default()
+        .insert(callee_id);
+    parsed
+        .callers_by_callee
+        .entry(callee_id)
+        .or_default()
+        .insert(caller);
+}
+
+/// Adds the functions of `objdump -d` output to the object. Only functions that have a
+/// symbol in `symbol_by_name` are included.
+fn parse_disassembly(
+    disassembly: &str,
+    symbol_by_name: &HashMap<&str, FunctionSymbol>,
+    object: ObjectID,
+    parsed: &mut ParsedData,
+) {
+    let mut current_function: Option<(FunctionID, &str)> = None;
+    for line in disassembly.lines() {
+        if line.starts_with("Disassembly of section") {
+            current_function = None;
+            continue;
+        }
+        // Function headers look like `0000000000001139 <main>:`.
+        if let Some(name) = line
+            .split_once(" <")
+            .and_then(|(_, rest)| rest.strip_suffix(">:"))
+        {
+            // PLT stubs and other symbols without a function symbol are skipped.
+            current_function = symbol_by_name.get(name).map(|_| {
+                let function_id =
+                    parsed.get_or_add_function(function_name(name, object, symbol_by_name));
+                crate::insert_sorted(
+                    parsed.functions_by_object.entry(object).or_default(),
+                    function_id,
+                );
+                parsed
+                    .objects_by_function
+                    .entry(function_id)
+                    .or_default()
+                    .insert(object);
+                parsed
+                    .instructions_by_object_by_function
+                    .entry(function_id)
+                    .or_default()
+                    .entry(object)
+                    .or_insert(0);
+                parsed
+                    .link_type_by_function
+                    .insert(function_id, symbol_by_name[name].link_type);
+                (function_id, name)
+            });
+            continue;
+        }
+        let Some((function_id, function)) = current_function else {
+            continue;
+        };
+        // Instruction lines look like `    1139:\tcall   1030 <printf@plt>`.
+        let Some((address, instruction)) = line.split_once(":\t") else {
+            continue;
+        };
+        let instruction = instruction.trim();
+        if instruction.is_empty() || instruction.starts_with("(bad)") {
+            continue;
+        }
+        let (mnemonic, operands) = instruction
+            .split_once(char::is_whitespace)
+            .unwrap_or((instruction, ""));
+        *parsed
+            .instructions_by_object_by_function
+            .entry(function_id)
+            .or_default()
+            .entry(object)
+            .or_default() += 1;
+        *parsed
+            .weighted_instructions_by_object_by_function
+            .entry(function_id)
+            .or_default()
+            .entry(object)
+            .or_default() += crate::x86_throughput_weight(mnemonic);
+        if crate::has_immediate_operand(instruction) {
+            *parsed
+                .instructions_with_immediate_by_function
+                .entry(function_id)
+                .or_default() += 1;
+        }
+        if crate::accesses_thread_local_storage(instruction) {
+            parsed.uses_tls.insert(function_id);
+        }
+        if let Some(extension) = crate::simd_extension(instruction) {
+            let newest = parsed
+                .simd_extension_by_function
+                .entry(function_id)
+                .or_insert(extension);
+            *newest = (*newest).max(extension);
+        }
+        *parsed
+            .return_count_by_function
+            .entry(function_id)
+            .or_default() += crate::is_return_instruction(instruction) as usize;
+        if crate::is_indirect_call(instruction) {
+            *parsed
+                .indirect_call_count_by_function
+                .entry(function_id)
+                .or_default() += 1;
+        }
+        match (mnemonic, branch_target(operands)) {
+            ("call" | "callq", Some(callee)) => {
+                add_call(parsed, function_id, callee, object, symbol_by_name);
+            }
+            ("jmp" | "jmpq", Some(target)) if target == function => {
+                parsed.tail_recursive_functions.insert(function_id);
+            }
+            // Jumps to other functions are tail calls, the cold part stays within the function.
+            ("jmp" | "jmpq", Some(target)) if !crate::is_cold_part_of(target, function) => {
+                add_call(parsed, function_id, target, object, symbol_by_name);
+            }
+            _ if mnemonic.starts_with('j') => {
+                let Some(target) = local_jump_target(operands, function) else {
+                    continue;
+                };
+                let Ok(address) = u64::from_str_radix(address.trim(), 16) else {
+                    continue;
+                };
+                let counts = if target <= address {
+                    &mut parsed.loop_count_by_function
+                } else {
+                    &mut parsed.branch_count_by_function
+                };
+                *counts.entry(function_id).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Analyzes a linked binary instead of individual objects, so that the effects of LTO,
+/// linker garbage collection and COMDAT deduplication are included. The whole binary is a
+/// single object. Names are kept mangled like in the assembly of objects, they are
+/// demangled for display.
+pub fn analyze_elf_binary(binary_path: &Path, objdump: &Path) -> Result<ParsedData> {
+    let symbols = run_objdump(objdump, &["--syms", "--wide"], binary_path)?;
+    let symbol_by_name = parse_function_symbols(&symbols);
+    let disassembly = run_objdump(
+        objdump,
+        &["-d", "--no-show-raw-insn", "--wide"],
+        binary_path,
+    )?;
+
+    let mut parsed = ParsedData::default();
+    let object = parsed.get_or_add_object(ObjectName::from_path(binary_path)?);
+    parse_disassembly(&disassembly, &symbol_by_name, object, &mut parsed);
+
+    let function_ids: Vec<FunctionID> = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .copied()
+        .collect();
+    crate::update_immediate_density(&mut parsed, function_ids.iter());
+    let bytes_num: u64 = symbol_by_name.values().map(|symbol| symbol.size).sum();
+    eprintln!(
+        "Disassembled {} functions with {} bytes of code.",
+        function_ids.len(),
+        bytes_num
+    );
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const SYMBOLS: &str = "\
+SYMBOL TABLE:
+0000000000000000 l    df *ABS*\t0000000000000000 crtstuff.c
+0000000000001130 l     F .text\t0000000000000001 helper
+0000000000001139 g     F .text\t0000000000000019 main
+0000000000001160 g     F .text\t0000000000000001 other
+0000000000001170 l     F .text.unlikely\t0000000000000001 main.cold
+0000000000001180  w    F .text\t0000000000000010 weak_function
+0000000000000000       F *UND*\t0000000000000000 printf@GLIBC_2.2.5
+0000000000004010 g     O .data\t0000000000000004 global_variable
+";
+
+    #[test]
+    fn function_symbols_are_parsed() {
+        let symbol_by_name = parse_function_symbols(SYMBOLS);
+        let mut names: Vec<&str> = symbol_by_name.keys().copied().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["helper", "main", "main.cold", "other", "weak_function"]
+        );
+        assert_eq!(symbol_by_name["helper"].link_type, LinkType::Local);
+        assert_eq!(symbol_by_name["main"].link_type, LinkType::Global);
+        assert_eq!(symbol_by_name["main"].size, 0x19);
+        assert_eq!(symbol_by_name["weak_function"].link_type, LinkType::Weak);
+    }
+
+    #[test]
+    fn branch_targets_are_parsed() {
+        assert_eq!(branch_target("1030 <printf@plt>"), Some("printf"));
+        assert_eq!(branch_target("1130 <helper>"), Some("helper"));
+        assert_eq!(branch_target("1150 <main+0x17>"), None);
+        assert_eq!(branch_target("*%rax"), None);
+
+        assert_eq!(local_jump_target("1150 <main+0x17>", "main"), Some(0x1150));
+        assert_eq!(local_jump_target("1139 <main>", "main"), None);
+        assert_eq!(local_jump_target("1163 <main_loop+0x3>", "main"), None);
+        assert_eq!(local_jump_target("1030 <printf@plt>", "main"), None);
+    }
+
+    #[test]
+    fn jumps_to_other_functions_are_tail_calls() {
+        let disassembly = "\
+Disassembly of section .text:
+
+0000000000001130 <helper>:
+    1130:\tret
+
+0000000000001139 <main>:
+    1139:\tpush   %rbp
+    113a:\tcall   1130 <helper>
+    113f:\tcall   1030 <printf@plt>
+    1144:\tjne    1150 <main+0x17>
+    1146:\tjmp    1139 <main>
+    1148:\tjmp    1160 <other>
+    114d:\tjmp    1170 <main.cold>
+    1150:\tjmp    113a <main+0x1>
+
+0000000000001160 <other>:
+    1160:\tret
+";
+        let symbol_by_name = parse_function_symbols(SYMBOLS);
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: "/build/app".into(),
+        });
+        parse_disassembly(disassembly, &symbol_by_name, object, &mut parsed);
+
+        let main = parsed.function_id_by_global_name("main").unwrap();
+        let helper = parsed.function_id_by_local_name(object, "helper").unwrap();
+        let printf = parsed.function_id_by_global_name("printf").unwrap();
+        let other = parsed.function_id_by_global_name("other").unwrap();
+        assert_eq!(
+            parsed.callees_by_caller[&main],
+            HashSet::from([helper, printf, other])
+        );
+        assert!(parsed.tail_recursive_functions.contains(&main));
+        assert_eq!(parsed.loop_count_by_function[&main], 1);
+        assert_eq!(parsed.branch_count_by_function[&main], 1);
+        assert_eq!(parsed.instructions_by_object_by_function[&main][&object], 8);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{DisplayFunctionName, FunctionName, ObjectName};
+use eyre::Result;
+use serde::Serialize;
+use std::io::Write;
+
+/// A single item of an analysis result.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record<'a> {
+    Function {
+        #[serde(skip)]
+        function: DisplayFunctionName<'a>,
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        link_type: Option<crate::LinkType>,
+        instruction_count: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        weighted_instructions: Option<f64>,
+        objects: Vec<&'a std::path::Path>,
+    },
+    Object {
+        #[serde(skip)]
+        object: &'a ObjectName,
+        path: &'a std::path::Path,
+        function_count: usize,
+        instruction_count: usize,
+    },
+    Call {
+        #[serde(skip)]
+        caller_function: DisplayFunctionName<'a>,
+        #[serde(skip)]
+        callee_function: DisplayFunctionName<'a>,
+        caller: &'a str,
+        callee: &'a str,
+    },
+}
+
+pub trait Formatter {
+    fn write_record(&mut self, record: &Record) -> Result<()>;
+}
+
+/// Human readable output.
+pub struct TextFormatter<W: Write> {
+    writer: W,
+    last_caller: Option<FunctionName>,
+}
+
+impl<W: Write> TextFormatter<W> {
+    pub fn new(writer: W) -> Self {
+        TextFormatter {
+            writer,
+            last_caller: None,
+        }
+    }
+}
+
+impl<W: Write> Formatter for TextFormatter<W> {
+    fn write_record(&mut self, record: &Record) -> Result<()> {
+        match record {
+            Record::Function {
+                function,
+                instruction_count,
+                weighted_instructions,
+                ..
+            } => match weighted_instructions {
+                Some(weighted) => writeln!(
+                    self.writer,
+                    "{}: {} (~{:.1} cycles)",
+                    function, instruction_count, weighted
+                )?,
+                None => writeln!(self.writer, "{}: {}", function, instruction_count)?,
+            },
+            Record::Object {
+                object,
+                function_count,
+                instruction_count,
+                ..
+            } => writeln!(
+                self.writer,
+                "{}: {} functions, {} instructions",
+                object, function_count, instruction_count
+            )?,
+            Record::Call {
+                caller_function,
+                callee_function,
+                ..
+            } => {
+                // Consecutive calls from the same caller are grouped below it.
+                if self.last_caller.as_ref() != Some(caller_function.name) {
+                    writeln!(self.writer, "{}", caller_function)?;
+                    self.last_caller = Some(caller_function.name.clone());
+                }
+                writeln!(self.writer, "  -> {}", callee_function)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Newline delimited JSON, i.e. one JSON object per line. Newlines within strings are
+/// escaped by the JSON serializer, so every line can be processed on its own.
+pub struct NdjsonFormatter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonFormatter<W> {
+    pub fn new(writer: W) -> Self {
+        NdjsonFormatter { writer }
+    }
+}
+
+impl<W: Write> Formatter for NdjsonFormatter<W> {
+    fn write_record(&mut self, record: &Record) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        writeln!(self.writer)?;
+        Ok(())
+    }
+}
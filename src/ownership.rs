@@ -0,0 +1,69 @@
+use crate::{ParsedData, Ranking};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Owner of functions whose source file is unknown or not tracked by git.
+const UNKNOWN_OWNER: &str = "[unknown]";
+
+/// Email of the author who changed the file last. Git runs in the directory of the file, so
+/// that files of different repositories, e.g. submodules, are supported.
+fn last_author(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["log", "--follow", "--format=%ae", "-1", "--"])
+        .arg(path)
+        .current_dir(path.parent()?)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let author = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    (!author.is_empty()).then_some(author)
+}
+
+/// Runs git once per source file, files that git does not know are left out.
+pub fn owner_by_source_file(parsed: &ParsedData) -> HashMap<PathBuf, String> {
+    let source_files: BTreeSet<&PathBuf> = parsed.source_file_by_function.values().collect();
+    source_files
+        .into_iter()
+        .filter_map(|path| Some((path.clone(), last_author(path)?)))
+        .collect()
+}
+
+/// Groups the instructions of all functions by the last author of their source file, largest
+/// share first.
+pub fn print_code_ownership(parsed: &ParsedData, ranking: &Ranking) {
+    let owner_by_source_file = owner_by_source_file(parsed);
+    // Pairs of (instructions, functions).
+    let mut totals_by_owner: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (function_id, _, instructions_num) in parsed.functions() {
+        let Some(instructions_num) = instructions_num else {
+            continue;
+        };
+        let owner = parsed
+            .source_file_by_function
+            .get(&function_id)
+            .and_then(|path| owner_by_source_file.get(path))
+            .map_or(UNKNOWN_OWNER, String::as_str);
+        let totals = totals_by_owner.entry(owner).or_default();
+        totals.0 += instructions_num;
+        totals.1 += 1;
+    }
+    let total_instructions: usize = totals_by_owner.values().map(|(num, _)| num).sum();
+    let mut owners: Vec<(&str, (usize, usize))> = totals_by_owner.into_iter().collect();
+    owners.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(b.0)));
+    for (owner, (instructions_num, functions_num)) in
+        ranking.apply(owners, |(_, (instructions_num, _))| *instructions_num)
+    {
+        println!(
+            "{} owns {:.1}% of total instructions ({} instructions in {} functions)",
+            owner,
+            instructions_num as f64 * 100.0 / total_instructions.max(1) as f64,
+            instructions_num,
+            functions_num
+        );
+    }
+}
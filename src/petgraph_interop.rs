@@ -0,0 +1,55 @@
+use crate::call_graph::CallGraph;
+use crate::{CallEdgeKind, FunctionID, ParsedData};
+use std::collections::HashMap;
+
+/// Builds a petgraph graph with one node per function, so that its algorithms like PageRank
+/// can be used. Every function is stored as node weight, virtual calls to every candidate
+/// callee are included as separate edge kind.
+pub fn to_petgraph(parsed: &ParsedData) -> petgraph::Graph<FunctionID, CallEdgeKind> {
+    let mut function_ids: Vec<FunctionID> = parsed.name_by_function_id.keys().copied().collect();
+    function_ids.sort();
+    let mut graph = petgraph::Graph::with_capacity(function_ids.len(), 0);
+    let node_by_function: HashMap<FunctionID, petgraph::graph::NodeIndex> = function_ids
+        .iter()
+        .map(|function_id| (*function_id, graph.add_node(*function_id)))
+        .collect();
+    for caller in function_ids.iter() {
+        for (callee, kind) in crate::callees_with_kind(parsed, *caller) {
+            if let Some(callee_node) = node_by_function.get(&callee) {
+                graph.add_edge(node_by_function[caller], *callee_node, kind);
+            }
+        }
+    }
+    graph
+}
+
+/// Converts a graph that was built with `to_petgraph`, and possibly modified, back so that
+/// the traversals of `CallGraph` can be used on it. Nodes are identified by their weight.
+pub fn from_petgraph(
+    graph: petgraph::Graph<FunctionID, CallEdgeKind>,
+    parsed: &ParsedData,
+) -> CallGraph {
+    let nodes_num = parsed
+        .name_by_function_id
+        .keys()
+        .chain(graph.node_weights())
+        .map(|function_id| function_id.0 + 1)
+        .max()
+        .unwrap_or_default();
+    let mut adjacency = vec![vec![]; nodes_num];
+    let mut reverse_adjacency = vec![vec![]; nodes_num];
+    for edge in graph.raw_edges() {
+        let caller = graph[edge.source()];
+        let callee = graph[edge.target()];
+        adjacency[caller.0].push(callee);
+        reverse_adjacency[callee.0].push(caller);
+    }
+    for neighbors in adjacency.iter_mut().chain(reverse_adjacency.iter_mut()) {
+        neighbors.sort_by_key(|function_id| function_id.0);
+        neighbors.dedup();
+    }
+    CallGraph {
+        adjacency,
+        reverse_adjacency,
+    }
+}
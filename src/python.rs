@@ -0,0 +1,159 @@
+// The code generated by `#[pyfunction]` converts the error of `PyResult` into itself.
+#![allow(clippy::useless_conversion)]
+
+use crate::demangle;
+use crate::display::unformatted_function_name;
+use crate::filter::PatternMatch;
+use crate::{FunctionName, ObjectName, ParseOptions, ParsedData};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::Path;
+
+/// Parsed data of an assembly file, exposed to Python as `ParsedData`.
+#[pyclass(name = "ParsedData")]
+struct PyParsedData {
+    parsed: ParsedData,
+}
+
+fn to_py_err(err: eyre::Report) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// Parses an assembly file that has been generated with `-S`, e.g. `foo.s`.
+#[pyfunction]
+fn parse_assembly_file(path: &str) -> PyResult<PyParsedData> {
+    let path = Path::new(path);
+    let assembly = std::fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("Can't read {}: {}", path.display(), err))
+        .map_err(to_py_err)?;
+    let mut parsed = ParsedData::default();
+    let object = parsed.get_or_add_object(ObjectName::from_path(path).map_err(to_py_err)?);
+    crate::parse_data(object, &assembly, ParseOptions::default(), &mut parsed);
+    crate::prepare_display(&parsed);
+    Ok(PyParsedData { parsed })
+}
+
+#[pymethods]
+impl PyParsedData {
+    /// One dict per function, so that it can be passed to `pandas.DataFrame` directly.
+    fn functions<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let parsed = &self.parsed;
+        let mut functions: Vec<(&FunctionName, usize)> = parsed
+            .functions()
+            .filter_map(|(_, function, instructions_num)| Some((function, instructions_num?)))
+            .collect();
+        functions.sort_by_key(|(function, _)| function.name());
+        functions
+            .into_iter()
+            .map(|(function, instructions_num)| {
+                let function_id = parsed.function_id_by_name[function];
+                let dict = PyDict::new_bound(py);
+                dict.set_item("name", unformatted_function_name(parsed, function))?;
+                dict.set_item("mangled_name", function.name())?;
+                dict.set_item("instructions", instructions_num)?;
+                dict.set_item(
+                    "callees",
+                    parsed
+                        .callees_by_caller
+                        .get(&function_id)
+                        .map_or(0, |c| c.len()),
+                )?;
+                dict.set_item(
+                    "callers",
+                    parsed
+                        .callers_by_callee
+                        .get(&function_id)
+                        .map_or(0, |c| c.len()),
+                )?;
+                dict.set_item(
+                    "loops",
+                    parsed
+                        .loop_count_by_function
+                        .get(&function_id)
+                        .copied()
+                        .unwrap_or_default(),
+                )?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// Pairs of (caller, callee) names, including calls to functions that are not defined.
+    fn call_edges(&self) -> Vec<(String, String)> {
+        let parsed = &self.parsed;
+        let mut edges: Vec<(String, String)> = parsed
+            .callees_by_caller
+            .iter()
+            .flat_map(|(caller, callees)| {
+                callees.iter().map(move |callee| {
+                    (
+                        unformatted_function_name(parsed, &parsed.name_by_function_id[caller]),
+                        unformatted_function_name(parsed, &parsed.name_by_function_id[callee]),
+                    )
+                })
+            })
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    /// The name has to match the mangled or demangled name, with or without parameters.
+    /// Counts the largest copy of a function that is defined in multiple objects. Local
+    /// functions with the same name in different objects are summed up. Raises `ValueError`
+    /// if the name matches different functions, like overloads when the parameters are
+    /// omitted. Returns `None` if no defined function has this name.
+    fn instruction_count(&self, name: &str) -> PyResult<Option<usize>> {
+        let parsed = &self.parsed;
+        let pattern = crate::filter::NamePattern::new(name);
+        let matches: Vec<(&FunctionName, PatternMatch, usize)> = parsed
+            .functions()
+            .filter_map(|(_, function, instructions_num)| {
+                Some((function, pattern.matches(function)?, instructions_num?))
+            })
+            .filter(|(_, kind, _)| *kind <= PatternMatch::Demangled)
+            .collect();
+        let Some(best_kind) = matches.iter().map(|(_, kind, _)| *kind).min() else {
+            return Ok(None);
+        };
+        let matches: Vec<(&FunctionName, usize)> = matches
+            .into_iter()
+            .filter(|(_, kind, _)| *kind == best_kind)
+            .map(|(function, _, instructions_num)| (function, instructions_num))
+            .collect();
+        let mut mangled_names: Vec<&str> = matches
+            .iter()
+            .map(|(function, _)| function.name())
+            .collect();
+        mangled_names.sort();
+        mangled_names.dedup();
+        if mangled_names.len() > 1 {
+            let mut message = format!(
+                "{} matches {} functions, pass one of their mangled names:",
+                name,
+                mangled_names.len()
+            );
+            for mangled_name in mangled_names {
+                message += &format!(
+                    "\n  {}: {}",
+                    mangled_name,
+                    demangle::demangled(mangled_name)
+                );
+            }
+            return Err(PyValueError::new_err(message));
+        }
+        Ok(Some(
+            matches
+                .iter()
+                .map(|(_, instructions_num)| instructions_num)
+                .sum(),
+        ))
+    }
+}
+
+#[pymodule]
+fn assembly_analysis(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyParsedData>()?;
+    module.add_function(wrap_pyfunction!(parse_assembly_file, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use crate::{ParsedData, ParsedDataDiff};
+use eyre::Result;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Maximum length of a GitHub comment.
+const MAX_REPORT_CARD_CHARS: usize = 65536;
+/// Number of rows of every table.
+const REPORT_CARD_ROWS_NUM: usize = 10;
+
+/// Function names are shown as code, pipes would still end the table cell.
+fn table_cell(name: &str) -> String {
+    format!("`{}`", name.replace('|', "\\|"))
+}
+
+/// Writes a Markdown summary that can be posted as comment on a pull request. With a diff,
+/// the changed functions and regressions are listed as well.
+pub fn write_report_card(
+    parsed: &ParsedData,
+    diff: Option<&ParsedDataDiff>,
+    path: &Path,
+) -> Result<()> {
+    let mut card = String::new();
+    writeln!(card, "## Code Size Analysis")?;
+    writeln!(card)?;
+
+    writeln!(card, "### Summary")?;
+    writeln!(card)?;
+    writeln!(card, "| | |")?;
+    writeln!(card, "|---|---:|")?;
+    // Functions are compared by their largest copy, see `diff_parsed_data`.
+    let total_num: usize = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .map(|function_id| crate::function_instructions_num(parsed, *function_id))
+        .sum();
+    match diff {
+        Some(diff) => {
+            let before_total: usize = diff.changed.iter().map(|(_, num, _)| num).sum::<usize>()
+                + diff.removed.iter().map(|(_, num)| num).sum::<usize>()
+                + diff.unchanged.iter().map(|(_, num)| num).sum::<usize>();
+            writeln!(
+                card,
+                "| Total instructions | {} ({:+}) |",
+                total_num,
+                total_num as i64 - before_total as i64
+            )?;
+        }
+        None => writeln!(card, "| Total instructions | {} |", total_num)?,
+    }
+    writeln!(card, "| Objects | {} |", parsed.name_by_object_id.len())?;
+    writeln!(
+        card,
+        "| Functions | {} |",
+        parsed.instructions_by_object_by_function.len()
+    )?;
+    let largest = parsed
+        .instructions_by_object_by_function
+        .keys()
+        .map(|function_id| {
+            (
+                function_id,
+                crate::function_instructions_num(parsed, *function_id),
+            )
+        })
+        .max_by_key(|(function_id, num)| (*num, std::cmp::Reverse(**function_id)));
+    if let Some((function_id, num)) = largest {
+        let function = parsed.display(*function_id).to_string();
+        writeln!(
+            card,
+            "| Largest function | {} ({} instructions) |",
+            table_cell(&function),
+            num
+        )?;
+    }
+
+    if let Some(diff) = diff {
+        writeln!(card)?;
+        writeln!(card, "### Top Changed Functions")?;
+        writeln!(card)?;
+        if diff.changed.is_empty() {
+            writeln!(card, "No function changed.")?;
+        } else {
+            writeln!(card, "| Function | Before | After | Delta |")?;
+            writeln!(card, "|---|---:|---:|---:|")?;
+            for (name, before_num, after_num) in diff.changed.iter().take(REPORT_CARD_ROWS_NUM) {
+                writeln!(
+                    card,
+                    "| {} | {} | {} | {:+} |",
+                    table_cell(&name.to_string()),
+                    before_num,
+                    after_num,
+                    *after_num as i64 - *before_num as i64
+                )?;
+            }
+            if diff.changed.len() > REPORT_CARD_ROWS_NUM {
+                writeln!(
+                    card,
+                    "\n... and {} more changed functions.",
+                    diff.changed.len() - REPORT_CARD_ROWS_NUM
+                )?;
+            }
+        }
+
+        writeln!(card)?;
+        writeln!(card, "### Notable Changes")?;
+        writeln!(card)?;
+        // Grown and added functions, with the largest growth first.
+        let mut regressions: Vec<(String, Option<usize>, usize)> = diff
+            .changed
+            .iter()
+            .filter(|(_, before_num, after_num)| after_num > before_num)
+            .map(|(name, before_num, after_num)| (name.to_string(), Some(*before_num), *after_num))
+            .chain(
+                diff.added
+                    .iter()
+                    .map(|(name, num)| (name.to_string(), None, *num)),
+            )
+            .collect();
+        regressions.sort_by_key(|(_, before_num, after_num)| {
+            std::cmp::Reverse(after_num - before_num.unwrap_or_default())
+        });
+        if regressions.is_empty() {
+            writeln!(card, "No regressions.")?;
+        }
+        for (name, before_num, after_num) in regressions.iter().take(REPORT_CARD_ROWS_NUM) {
+            match before_num {
+                Some(before_num) => writeln!(
+                    card,
+                    "- {} grew by {} instructions ({} -> {})",
+                    table_cell(name),
+                    after_num - before_num,
+                    before_num,
+                    after_num
+                )?,
+                None => writeln!(
+                    card,
+                    "- {} was added with {} instructions",
+                    table_cell(name),
+                    after_num
+                )?,
+            }
+        }
+        if regressions.len() > REPORT_CARD_ROWS_NUM {
+            writeln!(
+                card,
+                "- ... and {} more regressions",
+                regressions.len() - REPORT_CARD_ROWS_NUM
+            )?;
+        }
+    }
+
+    // Extremely long names can still exceed the limit, so whole lines are cut off.
+    if card.len() > MAX_REPORT_CARD_CHARS {
+        const TRUNCATION_NOTE: &str = "\n*The report was truncated.*\n";
+        let mut end = MAX_REPORT_CARD_CHARS - TRUNCATION_NOTE.len();
+        while !card.is_char_boundary(end) {
+            end -= 1;
+        }
+        end = card[..end].rfind('\n').map_or(0, |newline| newline + 1);
+        card.truncate(end);
+        card.push_str(TRUNCATION_NOTE);
+    }
+    std::fs::write(path, card)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ObjectName, ParseOptions};
+
+    #[test]
+    fn long_report_cards_are_truncated_at_a_line() {
+        let name = format!("f{}", "ä".repeat(MAX_REPORT_CARD_CHARS / 2));
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: "/build/a.o".into(),
+        });
+        let assembly = format!(
+            "\t.globl\t{name}\n\
+             \t.type\t{name}, @function\n\
+             {name}:\n\
+             \tret\n\
+             \t.size\t{name}, .-{name}\n"
+        );
+        crate::parse_data(object, &assembly, ParseOptions::default(), &mut parsed);
+        let path = std::env::temp_dir().join(format!("report-card-{}.md", std::process::id()));
+
+        write_report_card(&parsed, None, &path).unwrap();
+
+        let card = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(card.len() <= MAX_REPORT_CARD_CHARS, "{}", card.len());
+        assert!(card.ends_with("| Functions | 1 |\n\n*The report was truncated.*\n"));
+        assert!(!card.contains("Largest function"));
+    }
+}
@@ -0,0 +1,430 @@
+use crate::{FunctionName, LinkType, LocalFunctionName, ObjectID, ObjectName, ParsedData};
+use eyre::Result;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const BITCODE_MAGIC: &[u8] = b"BC\xC0\xDE";
+
+/// Splits a Unix `ar` archive like an rlib into its (name, data) members. Supports the GNU
+/// long name table that is used for names longer than 15 bytes.
+fn archive_members(archive: &[u8]) -> Result<Vec<(String, &[u8])>> {
+    let mut rest = archive
+        .strip_prefix(b"!<arch>\n")
+        .ok_or(eyre::eyre!("Not an ar archive."))?;
+    let mut long_names: &[u8] = &[];
+    let mut members = vec![];
+    while rest.len() >= 60 {
+        let (header, data) = rest.split_at(60);
+        let raw_name = String::from_utf8_lossy(&header[..16]).trim_end().to_owned();
+        let size: usize = std::str::from_utf8(&header[48..58])?.trim().parse()?;
+        let member = data
+            .get(..size)
+            .ok_or(eyre::eyre!("Truncated archive member {}.", raw_name))?;
+        // Members are aligned to two bytes.
+        rest = data.get(size + size % 2..).unwrap_or_default();
+        if raw_name == "//" {
+            long_names = member;
+            continue;
+        }
+        // The symbol table is "/" and "/SYM64/".
+        if raw_name == "/" || raw_name == "/SYM64/" {
+            continue;
+        }
+        let name = match raw_name.strip_prefix('/').map(str::parse::<usize>) {
+            Some(Ok(offset)) => {
+                let name = long_names.get(offset..).unwrap_or_default();
+                let end = name.iter().position(|&c| c == b'\n').unwrap_or(name.len());
+                String::from_utf8_lossy(&name[..end]).to_string()
+            }
+            _ => raw_name,
+        };
+        members.push((name.trim_end_matches('/').to_owned(), member));
+    }
+    Ok(members)
+}
+
+/// Returns the `.llvmbc` section of a 64-bit little-endian ELF object, which rustc fills with
+/// `-C embed-bitcode=yes`. Offsets that point outside of the object are an error.
+fn elf_bitcode_section(object: &[u8]) -> Result<Option<&[u8]>> {
+    if !object.starts_with(b"\x7fELF\x02\x01") {
+        return Ok(None);
+    }
+    let malformed = || eyre::eyre!("Malformed ELF object.");
+    let bytes = |offset: usize, size: usize| {
+        let end = offset.checked_add(size).ok_or_else(malformed)?;
+        object.get(offset..end).ok_or_else(malformed)
+    };
+    // Little-endian integer of `size` bytes at `field` in the structure at `offset`.
+    let read = |offset: usize, field: usize, size: usize| -> Result<usize> {
+        let value = bytes(offset.checked_add(field).ok_or_else(malformed)?, size)?;
+        Ok(value
+            .iter()
+            .rev()
+            .fold(0, |value, byte| value << 8 | *byte as usize))
+    };
+    let section_headers = read(0, 0x28, 8)?;
+    let header_size = read(0, 0x3a, 2)?;
+    let header_offset = |index: usize| {
+        index
+            .checked_mul(header_size)
+            .and_then(|offset| offset.checked_add(section_headers))
+            .ok_or_else(malformed)
+    };
+    let names_offset = read(header_offset(read(0, 0x3e, 2)?)?, 0x18, 8)?;
+    for index in 0..read(0, 0x3c, 2)? {
+        let header = header_offset(index)?;
+        let name_offset = names_offset
+            .checked_add(read(header, 0, 4)?)
+            .ok_or_else(malformed)?;
+        let name = object.get(name_offset..).ok_or_else(malformed)?;
+        if name.starts_with(b".llvmbc\0") {
+            return Ok(Some(bytes(read(header, 0x18, 8)?, read(header, 0x20, 8)?)?));
+        }
+    }
+    Ok(None)
+}
+
+/// Turns bitcode into the textual IR.
+fn disassemble_bitcode(bitcode: &[u8], llvm_dis: &Path) -> Result<String> {
+    let mut child = Command::new(llvm_dis)
+        .args(["-o", "-", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| eyre::eyre!("Can't run {}: {}", llvm_dis.display(), err))?;
+    // Written from another thread, so that a full stdout pipe can't block llvm-dis.
+    let mut stdin = child.stdin.take().unwrap();
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || stdin.write_all(bitcode));
+        child.wait_with_output()
+    })?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "{} failed, it might be older than the LLVM of rustc: {}",
+            llvm_dis.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Reads a global name like `@foo` or `@"_ZN3foo$LT$T$GT$3bar17h0123456789abcdefE"` at the
+/// start of the text, without the `@`. Returns the name and the text after it.
+fn global_name(text: &str) -> Option<(String, &str)> {
+    let text = text.strip_prefix('@')?;
+    if let Some(quoted) = text.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        // Other bytes are escaped as `\xx` in hex.
+        let mut name = vec![];
+        let mut bytes = quoted[..end].bytes();
+        while let Some(byte) = bytes.next() {
+            if byte == b'\\' {
+                let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                name.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            } else {
+                name.push(byte);
+            }
+        }
+        return Some((String::from_utf8(name).ok()?, &quoted[end + 1..]));
+    }
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '$' | '.' | '_')))
+        .unwrap_or(text.len());
+    (end > 0).then(|| (text[..end].to_owned(), &text[end..]))
+}
+
+/// Name and linkage of the function that a line like `define internal fastcc void @foo() {`
+/// starts.
+fn define_name(line: &str) -> Option<(String, LinkType)> {
+    let define = line.strip_prefix("define ")?;
+    let (name, _) = global_name(&define[define.find('@')?..])?;
+    let link_type = match define.split_whitespace().next() {
+        // Copies of functions from other crates that are only kept for inlining.
+        Some("available_externally") => return None,
+        Some("internal" | "private") => LinkType::Local,
+        Some("weak" | "weak_odr" | "linkonce" | "linkonce_odr") => LinkType::Weak,
+        _ => LinkType::Global,
+    };
+    Some((name, link_type))
+}
+
+/// The function that a `call` or `invoke` instruction calls directly, i.e. the first global
+/// that is followed by the argument list. Intrinsics like `@llvm.memcpy.p0.p0.i64` are not
+/// functions of the program and are skipped.
+fn direct_callee(instruction: &str) -> Option<String> {
+    let (_, mut rest) = instruction
+        .split_once("call ")
+        .or_else(|| instruction.split_once("invoke "))?;
+    while let Some(start) = rest.find('@') {
+        let (name, after) = global_name(&rest[start..])?;
+        if after.starts_with('(') {
+            return (!name.starts_with("llvm.")).then_some(name);
+        }
+        rest = after;
+    }
+    None
+}
+
+/// Parses the textual LLVM IR of one codegen unit. Instructions are counted in IR, so they
+/// are only comparable between functions that are analyzed the same way.
+pub fn parse_llvm_ir(object: ObjectID, ir: &str, parsed: &mut ParsedData) {
+    // Internal functions can be called before they are defined.
+    let local_names: HashSet<String> = ir
+        .lines()
+        .filter_map(define_name)
+        .filter(|(_, link_type)| *link_type == LinkType::Local)
+        .map(|(name, _)| name)
+        .collect();
+    let function_name = |name: &str| {
+        if local_names.contains(name) {
+            FunctionName::Local(LocalFunctionName {
+                object,
+                name: internment::ArcIntern::from(name),
+            })
+        } else {
+            FunctionName::global(name)
+        }
+    };
+
+    let mut current_function = None;
+    for line in ir.lines() {
+        if let Some((name, link_type)) = define_name(line) {
+            let function_id = parsed.get_or_add_function(function_name(&name));
+            parsed.link_type_by_function.insert(function_id, link_type);
+            crate::insert_sorted(
+                parsed.functions_by_object.entry(object).or_default(),
+                function_id,
+            );
+            parsed
+                .objects_by_function
+                .entry(function_id)
+                .or_default()
+                .insert(object);
+            parsed
+                .instructions_by_object_by_function
+                .entry(function_id)
+                .or_default()
+                .insert(object, 0);
+            current_function = Some(function_id);
+            continue;
+        }
+        let Some(function_id) = current_function else {
+            continue;
+        };
+        if line == "}" {
+            current_function = None;
+            continue;
+        }
+        // Skips labels like `bb3:  ; preds = %start`, comments and the second line of
+        // `invoke`, which lists the labels it continues at.
+        let trimmed_line = line.trim();
+        let is_label = trimmed_line
+            .split(';')
+            .next()
+            .is_some_and(|code| code.trim_end().ends_with(':'));
+        if trimmed_line.is_empty()
+            || trimmed_line.starts_with(';')
+            || trimmed_line.starts_with("to label")
+            || is_label
+        {
+            continue;
+        }
+        *parsed
+            .instructions_by_object_by_function
+            .get_mut(&function_id)
+            .unwrap()
+            .get_mut(&object)
+            .unwrap() += 1;
+        let Some(callee) = direct_callee(trimmed_line) else {
+            continue;
+        };
+        let callee_id = parsed.get_or_add_function(function_name(&callee));
+        *parsed
+            .call_sites_by_caller
+            .entry(function_id)
+            .or_default()
+            .entry(callee_id)
+            .or_default() += 1;
+        parsed
+            .callees_by_caller
+            .entry(function_id)
+            .or_default()
+            .insert(callee_id);
+        parsed
+            .callers_by_callee
+            .entry(callee_id)
+            .or_default()
+            .insert(function_id);
+    }
+}
+
+/// The rlib that belongs to the path. Cargo writes `lib<name>-<hash>.rmeta` next to the
+/// `.rlib`, but only the rlib contains code.
+fn rlib_path(path: &Path) -> Result<PathBuf> {
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "rmeta")
+    {
+        let rlib = path.with_extension("rlib");
+        if !rlib.exists() {
+            return Err(eyre::eyre!(
+                "{} only contains crate metadata and there is no {} next to it.",
+                path.display(),
+                rlib.display()
+            ));
+        }
+        return Ok(rlib);
+    }
+    Ok(path.to_owned())
+}
+
+/// Analyzes the LLVM bitcode that is embedded in the codegen units of a Rust rlib, without
+/// compiling anything. Every codegen unit is an object named `<rlib>/<member>`. The
+/// bitcode only exists when the crate is built with `-C embed-bitcode=yes`, which Cargo
+/// disables unless LTO is enabled in the profile, or with `-C linker-plugin-lto`.
+pub fn analyze_rlib(path: &Path, llvm_dis: &Path) -> Result<ParsedData> {
+    let rlib = rlib_path(path)?;
+    let archive = std::fs::read(&rlib)
+        .map_err(|err| eyre::eyre!("Can't read {}: {}", rlib.display(), err))?;
+    let members =
+        archive_members(&archive).map_err(|err| eyre::eyre!("{}: {}", rlib.display(), err))?;
+    let mut parsed = ParsedData::default();
+    for (member_name, member) in members {
+        let bitcode = if member.starts_with(BITCODE_MAGIC) {
+            member
+        } else {
+            let section = elf_bitcode_section(member)
+                .map_err(|err| eyre::eyre!("{}({}): {}", rlib.display(), member_name, err))?;
+            match section {
+                Some(bitcode) if bitcode.starts_with(BITCODE_MAGIC) => bitcode,
+                _ => continue,
+            }
+        };
+        let ir = disassemble_bitcode(bitcode, llvm_dis)
+            .map_err(|err| eyre::eyre!("{}({}): {}", rlib.display(), member_name, err))?;
+        let object = parsed.get_or_add_object(ObjectName::from_path(&rlib.join(&member_name))?);
+        parse_llvm_ir(object, &ir, &mut parsed);
+    }
+    if parsed.name_by_object_id.is_empty() {
+        return Err(eyre::eyre!(
+            "{} contains no LLVM bitcode. Build it with `-C embed-bitcode=yes`, e.g. by enabling \
+             `lto` in the Cargo profile, or with `-C linker-plugin-lto`.",
+            rlib.display()
+        ));
+    }
+    eprintln!(
+        "Parsed {} functions with {} IR instructions in {} codegen units.",
+        parsed.instructions_by_object_by_function.len(),
+        crate::all_instructions_num(&parsed),
+        parsed.name_by_object_id.len()
+    );
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ELF header followed by the section headers of a null section, `.llvmbc` and the
+    /// section names, and then the contents of the sections.
+    fn elf_with_bitcode(bitcode: &[u8]) -> Vec<u8> {
+        let names = b"\0.llvmbc\0.shstrtab\0";
+        let headers_offset = 64;
+        let names_offset = headers_offset + 3 * 64;
+        let bitcode_offset = names_offset + names.len();
+        let mut object = vec![0u8; bitcode_offset];
+        object[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        object[0x28..0x30].copy_from_slice(&(headers_offset as u64).to_le_bytes());
+        object[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        object[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+        object[0x3e..0x40].copy_from_slice(&2u16.to_le_bytes());
+        let mut section = |index: usize, name: u32, offset: usize, size: usize| {
+            let header = headers_offset + index * 64;
+            object[header..header + 4].copy_from_slice(&name.to_le_bytes());
+            object[header + 0x18..header + 0x20].copy_from_slice(&(offset as u64).to_le_bytes());
+            object[header + 0x20..header + 0x28].copy_from_slice(&(size as u64).to_le_bytes());
+        };
+        section(1, 1, bitcode_offset, bitcode.len());
+        section(2, 9, names_offset, names.len());
+        object[names_offset..].copy_from_slice(names);
+        object.extend_from_slice(bitcode);
+        object
+    }
+
+    #[test]
+    fn bitcode_section_is_found() {
+        let object = elf_with_bitcode(b"BC\xC0\xDEdata");
+        assert_eq!(
+            elf_bitcode_section(&object).unwrap(),
+            Some(&b"BC\xC0\xDEdata"[..])
+        );
+        assert_eq!(elf_bitcode_section(b"!<arch>\n").unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_objects_are_errors() {
+        let object = elf_with_bitcode(b"BC\xC0\xDEdata");
+        for len in [10, 0x3c, 100, object.len() - 1] {
+            assert!(elf_bitcode_section(&object[..len]).is_err(), "{len}");
+        }
+        // Offsets and sizes close to the maximum would overflow.
+        let mut corrupt = object.clone();
+        corrupt[0x28..0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(elf_bitcode_section(&corrupt).is_err());
+        let mut corrupt = object.clone();
+        let bitcode_header = 64 + 64;
+        corrupt[bitcode_header + 0x20..bitcode_header + 0x28]
+            .copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(elf_bitcode_section(&corrupt).is_err());
+        let mut corrupt = object;
+        corrupt[bitcode_header..bitcode_header + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(elf_bitcode_section(&corrupt).is_err());
+    }
+
+    #[test]
+    fn llvm_ir_calls_resolve_internal_functions_of_the_module() {
+        let ir = "define void @\"_ZN3foo$LT$T$GT$3bar17h0123456789abcdefE\"() {\n\
+                  start:\n\
+                  \x20 call void @helper(ptr @callback)\n\
+                  \x20 call void @llvm.donothing()\n\
+                  \x20 %1 = invoke i32 @external(i32 1)\n\
+                  \x20         to label %bb1 unwind label %cleanup\n\
+                  bb1:                                  ; preds = %start\n\
+                  \x20 ret void\n\
+                  }\n\
+                  \n\
+                  define internal fastcc void @helper(ptr %0) {\n\
+                  \x20 ret void\n\
+                  }\n\
+                  \n\
+                  define available_externally void @inlined() {\n\
+                  \x20 call void @helper(ptr null)\n\
+                  \x20 ret void\n\
+                  }\n\
+                  \n\
+                  declare void @llvm.donothing()\n";
+        let mut parsed = ParsedData::default();
+        let object = ObjectID(0);
+        parse_llvm_ir(object, ir, &mut parsed);
+
+        let global = FunctionName::global;
+        let bar = parsed.function_id_by_name[&global("_ZN3foo$LT$T$GT$3bar17h0123456789abcdefE")];
+        let helper = parsed.function_id_by_name[&FunctionName::Local(LocalFunctionName {
+            object,
+            name: internment::ArcIntern::from("helper"),
+        })];
+        let external = parsed.function_id_by_name[&global("external")];
+        assert_eq!(
+            parsed.callees_by_caller[&bar],
+            HashSet::from([helper, external])
+        );
+        assert_eq!(crate::function_instructions_num(&parsed, bar), 4);
+        assert!(!parsed.function_id_by_name.contains_key(&global("inlined")));
+        assert!(!parsed
+            .function_id_by_name
+            .contains_key(&global("llvm.donothing")));
+    }
+}
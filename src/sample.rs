@@ -0,0 +1,221 @@
+use crate::{AssemblyGenerationCommand, ObjectName, ParsedData};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Two-sided 95% quantile of the normal distribution.
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// Objects of one directory, of which some were sampled.
+pub struct Stratum {
+    pub objects_num: usize,
+    pub sampled: Vec<ObjectName>,
+}
+
+/// Randomly selects n commands. Every directory contributes proportionally to its number of
+/// objects, so that large directories are not over- or underrepresented by chance. The same
+/// seed selects the same commands.
+pub fn sample_commands(
+    commands: Vec<AssemblyGenerationCommand>,
+    n: usize,
+    seed: Option<u64>,
+) -> (Vec<AssemblyGenerationCommand>, Vec<Stratum>) {
+    let mut rng = match seed {
+        Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+        None => rand::rngs::StdRng::from_entropy(),
+    };
+    let total_num = commands.len();
+    let n = n.min(total_num);
+    let mut commands_by_directory: BTreeMap<PathBuf, Vec<AssemblyGenerationCommand>> =
+        BTreeMap::new();
+    for command in commands {
+        let object_path = std::path::Path::new(&command.cwd).join(&command.object);
+        let directory = object_path.parent().map(PathBuf::from).unwrap_or_default();
+        commands_by_directory
+            .entry(directory)
+            .or_default()
+            .push(command);
+    }
+
+    // Largest remainder method, so that the sample sizes add up to n.
+    let mut sample_sizes: Vec<(usize, f64)> = commands_by_directory
+        .values()
+        .map(|commands| {
+            let share = (n * commands.len()) as f64 / total_num.max(1) as f64;
+            (share.floor() as usize, share.fract())
+        })
+        .collect();
+    let remaining_num = n - sample_sizes.iter().map(|(size, _)| size).sum::<usize>();
+    let mut by_remainder: Vec<usize> = (0..sample_sizes.len()).collect();
+    by_remainder.sort_by(|a, b| sample_sizes[*b].1.total_cmp(&sample_sizes[*a].1));
+    for i in by_remainder.into_iter().take(remaining_num) {
+        sample_sizes[i].0 += 1;
+    }
+
+    let mut sampled_commands = vec![];
+    let mut strata = vec![];
+    for (commands, (size, _)) in commands_by_directory.into_values().zip(sample_sizes) {
+        let sampled: Vec<AssemblyGenerationCommand> =
+            commands.choose_multiple(&mut rng, size).cloned().collect();
+        strata.push(Stratum {
+            objects_num: commands.len(),
+            sampled: sampled
+                .iter()
+                .filter_map(|command| command.object_name().ok())
+                .collect(),
+        });
+        sampled_commands.extend(sampled);
+    }
+    (sampled_commands, strata)
+}
+
+fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+    let mean = values.iter().sum::<f64>() / values.len().max(1) as f64;
+    let variance = if values.len() > 1 {
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+    } else {
+        0.0
+    };
+    (mean, variance)
+}
+
+/// Extrapolates the total instruction count of all objects from the sampled ones with the
+/// stratified estimator. `values_by_stratum` contains the instruction counts of the sampled
+/// objects of every stratum. Directories with fewer than two samples use the mean and variance
+/// of the whole sample. Returns the estimate and the margin of the 95% confidence interval, or
+/// `None` without any samples.
+fn estimate_total(strata: &[Stratum], values_by_stratum: &[Vec<f64>]) -> Option<(f64, f64)> {
+    let all_values: Vec<f64> = values_by_stratum.iter().flatten().copied().collect();
+    let sampled_num = all_values.len();
+    if sampled_num == 0 {
+        return None;
+    }
+    let (overall_mean, overall_variance) = mean_and_variance(&all_values);
+
+    let mut estimate = 0.0;
+    let mut estimate_variance = 0.0;
+    for (stratum, values) in strata.iter().zip(values_by_stratum.iter()) {
+        let objects_num = stratum.objects_num as f64;
+        let (mean, variance, sampled_num) = match values.len() {
+            0 => (overall_mean, overall_variance, sampled_num),
+            1 => (values[0], overall_variance, 1),
+            _ => {
+                let (mean, variance) = mean_and_variance(values);
+                (mean, variance, values.len())
+            }
+        };
+        // Finite population correction, fully analyzed directories have no uncertainty.
+        let unsampled_fraction = 1.0 - (values.len() as f64 / objects_num).min(1.0);
+        estimate += objects_num * mean;
+        estimate_variance +=
+            objects_num.powi(2) * unsampled_fraction * variance / sampled_num as f64;
+    }
+    Some((estimate, CONFIDENCE_Z * estimate_variance.sqrt()))
+}
+
+/// Prints the total instruction count of all objects as estimated from the sampled ones.
+pub fn print_total_estimate(parsed: &ParsedData, strata: &[Stratum]) {
+    let instructions_num = |object: &ObjectName| -> f64 {
+        let Some(object_id) = parsed.object_id_by_name.get(object) else {
+            return 0.0;
+        };
+        parsed
+            .functions_by_object
+            .get(object_id)
+            .into_iter()
+            .flatten()
+            .map(|function_id| crate::instructions_in_object(parsed, *function_id, *object_id))
+            .sum::<usize>() as f64
+    };
+    let values_by_stratum: Vec<Vec<f64>> = strata
+        .iter()
+        .map(|stratum| stratum.sampled.iter().map(instructions_num).collect())
+        .collect();
+    let Some((estimate, margin)) = estimate_total(strata, &values_by_stratum) else {
+        return;
+    };
+    let sampled_num: usize = values_by_stratum.iter().map(Vec::len).sum();
+    let total_num: usize = strata.iter().map(|stratum| stratum.objects_num).sum();
+    eprintln!(
+        "Warning: only {} of {} objects were analyzed, all results are approximate.",
+        sampled_num, total_num
+    );
+    println!(
+        "Estimated total instructions: {:.0} ± {:.0} (95% confidence, {} directories)",
+        estimate,
+        margin,
+        strata.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Commands for the given number of objects in every directory.
+    fn commands(objects_num_by_directory: &[usize]) -> Vec<AssemblyGenerationCommand> {
+        objects_num_by_directory
+            .iter()
+            .enumerate()
+            .flat_map(|(directory_i, objects_num)| {
+                (0..*objects_num).map(move |object_i| {
+                    let object = format!("dir{directory_i}/{object_i}.o");
+                    let args = ["cc", "-c", "x.c", "-o", &object].map(str::to_owned);
+                    crate::adapt_args_to_generate_assembly(args.to_vec(), "/build").unwrap()
+                })
+            })
+            .collect()
+    }
+
+    fn sample_sizes(strata: &[Stratum]) -> Vec<usize> {
+        strata.iter().map(|stratum| stratum.sampled.len()).collect()
+    }
+
+    #[test]
+    fn sample_sizes_are_proportional_to_the_directories() {
+        // The shares are 2.0, 1.2 and 0.8, the remaining sample goes to the largest remainder.
+        let (sampled, strata) = sample_commands(commands(&[5, 3, 2]), 4, Some(1));
+        assert_eq!(sampled.len(), 4);
+        assert_eq!(sample_sizes(&strata), [2, 1, 1]);
+
+        for n in 0..=12 {
+            let (sampled, strata) = sample_commands(commands(&[7, 1, 3, 1]), n, Some(1));
+            assert_eq!(sampled.len(), n.min(12));
+            assert_eq!(sample_sizes(&strata).iter().sum::<usize>(), n.min(12));
+            for stratum in &strata {
+                assert!(stratum.sampled.len() <= stratum.objects_num);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_selects_the_same_commands() {
+        let objects = |seed: u64| -> Vec<String> {
+            let (sampled, _) = sample_commands(commands(&[20, 20]), 10, Some(seed));
+            sampled.into_iter().map(|command| command.object).collect()
+        };
+        assert_eq!(objects(7), objects(7));
+        assert_ne!(objects(7), objects(8));
+    }
+
+    #[test]
+    fn total_is_estimated_per_stratum() {
+        let stratum = |objects_num: usize| Stratum {
+            objects_num,
+            sampled: vec![],
+        };
+        assert_eq!(estimate_total(&[stratum(3)], &[vec![]]), None);
+        // Fully sampled directories are known exactly.
+        assert_eq!(
+            estimate_total(&[stratum(2)], &[vec![1.0, 3.0]]),
+            Some((4.0, 0.0))
+        );
+        // The second directory has a single sample and uses the variance of all samples,
+        // which is 4. The variance of the estimate is 10² * 0.8 * 2 / 2 + 4² * 0.75 * 4 / 1.
+        let (estimate, margin) =
+            estimate_total(&[stratum(10), stratum(4)], &[vec![1.0, 3.0], vec![5.0]]).unwrap();
+        assert_eq!(estimate, 40.0);
+        assert!((margin - CONFIDENCE_Z * 128f64.sqrt()).abs() < 1e-9);
+    }
+}
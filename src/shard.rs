@@ -0,0 +1,246 @@
+use crate::{FunctionID, FunctionName, LocalFunctionName, ObjectID, ParsedData};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Combines the parsed data of shards that analyzed disjoint sets of objects. Every shard
+/// allocates its ids independently, so they are mapped to the ids of the merged data first.
+pub fn merge_shards(shards: Vec<ParsedData>) -> ParsedData {
+    let mut merged = ParsedData::default();
+    for shard in shards {
+        let object_map: HashMap<ObjectID, ObjectID> = shard
+            .name_by_object_id
+            .iter()
+            .map(|(object_id, object)| (*object_id, merged.get_or_add_object(object.clone())))
+            .collect();
+        let function_map: HashMap<FunctionID, FunctionID> = shard
+            .name_by_function_id
+            .iter()
+            .map(|(function_id, function)| {
+                let function = remap_function_name(function, &object_map);
+                (*function_id, merged.get_or_add_function(function))
+            })
+            .collect();
+        let shard = remap_ids(shard, &object_map, &function_map);
+        crate::watch::merge_parsed_data(&mut merged, &shard);
+    }
+    merged
+}
+
+fn remap_function_name(
+    function: &FunctionName,
+    object_map: &HashMap<ObjectID, ObjectID>,
+) -> FunctionName {
+    match function {
+        FunctionName::Global(_) => function.clone(),
+        FunctionName::Local(local) => FunctionName::Local(LocalFunctionName {
+            name: local.name.clone(),
+            object: object_map[&local.object],
+        }),
+    }
+}
+
+/// Replaces all object and function ids in the data. The lookup maps and id allocators are
+/// not filled, the result is only meant to be merged into the data the ids belong to.
+fn remap_ids(
+    data: ParsedData,
+    object_map: &HashMap<ObjectID, ObjectID>,
+    function_map: &HashMap<FunctionID, FunctionID>,
+) -> ParsedData {
+    let function = |function_id: FunctionID| function_map[&function_id];
+    let object = |object_id: ObjectID| object_map[&object_id];
+    let functions = |functions: HashSet<FunctionID>| -> HashSet<FunctionID> {
+        functions.into_iter().map(function).collect()
+    };
+    fn remap_keys<K: Eq + Hash, V>(map: HashMap<K, V>, key: impl Fn(K) -> K) -> HashMap<K, V> {
+        map.into_iter().map(|(k, v)| (key(k), v)).collect()
+    }
+
+    ParsedData {
+        name_by_object_id: remap_keys(data.name_by_object_id, object),
+        name_by_function_id: data
+            .name_by_function_id
+            .into_iter()
+            .map(|(function_id, name)| {
+                (
+                    function(function_id),
+                    remap_function_name(&name, object_map),
+                )
+            })
+            .collect(),
+        functions_by_object: data
+            .functions_by_object
+            .into_iter()
+            .map(|(object_id, function_ids)| {
+                let mut function_ids: Vec<FunctionID> =
+                    function_ids.into_iter().map(function).collect();
+                function_ids.sort();
+                (object(object_id), function_ids)
+            })
+            .collect(),
+        objects_by_function: data
+            .objects_by_function
+            .into_iter()
+            .map(|(function_id, object_ids)| {
+                (
+                    function(function_id),
+                    object_ids.into_iter().map(object).collect(),
+                )
+            })
+            .collect(),
+        callers_by_callee: data
+            .callers_by_callee
+            .into_iter()
+            .map(|(callee, callers)| (function(callee), functions(callers)))
+            .collect(),
+        callees_by_caller: data
+            .callees_by_caller
+            .into_iter()
+            .map(|(caller, callees)| (function(caller), functions(callees)))
+            .collect(),
+        call_sites_by_caller: data
+            .call_sites_by_caller
+            .into_iter()
+            .map(|(caller, call_sites)| (function(caller), remap_keys(call_sites, function)))
+            .collect(),
+        instructions_by_object_by_function: data
+            .instructions_by_object_by_function
+            .into_iter()
+            .map(|(function_id, nums)| (function(function_id), remap_keys(nums, object)))
+            .collect(),
+        instructions_with_immediate_by_function: remap_keys(
+            data.instructions_with_immediate_by_function,
+            function,
+        ),
+        immediate_density_by_function: remap_keys(data.immediate_density_by_function, function),
+        tail_recursive_functions: functions(data.tail_recursive_functions),
+        has_unwind_personality: functions(data.has_unwind_personality),
+        function_pointer_referenced: functions(data.function_pointer_referenced),
+        windows_abi_functions: functions(data.windows_abi_functions),
+        uses_tls: functions(data.uses_tls),
+        simd_extension_by_function: remap_keys(data.simd_extension_by_function, function),
+        lto_discarded: functions(data.lto_discarded),
+        lto_private: functions(data.lto_private),
+        possibly_deoptimized: functions(data.possibly_deoptimized),
+        fully_inlined_functions: functions(data.fully_inlined_functions),
+        deprecated_functions: remap_keys(data.deprecated_functions, function),
+        link_type_by_function: remap_keys(data.link_type_by_function, function),
+        vtable_entries: data
+            .vtable_entries
+            .into_iter()
+            .map(|(vtable, entries)| {
+                (
+                    vtable,
+                    entries
+                        .into_iter()
+                        .map(|entry| entry.map(function))
+                        .collect(),
+                )
+            })
+            .collect(),
+        virtual_call_slots_by_function: remap_keys(data.virtual_call_slots_by_function, function),
+        saved_registers_by_function: remap_keys(data.saved_registers_by_function, function),
+        frame_size_by_function: remap_keys(data.frame_size_by_function, function),
+        source_file_by_function: remap_keys(data.source_file_by_function, function),
+        alignment_overhead_by_function: remap_keys(data.alignment_overhead_by_function, function),
+        estimated_padding_by_function: remap_keys(data.estimated_padding_by_function, function),
+        string_literal_refs_by_function: remap_keys(data.string_literal_refs_by_function, function),
+        loop_count_by_function: remap_keys(data.loop_count_by_function, function),
+        branch_count_by_function: remap_keys(data.branch_count_by_function, function),
+        return_count_by_function: remap_keys(data.return_count_by_function, function),
+        inline_memcpy_count_by_function: remap_keys(data.inline_memcpy_count_by_function, function),
+        inline_memset_count_by_function: remap_keys(data.inline_memset_count_by_function, function),
+        indirect_call_count_by_function: remap_keys(data.indirect_call_count_by_function, function),
+        weighted_instructions_by_object_by_function: data
+            .weighted_instructions_by_object_by_function
+            .into_iter()
+            .map(|(function_id, weights)| (function(function_id), remap_keys(weights, object)))
+            .collect(),
+        raw_assembly_by_function: remap_keys(data.raw_assembly_by_function, function),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ObjectName, ParseOptions};
+    use std::path::PathBuf;
+
+    fn shard(path: &str, assembly: &str) -> (ParsedData, ObjectID) {
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: PathBuf::from(path),
+        });
+        crate::parse_data(object, assembly, ParseOptions::default(), &mut parsed);
+        (parsed, object)
+    }
+
+    #[test]
+    fn shards_with_overlapping_ids_are_merged() {
+        let (a, a_object) = shard(
+            "/build/a.o",
+            "\t.type\thelper, @function\n\
+             helper:\n\
+             \tret\n\
+             \t.size\thelper, .-helper\n\
+             \t.globl\tshared\n\
+             \t.type\tshared, @function\n\
+             shared:\n\
+             \tcall\thelper\n\
+             \tret\n\
+             \t.size\tshared, .-shared\n",
+        );
+        let (b, b_object) = shard(
+            "/build/b.o",
+            "\t.type\thelper, @function\n\
+             helper:\n\
+             \tnop\n\
+             \tret\n\
+             \t.size\thelper, .-helper\n\
+             \t.globl\tuser\n\
+             \t.type\tuser, @function\n\
+             user:\n\
+             \tcall\thelper\n\
+             \tcall\tshared\n\
+             \tret\n\
+             \t.size\tuser, .-user\n",
+        );
+        assert_eq!(a_object, b_object);
+        assert_eq!(
+            a.function_id_by_local_name(a_object, "helper"),
+            b.function_id_by_local_name(b_object, "helper")
+        );
+
+        let merged = merge_shards(vec![a, b]);
+        assert_eq!(
+            crate::validate::validate_parsed_data(&merged),
+            Vec::<String>::new()
+        );
+        assert_eq!(merged.name_by_object_id.len(), 2);
+
+        let object = |path: &str| {
+            merged.object_id_by_name[&ObjectName {
+                path: PathBuf::from(path),
+            }]
+        };
+        let a_helper = merged
+            .function_id_by_local_name(object("/build/a.o"), "helper")
+            .unwrap();
+        let b_helper = merged
+            .function_id_by_local_name(object("/build/b.o"), "helper")
+            .unwrap();
+        let shared = merged.function_id_by_global_name("shared").unwrap();
+        let user = merged.function_id_by_global_name("user").unwrap();
+        assert_ne!(a_helper, b_helper);
+        assert_eq!(merged.callees_by_caller[&shared], HashSet::from([a_helper]));
+        assert_eq!(
+            merged.callees_by_caller[&user],
+            HashSet::from([b_helper, shared])
+        );
+        assert_eq!(merged.callers_by_callee[&shared], HashSet::from([user]));
+        assert_eq!(
+            merged.instructions_by_object_by_function[&b_helper][&object("/build/b.o")],
+            2
+        );
+    }
+}
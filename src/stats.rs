@@ -0,0 +1,256 @@
+use crate::{AssemblyGenerationCommand, ParseOptions, ParsedData};
+use eyre::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Rough number of source lines that are compiled to assembly and parsed per second. Only
+/// used when no file is benchmarked, so the estimate is an order of magnitude at best.
+const ASSUMED_LINES_PER_SECOND: f64 = 2000.0;
+
+/// Flags that only select what the compiler generates and are the same for every command.
+fn is_output_flag(arg: &str) -> bool {
+    matches!(arg, "-o" | "-c" | "-S")
+}
+
+/// Dependency tracking flags name a different file for every command. The second value is
+/// whether the flag takes a value, which is either the next argument or joined to the flag
+/// like in `-MFfoo.d`.
+const DEPENDENCY_FLAGS: &[(&str, bool)] = &[
+    ("-MD", false),
+    ("-MMD", false),
+    ("-MP", false),
+    ("-MF", true),
+    ("-MT", true),
+    ("-MQ", true),
+];
+
+/// Extensions of the source files that are passed to the compiler.
+const SOURCE_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx", "c++", "m", "mm"];
+
+/// The arguments of the command without the source and output files and without dependency
+/// tracking, i.e. the flags that should be the same for objects compiled the same way.
+fn compile_flags(command: &AssemblyGenerationCommand) -> Vec<&str> {
+    let mut flags = vec![];
+    let mut args = command.args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            args.next();
+            continue;
+        }
+        // The source file is removed below, it doesn't always follow `-c`, e.g. with
+        // `-c -o foo.o foo.c`.
+        if arg == "-c" || arg == "-S" {
+            continue;
+        }
+        if let Some((_, has_value)) = DEPENDENCY_FLAGS.iter().find(|(flag, _)| arg == *flag) {
+            if *has_value {
+                args.next();
+            }
+            continue;
+        }
+        if DEPENDENCY_FLAGS
+            .iter()
+            .any(|(flag, has_value)| *has_value && arg.starts_with(flag))
+        {
+            continue;
+        }
+        let is_source = !arg.starts_with('-')
+            && std::path::Path::new(arg)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension));
+        if !is_source {
+            flags.push(arg);
+        }
+    }
+    flags
+}
+
+/// Objects with the same hash are compiled with the same flags.
+pub fn hash_compile_flags(command: &AssemblyGenerationCommand) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    compile_flags(command).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Groups the objects by their compiler flags. Flags are compared to the largest group.
+/// Objects whose flags differ from all others, e.g. because of a missing `-D` or a different
+/// `-I`, are often misconfigured.
+pub fn print_flag_groups(commands: &[AssemblyGenerationCommand]) {
+    let mut commands_by_hash: BTreeMap<u64, Vec<&AssemblyGenerationCommand>> = BTreeMap::new();
+    for command in commands {
+        commands_by_hash
+            .entry(hash_compile_flags(command))
+            .or_default()
+            .push(command);
+    }
+    let mut groups: Vec<(u64, Vec<&AssemblyGenerationCommand>)> =
+        commands_by_hash.into_iter().collect();
+    groups.sort_by_key(|(_, commands)| std::cmp::Reverse(commands.len()));
+    let Some((_, largest_group)) = groups.first() else {
+        return;
+    };
+    let common_flags: HashSet<&str> = compile_flags(largest_group[0]).into_iter().collect();
+
+    println!("Flag groups:");
+    for (i, (hash, group)) in groups.iter().enumerate() {
+        let objects = if group.len() == 1 {
+            "object"
+        } else {
+            "objects"
+        };
+        print!("  {:016x}: {} {}", hash, group.len(), objects);
+        if i > 0 {
+            let flags = compile_flags(group[0]);
+            let added: Vec<&str> = flags
+                .iter()
+                .filter(|flag| !common_flags.contains(*flag))
+                .copied()
+                .collect();
+            let flags: HashSet<&str> = flags.into_iter().collect();
+            let mut missing: Vec<&str> = common_flags
+                .iter()
+                .filter(|flag| !flags.contains(*flag))
+                .copied()
+                .collect();
+            missing.sort();
+            if !added.is_empty() {
+                print!(", additional flags: {}", added.join(" "));
+            }
+            if !missing.is_empty() {
+                print!(", missing flags: {}", missing.join(" "));
+            }
+        }
+        println!();
+    }
+    if groups.len() < 2 {
+        return;
+    }
+    let unique: Vec<&str> = groups
+        .iter()
+        .filter(|(_, group)| group.len() == 1)
+        .map(|(_, group)| group[0].object.as_str())
+        .collect();
+    if !unique.is_empty() {
+        println!("Objects with unique flags, which might be misconfigured:");
+        for object in unique {
+            println!("  {}", object);
+        }
+    }
+}
+
+/// Prints numbers about the size of the project that can be computed from the compile
+/// commands alone, without compiling anything.
+pub fn print_stats(
+    commands: &[AssemblyGenerationCommand],
+    options: ParseOptions,
+    verbose_errors: bool,
+    estimate_time: bool,
+) -> Result<()> {
+    let mut lines_by_source: HashMap<PathBuf, usize> = HashMap::new();
+    let mut unreadable_sources: HashSet<PathBuf> = HashSet::new();
+    for source in commands.iter().filter_map(|command| command.source_file()) {
+        if lines_by_source.contains_key(&source) || unreadable_sources.contains(&source) {
+            continue;
+        }
+        match std::fs::read(&source) {
+            Ok(content) => {
+                let lines_num = content.iter().filter(|&&byte| byte == b'\n').count();
+                lines_by_source.insert(source, lines_num);
+            }
+            Err(_) => {
+                unreadable_sources.insert(source);
+            }
+        }
+    }
+    let total_lines_num: usize = lines_by_source.values().sum();
+
+    println!("Translation units: {}", commands.len());
+    println!(
+        "Unique source files: {}",
+        lines_by_source.len() + unreadable_sources.len()
+    );
+    println!("Source lines (without headers): {}", total_lines_num);
+    if !unreadable_sources.is_empty() {
+        println!("Unreadable source files: {}", unreadable_sources.len());
+    }
+
+    let mut commands_num_by_flag: HashMap<&str, usize> = HashMap::new();
+    for command in commands {
+        let flags: HashSet<&str> = command
+            .args
+            .iter()
+            .map(String::as_str)
+            .filter(|arg| arg.starts_with('-') && !is_output_flag(arg))
+            .collect();
+        for flag in flags {
+            *commands_num_by_flag.entry(flag).or_default() += 1;
+        }
+    }
+    let mut flags: Vec<_> = commands_num_by_flag.into_iter().collect();
+    flags.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    println!("Compiler flags:");
+    for (flag, commands_num) in flags {
+        println!("  {}: {}", flag, commands_num);
+    }
+
+    let seconds = if estimate_time {
+        benchmark_lines_per_second(commands, &lines_by_source, options, verbose_errors)?
+            .map(|lines_per_second| total_lines_num as f64 / lines_per_second)
+    } else {
+        Some(total_lines_num as f64 / ASSUMED_LINES_PER_SECOND)
+    };
+    match seconds {
+        Some(seconds) => println!(
+            "Estimated analysis time: {}",
+            format_duration(Duration::from_secs_f64(seconds))
+        ),
+        None => println!("Estimated analysis time: unknown, no source file could be read."),
+    }
+    Ok(())
+}
+
+/// Generates and parses the assembly of the source file with the median number of lines,
+/// which is more representative than the first file which is often tiny or generated.
+fn benchmark_lines_per_second(
+    commands: &[AssemblyGenerationCommand],
+    lines_by_source: &HashMap<PathBuf, usize>,
+    options: ParseOptions,
+    verbose_errors: bool,
+) -> Result<Option<f64>> {
+    let mut candidates: Vec<(&AssemblyGenerationCommand, usize)> = commands
+        .iter()
+        .filter_map(|command| {
+            let lines_num = *lines_by_source.get(&command.source_file()?)?;
+            Some((command, lines_num))
+        })
+        .filter(|(_, lines_num)| *lines_num > 0)
+        .collect();
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    candidates.sort_by_key(|(_, lines_num)| *lines_num);
+    let (command, lines_num) = candidates[candidates.len() / 2];
+    eprintln!("Benchmarking {}", command.object);
+
+    let now = Instant::now();
+    let assembly = crate::get_assembly(command, verbose_errors)?;
+    let mut parsed = ParsedData::default();
+    let object = parsed.get_or_add_object(command.object_name()?);
+    crate::parse_data(object, &assembly, options, &mut parsed);
+    let seconds = now.elapsed().as_secs_f64().max(0.001);
+    Ok(Some(lines_num as f64 / seconds))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        format!("{} s", seconds)
+    } else if seconds < 3600 {
+        format!("{} min {} s", seconds / 60, seconds % 60)
+    } else {
+        format!("{} h {} min", seconds / 3600, seconds % 3600 / 60)
+    }
+}
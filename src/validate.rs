@@ -0,0 +1,247 @@
+use crate::{FunctionName, ObjectID, ParsedData};
+
+/// Number of suspicious lines printed in the validation report.
+const SHOWN_SUSPICIOUS_LINES_NUM: usize = 20;
+
+/// A line that the parser does not recognize and therefore ignores.
+pub struct SuspiciousLine {
+    /// Starts at 1, like in editors.
+    pub line_number: usize,
+    pub content: String,
+    pub object: ObjectID,
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with("//") || line.starts_with("/*")
+}
+
+fn is_label(line: &str) -> bool {
+    let Some(name) = line.strip_suffix(':') else {
+        return false;
+    };
+    // Symbols with special characters are quoted.
+    if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') {
+        return true;
+    }
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$' | '@'))
+}
+
+/// Instructions are indented and start with a lowercase mnemonic, e.g. `movl` or `b.ne`.
+fn is_instruction_like(line: &str) -> bool {
+    if !line.starts_with(|c: char| c.is_whitespace()) {
+        return false;
+    }
+    let mnemonic = line.split_whitespace().next().unwrap_or_default();
+    mnemonic.starts_with(|c: char| c.is_ascii_lowercase())
+        && mnemonic
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '_')
+}
+
+/// Finds the lines that are neither directives, instructions, labels nor comments. They are
+/// usually caused by an unsupported architecture, corrupted files or new compiler output.
+pub fn validate_assembly(object: ObjectID, assembly: &str) -> Vec<SuspiciousLine> {
+    assembly
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let trimmed_line = line.trim();
+            !(trimmed_line.is_empty()
+                || is_comment(trimmed_line)
+                || is_label(trimmed_line)
+                || (trimmed_line.starts_with('.') && !trimmed_line.ends_with(':'))
+                || is_instruction_like(line))
+        })
+        .map(|(line_index, line)| SuspiciousLine {
+            line_number: line_index + 1,
+            content: line.to_owned(),
+            object,
+        })
+        .collect()
+}
+
+/// Prints the suspicious lines to stderr, so that it does not mix with report output.
+pub fn print_validation_report(parsed: &ParsedData, suspicious_lines: &[SuspiciousLine]) {
+    if suspicious_lines.is_empty() {
+        eprintln!("All assembly lines were recognized.");
+        return;
+    }
+    let mut objects: Vec<ObjectID> = suspicious_lines.iter().map(|line| line.object).collect();
+    objects.sort();
+    objects.dedup();
+    eprintln!(
+        "Found {} unrecognized assembly lines in {} objects, which might be parser issues:",
+        suspicious_lines.len(),
+        objects.len()
+    );
+    for line in suspicious_lines.iter().take(SHOWN_SUSPICIOUS_LINES_NUM) {
+        eprintln!(
+            "  {}:{}: {}",
+            parsed.name_by_object_id[&line.object],
+            line.line_number,
+            line.content.trim()
+        );
+    }
+    if suspicious_lines.len() > SHOWN_SUSPICIOUS_LINES_NUM {
+        eprintln!(
+            "  ... and {} more",
+            suspicious_lines.len() - SHOWN_SUSPICIOUS_LINES_NUM
+        );
+    }
+}
+
+/// Checks that the maps of the parsed data agree with each other, e.g. that every call edge
+/// is stored in both directions and that all ids are known. Returns a description of every
+/// inconsistency.
+pub fn validate_parsed_data(parsed: &ParsedData) -> Vec<String> {
+    let mut problems = vec![];
+    let function_known = |function_id| parsed.name_by_function_id.contains_key(function_id);
+    let object_known = |object_id| parsed.name_by_object_id.contains_key(object_id);
+
+    if parsed.function_id_by_name.len() != parsed.name_by_function_id.len() {
+        problems.push(format!(
+            "{} function names but {} function ids",
+            parsed.function_id_by_name.len(),
+            parsed.name_by_function_id.len()
+        ));
+    }
+    for (function_id, function) in &parsed.name_by_function_id {
+        if parsed.function_id_by_name.get(function) != Some(function_id) {
+            problems.push(format!(
+                "function {} is not found by its name",
+                function_id.0
+            ));
+        }
+        if let FunctionName::Local(local) = function {
+            if !object_known(&local.object) {
+                problems.push(format!(
+                    "local function {} belongs to unknown object {}",
+                    function_id.0, local.object.0
+                ));
+            }
+        }
+    }
+    if parsed.object_id_by_name.len() != parsed.name_by_object_id.len() {
+        problems.push(format!(
+            "{} object names but {} object ids",
+            parsed.object_id_by_name.len(),
+            parsed.name_by_object_id.len()
+        ));
+    }
+    for (object_id, object) in &parsed.name_by_object_id {
+        if parsed.object_id_by_name.get(object) != Some(object_id) {
+            problems.push(format!("object {} is not found by its name", object_id.0));
+        }
+    }
+
+    for (object_id, function_ids) in &parsed.functions_by_object {
+        if !object_known(object_id) {
+            problems.push(format!("unknown object {} defines functions", object_id.0));
+        }
+        for function_id in function_ids {
+            let is_inverse = parsed
+                .objects_by_function
+                .get(function_id)
+                .is_some_and(|objects| objects.contains(object_id));
+            if !is_inverse {
+                problems.push(format!(
+                    "object {} defines function {}, but not the other way around",
+                    object_id.0, function_id.0
+                ));
+            }
+        }
+    }
+    for (function_id, object_ids) in &parsed.objects_by_function {
+        if !function_known(function_id) {
+            problems.push(format!("unknown function {} is defined", function_id.0));
+        }
+        for object_id in object_ids {
+            let is_inverse = parsed
+                .functions_by_object
+                .get(object_id)
+                .is_some_and(|functions| functions.binary_search(function_id).is_ok());
+            if !is_inverse {
+                problems.push(format!(
+                    "function {} is defined in object {}, but not the other way around",
+                    function_id.0, object_id.0
+                ));
+            }
+        }
+    }
+
+    for (caller, callees) in &parsed.callees_by_caller {
+        if !function_known(caller) {
+            problems.push(format!("unknown function {} calls functions", caller.0));
+        }
+        for callee in callees {
+            if !function_known(callee) {
+                problems.push(format!("unknown function {} is called", callee.0));
+            }
+            let is_inverse = parsed
+                .callers_by_callee
+                .get(callee)
+                .is_some_and(|callers| callers.contains(caller));
+            if !is_inverse {
+                problems.push(format!(
+                    "{} calls {}, but is not one of its callers",
+                    caller.0, callee.0
+                ));
+            }
+        }
+    }
+    for (callee, callers) in &parsed.callers_by_callee {
+        for caller in callers {
+            let is_inverse = parsed
+                .callees_by_caller
+                .get(caller)
+                .is_some_and(|callees| callees.contains(callee));
+            if !is_inverse {
+                problems.push(format!(
+                    "{} is a caller of {}, but does not call it",
+                    caller.0, callee.0
+                ));
+            }
+        }
+    }
+    for (caller, call_sites) in &parsed.call_sites_by_caller {
+        for callee in call_sites.keys() {
+            let is_call = parsed
+                .callees_by_caller
+                .get(caller)
+                .is_some_and(|callees| callees.contains(callee));
+            if !is_call {
+                problems.push(format!(
+                    "{} has call sites of {}, but does not call it",
+                    caller.0, callee.0
+                ));
+            }
+        }
+    }
+
+    for function_id in parsed.instructions_by_object_by_function.keys() {
+        if !function_known(function_id) {
+            problems.push(format!(
+                "unknown function {} has instructions",
+                function_id.0
+            ));
+        }
+    }
+    for (function_id, nums) in &parsed.instructions_by_object_by_function {
+        for object_id in nums.keys() {
+            let is_defined = parsed
+                .objects_by_function
+                .get(function_id)
+                .is_some_and(|objects| objects.contains(object_id));
+            if !is_defined {
+                problems.push(format!(
+                    "function {} has instructions in object {}, which does not define it",
+                    function_id.0, object_id.0
+                ));
+            }
+        }
+    }
+    problems
+}
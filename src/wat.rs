@@ -0,0 +1,561 @@
+use crate::{
+    FunctionID, FunctionName, LinkType, LocalFunctionName, ObjectID, ObjectName, ParsedData,
+};
+use eyre::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Node of the s-expressions that WAT consists of.
+enum Sexpr {
+    Atom(String),
+    /// Quoted strings are only used for import and export names, which are not needed.
+    Str,
+    List(Vec<Sexpr>),
+}
+
+impl Sexpr {
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexpr::Atom(atom) => Some(atom),
+            _ => None,
+        }
+    }
+
+    /// The keyword at the start of a list like `func` in `(func $f ...)`.
+    fn head(&self) -> Option<&str> {
+        match self {
+            Sexpr::List(items) => items.first()?.atom(),
+            _ => None,
+        }
+    }
+}
+
+fn parse_sexprs(text: &str) -> Result<Vec<Sexpr>> {
+    let mut chars = text.chars().peekable();
+    // Lists that are still open, the outermost one collects the top level expressions.
+    let mut stack: Vec<Vec<Sexpr>> = vec![vec![]];
+    while let Some(c) = chars.next() {
+        match c {
+            ';' if chars.peek() == Some(&';') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '(' if chars.peek() == Some(&';') => {
+                // Block comments can be nested.
+                chars.next();
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('(') if chars.next_if_eq(&';').is_some() => depth += 1,
+                        Some(';') if chars.next_if_eq(&')').is_some() => depth -= 1,
+                        Some(_) => {}
+                        None => return Err(eyre::eyre!("Unterminated block comment.")),
+                    }
+                }
+            }
+            '(' => stack.push(vec![]),
+            ')' => {
+                let list = stack.pop().unwrap();
+                let Some(parent) = stack.last_mut() else {
+                    return Err(eyre::eyre!("Unexpected ')'."));
+                };
+                parent.push(Sexpr::List(list));
+            }
+            '"' => {
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            chars.next();
+                        }
+                        Some(_) => {}
+                        None => return Err(eyre::eyre!("Unterminated string.")),
+                    }
+                }
+                stack.last_mut().unwrap().push(Sexpr::Str);
+            }
+            _ if c.is_whitespace() => {}
+            _ => {
+                let mut atom = c.to_string();
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !"();\"".contains(c)) {
+                    atom.push(c);
+                }
+                stack.last_mut().unwrap().push(Sexpr::Atom(atom));
+            }
+        }
+    }
+    match <[Vec<Sexpr>; 1]>::try_from(stack) {
+        Ok([top_level]) => Ok(top_level),
+        Err(_) => Err(eyre::eyre!("Missing ')'.")),
+    }
+}
+
+/// Keywords and operands that look like instructions but are not.
+const NON_INSTRUCTION_ATOMS: &[&str] = &[
+    "then",
+    "else",
+    "end",
+    "type",
+    "param",
+    "result",
+    "local",
+    "mut",
+    "i32",
+    "i64",
+    "f32",
+    "f64",
+    "v128",
+    "funcref",
+    "externref",
+];
+
+fn is_instruction(atom: &str) -> bool {
+    atom.starts_with(|c: char| c.is_ascii_lowercase())
+        && !atom.contains('=')
+        && !atom.starts_with("nan")
+        && !atom.starts_with("inf")
+        && !NON_INSTRUCTION_ATOMS.contains(&atom)
+}
+
+/// Lists at the start of a function that declare it instead of being part of its body.
+fn is_function_header(item: &Sexpr) -> bool {
+    matches!(
+        item.head(),
+        Some("export" | "import" | "type" | "param" | "result" | "local")
+    )
+}
+
+/// Parameter and result types of a function or an indirect call.
+#[derive(Clone, Default, PartialEq, Eq)]
+struct Signature<'a> {
+    params: Vec<&'a str>,
+    results: Vec<&'a str>,
+}
+
+/// Value types of a `param` or `result` list, which may name a single parameter.
+fn value_types(items: &[Sexpr]) -> impl Iterator<Item = &str> {
+    items
+        .iter()
+        .filter_map(Sexpr::atom)
+        .filter(|atom| !atom.starts_with('$'))
+}
+
+/// Resolves a type use, which is a `(type $t)` reference and/or inline `param` and `result`
+/// lists. Both forms give the same signature so that they can be compared. `None` when the
+/// referenced type is unknown.
+fn type_use_signature<'a>(
+    items: &'a [Sexpr],
+    types: &HashMap<String, Signature<'a>>,
+) -> Option<Signature<'a>> {
+    let mut inline = Signature::default();
+    for item in items {
+        let Sexpr::List(list) = item else {
+            continue;
+        };
+        match item.head() {
+            Some("type") => return types.get(list.get(1)?.atom()?).cloned(),
+            Some("param") => inline.params.extend(value_types(&list[1..])),
+            Some("result") => inline.results.extend(value_types(&list[1..])),
+            _ => {}
+        }
+    }
+    Some(inline)
+}
+
+/// A function of the module's function index space, in which imports come first.
+struct WatFunction<'a> {
+    name: String,
+    /// Items after the name, `None` for imported functions.
+    items: Option<&'a [Sexpr]>,
+    signature: Option<Signature<'a>>,
+    is_exported: bool,
+}
+
+fn wat_function<'a>(
+    items: &'a [Sexpr],
+    index: usize,
+    is_imported: bool,
+    types: &HashMap<String, Signature<'a>>,
+) -> WatFunction<'a> {
+    // `items` starts after the `func` keyword.
+    let (name, items) = match items.first().and_then(Sexpr::atom) {
+        Some(name) if name.starts_with('$') => (name.to_owned(), &items[1..]),
+        _ => (format!("func[{}]", index), items),
+    };
+    let is_imported = is_imported || items.iter().any(|item| item.head() == Some("import"));
+    let header_len = items
+        .iter()
+        .position(|item| !is_function_header(item))
+        .unwrap_or(items.len());
+    WatFunction {
+        name,
+        items: (!is_imported).then_some(items),
+        signature: type_use_signature(&items[..header_len], types),
+        is_exported: items.iter().any(|item| item.head() == Some("export")),
+    }
+}
+
+/// What the body of a function references.
+#[derive(Default)]
+struct BodyScan<'a> {
+    instructions_num: usize,
+    /// Targets of direct calls, by name or index.
+    callees: Vec<&'a str>,
+    /// Type uses of indirect calls through tables.
+    indirect_call_types: Vec<&'a [Sexpr]>,
+    /// Functions whose reference is taken with `ref.func`.
+    referenced: Vec<&'a str>,
+}
+
+/// Folded and flat instructions are handled the same way, because in both the operands of
+/// an instruction follow it.
+fn scan_body<'a>(items: &'a [Sexpr], scan: &mut BodyScan<'a>) {
+    for (i, item) in items.iter().enumerate() {
+        let atom = match item {
+            Sexpr::List(list) => {
+                scan_body(list, scan);
+                continue;
+            }
+            Sexpr::Str => continue,
+            Sexpr::Atom(atom) => atom.as_str(),
+        };
+        if is_instruction(atom) {
+            scan.instructions_num += 1;
+        }
+        let next_atom = items.get(i + 1).and_then(Sexpr::atom);
+        match atom {
+            "call" | "return_call" => scan.callees.extend(next_atom),
+            "ref.func" => scan.referenced.extend(next_atom),
+            "call_indirect" | "return_call_indirect" => {
+                // The table index is optional and comes before the type use.
+                let start = if next_atom.is_some() { i + 2 } else { i + 1 };
+                let type_use_len = items[start.min(items.len())..]
+                    .iter()
+                    .take_while(|item| matches!(item.head(), Some("type" | "param" | "result")))
+                    .count();
+                scan.indirect_call_types
+                    .push(&items[start.min(items.len())..][..type_use_len]);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects the function references of an element segment, which fill the tables that
+/// `call_indirect` calls through.
+fn collect_element_functions<'a>(items: &'a [Sexpr], functions: &mut Vec<&'a str>) {
+    for item in items {
+        match item {
+            Sexpr::Atom(atom) if atom.starts_with('$') => functions.push(atom),
+            Sexpr::List(list) => collect_element_functions(list, functions),
+            _ => {}
+        }
+    }
+}
+
+/// Parses a module in the WebAssembly text format. Every instruction of a function body
+/// counts as one instruction. Indirect calls are added as calls to all functions in element
+/// segments that declare the same type, since they are the possible targets. Functions are
+/// local to the object unless they are imported or exported.
+pub fn parse_wat_data(object: ObjectID, module: &str, parsed: &mut ParsedData) -> Result<()> {
+    let top_level = parse_sexprs(module)?;
+    // The module wrapper is optional when the file only contains module fields.
+    let fields: &[Sexpr] = match top_level.as_slice() {
+        [Sexpr::List(items)] if items.first().and_then(Sexpr::atom) == Some("module") => {
+            match items.get(1).and_then(Sexpr::atom) {
+                Some(name) if name.starts_with('$') => &items[2..],
+                _ => &items[1..],
+            }
+        }
+        fields => fields,
+    };
+
+    let mut imported = vec![];
+    let mut defined = vec![];
+    let mut exported_names: HashSet<&str> = HashSet::new();
+    let mut element_functions: Vec<&str> = vec![];
+    // Types are referenced by name or index.
+    let mut types: HashMap<String, Signature> = HashMap::new();
+    let mut types_num = 0;
+    for field in fields {
+        let Sexpr::List(items) = field else {
+            continue;
+        };
+        match field.head() {
+            // Functions with an inline import belong to the imports in the index space,
+            // which keep the order of the module.
+            Some("func") if items.iter().any(|item| item.head() == Some("import")) => {
+                imported.push(&items[1..])
+            }
+            Some("func") => defined.push(&items[1..]),
+            Some("import") => {
+                if let Some(Sexpr::List(func)) =
+                    items.iter().find(|item| item.head() == Some("func"))
+                {
+                    imported.push(&func[1..]);
+                }
+            }
+            Some("export") => {
+                if let Some(Sexpr::List(func)) =
+                    items.iter().find(|item| item.head() == Some("func"))
+                {
+                    exported_names.extend(func.get(1).and_then(Sexpr::atom));
+                }
+            }
+            Some("elem") => collect_element_functions(&items[1..], &mut element_functions),
+            Some("type") => {
+                let signature = items
+                    .iter()
+                    .find_map(|item| match item {
+                        Sexpr::List(func) if item.head() == Some("func") => {
+                            type_use_signature(&func[1..], &HashMap::new())
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                if let Some(name) = items.get(1).and_then(Sexpr::atom) {
+                    types.insert(name.to_owned(), signature.clone());
+                }
+                types.insert(types_num.to_string(), signature);
+                types_num += 1;
+            }
+            _ => {}
+        }
+    }
+    let functions: Vec<WatFunction> = imported
+        .into_iter()
+        .map(|items| (items, true))
+        .chain(defined.into_iter().map(|items| (items, false)))
+        .enumerate()
+        .map(|(index, (items, is_imported))| wat_function(items, index, is_imported, &types))
+        .collect();
+
+    let mut function_ids = Vec::with_capacity(functions.len());
+    for (index, function) in functions.iter().enumerate() {
+        let is_exported = function.is_exported
+            || exported_names.contains(function.name.as_str())
+            || exported_names.contains(index.to_string().as_str());
+        let link_type = if is_exported || function.items.is_none() {
+            LinkType::Global
+        } else {
+            LinkType::Local
+        };
+        let name = match link_type {
+            LinkType::Local => FunctionName::Local(LocalFunctionName {
+                object,
+                name: internment::ArcIntern::from(function.name.as_str()),
+            }),
+            _ => FunctionName::global(&function.name),
+        };
+        let function_id = parsed.get_or_add_function(name);
+        if function.items.is_some() {
+            parsed.link_type_by_function.insert(function_id, link_type);
+        }
+        function_ids.push(function_id);
+    }
+    // References use either the name or the index.
+    let function_id_by_reference: HashMap<String, FunctionID> = functions
+        .iter()
+        .zip(function_ids.iter())
+        .enumerate()
+        .flat_map(|(index, (function, function_id))| {
+            [
+                (function.name.clone(), *function_id),
+                (index.to_string(), *function_id),
+            ]
+        })
+        .collect();
+    let element_function_ids: Vec<FunctionID> = element_functions
+        .iter()
+        .filter_map(|reference| function_id_by_reference.get(*reference).copied())
+        .collect();
+    let signature_by_function: HashMap<FunctionID, &Signature> = function_ids
+        .iter()
+        .zip(functions.iter())
+        .filter_map(|(function_id, function)| Some((*function_id, function.signature.as_ref()?)))
+        .collect();
+    parsed
+        .function_pointer_referenced
+        .extend(element_function_ids.iter().copied());
+
+    for (function, function_id) in functions.iter().zip(function_ids.iter().copied()) {
+        let Some(items) = function.items else {
+            continue;
+        };
+        let body_start = items
+            .iter()
+            .position(|item| !is_function_header(item))
+            .unwrap_or(items.len());
+        let mut scan = BodyScan::default();
+        scan_body(&items[body_start..], &mut scan);
+
+        crate::insert_sorted(
+            parsed.functions_by_object.entry(object).or_default(),
+            function_id,
+        );
+        parsed
+            .objects_by_function
+            .entry(function_id)
+            .or_default()
+            .insert(object);
+        *parsed
+            .instructions_by_object_by_function
+            .entry(function_id)
+            .or_default()
+            .entry(object)
+            .or_default() += scan.instructions_num;
+
+        let mut callees: Vec<FunctionID> = scan
+            .callees
+            .iter()
+            .filter_map(|reference| function_id_by_reference.get(*reference).copied())
+            .collect();
+        for type_use in scan.indirect_call_types {
+            let Some(signature) = type_use_signature(type_use, &types) else {
+                continue;
+            };
+            let candidates = element_function_ids
+                .iter()
+                .filter(|candidate| signature_by_function.get(candidate) == Some(&&signature));
+            callees.extend(candidates);
+        }
+        for callee in callees {
+            *parsed
+                .call_sites_by_caller
+                .entry(function_id)
+                .or_default()
+                .entry(callee)
+                .or_default() += 1;
+            parsed
+                .callees_by_caller
+                .entry(function_id)
+                .or_default()
+                .insert(callee);
+            parsed
+                .callers_by_callee
+                .entry(callee)
+                .or_default()
+                .insert(function_id);
+        }
+        parsed.function_pointer_referenced.extend(
+            scan.referenced
+                .iter()
+                .filter_map(|reference| function_id_by_reference.get(*reference).copied()),
+        );
+    }
+    Ok(())
+}
+
+/// Analyzes a WebAssembly module in the text format, e.g. written by `wasm2wat`. The module
+/// is a single object.
+pub fn analyze_wat_file(path: &Path) -> Result<ParsedData> {
+    let module = std::fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("Can't read {}: {}", path.display(), err))?;
+    let mut parsed = ParsedData::default();
+    let object = parsed.get_or_add_object(ObjectName::from_path(path)?);
+    parse_wat_data(object, &module, &mut parsed)
+        .map_err(|err| eyre::eyre!("{}: {}", path.display(), err))?;
+    eprintln!(
+        "Parsed {} functions with {} instructions.",
+        parsed.instructions_by_object_by_function.len(),
+        crate::all_instructions_num(&parsed)
+    );
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Table of three functions that are called indirectly. `$mul` declares its signature
+    /// inline and `$add` through a type, but both have the same one.
+    const MODULE: &str = r#"(module
+  (type $binop (func (param i32 i32) (result i32)))
+  (type (func (param i32) (result i32)))
+  (import "env" "log" (func $log (param i32)))
+  (memory 1)
+  (data (i32.const 0) "(;not a comment ;; \"quoted\" )")
+  (table 3 funcref)
+  (elem (i32.const 0) $add $neg $mul)
+  (func $add (type $binop) local.get 0 local.get 1 i32.add)
+  (func $mul (param $a i32) (param $b i32) (result i32)
+    (i32.mul (local.get $a) (local.get $b)))
+  (func $neg (param i32) (result i32) (i32.sub (i32.const 0) (local.get 0)))
+  (func $apply (export "apply") (param i32 i32 i32) (result i32)
+    (call $log (local.get 0))
+    (call_indirect (type $binop) (local.get 1) (local.get 2) (local.get 0)))
+  (func $apply_unary (param i32) (result i32)
+    (call_indirect (type 1) (local.get 0) (i32.const 0)))
+)"#;
+
+    #[test]
+    fn indirect_calls_are_matched_by_signature() {
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: "/build/module.wat".into(),
+        });
+        parse_wat_data(object, MODULE, &mut parsed).unwrap();
+
+        let local = |name: &str| parsed.function_id_by_local_name(object, name).unwrap();
+        let apply = parsed.function_id_by_global_name("$apply").unwrap();
+        let log = parsed.function_id_by_global_name("$log").unwrap();
+        assert_eq!(
+            parsed.callees_by_caller[&apply],
+            HashSet::from([log, local("$add"), local("$mul")])
+        );
+        assert_eq!(
+            parsed.callees_by_caller[&local("$apply_unary")],
+            HashSet::from([local("$neg")])
+        );
+        assert_eq!(
+            parsed.function_pointer_referenced,
+            HashSet::from([local("$add"), local("$mul"), local("$neg")])
+        );
+    }
+
+    #[test]
+    fn data_segments_are_not_parsed_as_code() {
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: "/build/module.wat".into(),
+        });
+        parse_wat_data(object, MODULE, &mut parsed).unwrap();
+
+        // The imported function is known, but not defined.
+        assert_eq!(parsed.name_by_function_id.len(), 6);
+        assert_eq!(parsed.instructions_by_object_by_function.len(), 5);
+        let instructions_num = |name: &str| {
+            crate::function_instructions_num(
+                &parsed,
+                parsed.function_id_by_local_name(object, name).unwrap(),
+            )
+        };
+        assert_eq!(instructions_num("$add"), 3);
+        assert_eq!(instructions_num("$mul"), 3);
+        assert_eq!(instructions_num("$neg"), 3);
+        assert_eq!(instructions_num("$apply_unary"), 3);
+
+        assert!(parse_wat_data(object, "(module (data \"unterminated))", &mut parsed).is_err());
+    }
+
+    #[test]
+    fn imports_keep_their_order_in_the_index_space() {
+        let mut parsed = ParsedData::default();
+        let object = parsed.get_or_add_object(ObjectName {
+            path: "/build/module.wat".into(),
+        });
+        let module = r#"(module
+  (func $inline (import "env" "inline") (param i32))
+  (import "env" "separate" (func $separate))
+  (func $main (export "main") (call 1) (call 2))
+  (func $helper nop)
+)"#;
+        parse_wat_data(object, module, &mut parsed).unwrap();
+
+        let main = parsed.function_id_by_global_name("$main").unwrap();
+        assert_eq!(
+            parsed.callees_by_caller[&main],
+            HashSet::from([
+                parsed.function_id_by_global_name("$separate").unwrap(),
+                main
+            ])
+        );
+    }
+}
@@ -0,0 +1,595 @@
+use crate::{
+    AssemblyGenerationCommand, FunctionID, ObjectID, ObjectName, ParseOptions, ParsedData,
+};
+use eyre::Result;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Number of functions shown after every rebuild.
+const RECENT_CHANGES_NUM: usize = 10;
+
+/// Fields of `ParsedData` whose entries are merged one key at a time, together with how the
+/// values that different objects have for the same key are combined. `merge_parsed_data` and
+/// `remove_object_contribution` both use this list, so that removing an object gives the same
+/// result as merging the remaining objects again.
+macro_rules! merged_fields {
+    ($set:ident, $map:ident) => {
+        $set!(tail_recursive_functions);
+        $set!(has_unwind_personality);
+        $set!(function_pointer_referenced);
+        $set!(windows_abi_functions);
+        $set!(uses_tls);
+        $set!(lto_discarded);
+        $set!(lto_private);
+        $set!(possibly_deoptimized);
+        $set!(fully_inlined_functions);
+        $map!(string_literal_refs_by_function, keep_max);
+        $map!(loop_count_by_function, keep_max);
+        $map!(branch_count_by_function, keep_max);
+        $map!(return_count_by_function, keep_max);
+        $map!(inline_memcpy_count_by_function, keep_max);
+        $map!(inline_memset_count_by_function, keep_max);
+        $map!(indirect_call_count_by_function, keep_max);
+        $map!(simd_extension_by_function, keep_max);
+        $map!(saved_registers_by_function, keep_first);
+        $map!(vtable_entries, keep_first);
+        $map!(virtual_call_slots_by_function, keep_first);
+        $map!(link_type_by_function, keep_first);
+        $map!(raw_assembly_by_function, keep_first);
+        $map!(alignment_overhead_by_function, keep_first);
+        $map!(estimated_padding_by_function, keep_first);
+        $map!(frame_size_by_function, keep_first);
+        $map!(deprecated_functions, keep_first);
+        $map!(source_file_by_function, keep_first);
+    };
+}
+
+/// Everything that parsing a single object added to the combined `ParsedData`. It is stored
+/// as a `ParsedData` without names that only contains the data of that object, but uses the
+/// same ids as the combined data.
+pub struct ObjectParsedContribution {
+    data: ParsedData,
+    /// Functions that the object refers to, their names are kept as long as any object
+    /// refers to them.
+    functions: HashSet<FunctionID>,
+    /// Contributions with a smaller index were merged into the combined data earlier.
+    merge_index: usize,
+}
+
+/// Parsed data that can be updated one object at a time.
+#[derive(Default)]
+pub struct IncrementalAnalysis {
+    pub parsed: ParsedData,
+    pub options: ParseOptions,
+    contribution_by_object: HashMap<ObjectName, ObjectParsedContribution>,
+    next_merge_index: usize,
+}
+
+impl IncrementalAnalysis {
+    /// Parses the object again and replaces its previous contribution, if there is one.
+    pub fn update_object(
+        &mut self,
+        command: &AssemblyGenerationCommand,
+        assembly: &str,
+    ) -> Result<()> {
+        let object_name = command.object_name()?;
+        let object = self.parsed.get_or_add_object(object_name.clone());
+
+        // Functions that the object refers to keep their ids when it is parsed again, the
+        // others are only forgotten afterwards.
+        let previous_functions = self.remove_object_contribution(object);
+
+        // Parse with the combined names and ids, so that known functions are looked up and
+        // new functions get ids that are unused in the combined data. They are moved back
+        // afterwards, so the contribution only contains the data of the object.
+        let parsed = &mut self.parsed;
+        let mut data = ParsedData {
+            object_id_by_name: std::mem::take(&mut parsed.object_id_by_name),
+            name_by_object_id: std::mem::take(&mut parsed.name_by_object_id),
+            function_id_by_name: std::mem::take(&mut parsed.function_id_by_name),
+            name_by_function_id: std::mem::take(&mut parsed.name_by_function_id),
+            function_ids: std::mem::take(&mut parsed.function_ids),
+            ..Default::default()
+        };
+        crate::parse_data(object, assembly, self.options, &mut data);
+        data.resolve_source_files(object, command);
+        parsed.object_id_by_name = std::mem::take(&mut data.object_id_by_name);
+        parsed.name_by_object_id = std::mem::take(&mut data.name_by_object_id);
+        parsed.function_id_by_name = std::mem::take(&mut data.function_id_by_name);
+        parsed.name_by_function_id = std::mem::take(&mut data.name_by_function_id);
+        parsed.function_ids = std::mem::take(&mut data.function_ids);
+
+        merge_parsed_data(parsed, &data);
+        let functions = data.referenced_functions();
+        self.contribution_by_object.insert(
+            object_name,
+            ObjectParsedContribution {
+                data,
+                functions,
+                merge_index: self.next_merge_index,
+            },
+        );
+        self.next_merge_index += 1;
+        self.forget_unreferenced_functions(previous_functions);
+        Ok(())
+    }
+
+    /// Removes the object and everything it contributed. Its id is reused for the next new
+    /// object, and so are the ids of functions that no other object refers to.
+    pub fn remove_object(&mut self, object_name: &ObjectName) {
+        let Some(object) = self.parsed.object_id_by_name.get(object_name).copied() else {
+            return;
+        };
+        let functions = self.remove_object_contribution(object);
+        self.forget_unreferenced_functions(functions);
+        self.parsed.object_id_by_name.remove(object_name);
+        self.parsed.name_by_object_id.remove(&object);
+        self.parsed.object_ids.free(object);
+    }
+
+    /// Removes the names of the functions that no object refers to anymore and frees their
+    /// ids.
+    fn forget_unreferenced_functions(&mut self, functions: HashSet<FunctionID>) {
+        for function_id in functions {
+            let is_referenced = self
+                .contribution_by_object
+                .values()
+                .any(|contribution| contribution.functions.contains(&function_id));
+            if is_referenced {
+                continue;
+            }
+            if let Some(name) = self.parsed.name_by_function_id.remove(&function_id) {
+                self.parsed.function_id_by_name.remove(&name);
+                self.parsed.function_ids.free(function_id);
+            }
+        }
+    }
+
+    /// Undoes everything that `merge_parsed_data` did for that object. Calls and flags
+    /// that other objects contribute as well are kept. Returns the functions that the object
+    /// referred to, their names are still kept.
+    pub fn remove_object_contribution(&mut self, object: ObjectID) -> HashSet<FunctionID> {
+        let Some(object_name) = self.parsed.name_by_object_id.get(&object) else {
+            return HashSet::new();
+        };
+        let Some(contribution) = self.contribution_by_object.remove(object_name) else {
+            return HashSet::new();
+        };
+        let data = &contribution.data;
+        let parsed = &mut self.parsed;
+        // In the order in which they were merged, so that the values that are kept are the same
+        // as when merging them again.
+        let mut others: Vec<&ObjectParsedContribution> =
+            self.contribution_by_object.values().collect();
+        others.sort_by_key(|other| other.merge_index);
+        let others: Vec<&ParsedData> = others.into_iter().map(|other| &other.data).collect();
+
+        if let Some(functions) = parsed.functions_by_object.remove(&object) {
+            for function_id in functions {
+                remove_from_set_map(&mut parsed.objects_by_function, function_id, &object);
+            }
+        }
+
+        for (caller, callees) in data.callees_by_caller.iter() {
+            for callee in callees {
+                let is_used_elsewhere = others.iter().any(|other| {
+                    other
+                        .callees_by_caller
+                        .get(caller)
+                        .is_some_and(|callees| callees.contains(callee))
+                });
+                if !is_used_elsewhere {
+                    remove_from_set_map(&mut parsed.callees_by_caller, *caller, callee);
+                    remove_from_set_map(&mut parsed.callers_by_callee, *callee, caller);
+                }
+            }
+        }
+
+        macro_rules! remove_set {
+            ($field:ident) => {
+                let other_fields: Vec<_> = others.iter().map(|other| &other.$field).collect();
+                remove_set(&mut parsed.$field, &data.$field, &other_fields)
+            };
+        }
+        macro_rules! remove_map {
+            ($field:ident, $combine:expr) => {
+                let other_fields: Vec<_> = others.iter().map(|other| &other.$field).collect();
+                remove_map(&mut parsed.$field, &data.$field, &other_fields, $combine)
+            };
+        }
+        merged_fields!(remove_set, remove_map);
+
+        // Like in `merge_parsed_data`, every callee gets the maximum over the remaining objects.
+        for caller in data.call_sites_by_caller.keys() {
+            let mut call_sites: HashMap<FunctionID, usize> = HashMap::new();
+            for other_call_sites in others
+                .iter()
+                .filter_map(|other| other.call_sites_by_caller.get(caller))
+            {
+                for (callee, num) in other_call_sites {
+                    let merged_num = call_sites.entry(*callee).or_default();
+                    *merged_num = (*merged_num).max(*num);
+                }
+            }
+            if call_sites.is_empty() {
+                parsed.call_sites_by_caller.remove(caller);
+            } else {
+                parsed.call_sites_by_caller.insert(*caller, call_sites);
+            }
+        }
+
+        for function_id in data.instructions_by_object_by_function.keys() {
+            let Some(nums) = parsed
+                .instructions_by_object_by_function
+                .get_mut(function_id)
+            else {
+                continue;
+            };
+            nums.remove(&object);
+            // Empty functions are stored with zero instructions as long as they are defined.
+            if nums.is_empty() {
+                parsed
+                    .instructions_by_object_by_function
+                    .remove(function_id);
+                parsed
+                    .weighted_instructions_by_object_by_function
+                    .remove(function_id);
+                parsed.immediate_density_by_function.remove(function_id);
+            } else if let Some(weights) = parsed
+                .weighted_instructions_by_object_by_function
+                .get_mut(function_id)
+            {
+                weights.remove(&object);
+            }
+        }
+        for (function_id, num) in data.instructions_with_immediate_by_function.iter() {
+            let remaining = parsed.instructions_with_immediate_by_function[function_id] - num;
+            if remaining == 0 {
+                parsed
+                    .instructions_with_immediate_by_function
+                    .remove(function_id);
+            } else {
+                parsed
+                    .instructions_with_immediate_by_function
+                    .insert(*function_id, remaining);
+            }
+        }
+        crate::update_immediate_density(parsed, data.instructions_by_object_by_function.keys());
+        contribution.functions
+    }
+}
+
+/// The value of the object that was merged first is kept.
+fn keep_first<V>(_merged: &mut V, _value: &V) {}
+
+fn keep_max<V: Ord + Clone>(merged: &mut V, value: &V) {
+    if *value > *merged {
+        *merged = value.clone();
+    }
+}
+
+fn merge_map<K: Eq + Hash + Clone, V: Clone>(
+    merged: &mut HashMap<K, V>,
+    added: &HashMap<K, V>,
+    combine: fn(&mut V, &V),
+) {
+    for (key, value) in added {
+        match merged.entry(key.clone()) {
+            Entry::Occupied(mut entry) => combine(entry.get_mut(), value),
+            Entry::Vacant(entry) => {
+                entry.insert(value.clone());
+            }
+        }
+    }
+}
+
+/// Removes the keys of the removed object that none of the other objects has.
+fn remove_set<K: Eq + Hash>(merged: &mut HashSet<K>, removed: &HashSet<K>, others: &[&HashSet<K>]) {
+    for key in removed {
+        if !others.iter().any(|other| other.contains(key)) {
+            merged.remove(key);
+        }
+    }
+}
+
+/// Merges the values of the other objects again for every key of the removed object. The
+/// others have to be in the order in which they were merged.
+fn remove_map<K: Eq + Hash + Clone, V: Clone>(
+    merged: &mut HashMap<K, V>,
+    removed: &HashMap<K, V>,
+    others: &[&HashMap<K, V>],
+    combine: fn(&mut V, &V),
+) {
+    for key in removed.keys() {
+        let mut values = others.iter().filter_map(|other| other.get(key));
+        match values.next() {
+            Some(first) => {
+                let mut value = first.clone();
+                for other_value in values {
+                    combine(&mut value, other_value);
+                }
+                merged.insert(key.clone(), value);
+            }
+            None => {
+                merged.remove(key);
+            }
+        }
+    }
+}
+
+fn remove_from_set_map<K: Eq + std::hash::Hash, V: Eq + std::hash::Hash>(
+    map: &mut HashMap<K, HashSet<V>>,
+    key: K,
+    value: &V,
+) {
+    if let Some(set) = map.get_mut(&key) {
+        set.remove(value);
+        if set.is_empty() {
+            map.remove(&key);
+        }
+    }
+}
+
+/// Adds parsed data that uses the same ids as the combined data, e.g. the contribution of a
+/// single object. Instruction counts are summed, so every object must only be added once.
+pub fn merge_parsed_data(parsed: &mut ParsedData, data: &ParsedData) {
+    for (function_id, name) in data.name_by_function_id.iter() {
+        parsed
+            .function_id_by_name
+            .insert(name.clone(), *function_id);
+        parsed
+            .name_by_function_id
+            .insert(*function_id, name.clone());
+    }
+    for (object, functions) in data.functions_by_object.iter() {
+        for function_id in functions {
+            crate::insert_sorted(
+                parsed.functions_by_object.entry(*object).or_default(),
+                *function_id,
+            );
+            parsed
+                .objects_by_function
+                .entry(*function_id)
+                .or_default()
+                .insert(*object);
+        }
+    }
+    for (caller, callees) in data.callees_by_caller.iter() {
+        parsed
+            .callees_by_caller
+            .entry(*caller)
+            .or_default()
+            .extend(callees);
+        for callee in callees {
+            parsed
+                .callers_by_callee
+                .entry(*callee)
+                .or_default()
+                .insert(*caller);
+        }
+    }
+    macro_rules! merge_set {
+        ($field:ident) => {
+            parsed.$field.extend(&data.$field)
+        };
+    }
+    macro_rules! merge_map {
+        ($field:ident, $combine:expr) => {
+            merge_map(&mut parsed.$field, &data.$field, $combine)
+        };
+    }
+    merged_fields!(merge_set, merge_map);
+    for (caller, call_sites) in data.call_sites_by_caller.iter() {
+        let merged_call_sites = parsed.call_sites_by_caller.entry(*caller).or_default();
+        for (callee, num) in call_sites {
+            let merged_num = merged_call_sites.entry(*callee).or_default();
+            *merged_num = (*merged_num).max(*num);
+        }
+    }
+    for (function_id, nums) in data.instructions_by_object_by_function.iter() {
+        parsed
+            .instructions_by_object_by_function
+            .entry(*function_id)
+            .or_default()
+            .extend(nums);
+    }
+    for (function_id, num) in data.instructions_with_immediate_by_function.iter() {
+        *parsed
+            .instructions_with_immediate_by_function
+            .entry(*function_id)
+            .or_default() += num;
+    }
+    for (function_id, weights) in data.weighted_instructions_by_object_by_function.iter() {
+        parsed
+            .weighted_instructions_by_object_by_function
+            .entry(*function_id)
+            .or_default()
+            .extend(weights);
+    }
+    crate::update_immediate_density(parsed, data.instructions_by_object_by_function.keys());
+}
+
+/// Incremental analysis that remembers how the instruction counts of functions changed over
+/// the rebuilds.
+pub struct AnalysisSession {
+    pub analysis: IncrementalAnalysis,
+    instruction_count_history: HashMap<FunctionID, Vec<(Instant, usize)>>,
+}
+
+impl AnalysisSession {
+    pub fn new(options: ParseOptions) -> Self {
+        AnalysisSession {
+            analysis: IncrementalAnalysis {
+                options,
+                ..Default::default()
+            },
+            instruction_count_history: HashMap::new(),
+        }
+    }
+
+    /// Functions that are defined in the object, which may change when it is rebuilt.
+    pub fn object_functions(&self, object_name: &ObjectName) -> HashSet<FunctionID> {
+        let parsed = &self.analysis.parsed;
+        parsed
+            .object_id_by_name
+            .get(object_name)
+            .and_then(|object| parsed.functions_by_object.get(object))
+            .map(|functions| functions.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Stores the current instruction counts of the functions and returns the (function,
+    /// previous count, current count) of those that changed since they were stored last,
+    /// largest change first. Functions without history had no instructions before, functions
+    /// that don't exist anymore are skipped.
+    pub fn record_instruction_counts(
+        &mut self,
+        functions: impl IntoIterator<Item = FunctionID>,
+    ) -> Vec<(FunctionID, usize, usize)> {
+        let now = Instant::now();
+        let mut changes = vec![];
+        for function_id in functions {
+            if !self
+                .analysis
+                .parsed
+                .name_by_function_id
+                .contains_key(&function_id)
+            {
+                // The function is gone and its id may be reused by a different one.
+                self.instruction_count_history.remove(&function_id);
+                continue;
+            }
+            let num = crate::function_instructions_num(&self.analysis.parsed, function_id);
+            let history = self
+                .instruction_count_history
+                .entry(function_id)
+                .or_default();
+            let previous_num = history.last().map_or(0, |(_, num)| *num);
+            if history.is_empty() || previous_num != num {
+                history.push((now, num));
+            }
+            if previous_num != num {
+                changes.push((function_id, previous_num, num));
+            }
+        }
+        changes.sort_by_key(|(function_id, previous_num, num)| {
+            (std::cmp::Reverse(previous_num.abs_diff(*num)), *function_id)
+        });
+        changes
+    }
+}
+
+/// Prints how the instruction counts changed in the last rebuild, e.g. "baz grew by 12
+/// instructions".
+fn print_recent_changes(parsed: &ParsedData, changes: &[(FunctionID, usize, usize)]) {
+    if changes.is_empty() {
+        eprintln!("No instruction counts changed.");
+        return;
+    }
+    eprintln!("Recent changes:");
+    for (function_id, previous_num, num) in changes.iter().take(RECENT_CHANGES_NUM) {
+        let verb = if num > previous_num { "grew" } else { "shrank" };
+        eprintln!(
+            "  {} {} by {} instructions ({} -> {})",
+            parsed.display(*function_id),
+            verb,
+            previous_num.abs_diff(*num),
+            previous_num,
+            num
+        );
+    }
+    if changes.len() > RECENT_CHANGES_NUM {
+        eprintln!("  ... and {} more", changes.len() - RECENT_CHANGES_NUM);
+    }
+}
+
+fn modified_time(path: Option<&Path>) -> Option<SystemTime> {
+    std::fs::metadata(path?).ok()?.modified().ok()
+}
+
+/// Analyzes all objects and then keeps updating the output whenever the source file of an
+/// object changes. Changes in included headers are not detected.
+pub fn watch(
+    commands: &[AssemblyGenerationCommand],
+    options: ParseOptions,
+    verbose_errors: bool,
+    output_json: &Path,
+    deterministic_output: bool,
+    compression: crate::cli::CompressionArgs,
+    metrics_server: Option<&crate::metrics::MetricsServer>,
+) -> Result<()> {
+    let mut session = AnalysisSession::new(options);
+    let mut modified_times = vec![];
+    for command in commands {
+        let assembly = crate::get_assembly(command, verbose_errors)?;
+        session.analysis.update_object(command, &assembly)?;
+        modified_times.push(modified_time(command.source_file().as_deref()));
+    }
+    let all_functions: Vec<FunctionID> = session
+        .analysis
+        .parsed
+        .name_by_function_id
+        .keys()
+        .copied()
+        .collect();
+    session.record_instruction_counts(all_functions);
+    crate::write_parsed_data(
+        &session.analysis.parsed,
+        output_json,
+        deterministic_output,
+        compression,
+    )?;
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.update(&session.analysis.parsed);
+    }
+    eprintln!("Watching {} source files for changes.", commands.len());
+
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+        let mut any_updated = false;
+        // Functions of the rebuilt objects, before and after the rebuild.
+        let mut rebuilt_functions: HashSet<FunctionID> = HashSet::new();
+        for (command, last_modified_time) in commands.iter().zip(modified_times.iter_mut()) {
+            let modified_time = modified_time(command.source_file().as_deref());
+            if modified_time == *last_modified_time {
+                continue;
+            }
+            *last_modified_time = modified_time;
+            let object_name = command.object_name()?;
+            rebuilt_functions.extend(session.object_functions(&object_name));
+            if modified_time.is_none() {
+                // The source file has been deleted.
+                session.analysis.remove_object(&object_name);
+                eprintln!("Removed {}", command.object);
+                any_updated = true;
+                continue;
+            }
+            // Keep watching when the file does not compile, it is probably edited further.
+            match crate::get_assembly(command, verbose_errors) {
+                Ok(assembly) => {
+                    session.analysis.update_object(command, &assembly)?;
+                    rebuilt_functions.extend(session.object_functions(&object_name));
+                    eprintln!("Updated {}", command.object);
+                    any_updated = true;
+                }
+                Err(err) => eprintln!("Failed to update {}: {:?}", command.object, err),
+            }
+        }
+        if any_updated {
+            let changes = session.record_instruction_counts(rebuilt_functions);
+            print_recent_changes(&session.analysis.parsed, &changes);
+            crate::write_parsed_data(
+                &session.analysis.parsed,
+                output_json,
+                deterministic_output,
+                compression,
+            )?;
+            if let Some(metrics_server) = metrics_server {
+                metrics_server.update(&session.analysis.parsed);
+            }
+        }
+    }
+}